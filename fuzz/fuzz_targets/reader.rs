@@ -0,0 +1,17 @@
+//! Feeds arbitrary input to the reader, which should raise errors for it
+//! rather than panicking. Run with `cargo fuzz run reader` from the root of
+//! the repository.
+
+#![no_main]
+
+use crisp::{expr::CrispExpr, reader};
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    let Ok(code) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = reader::parse(&reader::tokenize(code.to_string()));
+    let _ = reader::locate(code, &CrispExpr::Nil);
+    reader::Reader::new(data).take_while(Result::is_ok).count();
+});
@@ -0,0 +1,84 @@
+use std::{alloc::{GlobalAlloc, Layout, System}, sync::atomic::{AtomicUsize, Ordering}};
+
+/// How many allocations have been made, and how many bytes they asked for in
+/// total, since the program started. See [`CountingAllocator`].
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps the [`System`] allocator, keeping count of the allocations that are
+/// made so that the REPL's `:memory` command can show how many an expression
+/// needed. Memory that is freed again isn't subtracted, so this is a measure
+/// of how much work the allocator did rather than of how much memory is in use.
+pub struct CountingAllocator;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size.saturating_sub(layout.size()));
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Records an allocation of `bytes`.
+fn count(bytes: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// A count of allocations, as taken by [`AllocStats::now()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AllocStats {
+    pub allocations: usize,
+    pub bytes: usize
+}
+
+impl AllocStats {
+    /// The allocations made so far, by every thread.
+    pub fn now() -> Self {
+        AllocStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed)
+        }
+    }
+
+    /// The allocations made between `earlier` and `self`.
+    pub fn since(self, earlier: AllocStats) -> Self {
+        AllocStats {
+            allocations: self.allocations.saturating_sub(earlier.allocations),
+            bytes: self.bytes.saturating_sub(earlier.bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_stats() {
+        let before = AllocStats::now();
+        let items: Vec<u64> = Vec::with_capacity(1000);
+        let allocated = AllocStats::now().since(before);
+
+        // Tests on other threads may be allocating at the same time
+        assert!(allocated.allocations >= 1);
+        assert!(allocated.bytes >= 8000);
+        drop(items);
+    }
+}
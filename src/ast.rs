@@ -0,0 +1,325 @@
+use crate::{expr::CrispExpr, reader::Span};
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+/// A handle to a node in an [`Ast`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeId(usize);
+
+/// A node in an [`Ast`]. A list doesn't own its children, but refers to a run
+/// of the arena's `children`.
+#[derive(Debug)]
+enum Node {
+    Atom(CrispExpr),
+    List { start: usize, len: usize }
+}
+
+/// An arena that the reader builds parsed expressions in (see
+/// [`parse_into()`](crate::reader::parse_into)), before they're turned into
+/// [`CrispExpr`]s with [`take_expr()`](Ast::take_expr).
+///
+/// Every node is kept in one [`Vec`], and the children of every list in
+/// another, rather than each list growing a `Vec` of its own as it's read. An
+/// arena can be [cleared](Ast::clear) and used again, keeping its capacity, so
+/// reading a file full of expressions hardly touches the allocator until the
+/// finished expressions are taken out, which each `List` only needs to be
+/// allocated once for, at its final size.
+#[derive(Debug, Default)]
+pub struct Ast {
+    nodes: Vec<Node>,
+    /// The first and last positions of each node in the source code, if it
+    /// was read with them (see [`set_span()`](Ast::set_span)).
+    spans: Vec<Option<(Span, Span)>>,
+    /// The children of every finished list, each list's in a contiguous run.
+    children: Vec<NodeId>,
+    /// The children of the lists that are still being read, innermost last.
+    pending: Vec<NodeId>
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes every node, keeping the memory that was allocated for them.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.spans.clear();
+        self.children.clear();
+        self.pending.clear();
+    }
+
+    /// Adds an atom (or any expression that's already been built) to the
+    /// arena.
+    pub fn atom(&mut self, expr: CrispExpr) -> NodeId {
+        self.nodes.push(Node::Atom(expr));
+        self.spans.push(None);
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Starts reading a list. Its children are then [pushed](Ast::push) in
+    /// order, until it's [closed](Ast::close) with the mark returned here.
+    pub fn open(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds `child` to the innermost list that's being read.
+    pub fn push(&mut self, child: NodeId) {
+        self.pending.push(child);
+    }
+
+    /// Finishes reading the list that was [opened](Ast::open) at `mark`.
+    pub fn close(&mut self, mark: usize) -> NodeId {
+        let start = self.children.len();
+        self.children.extend(self.pending.drain(mark..));
+
+        self.nodes.push(Node::List { start, len: self.children.len() - start });
+        self.spans.push(None);
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// Records that the node `id` was read from between the positions `start`
+    /// and `end` (inclusive) in the source code.
+    pub fn set_span(&mut self, id: NodeId, start: Span, end: Span) {
+        self.spans[id.0] = Some((start, end));
+    }
+
+    /// The first and last positions of the node `id` in the source code, if
+    /// they were [recorded](Ast::set_span).
+    pub fn span(&self, id: NodeId) -> Option<(Span, Span)> {
+        self.spans[id.0]
+    }
+
+    /// Finishes reading the list that was [opened](Ast::open) at `mark`, and
+    /// takes its children out of the arena rather than making a node of it.
+    /// This is for reader macros, which are given the expressions they expand
+    /// as they are.
+    pub fn close_into_exprs(&mut self, mark: usize) -> Vec<CrispExpr> {
+        let ids: Vec<NodeId> = self.pending.drain(mark..).collect();
+        ids.into_iter().map(|id| self.take_expr(id)).collect()
+    }
+
+    /// Abandons any lists that were opened after `mark` and never closed,
+    /// e.g. because of a parse error.
+    pub fn abandon(&mut self, mark: usize) {
+        self.pending.truncate(mark);
+    }
+
+    /// The children of the node `id`, which are empty if it isn't a list.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match self.nodes[id.0] {
+            Node::List { start, len } => &self.children[start..start + len],
+            Node::Atom(_) => &[]
+        }
+    }
+
+    /// The expression at the node `id`, or `None` if it's a list.
+    pub fn atom_at(&self, id: NodeId) -> Option<&CrispExpr> {
+        match &self.nodes[id.0] {
+            Node::Atom(expr) => Some(expr),
+            Node::List { .. } => None
+        }
+    }
+
+    /// Builds the [`CrispExpr`] for the node `id`. Atoms are moved out of the
+    /// arena rather than cloned, so each node can only be taken once; it's
+    /// left as `nil`.
+    pub fn take_expr(&mut self, id: NodeId) -> CrispExpr {
+        self.take_node(id, None)
+    }
+
+    /// Builds the [`CrispExpr`] for the node `id` like
+    /// [`take_expr()`](Ast::take_expr), adding the span of each list in it to
+    /// `map`.
+    pub fn take_expr_mapped(&mut self, id: NodeId, map: &mut SourceMap) -> CrispExpr {
+        self.take_node(id, Some(map))
+    }
+
+    fn take_node(&mut self, id: NodeId, mut map: Option<&mut SourceMap>) -> CrispExpr {
+        match &mut self.nodes[id.0] {
+            Node::Atom(expr) => std::mem::replace(expr, CrispExpr::Nil),
+            Node::List { start, len } => {
+                let (start, len) = (*start, *len);
+                let list: Vec<CrispExpr> = (start..start + len).map(|i| {
+                    self.take_node(self.children[i], map.as_deref_mut())
+                }).collect();
+
+                if let (Some(map), Some((start, end))) = (map, self.spans[id.0]) {
+                    map.insert(&list, start, end);
+                }
+
+                CrispExpr::List(list)
+            }
+        }
+    }
+}
+
+/// Where each [`List`](CrispExpr) in some parsed code was read from, so that
+/// an error raised while evaluating a list can be traced back to the source
+/// code (see [`take_error_source()`](crate::eval::take_error_source)).
+///
+/// Lists are told apart by where their elements are kept in memory rather
+/// than by value, so identical lists are never mixed up. A map is only good
+/// for as long as the code that it was made for: a clone of a list keeps its
+/// elements somewhere else, and needs a map of its own (see
+/// [`map_clone()`](SourceMap::map_clone)). An empty list has nowhere to keep
+/// its elements, and is never mapped.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    /// The file that the code was read from, if any.
+    pub file: Option<PathBuf>,
+    spans: HashMap<usize, (Span, Span)>
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the list with the elements `list` was read from between
+    /// the positions `start` and `end` (inclusive).
+    pub fn insert(&mut self, list: &[CrispExpr], start: Span, end: Span) {
+        if !list.is_empty() {
+            self.spans.insert(list.as_ptr() as usize, (start, end));
+        }
+    }
+
+    /// The first and last positions of `expr` in the source code, if it's a
+    /// list in this map.
+    pub fn get(&self, expr: &CrispExpr) -> Option<(Span, Span)> {
+        match expr {
+            CrispExpr::List(list) if !list.is_empty() => {
+                self.spans.get(&(list.as_ptr() as usize)).copied()
+            },
+            _ => None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Moves every span down by `lines`, e.g. for code that was read on its
+    /// own from further down a file.
+    pub fn offset(&mut self, lines: usize) {
+        for (start, end) in self.spans.values_mut() {
+            start.line += lines;
+            end.line += lines;
+        }
+    }
+
+    /// Adds the span of each list in `clone`, a clone of `original`, wherever
+    /// the list it was cloned from is in one of `maps`, innermost last. This
+    /// map's file is set to that of the map it's found in.
+    pub fn map_clone(&mut self, original: &CrispExpr, clone: &CrispExpr, maps: &[Arc<SourceMap>]) {
+        let (CrispExpr::List(originals), CrispExpr::List(clones)) = (original, clone) else {
+            return;
+        };
+
+        let found = maps.iter().rev().find_map(|map| Some((map, map.get(original)?)));
+        if let Some((map, (start, end))) = found {
+            self.insert(clones, start, end);
+            self.file.clone_from(&map.file);
+        }
+
+        for (original, clone) in originals.iter().zip(clones) {
+            self.map_clone(original, clone, maps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::CrispExpr::*;
+
+    #[test]
+    fn test_ast() {
+        // (+ 1 (* 2 3))
+        let mut ast = Ast::new();
+        let outer = ast.open();
+        let plus = ast.atom(sym!("+"));
+        ast.push(plus);
+        let one = ast.atom(Number(1.0));
+        ast.push(one);
+        let inner = ast.open();
+        for atom in [sym!("*"), Number(2.0), Number(3.0)] {
+            let id = ast.atom(atom);
+            ast.push(id);
+        }
+        let times = ast.close(inner);
+        ast.push(times);
+        let root = ast.close(outer);
+
+        assert_eq!(ast.children(root), &[plus, one, times]);
+        assert_eq!(ast.children(times).len(), 3);
+        assert_eq!(ast.children(one), &[]);
+        assert_eq!(ast.atom_at(one), Some(&Number(1.0)));
+        assert_eq!(ast.atom_at(times), None);
+        assert_eq!(ast.take_expr(root), list![sym!("+"), Number(1.0),
+                                              list![sym!("*"), Number(2.0), Number(3.0)]]);
+
+        // Abandoned lists don't end up in the next one
+        ast.clear();
+        let outer = ast.open();
+        let x = ast.atom(sym!("x"));
+        ast.push(x);
+        let _ = ast.open();
+        let y = ast.atom(sym!("y"));
+        ast.push(y);
+        ast.abandon(outer);
+        let root = ast.close(outer);
+        assert_eq!(ast.take_expr(root), list![]);
+    }
+
+    #[test]
+    fn test_source_map() {
+        let at = |line, column| Span { line, column };
+
+        // (+ 1 (+ 1))
+        let mut ast = Ast::new();
+        let outer = ast.open();
+        let inner = ast.open();
+        for atom in [sym!("+"), Number(1.0)] {
+            let id = ast.atom(atom);
+            ast.push(id);
+        }
+        let first = ast.close(inner);
+        ast.set_span(first, at(1, 1), at(1, 5));
+        let inner = ast.open();
+        for atom in [sym!("+"), Number(1.0)] {
+            let id = ast.atom(atom);
+            ast.push(id);
+        }
+        let second = ast.close(inner);
+        ast.set_span(second, at(2, 1), at(2, 5));
+        ast.push(first);
+        ast.push(second);
+        let root = ast.close(outer);
+
+        assert_eq!(ast.span(first), Some((at(1, 1), at(1, 5))));
+        assert_eq!(ast.span(root), None);
+
+        let mut map = SourceMap::new();
+        let expr = ast.take_expr_mapped(root, &mut map);
+        let CrispExpr::List(lists) = &expr else { unreachable!() };
+
+        // Identical lists are told apart
+        assert_eq!(lists[0], lists[1]);
+        assert_eq!(map.get(&lists[0]), Some((at(1, 1), at(1, 5))));
+        assert_eq!(map.get(&lists[1]), Some((at(2, 1), at(2, 5))));
+        assert_eq!(map.get(&expr), None);
+        assert_eq!(map.get(&lists[0].clone()), None);
+
+        map.offset(2);
+        assert_eq!(map.get(&lists[1]), Some((at(4, 1), at(4, 5))));
+
+        // A clone needs a map of its own
+        let clone = expr.clone();
+        let mut cloned = SourceMap::new();
+        cloned.map_clone(&expr, &clone, &[Arc::new(map)]);
+        let CrispExpr::List(cloned_lists) = &clone else { unreachable!() };
+        assert_eq!(cloned.get(&cloned_lists[1]), Some((at(4, 1), at(4, 5))));
+        assert_eq!(cloned.get(&lists[1]), None);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{ast::{Ast, NodeId}, env::{CrispEnv, initialize_environment}, error::CrispError,
+            expr::CrispExpr, keywords::find_load_path, package::library_files, project,
+            reader::{parse_into, tokenize}, split_exprs};
+
+use std::{env, fs::{self, File}, io::{Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+/// Marks the end of a binary with a program embedded in it. See [`embed()`].
+const MAGIC: &[u8; 8] = b"CRISPBDL";
+
+/// Runs `crisp bundle`, which bundles the file `input` (or the main file of
+/// the project in the current directory, see [`project::prepare()`]) and
+/// everything it loads into a single file with [`bundle()`]. If `binary` is
+/// set, the bundle is embedded into a copy of the interpreter with
+/// [`embed()`], which runs it when started.
+///
+/// The bundle is written to `output` if given (`-` for stdout), otherwise
+/// next to the input file, named after it: `main.bundle.crisp`, or `main` for a
+/// binary.
+///
+/// # Returns
+///
+/// The exit code for the process.
+pub fn run(input: Option<&str>, output: Option<&str>, binary: bool) -> i32 {
+    let input = match input {
+        Some(input) => Ok(PathBuf::from(input)),
+        None => env::current_dir().map_err(|e| CrispError::StandardError(e.to_string()))
+                                  .and_then(|dir| project::prepare(&dir))
+    };
+
+    let result = input.and_then(|input| {
+        let source = bundle(&input)?;
+
+        let output = match output {
+            Some("-") if !binary => {
+                print!("{}", source);
+                return Ok(());
+            },
+            Some(output) => PathBuf::from(output),
+            None => default_output(&input, binary)
+        };
+
+        match binary {
+            true => {
+                let exe = env::current_exe().map_err(|e| {
+                    CrispError::StandardError(format!("Couldn't find the interpreter: {}", e))
+                })?;
+                embed(&exe, &source, &output)?;
+            },
+            false => fs::write(&output, source).map_err(|e| {
+                CrispError::StandardError(format!("Couldn't write {}: {}", output.display(), e))
+            })?
+        }
+
+        eprintln!("Bundled {} into {}", input.display(), output.display());
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Where the bundle of `input` is written if no output is given. See
+/// [`run()`].
+fn default_output(input: &Path, binary: bool) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    match binary {
+        true => input.with_file_name(format!("{}{}", stem, env::consts::EXE_SUFFIX)),
+        false => input.with_file_name(format!("{}.bundle.crisp", stem))
+    }
+}
+
+/// Bundles the file at `path` into a single program, by replacing each
+/// top-level `load` or `require` of a literal string with the contents of the
+/// file that it names, recursively. Each is marked with a comment naming the
+/// file. Loads that aren't at the top level, or that don't name their file
+/// with a literal, are left alone, since it can't be known which file they
+/// load until the program is run.
+pub fn bundle(path: &Path) -> Result<String, CrispError> {
+    let mut bundled = String::new();
+    inline(path, &mut Vec::new(), &mut bundled)?;
+
+    Ok(bundled)
+}
+
+/// Appends the source code of the file at `path` to `bundled`, for
+/// [`bundle()`]. `loading` holds the files that are being inlined, to detect
+/// circular loads.
+fn inline(path: &Path, loading: &mut Vec<PathBuf>, bundled: &mut String) -> Result<(), CrispError> {
+    let canonical_path = path.canonicalize()
+                             .map_err(|_| CrispError::LoadError(path.display().to_string()))?;
+    if loading.contains(&canonical_path) {
+        return standard_error!(format!("Circular load detected: {}", path.display()));
+    }
+
+    let source = fs::read_to_string(path)
+                    .map_err(|_| CrispError::LoadError(path.display().to_string()))?;
+    // Loads are found relative to `path` as given, so that the comments
+    // marking each file give paths as they were written
+    let mut env = initialize_environment();
+    env.file = Some(path.to_path_buf());
+
+    let mut ast = Ast::new();
+    loading.push(canonical_path);
+    for (_, expr) in split_exprs(&source) {
+        ast.clear();
+        let loaded = match parse_into(&tokenize(expr.clone()), &mut ast) {
+            Ok((root, _)) => loaded_file(&ast, root, &env)?,
+            Err(_) => None
+        };
+
+        match loaded {
+            Some(loaded) => {
+                bundled.push_str(&format!(";;; {}\n", loaded.display()));
+                inline(&loaded, loading, bundled)?;
+                bundled.push_str(&format!(";;; end of {}\n", loaded.display()));
+            },
+            None => bundled.push_str(&expr)
+        }
+    }
+    loading.pop();
+
+    Ok(())
+}
+
+/// Finds the file that the expression `root` in `ast` loads, if it's a `load`
+/// or `require` of a literal string, for [`inline()`]. Only the nodes that are
+/// needed to tell are looked at, so this is cheap for everything else.
+fn loaded_file(ast: &Ast, root: NodeId, env: &CrispEnv) -> Result<Option<PathBuf>, CrispError> {
+    let atoms: Vec<Option<&CrispExpr>> = match ast.children(root) {
+        children @ [_, _] => children.iter().map(|id| ast.atom_at(*id)).collect(),
+        _ => return Ok(None)
+    };
+
+    match &atoms[..] {
+        [Some(CrispExpr::Symbol(keyword)), Some(CrispExpr::CrispString(name))]
+        if &**keyword == "load" => {
+            find_load_path(name, env).map(Some)
+        },
+        [Some(CrispExpr::Symbol(keyword)), Some(CrispExpr::CrispString(name))]
+        if &**keyword == "require" => {
+            library_files(name).into_iter()
+                               .find(|file| file.is_file())
+                               .map(Some)
+                               .ok_or(CrispError::LoadError(name.to_string()))
+        },
+        _ => Ok(None)
+    }
+}
+
+/// Writes a copy of the interpreter `exe` to `output`, with the program
+/// `source` embedded in it, which it runs when started (see [`embedded()`])
+/// rather than behaving as the usual CLI. The program is appended to the end
+/// of the binary, followed by its length and the [`MAGIC`] bytes. Any program
+/// already embedded in `exe` is replaced.
+pub fn embed(exe: &Path, source: &str, output: &Path) -> Result<(), CrispError> {
+    let error = |e: std::io::Error| {
+        CrispError::StandardError(format!("Couldn't write {}: {}", output.display(), e))
+    };
+
+    let mut binary = fs::read(exe).map_err(error)?;
+    if let Some(start) = embedded_start(&binary) {
+        binary.truncate(start);
+    }
+
+    binary.extend_from_slice(source.as_bytes());
+    binary.extend_from_slice(&(source.len() as u64).to_le_bytes());
+    binary.extend_from_slice(MAGIC);
+
+    fs::write(output, binary).map_err(error)?;
+    fs::set_permissions(output, fs::metadata(exe).map_err(error)?.permissions()).map_err(error)
+}
+
+/// Finds where the program embedded in `binary` by [`embed()`] starts, if
+/// there is one.
+fn embedded_start(binary: &[u8]) -> Option<usize> {
+    let trailer = binary.len().checked_sub(16)?;
+    if &binary[trailer + 8..] != MAGIC {
+        return None;
+    }
+
+    let len = u64::from_le_bytes(binary[trailer..trailer + 8].try_into().ok()?);
+    trailer.checked_sub(usize::try_from(len).ok()?)
+}
+
+/// Reads the program embedded in the binary at `exe` by [`embed()`], if there
+/// is one. Only the end of the file is read unless there is.
+pub fn embedded(exe: &Path) -> Option<String> {
+    let mut file = File::open(exe).ok()?;
+
+    let mut trailer = [0; 16];
+    file.seek(SeekFrom::End(-16)).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+    if &trailer[8..] != MAGIC {
+        return None;
+    }
+
+    let len = u64::from_le_bytes(trailer[..8].try_into().ok()?);
+    file.seek(SeekFrom::End(-16 - i64::try_from(len).ok()?)).ok()?;
+
+    let mut source = String::new();
+    file.take(len).read_to_string(&mut source).ok()?;
+    Some(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle() {
+        let bundled = bundle(Path::new("tests/bundle/main.crisp")).unwrap();
+
+        assert!(bundled.contains(";;; tests/bundle/lib/shapes.crisp\n"));
+        assert!(bundled.contains(";;; tests/bundle/lib/square.crisp\n\
+                                  fn square n (* n n)\n\
+                                  ;;; end of tests/bundle/lib/square.crisp\n"));
+        assert!(!bundled.contains("load \"lib/shapes.crisp\""));
+
+        // Only loads at the top level are inlined
+        assert!(bundled.contains("(load \"missing.crisp\")"));
+
+        crisp_assert_err!(bundle(Path::new("tests/lib/cycle_a.crisp")), StandardError);
+        crisp_assert_err!(bundle(Path::new("tests/does_not_exist.crisp")), LoadError);
+    }
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(default_output(Path::new("src/main.crisp"), false),
+                   PathBuf::from("src/main.bundle.crisp"));
+        assert_eq!(default_output(Path::new("src/main.crisp"), true),
+                   PathBuf::from(format!("src/main{}", env::consts::EXE_SUFFIX)));
+    }
+
+    #[test]
+    fn test_embed() {
+        let dir = env::temp_dir().join(format!("crisp-bundle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let exe = dir.join("crisp");
+        fs::write(&exe, b"not really an interpreter").unwrap();
+        assert_eq!(embedded(&exe), None);
+
+        let output = dir.join("program");
+        embed(&exe, "puts \"Hello!\"\n", &output).unwrap();
+        assert_eq!(embedded(&output), Some("puts \"Hello!\"\n".to_string()));
+
+        // Bundling again from a bundled binary replaces the program
+        let again = dir.join("again");
+        embed(&output, "puts 1\n", &again).unwrap();
+        assert_eq!(embedded(&again), Some("puts 1\n".to_string()));
+        assert_eq!(fs::read(&again).unwrap().len(), b"not really an interpreter".len() + 7 + 16);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
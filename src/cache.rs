@@ -0,0 +1,369 @@
+use crate::{ast::{Ast, SourceMap}, error::CrispError, expr::CrispExpr,
+            reader::{Span, custom_reader_macros, parse_into, tokenize_with_spans}};
+
+use std::{env, fs, path::{Path, PathBuf}, sync::atomic::{AtomicBool, Ordering}};
+
+use directories::ProjectDirs;
+
+/// Whether parsed files are cached. See [`configure()`].
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Starts every cache file, followed by a version number that's bumped
+/// whenever the format changes.
+const MAGIC: &[u8; 6] = b"CRISPC";
+const VERSION: u8 = 3;
+
+/// Configures whether the parsed forms of files are cached for the rest of the
+/// program (see [`parse_exprs()`]). The CLI enables this unless it's given
+/// `--no-cache`; otherwise, e.g. when embedding crisp, nothing is cached.
+pub fn configure(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Parses the numbered top-level expressions `exprs` of the file at `file`, as
+/// split by [`split_exprs()`](crate::split_exprs).
+///
+/// If caching is enabled (see [`configure()`]), the parsed expressions are
+/// kept in a `.crispc` file in the cache directory, named after a hash of the
+/// file's canonical path, so that the next time the same file is run it
+/// needn't be parsed again. The cache file records a hash of the source code
+/// too, and is replaced when the file changes, so there's only ever one for
+/// each file. A file that doesn't parse isn't cached, nor is anything while a
+/// reader macro other than crisp's own is registered, since it could change
+/// how the source is read.
+///
+/// # Returns
+///
+/// The result of parsing each expression, in order, along with a
+/// [`SourceMap`] of where in the file each of the lists in it are.
+pub fn parse_exprs(
+    file: &Path,
+    exprs: &[(usize, String)]
+) -> Vec<Result<(CrispExpr, SourceMap), CrispError>> {
+    let path = cache_dir().filter(|_| ENABLED.load(Ordering::Relaxed) && !custom_reader_macros())
+                          .zip(file.canonicalize().ok())
+                          .map(|(dir, file)| cache_file(&dir, &file));
+    parse_cached(exprs, path.as_deref())
+}
+
+/// Parses expressions for [`parse_exprs()`], caching them in the file at `path`
+/// if given.
+fn parse_cached(
+    exprs: &[(usize, String)],
+    path: Option<&Path>
+) -> Vec<Result<(CrispExpr, SourceMap), CrispError>> {
+    let hash = source_hash(exprs);
+
+    // Spans are kept relative to the start of each expression, and moved to
+    // where it is in the file once it's been read
+    let in_file = |parsed: Vec<Result<(CrispExpr, SourceMap), CrispError>>| {
+        parsed.into_iter().zip(exprs).map(|(parsed, (line, _))| {
+            parsed.map(|(expr, mut map)| {
+                map.offset(line - 1);
+                (expr, map)
+            })
+        }).collect()
+    };
+
+    if let Some(cached) = path.and_then(|path| fs::read(path).ok())
+                              .and_then(|bytes| decode(&bytes, hash))
+                              .filter(|cached| cached.len() == exprs.len()) {
+        return in_file(cached.into_iter().map(Ok).collect());
+    }
+
+    let mut ast = Ast::new();
+    let parsed: Vec<_> = exprs.iter().map(|(_, expr)| {
+        ast.clear();
+        let mut map = SourceMap::new();
+        parse_into(&tokenize_with_spans(expr), &mut ast)
+            .map(|(root, _)| (ast.take_expr_mapped(root, &mut map), map))
+    }).collect();
+
+    let encoded = parsed.iter().map(|parsed| parsed.as_ref().ok().map(|(expr, map)| (expr, map)))
+                               .collect::<Option<Vec<_>>>()
+                               .and_then(|parsed| encode(hash, &parsed));
+    if let (Some(path), Some(encoded)) = (path, encoded) {
+        // The cache is only an optimization, so it doesn't matter if this fails
+        let _ = path.parent().map(fs::create_dir_all);
+        let _ = fs::write(path, encoded);
+    }
+
+    in_file(parsed)
+}
+
+/// Finds the directory that parsed files are cached in: the one given by the
+/// `CRISP_CACHE` environment variable if it's set, otherwise `ast` within the
+/// platform's cache directory (e.g. `~/.cache/crisp` on Linux).
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("CRISP_CACHE").map(PathBuf::from).or_else(|| {
+        ProjectDirs::from("", "", "crisp").map(|dirs| dirs.cache_dir().join("ast"))
+    })
+}
+
+/// Finds where the parsed form of the file at the canonical path `file` is
+/// cached in `dir`: a file named after a hash of the path.
+fn cache_file(dir: &Path, file: &Path) -> PathBuf {
+    dir.join(format!("{:016x}.crispc", fnv1a(FNV_OFFSET, file.as_os_str().as_encoded_bytes())))
+}
+
+/// Hashes the source code `exprs`, to tell whether a cache file is up to date.
+/// The hash also covers the version of crisp and the features that change how
+/// source is read, so that a cache isn't used by a version that would read the
+/// source differently.
+fn source_hash(exprs: &[(usize, String)]) -> u64 {
+    let version = format!("{}:{}:{}", env!("CARGO_PKG_VERSION"), VERSION,
+                          cfg!(feature = "decimal"));
+    exprs.iter().fold(fnv1a(FNV_OFFSET, version.as_bytes()), |hash, (_, expr)| {
+        fnv1a(fnv1a(hash, expr.as_bytes()), b"\0")
+    })
+}
+
+/// Where [`fnv1a()`] starts from.
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// Hashes `bytes` with 64-bit FNV-1a, continuing from `hash`. Unlike the
+/// hashers in the standard library, this is guaranteed to give the same hash
+/// in every build.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+}
+
+/// Encodes parsed expressions for the cache, each with the [`SourceMap`] of its
+/// lists, along with the [`source_hash()`] they were parsed from, or returns
+/// `None` if any contains something that can't be read from source, such as a
+/// [`Lambda`](CrispExpr).
+fn encode(hash: u64, exprs: &[(&CrispExpr, &SourceMap)]) -> Option<Vec<u8>> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&hash.to_le_bytes());
+    bytes.extend_from_slice(&(exprs.len() as u64).to_le_bytes());
+
+    for (expr, map) in exprs {
+        encode_expr(expr, map, &mut bytes)?;
+    }
+
+    Some(bytes)
+}
+
+fn encode_expr(expr: &CrispExpr, map: &SourceMap, bytes: &mut Vec<u8>) -> Option<()> {
+    match expr {
+        CrispExpr::Nil => bytes.push(0),
+        CrispExpr::Bool(b) => bytes.extend_from_slice(&[1, u8::from(*b)]),
+        CrispExpr::Number(n) => {
+            bytes.push(2);
+            bytes.extend_from_slice(&n.to_le_bytes());
+        },
+        CrispExpr::Char(c) => {
+            bytes.push(3);
+            bytes.extend_from_slice(&u32::from(*c).to_le_bytes());
+        },
+        CrispExpr::CrispString(s) => encode_str(4, s, bytes),
+        CrispExpr::Symbol(s) => encode_str(5, s, bytes),
+        CrispExpr::List(list) => {
+            bytes.push(6);
+            bytes.extend_from_slice(&(list.len() as u64).to_le_bytes());
+            for expr in list {
+                encode_expr(expr, map, bytes)?;
+            }
+
+            // Followed by where it was read from, if known
+            match map.get(expr) {
+                Some((start, end)) => {
+                    bytes.push(1);
+                    for n in [start.line, start.column, end.line, end.column] {
+                        bytes.extend_from_slice(&(n as u64).to_le_bytes());
+                    }
+                },
+                None => bytes.push(0)
+            }
+        },
+        #[cfg(feature = "decimal")]
+        CrispExpr::Decimal(d) => {
+            bytes.push(7);
+            bytes.extend_from_slice(&d.serialize());
+        },
+        _ => return None
+    }
+
+    Some(())
+}
+
+fn encode_str(tag: u8, s: &str, bytes: &mut Vec<u8>) {
+    bytes.push(tag);
+    bytes.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+/// Decodes expressions encoded by [`encode()`], or returns `None` if `bytes`
+/// aren't a valid cache file, or weren't parsed from the source with the
+/// [`source_hash()`] `hash`.
+fn decode(bytes: &[u8], hash: u64) -> Option<Vec<(CrispExpr, SourceMap)>> {
+    let mut decoder = Decoder { bytes: bytes.strip_prefix(MAGIC)?, map: SourceMap::new() };
+    if decoder.take(1)? != [VERSION] || decoder.array()? != hash.to_le_bytes() {
+        return None;
+    }
+
+    let exprs = (0..decoder.len()?).map(|_| {
+        decoder.expr().map(|expr| (expr, std::mem::take(&mut decoder.map)))
+    }).collect();
+    match decoder.bytes.is_empty() {
+        true => exprs,
+        false => None
+    }
+}
+
+/// Reads through the bytes of a cache file for [`decode()`].
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    /// The spans of the lists in the expression being decoded.
+    map: SourceMap
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if n > self.bytes.len() {
+            return None;
+        }
+
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        usize::try_from(u64::from_le_bytes(self.array()?)).ok()
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn expr(&mut self) -> Option<CrispExpr> {
+        let expr = match self.take(1)?[0] {
+            0 => CrispExpr::Nil,
+            1 => CrispExpr::Bool(self.take(1)?[0] != 0),
+            2 => CrispExpr::Number(f64::from_le_bytes(self.array()?)),
+            3 => CrispExpr::Char(char::from_u32(u32::from_le_bytes(self.array()?))?),
+            4 => CrispExpr::CrispString(self.str()?.into()),
+            5 => CrispExpr::Symbol(self.str()?.into()),
+            6 => {
+                let list: Vec<_> = (0..self.len()?).map(|_| self.expr()).collect::<Option<_>>()?;
+                if self.take(1)? == [1] {
+                    let start = Span { line: self.len()?, column: self.len()? };
+                    let end = Span { line: self.len()?, column: self.len()? };
+                    self.map.insert(&list, start, end);
+                }
+
+                CrispExpr::List(list)
+            },
+            #[cfg(feature = "decimal")]
+            7 => CrispExpr::Decimal(rust_decimal::Decimal::deserialize(self.array()?)),
+            _ => return None
+        };
+
+        Some(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::{CrispExpr::*, CrispLambda}, split_exprs};
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_encode_decode() {
+        let exprs = vec![
+            list![sym!("puts"), str!("héllo"), Char('λ'), Number(-1.5), Bool(true), Nil],
+            list![],
+            sym!("x")
+        ];
+
+        let mut maps: Vec<SourceMap> = exprs.iter().map(|_| SourceMap::new()).collect();
+        let span = (Span { line: 1, column: 1 }, Span { line: 1, column: 30 });
+        if let List(list) = &exprs[0] {
+            maps[0].insert(list, span.0, span.1);
+        }
+
+        let encoded = encode(42, &exprs.iter().zip(&maps).collect::<Vec<_>>()).unwrap();
+        let decoded = decode(&encoded, 42).unwrap();
+        assert_eq!(decoded.iter().map(|(expr, _)| expr.clone()).collect::<Vec<_>>(), exprs);
+
+        // Along with where each list was read from
+        assert_eq!(decoded[0].1.get(&decoded[0].0), Some(span));
+        assert!(decoded[1].1.is_empty());
+
+        // Of different source, truncated, or with anything left over
+        assert!(decode(&encoded, 43).is_none());
+        assert!(decode(&encoded[..encoded.len() - 1], 42).is_none());
+        assert!(decode(&[&encoded[..], &[0]].concat(), 42).is_none());
+        assert!(decode(b"not a cache", 42).is_none());
+
+        let lambda = lambda!(args: [], func: [Nil]);
+        assert_eq!(encode(42, &[(&list![lambda], &SourceMap::new())]), None);
+    }
+
+    #[test]
+    fn test_parse_exprs() {
+        let dir = env::temp_dir().join(format!("crisp-cache-{}", std::process::id()));
+
+        let path = cache_file(&dir, Path::new("/src/main.crisp"));
+
+        // The expressions, and where the `+` call in the second one is
+        let parse = |exprs: &[(usize, String)]| {
+            let parsed = parse_cached(exprs, Some(&path));
+            let span = match &parsed[..] {
+                [_, Ok((List(list), map))] => map.get(&list[1]),
+                _ => None
+            };
+
+            (parsed.into_iter().map(|parsed| parsed.map(|(expr, _)| expr)).collect::<Vec<_>>(),
+             span)
+        };
+
+        let exprs = split_exprs("let x 5\n\nputs (+ x 1)\n");
+        let parsed = parse(&exprs);
+        let at = |line, column| Span { line, column };
+        assert_eq!(parsed, (vec![Ok(list![sym!("let"), sym!("x"), Number(5.0)]),
+                                 Ok(list![sym!("puts"),
+                                          list![sym!("+"), sym!("x"), Number(1.0)]])],
+                            Some((at(3, 6), at(3, 12)))));
+
+        // Read back from the cache
+        assert!(path.is_file());
+        assert_eq!(parse(&exprs), parsed);
+
+        // A cache file that's been corrupted is ignored, and replaced
+        fs::write(&path, b"CRISPC").unwrap();
+        assert_eq!(parse(&exprs), parsed);
+        assert_ne!(fs::read(&path).unwrap(), b"CRISPC");
+
+        // A file that's been changed replaces its old cache file
+        let changed = split_exprs("let x 6\n");
+        assert_eq!(parse(&changed).0, vec![Ok(list![sym!("let"), sym!("x"), Number(6.0)])]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert_eq!(parse(&exprs), parsed);
+
+        // Something that doesn't parse isn't cached
+        let path = cache_file(&dir, Path::new("/src/broken.crisp"));
+        let exprs = split_exprs("puts (+ 1\n");
+        crisp_assert_err!(parse_cached(&exprs, Some(&path)).remove(0).map(|(expr, _)| expr),
+                          ParseError);
+        assert!(!path.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(fnv1a(FNV_OFFSET, b"a"), b"b"), fnv1a(FNV_OFFSET, b"ab"));
+    }
+}
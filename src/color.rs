@@ -0,0 +1,73 @@
+use std::{env, ffi::OsString, io::{self, IsTerminal}, sync::atomic::{AtomicBool, Ordering}};
+
+/// Whether errors are rendered without any formatting. See
+/// [`configure_errors()`].
+static PLAIN_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Configures whether or not [`colored`] output is emitted for the rest of
+/// the program. Colors are disabled if `no_color` is set (e.g. by the
+/// `--no-color` flag), if the `NO_COLOR` environment variable is set to a
+/// non-empty value, or if stdout or stderr is not a terminal.
+pub fn configure(no_color: bool) {
+    let is_tty = io::stdout().is_terminal() && io::stderr().is_terminal();
+
+    colored::control::set_override(use_color(no_color, env::var_os("NO_COLOR"), is_tty));
+}
+
+/// Configures whether errors are rendered in plain text for the rest of the
+/// program: no colors or bold, whether or not output is a terminal, so that it
+/// can be matched byte-for-byte (e.g. against a golden file). This is the case
+/// if `plain` is set (e.g. by the `--plain-errors` flag), or if the
+/// `CRISP_PLAIN_ERRORS` environment variable is set to a non-empty value.
+pub fn configure_errors(plain: bool) {
+    PLAIN_ERRORS.store(use_plain_errors(plain, env::var_os("CRISP_PLAIN_ERRORS")), Ordering::Relaxed);
+}
+
+/// Whether errors should be rendered in plain text. See
+/// [`configure_errors()`].
+pub fn plain_errors() -> bool {
+    PLAIN_ERRORS.load(Ordering::Relaxed)
+}
+
+/// Decides whether colors should be used. See [`configure()`].
+fn use_color(no_color: bool, no_color_env: Option<OsString>, is_tty: bool) -> bool {
+    let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+
+    !no_color && !no_color_env && is_tty
+}
+
+/// Decides whether errors should be rendered in plain text. See
+/// [`configure_errors()`].
+fn use_plain_errors(plain: bool, plain_env: Option<OsString>) -> bool {
+    plain || plain_env.is_some_and(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_use_color() {
+        assert!(use_color(false, None, true));
+
+        // Flag
+        assert!(!use_color(true, None, true));
+
+        // Environment variable; an empty value doesn't count
+        assert!(!use_color(false, Some(OsString::from("1")), true));
+        assert!(use_color(false, Some(OsString::new()), true));
+
+        // Not a terminal
+        assert!(!use_color(false, None, false));
+    }
+
+    #[test]
+    fn test_use_plain_errors() {
+        assert!(!use_plain_errors(false, None));
+        assert!(use_plain_errors(true, None));
+
+        // Environment variable; an empty value doesn't count
+        assert!(use_plain_errors(false, Some(OsString::from("1"))));
+        assert!(!use_plain_errors(false, Some(OsString::new())));
+    }
+}
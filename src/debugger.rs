@@ -0,0 +1,151 @@
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv,
+            functions::{crisp_flush, crisp_gets, write_stdout}, send};
+
+use std::cell::Cell;
+
+thread_local! {
+    /// Whether `break` pauses evaluation, set by `--debugger`. See
+    /// [`enable()`].
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+
+    /// Whether evaluation should pause again before the next expression. See
+    /// [`is_stepping()`].
+    static STEPPING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Turns on the debugger for the rest of the program on this thread, so that
+/// `break` pauses evaluation rather than doing nothing.
+pub fn enable() {
+    ENABLED.with(|enabled| enabled.set(true));
+}
+
+/// Whether the debugger has been turned on with [`enable()`].
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Whether the user asked to `step` at the last pause, in which case
+/// [`eval()`](crate::eval::eval) pauses before evaluating each expression.
+pub fn is_stepping() -> bool {
+    STEPPING.with(Cell::get)
+}
+
+/// Pauses evaluation and runs a mini-REPL in the scope `env`, until the user
+/// chooses to `continue` or `step`. `expr` is the expression about to be
+/// evaluated, if stepping.
+///
+/// Anything else that is entered is evaluated in `env`, so variables can be
+/// inspected (or changed) by name. `locals` lists the names bound in each
+/// scope of the environment chain, innermost first.
+pub fn pause(expr: Option<&CrispExpr>, env: &mut CrispEnv) -> Result<(), CrispError> {
+    match expr {
+        Some(expr) => write_stdout(&format!("[debug] {}\n", expr.inspect()), env)?,
+        None => write_stdout("[debug] break\n", env)?
+    }
+
+    // Don't step through the expressions entered at the prompt
+    STEPPING.with(|stepping| stepping.set(false));
+
+    loop {
+        write_stdout("debug> ", env)?;
+        crisp_flush(&[], env)?;
+
+        let line = match crisp_gets(&[], env)? {
+            CrispExpr::CrispString(line) => line,
+            // End of input
+            _ => return Ok(())
+        };
+
+        match line.trim() {
+            "" => continue,
+            "c" | "continue" => return Ok(()),
+            "s" | "step" => {
+                STEPPING.with(|stepping| stepping.set(true));
+                return Ok(());
+            },
+            "locals" => write_stdout(&locals(env), env)?,
+            "help" => write_stdout(HELP, env)?,
+            input => match send(input.to_string(), env) {
+                Ok(value) => write_stdout(&format!("=> {}\n", value.inspect()), env)?,
+                Err(e) => write_stdout(&format!("{}\n", e), env)?
+            }
+        }
+    }
+}
+
+const HELP: &str = "\
+continue, c  Resume evaluation
+step, s      Pause again before the next expression
+locals       List the names bound in each scope
+help         Show this message
+Anything else is evaluated in the current scope.
+";
+
+/// Lists the names bound in each scope of `env`, one scope per line,
+/// innermost first. Builtin functions are left out.
+fn locals(env: &CrispEnv) -> String {
+    let mut lines = String::new();
+    let mut scope = Some(env);
+
+    while let Some(current) = scope {
+        let mut names: Vec<&String> = current.data.iter()
+                                                  .filter(|(_, value)| !matches!(value, CrispExpr::Func(_)))
+                                                  .map(|(name, _)| name)
+                                                  .collect();
+        names.sort();
+
+        let names: Vec<&str> = names.into_iter().map(String::as_str).collect();
+        lines.push_str(&format!("  {}\n", names.join(" ")));
+        scope = current.parent;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::{env_new_scope, initialize_environment}, expr::CrispExpr::*};
+    use std::{collections::HashMap, io::Cursor, sync::{Arc, Mutex}};
+
+    /// Runs [`pause()`] in a scope with `x` bound, feeding it `input`, and
+    /// returns what it printed.
+    fn debug_session(input: &str) -> String {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let env = initialize_environment().with_stdout(output.clone())
+                                          .with_stdin(Arc::new(Mutex::new(Cursor::new(input.to_string()))));
+        let mut scope = env_new_scope(HashMap::from([("x".to_string(), Number(5.0))]), &env);
+
+        pause(None, &mut scope).unwrap();
+
+        let output = output.lock().unwrap();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_pause() {
+        assert_eq!(debug_session("x\n(+ x 1)\ncontinue\nx\n"),
+                   "[debug] break\ndebug> => 5\ndebug> => 6\ndebug> ");
+        assert!(!is_stepping());
+
+        // Errors are printed rather than raised
+        assert!(debug_session("y\nc\n").contains("Could not find symbol: y"));
+
+        // End of input continues
+        assert_eq!(debug_session(""), "[debug] break\ndebug> ");
+    }
+
+    #[test]
+    fn test_pause_locals() {
+        assert_eq!(debug_session("locals\nc\n"), "[debug] break\ndebug>   x\n  \ndebug> ");
+    }
+
+    #[test]
+    fn test_pause_step() {
+        debug_session("step\n");
+        assert!(is_stepping());
+
+        debug_session("c\n");
+        assert!(!is_stepping());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{error::CrispError, expr::CrispExpr, reader::Span, warning::CrispWarning};
+
+use std::{fmt, sync::atomic::{AtomicBool, Ordering}};
+
+use serde_json::{json, Value};
+
+/// Whether errors are reported as JSON. See [`configure()`].
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Configures how errors are reported for the rest of the program, by the name
+/// given to `--error-format`: `"human"` (the default), or `"json"` for a JSON
+/// object per error, to be consumed by other tools such as editors. See
+/// [`Diagnostic`].
+pub fn configure(format: &str) {
+    JSON.store(format == "json", Ordering::Relaxed);
+}
+
+/// Where an error was raised in the source code.
+pub struct ErrorLocation {
+    /// The positions of the first and last characters of the code.
+    pub start: Span,
+    pub end: Span,
+    /// The innermost expression that raised the error, if it's known.
+    /// Otherwise the location is that of the whole top-level expression.
+    pub expr: Option<CrispExpr>
+}
+
+/// An error to be reported to the user, along with whatever is known about
+/// where it came from.
+pub struct Diagnostic<'a> {
+    pub error: &'a CrispError,
+    pub file: Option<&'a str>,
+    pub location: Option<ErrorLocation>
+}
+
+impl Diagnostic<'_> {
+    /// The diagnostic as a JSON object, e.g.:
+    ///
+    /// ```json
+    /// {"code": "TypeError", "message": "Expected Number.", "file": "x.crisp",
+    ///  "line": 4, "column": 8, "expr": "(* 2 'a')",
+    ///  "span": {"start": {"line": 4, "column": 8}, "end": {"line": 4, "column": 16}}}
+    /// ```
+    ///
+    /// `file` is `null` if the program came from stdin, and `line`, `column`,
+    /// `expr` and `span` are `null` if the location isn't known. `expr` is
+    /// also `null` if only the top-level expression is known.
+    pub fn to_json(&self) -> Value {
+        to_json(self.error.name(), self.error.message(), self.file, self.location.as_ref())
+    }
+}
+
+/// Renders the diagnostic in the format chosen with [`configure()`]: by
+/// default, the error followed by an `at` line giving its location.
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if JSON.load(Ordering::Relaxed) {
+            return write!(f, "{}", self.to_json());
+        }
+
+        write!(f, "{}", self.error)?;
+        write_location(f, self.file, self.location.as_ref())
+    }
+}
+
+/// A warning to be reported to the user, along with whatever is known about
+/// where it came from, like a [`Diagnostic`] for an error.
+pub struct WarningDiagnostic<'a> {
+    pub warning: &'a CrispWarning,
+    pub file: Option<&'a str>,
+    pub location: Option<ErrorLocation>
+}
+
+impl WarningDiagnostic<'_> {
+    /// The warning as a JSON object, in the same form as
+    /// [`Diagnostic::to_json()`], with `"severity": "warning"` added.
+    pub fn to_json(&self) -> Value {
+        let mut json = to_json(self.warning.name(), self.warning.message(), self.file,
+                               self.location.as_ref());
+        json["severity"] = json!("warning");
+
+        json
+    }
+}
+
+/// Renders the warning in the format chosen with [`configure()`], like a
+/// [`Diagnostic`].
+impl fmt::Display for WarningDiagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if JSON.load(Ordering::Relaxed) {
+            return write!(f, "{}", self.to_json());
+        }
+
+        write!(f, "{}", self.warning)?;
+        write_location(f, self.file, self.location.as_ref())
+    }
+}
+
+/// Builds the JSON object for a diagnostic. See [`Diagnostic::to_json()`].
+fn to_json(
+    code: &str,
+    message: String,
+    file: Option<&str>,
+    location: Option<&ErrorLocation>
+) -> Value {
+    let position = |span: Span| json!({ "line": span.line, "column": span.column });
+
+    json!({
+        "code": code,
+        "message": message,
+        "file": file,
+        "line": location.map(|location| location.start.line),
+        "column": location.map(|location| location.start.column),
+        "expr": location.and_then(|location| location.expr.as_ref()).map(CrispExpr::inspect),
+        "span": location.map(|location| json!({
+            "start": position(location.start),
+            "end": position(location.end)
+        }))
+    })
+}
+
+/// Writes the `at` line giving where a diagnostic came from, if it's known.
+fn write_location(
+    f: &mut fmt::Formatter<'_>,
+    file: Option<&str>,
+    location: Option<&ErrorLocation>
+) -> fmt::Result {
+    if let (Some(file), Some(location)) = (file, location) {
+        write!(f, "\n    at {}:{}:{}", file, location.start.line, location.start.column)?;
+        if let Some(expr) = &location.expr {
+            write!(f, ": {}", expr.inspect())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::CrispExpr::*;
+
+    #[test]
+    fn test_to_json() {
+        let error = CrispError::TypeError("Number".to_string());
+        let diagnostic = Diagnostic {
+            error: &error,
+            file: Some("x.crisp"),
+            location: Some(ErrorLocation {
+                start: Span { line: 4, column: 8 },
+                end: Span { line: 4, column: 16 },
+                expr: Some(list![sym!("*"), Number(2.0), str!("a")])
+            })
+        };
+
+        assert_eq!(diagnostic.to_json(), json!({
+            "code": "TypeError",
+            "message": "Expected Number.",
+            "file": "x.crisp",
+            "line": 4,
+            "column": 8,
+            "expr": "(* 2 'a')",
+            "span": { "start": { "line": 4, "column": 8 }, "end": { "line": 4, "column": 16 } }
+        }));
+
+        // Unknown location
+        let diagnostic = Diagnostic { error: &error, file: None, location: None };
+        assert_eq!(diagnostic.to_json(), json!({
+            "code": "TypeError",
+            "message": "Expected Number.",
+            "file": null,
+            "line": null,
+            "column": null,
+            "expr": null,
+            "span": null
+        }));
+    }
+
+    #[test]
+    fn test_warning_to_json() {
+        let warning = CrispWarning::ShadowedBuiltin("+".to_string());
+        let diagnostic = WarningDiagnostic {
+            warning: &warning,
+            file: Some("x.crisp"),
+            location: Some(ErrorLocation {
+                start: Span { line: 2, column: 1 },
+                end: Span { line: 2, column: 10 },
+                expr: None
+            })
+        };
+
+        assert_eq!(diagnostic.to_json(), json!({
+            "code": "shadowed-builtin",
+            "message": "Shadowing builtin `+`.",
+            "severity": "warning",
+            "file": "x.crisp",
+            "line": 2,
+            "column": 1,
+            "expr": null,
+            "span": { "start": { "line": 2, "column": 1 }, "end": { "line": 2, "column": 10 } }
+        }));
+    }
+}
@@ -1,56 +1,469 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::{HashMap, HashSet}, io::{BufRead, Write}, path::{Path, PathBuf},
+          sync::{Arc, Mutex, OnceLock}};
 
-use crate::{error::CrispError, expr::CrispExpr, functions};
+use crate::{error::CrispError, expr::{CrispExpr, CrispFunc}, functions,
+            host::{CrispHost, NativeHost}};
 
-#[derive(Clone)]
+/// The name that is never bound to anything, so that it can be used for a
+/// value that is ignored, e.g. an argument of a [`Lambda`](CrispExpr) that
+/// isn't needed, or with `let` to evaluate something only for its effects. It
+/// may be used any number of times in the same argument list. The only
+/// exception is the REPL, which binds it to the most recent result.
+pub const PLACEHOLDER: &str = "_";
+
+#[derive(Clone, Default)]
 pub struct CrispEnv<'a> {
     pub data: HashMap<String, CrispExpr>,
-    pub parent: Option<&'a CrispEnv<'a>>
+    pub parent: Option<&'a CrispEnv<'a>>,
+    /// Names that have been bound with `const` in this scope, which can't be
+    /// rebound in it. See [`env_bind()`].
+    pub consts: HashSet<String>,
+    /// Names that have been defined as parameters with `defparam` in this
+    /// scope. See [`env_param()`].
+    pub params: HashSet<String>,
+    /// Names which can't be rebound in this scope or any inner scope except
+    /// with `shadow`, besides the builtins. See [`env_protected()`].
+    pub protected: HashSet<String>,
+    /// Whether the builtins are bound in this scope, beneath anything bound in
+    /// `data`. They aren't copied into each environment, but looked up in the
+    /// [`Registry`] when a name isn't found in `data`. Only the top-level scope
+    /// made by [`initialize_environment()`] has them.
+    pub builtins: bool,
+    /// The file that is being evaluated in this scope, if any. Nested scopes
+    /// leave this empty and defer to their parent; see [`env_source_dir()`].
+    pub file: Option<PathBuf>,
+    /// Canonicalized paths of the files that are being loaded within this
+    /// scope, outermost first. Used to detect circular loads; see
+    /// [`env_loading()`].
+    pub loading: Vec<PathBuf>,
+    /// Tests that have been registered with `deftest` in this scope, in the
+    /// order they were defined.
+    pub tests: Vec<CrispTest>,
+    /// Where `puts` and `print` write to, if not stdout. See
+    /// [`env_stdout()`].
+    pub stdout: Option<CrispWriter>,
+    /// Where `eputs` and `eprint` write to, if not stderr. See
+    /// [`env_stderr()`].
+    pub stderr: Option<CrispWriter>,
+    /// Where `gets` reads from, if not stdin. See [`env_stdin()`].
+    pub stdin: Option<CrispReader>,
+    /// Provides access to files and the process, if not the
+    /// [`NativeHost`]. See [`env_host()`].
+    pub host: Option<Arc<dyn CrispHost>>,
+    /// Functions provided by the embedder, which scripts can call with
+    /// `host-call`. See [`env_host_fn()`].
+    pub host_fns: HashMap<String, CrispHostFn>,
+    /// The command line arguments given to the program, for `args`. See
+    /// [`env_args()`].
+    pub args: Option<Vec<String>>
+}
+
+/// A stream that output can be redirected to, e.g. by
+/// `with-output-to-string`.
+pub type CrispWriter = Arc<Mutex<dyn Write + Send>>;
+
+/// A stream that input can be read from in place of stdin.
+pub type CrispReader = Arc<Mutex<dyn BufRead + Send>>;
+
+/// A function provided by the embedder for scripts to call with `host-call`,
+/// taking the arguments it was called with.
+pub type CrispHostFn = Arc<dyn Fn(&[CrispExpr]) -> Result<CrispExpr, CrispError> + Send + Sync>;
+
+impl CrispEnv<'_> {
+    /// Sends everything written to stdout within this environment (e.g. by
+    /// `puts`) to `stdout` instead. This is useful when embedding crisp, e.g.
+    /// to show a script's output somewhere other than the terminal.
+    pub fn with_stdout(mut self, stdout: CrispWriter) -> Self {
+        self.stdout = Some(stdout);
+        self
+    }
+
+    /// Sends everything written to stderr within this environment (e.g. by
+    /// `eputs`) to `stderr` instead.
+    pub fn with_stderr(mut self, stderr: CrispWriter) -> Self {
+        self.stderr = Some(stderr);
+        self
+    }
+
+    /// Reads input within this environment (e.g. by `gets`) from `stdin`
+    /// instead of stdin.
+    pub fn with_stdin(mut self, stdin: CrispReader) -> Self {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Gives code within this environment access to files and the process
+    /// through `host`, e.g. a [`SandboxHost`](crate::host::SandboxHost) to
+    /// deny it.
+    pub fn with_host(mut self, host: Arc<dyn CrispHost>) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Sets the command line arguments that the program sees with `args`.
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        self.args = Some(args.to_vec());
+        self
+    }
+
+    /// Makes `f` available to code within this environment as `name`, to be
+    /// called with `host-call`. This lets an embedding application offer
+    /// scripts a fixed set of operations, without having to expose Rust
+    /// functions to them directly.
+    pub fn with_host_fn<F>(mut self, name: &str, f: F) -> Self
+    where F: Fn(&[CrispExpr]) -> Result<CrispExpr, CrispError> + Send + Sync + 'static, {
+        self.host_fns.insert(name.to_string(), Arc::new(f));
+        self
+    }
+}
+
+/// What is known about a builtin [`Func`](CrispExpr), which can't be found
+/// from the function itself. See [`env_builtin()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrispBuiltin {
+    /// The name that the builtin is bound to. If it's bound to more than one,
+    /// this is the first.
+    pub name: &'static str,
+    pub min_args: usize,
+    /// The most arguments that the builtin takes, or `None` if there's no
+    /// limit.
+    pub max_args: Option<usize>,
+    /// What sort of builtin this is, e.g. `"Lists"`, for `help`.
+    pub category: &'static str,
+    /// A one-line description of the builtin, for `help`.
+    pub doc: &'static str
+}
+
+impl CrispBuiltin {
+    /// Describes a builtin taking from `min_args` to `max_args` arguments, in
+    /// the form given to `check_argument_error!` (so `-1` is no limit).
+    fn new(
+        name: &'static str,
+        min_args: i32,
+        max_args: i32,
+        category: &'static str,
+        doc: &'static str
+    ) -> Self {
+        CrispBuiltin {
+            name,
+            min_args: min_args.try_into().unwrap_or(0),
+            max_args: max_args.try_into().ok(),
+            category,
+            doc
+        }
+    }
+}
+
+/// The builtin functions, which are the same for every environment. See
+/// [`registry()`].
+struct Registry {
+    /// The [`Func`](CrispExpr)s, by the names they're bound to.
+    funcs: HashMap<&'static str, CrispFunc>,
+    /// The [`CrispBuiltin`]s, by the address of their `Func`s.
+    builtins: HashMap<usize, CrispBuiltin>
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// A named test registered with `deftest`, to be run by `crisp test`.
+#[derive(Clone)]
+pub struct CrispTest {
+    pub name: String,
+    pub body: Vec<CrispExpr>
 }
 
 /// Initializes and returns an environment with all of the built-in functions.
 /// This will be the top-level scope under which all other environments
-/// will nest.
+/// will nest. The builtins are looked up in the [`registry()`] as they're
+/// needed, so this is cheap.
 pub fn initialize_environment<'a>() -> CrispEnv<'a> {
-    let mut data: HashMap<String, CrispExpr> = HashMap::new();
+    CrispEnv { builtins: true, ..Default::default() }
+}
+
+/// The [`Registry`] of builtin functions, which is only built the first time
+/// that it's needed.
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(build_registry)
+}
+
+fn build_registry() -> Registry {
+    let mut funcs: HashMap<&'static str, CrispFunc> = HashMap::new();
+    let mut builtins = Vec::new();
 
+    // The category that the functions after it are listed under by `help`
+    let mut category;
+
+    // The number of arguments is the same as the function checks for with
+    // `check_argument_error!`, and the description is shown by `help`
     macro_rules! add_function {
-        ($name:expr, $rust_function:ident) => {
-            data.insert($name.to_string(), CrispExpr::Func(functions::$rust_function));
-        }
+        ($name:expr, $rust_function:ident, $min_args:expr, $max_args:expr, $doc:expr) => {{
+            let func: CrispFunc = functions::$rust_function;
+            funcs.insert($name, func);
+            builtins.push((func as usize,
+                           CrispBuiltin::new($name, $min_args, $max_args, category, $doc)));
+        }}
     }
 
-    add_function!("assert", crisp_assert);
-    add_function!("assert-false", crisp_assert_false);
-    add_function!("assert-eq", crisp_assert_eq);
-    add_function!("assert-not-eq", crisp_assert_not_eq);
+    category = "Testing";
+    add_function!("assert", crisp_assert, 1, 1, "Fails unless the value is true.");
+    add_function!("assert-false", crisp_assert_false, 1, 1, "Fails unless the value is false.");
+    add_function!("assert-eq", crisp_assert_eq, 2, -1, "Fails unless all of the values are equal.");
+    add_function!("assert-not-eq", crisp_assert_not_eq, 2, -1,
+                  "Fails if all of the values are equal.");
+    add_function!("assert-type", crisp_assert_type, 2, 2,
+                  "Fails unless the value has the named type.");
+    add_function!("assert-in-delta", crisp_assert_in_delta, 3, 3,
+                  "Fails unless two numbers are within delta of each other.");
+
+    category = "Input/Output";
+    add_function!("format", crisp_format, 1, -1, "Fills the {} in a string with values.");
+    add_function!("puts", crisp_puts, 0, -1, "Prints a formatted string and a newline.");
+    add_function!("print", crisp_print, 1, -1, "Prints a formatted string, with no newline.");
+    add_function!("write", crisp_write, 1, -1, "Prints values in a form that can be read back in.");
+    add_function!("inspect", crisp_inspect, 1, 1,
+                  "Returns a value as a string that can be read back in.");
+    add_function!("eputs", crisp_eputs, 0, -1, "Like puts, but prints to stderr.");
+    add_function!("eprint", crisp_eprint, 1, -1, "Like print, but prints to stderr.");
+    add_function!("flush", crisp_flush, 0, 0, "Flushes stdout and stderr.");
+    add_function!("gets", crisp_gets, 0, 0, "Reads a line from stdin, or returns nil at the end.");
+
+    category = "System";
+    add_function!("pid", crisp_pid, 0, 0, "Returns the ID of the process.");
+    add_function!("cwd", crisp_cwd, 0, 0, "Returns the current working directory.");
+    add_function!("chdir", crisp_chdir, 1, 1, "Changes the current working directory.");
+    add_function!("args", crisp_args, 0, 0, "Returns the command line arguments.");
+
+    category = "Files";
+    add_function!("ls", crisp_ls, 0, 1, "Lists the entries in a directory.");
+    add_function!("mkdir", crisp_mkdir, 1, 2, "Creates a directory.");
+    add_function!("rm", crisp_rm, 1, 2, "Removes a file or directory.");
+    add_function!("cp", crisp_cp, 2, 2, "Copies a file or directory.");
+    add_function!("mv", crisp_mv, 2, 2, "Moves a file or directory.");
+
+    category = "Math";
+    add_function!("+", crisp_add, 2, -1, "Adds numbers.");
+    add_function!("-", crisp_sub, 2, -1, "Subtracts numbers from the first.");
+    add_function!("*", crisp_mult, 2, -1, "Multiplies numbers.");
+    add_function!("/", crisp_div, 2, -1, "Divides the first number by the rest.");
+    add_function!("mod", crisp_mod, 2, -1,
+                  "Returns the remainder of dividing the first number by the rest.");
+    add_function!("sqrt", crisp_sqrt, 1, 1, "Returns the square root of a number.");
+    add_function!("log", crisp_log, 1, 2, "Returns the logarithm of a number, natural by default.");
+    add_function!("dot", crisp_dot, 2, 2, "Returns the dot product of two vectors.");
+    add_function!("transpose", crisp_transpose, 1, 1, "Flips a matrix over its diagonal.");
+    add_function!("matrix-mul", crisp_matrix_mul, 2, 2, "Multiplies two matrices.");
+    add_function!("reshape", crisp_reshape, 3, 3, "Arranges a list of numbers into a matrix.");
+    add_function!("num-format", crisp_num_format, 2, 2,
+                  "Formats a number as a string, according to a spec.");
+    #[cfg(feature = "decimal")]
+    add_function!("decimal", crisp_decimal, 1, 1, "Converts a number or string to a Decimal.");
+
+    category = "Comparison";
+    add_function!("=", crisp_eq, 2, -1, "Checks whether all of the values are equal.");
+    add_function!("!=", crisp_not_eq, 2, -1, "Checks whether all of the values are different.");
+    add_function!("secure-eq", crisp_secure_eq, 2, 2, "Compares two strings in constant time.");
+    add_function!("hash", crisp_hash, 1, 1, "Returns a hash of any value.");
+    add_function!(">", crisp_gt, 2, -1, "Checks whether numbers are in descending order.");
+    add_function!(">=", crisp_gte, 2, -1,
+                  "Checks whether numbers are in descending order, or equal.");
+    add_function!("<", crisp_lt, 2, -1, "Checks whether numbers are in ascending order.");
+    add_function!("<=", crisp_lte, 2, -1,
+                  "Checks whether numbers are in ascending order, or equal.");
+    add_function!("compare", crisp_compare, 2, 2, "Returns -1, 0 or 1, ordering any two values.");
+    add_function!("!", crisp_not, 1, -1, "Inverts one or more bools.");
+
+    category = "Lists";
+    add_function!("list", crisp_list, 0, -1, "Returns its arguments as a list.");
+    add_function!("range", crisp_range, 2, 3, "Returns the numbers from start to just before end.");
+    add_function!("range-inclusive", crisp_range_inclusive, 2, 3,
+                  "Returns the numbers from start to end.");
+    add_function!("cons", crisp_cons, 2, 2, "Adds an element to the front of a list.");
+    add_function!("map", crisp_map, 2, -1, "Calls a function with each element of a list.");
+    add_function!("foldl", crisp_foldl, 3, 3,
+                  "Combines the elements of a list, from a starting value.");
+    add_function!("foldl1", crisp_foldl1, 2, 2,
+                  "Combines the elements of a list, starting with the first.");
+    add_function!("iterate", crisp_iterate, 3, 3,
+                  "Returns n values, each made from the last by a function.");
+    add_function!("unfold", crisp_unfold, 4, 4, "Builds a list from a seed value.");
+    add_function!("take", crisp_take, 2, 2, "Returns the first n elements of a list.");
+    add_function!("drop", crisp_drop, 2, 2, "Returns a list without its first n elements.");
+    add_function!("slice", crisp_slice, 3, 3,
+                  "Returns the elements of a list between two indices.");
+    add_function!("chunks", crisp_chunks, 2, 2, "Splits a list into lists of n elements.");
+    add_function!("windows", crisp_windows, 2, 2,
+                  "Returns every run of n consecutive elements of a list.");
+    add_function!("split-at", crisp_split_at, 2, 2, "Splits a list in two after n elements.");
+    add_function!("reverse", crisp_reverse, 1, 1, "Reverses a list.");
+    add_function!("sort", crisp_sort, 1, 1, "Sorts a list in ascending order.");
+    add_function!("sort-by", crisp_sort_by, 2, 2, "Sorts a list by a key or comparator function.");
+    add_function!("min-by", crisp_min_by, 2, 2, "Returns the element with the smallest key.");
+    add_function!("max-by", crisp_max_by, 2, 2, "Returns the element with the largest key.");
+    add_function!("extent", crisp_extent, 1, 1,
+                  "Returns the smallest and largest elements of a list.");
+    add_function!("diff", crisp_diff, 2, 2, "Returns the edits that turn one list into another.");
+
+    category = "Results";
+    add_function!("ok", crisp_ok, 1, 1, "Wraps the value of an operation that succeeded.");
+    add_function!("err", crisp_err, 1, 1, "Wraps the error of an operation that failed.");
+    add_function!("some", crisp_some, 1, 1, "Wraps a value that may have been missing.");
+    add_function!("none", crisp_none, 0, 0, "Stands for a missing value.");
+    add_function!("ok?", crisp_is_ok, 1, 1, "Checks whether a value was made with ok.");
+    add_function!("err?", crisp_is_err, 1, 1, "Checks whether a value was made with err.");
+    add_function!("some?", crisp_is_some, 1, 1, "Checks whether a value was made with some.");
+    add_function!("none?", crisp_is_none, 1, 1, "Checks whether a value was made with none.");
+    add_function!("unwrap-or", crisp_unwrap_or, 2, 2,
+                  "Returns the value in an ok or some, or a default.");
+    add_function!("and-then", crisp_and_then, 2, 2,
+                  "Calls a function with the value in an ok or some.");
+    add_function!("or-else", crisp_or_else, 2, 2,
+                  "Calls a function to recover from an err or none.");
+
+    category = "Atoms";
+    add_function!("atom", crisp_atom, 1, 1, "Creates a reference cell holding a value.");
+    add_function!("deref", crisp_deref, 1, 1, "Returns the value of an atom.");
+    add_function!("reset!", crisp_reset, 2, 2, "Sets the value of an atom.");
+    add_function!("swap!", crisp_swap, 2, -1, "Updates the value of an atom with a function.");
+
+    category = "Heaps";
+    add_function!("heap-new", crisp_heap_new, 0, 1,
+                  "Creates a priority queue, smallest value first.");
+    add_function!("heap-push!", crisp_heap_push, 2, 2, "Adds a value to a heap.");
+    add_function!("heap-pop!", crisp_heap_pop, 1, 1,
+                  "Removes and returns the smallest value of a heap.");
+    add_function!("heap-peek", crisp_heap_peek, 1, 1, "Returns the smallest value of a heap.");
+
+    category = "Queues";
+    add_function!("queue", crisp_queue, 0, -1, "Creates a double-ended queue.");
+    add_function!("enqueue!", crisp_enqueue, 2, 2, "Adds a value to the back of a queue.");
+    add_function!("dequeue!", crisp_dequeue, 1, 1,
+                  "Removes and returns the value at the front of a queue.");
+    add_function!("push-front!", crisp_push_front, 2, 2, "Adds a value to the front of a queue.");
+    add_function!("pop-back!", crisp_pop_back, 1, 1,
+                  "Removes and returns the value at the back of a queue.");
 
-    add_function!("format", crisp_format);
-    add_function!("puts", crisp_puts);
-    add_function!("print", crisp_print);
+    category = "Lazy";
+    add_function!("force", crisp_force, 1, 1, "Evaluates a delay, once.");
+    add_function!("next", crisp_next, 1, 1, "Returns the next value of a generator.");
+    add_function!("yield", crisp_yield, 1, 1, "Hands a value back from a generator.");
 
-    add_function!("+", crisp_add);
-    add_function!("-", crisp_sub);
-    add_function!("*", crisp_mult);
-    add_function!("/", crisp_div);
-    add_function!("mod", crisp_mod);
+    category = "Concurrency";
+    add_function!("await", crisp_await, 1, 1, "Waits for a task to finish and returns its result.");
+    add_function!("join", crisp_join, 1, -1,
+                  "Waits for tasks to finish and returns their results.");
+    add_function!("par-map", crisp_par_map, 2, 2, "Like map, but across multiple threads.");
+    add_function!("chan", crisp_chan, 0, 0, "Creates a channel for passing values between tasks.");
+    add_function!("send!", crisp_send, 2, 2, "Sends a value over a channel.");
+    add_function!("recv!", crisp_recv, 1, 1, "Receives the next value from a channel.");
+    add_function!("close!", crisp_close, 1, 1, "Closes a channel.");
 
-    add_function!("=", crisp_eq);
-    add_function!("!=", crisp_not_eq);
-    add_function!(">", crisp_gt);
-    add_function!(">=", crisp_gte);
-    add_function!("<", crisp_lt);
-    add_function!("<=", crisp_lte);
-    add_function!("!", crisp_not);
-    add_function!("&&", crisp_and);
-    add_function!("||", crisp_or);
+    category = "Meta";
+    add_function!("read", crisp_read, 1, 1, "Parses a string of code without evaluating it.");
+    add_function!("eval", crisp_eval, 1, 2, "Evaluates an expression.");
+    add_function!("serialize", crisp_serialize, 1, 1,
+                  "Converts a value to a string that deserialize reads back.");
+    add_function!("deserialize", crisp_deserialize, 1, 1,
+                  "Reads a value back from a string made by serialize.");
+    add_function!("env-keys", crisp_env_keys, 0, 0,
+                  "Returns the names of everything that is bound.");
+    add_function!("bound?", crisp_bound, 1, 1, "Checks whether a name is bound.");
+    add_function!("unbind!", crisp_unbind, 1, 1, "Removes a name from the current scope.");
+    add_function!("arity", crisp_arity, 1, 1,
+                  "Returns the fewest and most arguments a function takes.");
+    add_function!("fn-name", crisp_fn_name, 1, 1, "Returns the name of a function.");
+    add_function!("fn-args", crisp_fn_args, 1, 1,
+                  "Returns the names of the arguments of a lambda.");
+    add_function!("make-env", crisp_make_env, 0, 0,
+                  "Creates a new environment with only the builtins.");
+    add_function!("env-get", crisp_env_get, 2, 2, "Gets the value of a name in an environment.");
+    add_function!("env-set!", crisp_env_set, 3, 3, "Binds a name to a value in an environment.");
+    add_function!("eval-in", crisp_eval_in, 2, 2, "Evaluates an expression in an environment.");
+    add_function!("host-call", crisp_host_call, 1, 2,
+                  "Calls a function provided by the embedding application.");
+    add_function!("break", crisp_break, 0, 0, "Drops into the debugger, if it's enabled.");
+    add_function!("help", crisp_help, 0, 1, "Lists the builtins, by category.");
+    #[cfg(feature = "unsafe-ffi")]
+    add_function!("extern-load", crisp_extern_load, 1, 1,
+                  "Loads functions from a native extension.");
 
-    add_function!("cons", crisp_cons);
-    add_function!("map", crisp_map);
-    add_function!("foldl", crisp_foldl);
-    add_function!("foldl1", crisp_foldl1);
+    category = "Strings";
+    add_function!("lines", crisp_lines, 1, 1, "Splits a string into lines.");
+    add_function!("words", crisp_words, 1, 1, "Splits a string at whitespace.");
+    add_function!("unlines", crisp_unlines, 1, 1, "Joins strings into lines.");
+    add_function!("unwords", crisp_unwords, 1, 1, "Joins strings with spaces.");
+    add_function!("str-concat", crisp_str_concat, 1, -1, "Joins strings together.");
+    add_function!("str-join", crisp_str_join, 2, 2, "Joins strings with a separator.");
+    add_function!("kebab->camel", crisp_kebab_to_camel, 1, 1, "Converts kebab-case to camelCase.");
+    add_function!("camel->snake", crisp_camel_to_snake, 1, 1, "Converts camelCase to snake_case.");
+    add_function!("capitalize", crisp_capitalize, 1, 1,
+                  "Uppercases the first character of a string.");
+    add_function!("titlecase", crisp_titlecase, 1, 1, "Capitalizes each word of a string.");
+
+    #[cfg(feature = "datetime")]
+    {
+        category = "Time";
+        add_function!("now", crisp_now, 0, 0,
+                      "Returns the current time, in seconds since the epoch.");
+        add_function!("time-format", crisp_time_format, 2, 2,
+                      "Formats a time with a strftime-style pattern.");
+        add_function!("time-parse", crisp_time_parse, 2, 2,
+                      "Parses a time with a strftime-style pattern.");
+        add_function!("date-add", crisp_date_add, 3, 3, "Adds an amount of time to a time.");
+        add_function!("date-diff", crisp_date_diff, 2, 3, "Returns the time between two times.");
+    }
+
+    category = "Data";
+    add_function!("csv-parse", crisp_csv_parse, 1, 2, "Parses CSV into a list of rows.");
+    add_function!("csv-write", crisp_csv_write, 1, 1, "Formats a list of rows as CSV.");
+
+    category = "Random";
+    add_function!("uuid", crisp_uuid, 0, 0, "Generates a random UUID.");
+    add_function!("random-bytes", crisp_random_bytes, 1, 1,
+                  "Generates secure random bytes, hex-encoded.");
+
+    category = "Encoding";
+    add_function!("base64-encode", crisp_base64_encode, 1, 1, "Encodes a string as base64.");
+    add_function!("base64-decode", crisp_base64_decode, 1, 1, "Decodes a base64 string.");
+    add_function!("hex-encode", crisp_hex_encode, 1, 1, "Encodes a string as hexadecimal.");
+    add_function!("hex-decode", crisp_hex_decode, 1, 1, "Decodes a hexadecimal string.");
+    #[cfg(feature = "digest")]
+    add_function!("md5", crisp_md5, 1, 1, "Returns the MD5 digest of a string, hex-encoded.");
+    #[cfg(feature = "digest")]
+    add_function!("sha256", crisp_sha256, 1, 1,
+                  "Returns the SHA-256 digest of a string, hex-encoded.");
+    #[cfg(feature = "digest")]
+    add_function!("sha512", crisp_sha512, 1, 1,
+                  "Returns the SHA-512 digest of a string, hex-encoded.");
+
+    let mut by_address = HashMap::new();
+    for (address, builtin) in builtins {
+        by_address.entry(address).or_insert(builtin);
+    }
+
+    Registry { funcs, builtins: by_address }
+}
+
+/// Finds the builtin function bound to `name`, if there is one.
+pub fn builtin_func(name: &str) -> Option<CrispFunc> {
+    registry().funcs.get(name).copied()
+}
+
+/// The names of all of the builtin functions, in no particular order.
+pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+    registry().funcs.keys().copied()
+}
+
+/// Finds the [`CrispBuiltin`] describing `func`, or `None` if it isn't one of
+/// the builtins.
+pub fn env_builtin(func: CrispFunc) -> Option<&'static CrispBuiltin> {
+    registry().builtins.get(&(func as usize))
+}
 
-    CrispEnv { data, parent: None }
+/// All of the [`CrispBuiltin`]s, sorted by name.
+pub fn env_builtins() -> Vec<&'static CrispBuiltin> {
+    let mut builtins: Vec<_> = registry().builtins.values().collect();
+    builtins.sort_by_key(|builtin| builtin.name);
+    builtins
 }
 
 /// Searches for a key `name` within the scope `env` or any outer scope
@@ -59,14 +472,213 @@ pub fn env_get(name: &str, env: &CrispEnv) -> Option<CrispExpr> {
     match env.data.get(name) {
         Some(expr) => Some(expr.clone()),
         None => {
-            match &env.parent {
-                Some(parent) => env_get(name, &parent),
-                None => None
+            let builtin = if env.builtins { builtin_func(name) } else { None };
+            match (builtin, &env.parent) {
+                (Some(func), _) => Some(CrispExpr::Func(func)),
+                (None, Some(parent)) => env_get(name, parent),
+                (None, None) => None
             }
         }
     }
 }
 
+/// Binds `name` to `value` in the scope `env`, e.g. for `let`. Fails if `name`
+/// is a constant in this scope (it may still be shadowed in an inner scope),
+/// or if it's a builtin; see [`env_shadow()`].
+pub fn env_bind(name: String, value: CrispExpr, env: &mut CrispEnv) -> Result<(), CrispError> {
+    if env_protected(&name, env) {
+        return standard_error!(format!("Can't rebind builtin: {} (use `shadow` if you mean to)", name));
+    }
+
+    env_shadow(name, value, env)
+}
+
+/// Binds `name` to `value` in the scope `env` like [`env_bind()`], even if
+/// `name` is a builtin, e.g. for `shadow`. Constants still can't be rebound.
+/// The [`PLACEHOLDER`] is never bound.
+pub fn env_shadow(name: String, value: CrispExpr, env: &mut CrispEnv) -> Result<(), CrispError> {
+    if name == PLACEHOLDER {
+        return Ok(());
+    }
+
+    if env.consts.contains(&name) {
+        return standard_error!(format!("Can't rebind constant: {}", name));
+    }
+
+    env.data.insert(name, value);
+    Ok(())
+}
+
+/// Whether `name` is a parameter defined with `defparam` in the scope `env` or
+/// one of its outer scopes, so that it can be overridden with `binding`.
+pub fn env_param(name: &str, env: &CrispEnv) -> bool {
+    env.params.contains(name) || env.parent.is_some_and(|parent| env_param(name, parent))
+}
+
+/// Whether `name` is a builtin which is protected from being rebound within the
+/// scope `env`.
+pub fn env_protected(name: &str, env: &CrispEnv) -> bool {
+    env.protected.contains(name) || (env.builtins && builtin_func(name).is_some())
+        || env.parent.is_some_and(|parent| env_protected(name, parent))
+}
+
+/// Finds the directory containing the file that is being evaluated within
+/// the scope `env`, searching outer scopes if need be. Relative paths in e.g.
+/// `embed` are resolved against this. If no file is being evaluated (such as
+/// in the REPL), this is the current working directory.
+pub fn env_source_dir(env: &CrispEnv) -> PathBuf {
+    match (&env.file, &env.parent) {
+        (Some(file), _) => file.parent().map(|dir| dir.to_path_buf()).unwrap_or_default(),
+        (None, Some(parent)) => env_source_dir(parent),
+        (None, None) => PathBuf::from(".")
+    }
+}
+
+/// Finds the file that is being evaluated within the scope `env`, searching
+/// outer scopes if need be. There is none e.g. in the REPL.
+pub fn env_file<'a>(env: &'a CrispEnv) -> Option<&'a Path> {
+    match (&env.file, &env.parent) {
+        (Some(file), _) => Some(file),
+        (None, Some(parent)) => env_file(parent),
+        (None, None) => None
+    }
+}
+
+/// Collects the files that are currently being loaded in the scope `env` and
+/// all of its outer scopes, outermost first.
+pub fn env_loading(env: &CrispEnv) -> Vec<PathBuf> {
+    let mut loading = match &env.parent {
+        Some(parent) => env_loading(parent),
+        None => Vec::new()
+    };
+
+    loading.extend(env.loading.iter().cloned());
+    loading
+}
+
+/// Finds where output from `puts` and `print` within the scope `env` should
+/// go, searching outer scopes if need be. `None` means stdout.
+pub fn env_stdout(env: &CrispEnv) -> Option<CrispWriter> {
+    match (&env.stdout, &env.parent) {
+        (Some(stdout), _) => Some(stdout.clone()),
+        (None, Some(parent)) => env_stdout(parent),
+        (None, None) => None
+    }
+}
+
+/// Finds where output from `eputs` and `eprint` within the scope `env` should
+/// go, searching outer scopes if need be. `None` means stderr.
+pub fn env_stderr(env: &CrispEnv) -> Option<CrispWriter> {
+    match (&env.stderr, &env.parent) {
+        (Some(stderr), _) => Some(stderr.clone()),
+        (None, Some(parent)) => env_stderr(parent),
+        (None, None) => None
+    }
+}
+
+/// Finds where input for `gets` within the scope `env` should come from,
+/// searching outer scopes if need be. `None` means stdin.
+pub fn env_stdin(env: &CrispEnv) -> Option<CrispReader> {
+    match (&env.stdin, &env.parent) {
+        (Some(stdin), _) => Some(stdin.clone()),
+        (None, Some(parent)) => env_stdin(parent),
+        (None, None) => None
+    }
+}
+
+/// Finds the host that gives code within the scope `env` access to files and
+/// the process, searching outer scopes if need be.
+pub fn env_host(env: &CrispEnv) -> Arc<dyn CrispHost> {
+    match (&env.host, &env.parent) {
+        (Some(host), _) => host.clone(),
+        (None, Some(parent)) => env_host(parent),
+        (None, None) => Arc::new(NativeHost)
+    }
+}
+
+/// Finds the command line arguments given to the program running in the scope
+/// `env`, searching outer scopes if need be. There are none if they were
+/// never set.
+pub fn env_args(env: &CrispEnv) -> Vec<String> {
+    match (&env.args, &env.parent) {
+        (Some(args), _) => args.clone(),
+        (None, Some(parent)) => env_args(parent),
+        (None, None) => Vec::new()
+    }
+}
+
+/// Finds the host function `name` registered for the scope `env` or one of its
+/// outer scopes.
+pub fn env_host_fn(name: &str, env: &CrispEnv) -> Option<CrispHostFn> {
+    match (env.host_fns.get(name), &env.parent) {
+        (Some(f), _) => Some(f.clone()),
+        (None, Some(parent)) => env_host_fn(name, parent),
+        (None, None) => None
+    }
+}
+
+/// Flattens the scope `env` and all of its outer scopes into a single
+/// standalone environment, with inner bindings taking precedence. This is
+/// used to give code running on another thread its own copy of everything
+/// that was visible where it was started.
+pub fn env_snapshot(env: &CrispEnv) -> CrispEnv<'static> {
+    let mut snapshot = match &env.parent {
+        Some(parent) => env_snapshot(parent),
+        None => CrispEnv::default()
+    };
+
+    snapshot.data.extend(env.data.iter().map(|(k, v)| (k.clone(), v.clone())));
+    snapshot.consts.extend(env.consts.iter().cloned());
+    snapshot.params.extend(env.params.iter().cloned());
+    snapshot.protected.extend(env.protected.iter().cloned());
+    snapshot.builtins |= env.builtins;
+    snapshot.loading.extend(env.loading.iter().cloned());
+    if env.file.is_some() {
+        snapshot.file = env.file.clone();
+    }
+    if env.stdout.is_some() {
+        snapshot.stdout = env.stdout.clone();
+    }
+    if env.stderr.is_some() {
+        snapshot.stderr = env.stderr.clone();
+    }
+    if env.stdin.is_some() {
+        snapshot.stdin = env.stdin.clone();
+    }
+    if env.host.is_some() {
+        snapshot.host = env.host.clone();
+    }
+    if env.args.is_some() {
+        snapshot.args = env.args.clone();
+    }
+    snapshot.host_fns.extend(env.host_fns.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    snapshot
+}
+
+/// Creates an environment with only the builtins defined, like
+/// [`initialize_environment()`], but which runs on the same host as the scope
+/// `env`, with the same IO handles and host functions. None of the names bound
+/// in `env` are carried over.
+pub fn env_fresh(env: &CrispEnv) -> CrispEnv<'static> {
+    let mut fresh = initialize_environment().with_host(env_host(env));
+    fresh.stdout = env_stdout(env);
+    fresh.stderr = env_stderr(env);
+    fresh.stdin = env_stdin(env);
+    fresh.host_fns = env_host_fns(env);
+
+    fresh
+}
+
+/// Collects the host functions available within the scope `env`, for
+/// [`env_fresh()`]. Those of inner scopes take precedence.
+fn env_host_fns(env: &CrispEnv) -> HashMap<String, CrispHostFn> {
+    let mut host_fns = env.parent.map(env_host_fns).unwrap_or_default();
+    host_fns.extend(env.host_fns.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    host_fns
+}
+
 /// When a [`Lambda`](CrispExpr) is called, this routine is called, creating a
 /// new scope.
 ///
@@ -83,7 +695,7 @@ pub fn env_get(name: &str, env: &CrispEnv) -> Option<CrispExpr> {
 /// The [`CrispEnv`] for this scope, or a [`CrispError`] if there were any
 /// problems.
 pub fn env_new_for_lambda<'a>(
-    lambda_args: Rc<CrispExpr>,
+    lambda_args: &CrispExpr,
     arg_passed_exprs: &[CrispExpr],
     parent_env: &'a mut CrispEnv
 ) -> Result<CrispEnv<'a>, CrispError> {
@@ -97,23 +709,34 @@ pub fn env_new_for_lambda<'a>(
     // Insert the inputs to the arguments into the `env.data` for this scope
     let mut data: HashMap<String, CrispExpr> = HashMap::new();
     for (name, value) in arg_names.iter().zip(arg_passed_exprs.iter()) {
-        data.insert(name.clone(), value.clone());
+        if *name != PLACEHOLDER {
+            data.insert(name.to_string(), value.clone());
+        }
     }
 
-    Ok(CrispEnv { data, parent: Some(parent_env) })
+    Ok(env_new_scope(data, parent_env))
+}
+
+/// Creates a new scope nested inside of `parent_env`, with the bindings in
+/// `data`.
+pub fn env_new_scope<'a>(
+    data: HashMap<String, CrispExpr>,
+    parent_env: &'a CrispEnv
+) -> CrispEnv<'a> {
+    CrispEnv { data, parent: Some(parent_env), ..Default::default() }
 }
 
-/// Given a reference counted pointer to a [`List`](CrispExpr) full of
-/// [`Symbol`](CrispExpr)s, processes it into a [`Vec<String>`].
-fn parse_symbol_list(list: Rc<CrispExpr>) -> Result<Vec<String>, CrispError> {
-    let arg_names = match list.as_ref() {
-        CrispExpr::List(list) => Ok(list.clone()),
+/// Given a [`List`](CrispExpr) full of [`Symbol`](CrispExpr)s, processes it
+/// into a [`Vec`] of their names.
+fn parse_symbol_list(list: &CrispExpr) -> Result<Vec<&str>, CrispError> {
+    let arg_names = match list {
+        CrispExpr::List(list) => Ok(list),
         _ => parse_error!("Lambda expected a list of arguments.")
     }?;
 
     arg_names.iter().map(|arg| {
         match arg {
-            CrispExpr::Symbol(name) => Ok(name.clone()),
+            CrispExpr::Symbol(name) => Ok(name.as_ref()),
             _ => parse_error!("Lambda expected symbols in the argument list.")
         }
     }).collect()
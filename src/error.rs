@@ -1,54 +1,94 @@
-use std::fmt;
+use std::{fmt, panic::{self, AssertUnwindSafe}};
 
 use colored::*;
 
-#[derive(PartialEq)]
+use crate::color;
+
+#[derive(Clone, PartialEq)]
 pub enum CrispError {
     ArgumentError(i32, i32),
+    AssertionError(String),
+    IndexError(String),
+    /// Something went wrong within crisp itself, rather than in the program
+    /// being run. See [`catch_panic()`].
+    InternalError(String),
     LoadError(String),
+    MathError(String),
+    NotCallableError(String),
     ParseError(String),
     StandardError(String),
     TypeError(String)
 }
 
-macro_rules! format_error {
-    ($error_type:ident, $fmt:expr) => {
-        format!(concat!("[{}] ", $fmt), stringify!($error_type).bright_red()).bold()
-    };
-
-    ($error_type:ident, $fmt:expr, $msg:expr) => {
-        format!(concat!("[{}] ", $fmt), stringify!($error_type).bright_red(), $msg).bold()
-    };
-
-    ($error_type:ident, $fmt:expr, $msg:expr, $($arg:expr),*) => {
-        format!(concat!("[{}] ", $fmt), stringify!($error_type).bright_red(), $msg, $($arg),*).bold()
-    };
-}
+impl CrispError {
+    /// The name of this kind of error, e.g. `"TypeError"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrispError::ArgumentError(_, _) => "ArgumentError",
+            CrispError::AssertionError(_) => "AssertionError",
+            CrispError::IndexError(_) => "IndexError",
+            CrispError::InternalError(_) => "InternalError",
+            CrispError::LoadError(_) => "LoadError",
+            CrispError::MathError(_) => "MathError",
+            CrispError::NotCallableError(_) => "NotCallableError",
+            CrispError::ParseError(_) => "ParseError",
+            CrispError::StandardError(_) => "StandardError",
+            CrispError::TypeError(_) => "TypeError"
+        }
+    }
 
-impl fmt::Display for CrispError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let error_message = match self {
+    /// Describes what went wrong, without the name of the error.
+    pub fn message(&self) -> String {
+        match self {
             CrispError::ArgumentError(min, max) => {
                 if min == max {
-                    format_error!(ArgumentError, "{} arguments expected.", min)
+                    format!("{} arguments expected.", min)
                 } else if min == &0 && max == &0 {
-                    format_error!(ArgumentError, "No arguments expected.")
+                    "No arguments expected.".to_string()
                 } else if max < &0 {
-                    format_error!(ArgumentError, "{}+ arguments expected.", min)
+                    format!("{}+ arguments expected.", min)
                 } else if min <= &0 {
-                    format_error!(ArgumentError, "Up to {} arguments expected.", max)
+                    format!("Up to {} arguments expected.", max)
                 } else {
-                    format_error!(ArgumentError, "{} to {} arguments expected.", min, max)
+                    format!("{} to {} arguments expected.", min, max)
                 }
             },
 
-            CrispError::LoadError(name) => format_error!(LoadError, "No such file or directory: {}", name),
-            CrispError::ParseError(msg) => format_error!(ParseError, "{}", msg),
-            CrispError::StandardError(msg) => format_error!(StandardError, "{}", msg),
-            CrispError::TypeError(expected) => format_error!(TypeError, "Expected {}.", expected)
-        };
+            CrispError::AssertionError(msg) => msg.clone(),
+            CrispError::IndexError(msg) => msg.clone(),
+            CrispError::InternalError(msg) => format!("Internal error (this is a bug): {}", msg),
+            CrispError::LoadError(name) => format!("No such file or directory: {}", name),
+            CrispError::MathError(msg) => msg.clone(),
+            CrispError::NotCallableError(head) => {
+                format!("Not a function or keyword: {}", head)
+            },
+            CrispError::ParseError(msg) => msg.clone(),
+            CrispError::StandardError(msg) => msg.clone(),
+            CrispError::TypeError(expected) => format!("Expected {}.", expected)
+        }
+    }
+}
+
+/// Runs `f`, raising an `InternalError` if it panics rather than letting the
+/// panic unwind any further. This is a last line of defense for callers which
+/// embed crisp, so that a bug in the interpreter doesn't take them down with
+/// it; the interpreter itself should never panic.
+pub fn catch_panic<T>(f: impl FnOnce() -> Result<T, CrispError>) -> Result<T, CrispError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                             .or_else(|| payload.downcast_ref::<String>().cloned())
+                             .unwrap_or_else(|| "Panicked.".to_string());
+        Err(CrispError::InternalError(message))
+    })
+}
 
-        write!(f, "{}", error_message)
+impl fmt::Display for CrispError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if color::plain_errors() {
+            write!(f, "[{}] {}", self.name(), self.message())
+        } else {
+            write!(f, "{}", format!("[{}] {}", self.name().bright_red(), self.message()).bold())
+        }
     }
 }
 
@@ -112,8 +152,12 @@ macro_rules! generate_unwrapped_error_macro {
     }
 }
 
+generate_error_macro!(assertion_error, AssertionError);
+generate_error_macro!(index_error, IndexError);
 generate_error_macro!(load_error, LoadError);
+generate_error_macro!(math_error, MathError);
 generate_error_macro!(parse_error, ParseError);
 generate_unwrapped_error_macro!(parse_error_unwrapped, ParseError);
 generate_error_macro!(standard_error, StandardError);
+generate_unwrapped_error_macro!(standard_error_unwrapped, StandardError);
 generate_error_macro!(type_error, TypeError);
@@ -1,15 +1,450 @@
-use crate::{error::CrispError, expr::{CrispExpr, CrispLambda},
-            env::{CrispEnv, env_get, env_new_for_lambda}, keywords::eval_keyword};
+use crate::{ast::SourceMap, debugger, error::CrispError, expr::{CrispExpr, CrispLambda},
+            env::{CrispEnv, env_file, env_get, env_new_for_lambda, env_new_scope},
+            keywords::eval_keyword, reader::Span};
+
+use std::{cell::RefCell, collections::{HashMap, HashSet, VecDeque}, env, ffi::OsString, path::PathBuf,
+          sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}}};
+
+/// Whether a list must start with a function or keyword. See
+/// [`configure_strict_calls()`].
+static STRICT_CALLS: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The most recent error, along with where it was raised. See
+    /// [`take_error_source()`].
+    static ERROR_SOURCE: RefCell<Option<(CrispError, ErrorSource)>> = const { RefCell::new(None) };
+
+    /// The [`SourceMap`]s of the code being evaluated on this thread: that of
+    /// the top-level expression, then that of each [`Lambda`](CrispExpr)
+    /// being called, innermost last. See [`with_source_map()`].
+    static SOURCE_MAPS: RefCell<Vec<Arc<SourceMap>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The innermost [`List`](CrispExpr) expression which raised an error, and the
+/// file that it was read from (or that was being evaluated at the time), if
+/// any. `span` gives its first and last positions in that file, if it was
+/// read from source code that's been [mapped](with_source_map).
+#[derive(Debug, PartialEq)]
+pub struct ErrorSource {
+    pub expr: CrispExpr,
+    pub file: Option<PathBuf>,
+    pub span: Option<(Span, Span)>
+}
+
+/// Configures whether calls are strict for the rest of the program. Normally
+/// a [`List`](CrispExpr) that doesn't start with a function or keyword, such
+/// as `(5 1 2)`, evaluates to a `List` of its evaluated items. With strict
+/// calls, this raises a `NotCallableError` instead, so that mistakes such as
+/// calling a variable that isn't a function don't go unnoticed. This is the
+/// case if `strict` is set (e.g. by the `--strict-calls` flag), or if the
+/// `CRISP_STRICT_CALLS` environment variable is set to a non-empty value.
+pub fn configure_strict_calls(strict: bool) {
+    STRICT_CALLS.store(use_strict_calls(strict, env::var_os("CRISP_STRICT_CALLS")),
+                       Ordering::Relaxed);
+}
+
+/// Decides whether calls should be strict. See [`configure_strict_calls()`].
+fn use_strict_calls(strict: bool, strict_env: Option<OsString>) -> bool {
+    strict || strict_env.is_some_and(|value| !value.is_empty())
+}
+
+/// The `NotCallableError` for a list starting with `head`, or `None` if calls
+/// aren't strict. `value` is what `head` evaluated to, if it's different.
+fn not_callable(head: &CrispExpr, value: Option<&CrispExpr>) -> Option<CrispError> {
+    if !STRICT_CALLS.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    Some(CrispError::NotCallableError(match value {
+        Some(value) => format!("{} (which is {})", head.inspect(), value.inspect()),
+        None => head.inspect()
+    }))
+}
+
+/// Takes the [`ErrorSource`] of the most recent error on this thread, so that
+/// the error can be traced back to the source code.
+pub fn take_error_source() -> Option<ErrorSource> {
+    ERROR_SOURCE.with(|source| source.borrow_mut().take()).map(|(_, source)| source)
+}
+
+/// Records `expr`, evaluated within `env`, as the source of the error `e`,
+/// unless `e` is just being passed up from an expression nested inside of
+/// `expr`.
+fn record_error_source(e: &CrispError, expr: &CrispExpr, env: &CrispEnv) {
+    ERROR_SOURCE.with(|source| {
+        let mut source = source.borrow_mut();
+
+        if source.as_ref().is_none_or(|(recorded, _)| recorded != e) {
+            let (file, span) = match find_source(expr) {
+                Some((file, span)) => (file, Some(span)),
+                None => (env_file(env).map(|file| file.to_path_buf()), None)
+            };
+
+            *source = Some((e.clone(), ErrorSource { expr: expr.clone(), file, span }));
+        }
+    });
+}
+
+/// Calls `f`, during which errors raised by the lists in `map` are traced back
+/// to where they were read from (see [`take_error_source()`]). The code that
+/// `map` was made for must outlive the call.
+pub fn with_source_map<T>(map: &Arc<SourceMap>, f: impl FnOnce() -> T) -> T {
+    /// Removes the map again, even if `f` panics.
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            SOURCE_MAPS.with(|maps| maps.borrow_mut().pop());
+        }
+    }
+
+    SOURCE_MAPS.with(|maps| maps.borrow_mut().push(map.clone()));
+    let _guard = Guard;
+    f()
+}
+
+/// Finds where `expr` was read from, in the [`SourceMap`]s of the code being
+/// evaluated.
+///
+/// # Returns
+///
+/// The file, if any, and the first and last positions of `expr` in it.
+fn find_source(expr: &CrispExpr) -> Option<(Option<PathBuf>, (Span, Span))> {
+    SOURCE_MAPS.with(|maps| maps.borrow().iter().rev().find_map(|map| {
+        map.get(expr).map(|span| (map.file.clone(), span))
+    }))
+}
+
+/// Makes a [`SourceMap`] for code that was cloned from code being evaluated,
+/// e.g. the body of a [`Lambda`](CrispExpr), given each of the `(original,
+/// clone)` pairs that it was made of. Returns `None` if none of it was read
+/// from source code that's been [mapped](with_source_map).
+pub fn map_clones<'a>(
+    clones: impl IntoIterator<Item = (&'a CrispExpr, &'a CrispExpr)>
+) -> Option<Arc<SourceMap>> {
+    let mut cloned = SourceMap::new();
+
+    SOURCE_MAPS.with(|maps| {
+        let maps = maps.borrow();
+        if maps.is_empty() {
+            return;
+        }
+
+        for (original, clone) in clones {
+            cloned.map_clone(original, clone, &maps);
+        }
+    });
+
+    (!cloned.is_empty()).then(|| Arc::new(cloned))
+}
+
+/// The body of a [`Generator`](CrispExpr), partway through being evaluated.
+/// See [`resume_generator()`].
+pub struct GeneratorBody {
+    body: CrispExpr,
+    /// The generator's own scope, e.g. for `let`s in its body.
+    scope: Bindings,
+    /// The expressions that were being evaluated when the body last yielded,
+    /// outermost first.
+    frames: Vec<Frame>
+}
+
+impl GeneratorBody {
+    /// A generator body which evaluates `exprs` in order.
+    pub fn new(exprs: &[CrispExpr]) -> Self {
+        let body = CrispExpr::List(std::iter::once(sym!("do")).chain(exprs.iter().cloned())
+                                                               .collect());
+        Self { body, scope: Bindings::default(), frames: Vec::new() }
+    }
+}
+
+/// What has been bound in a scope, kept while a generator's body is paused.
+#[derive(Default)]
+struct Bindings {
+    data: HashMap<String, CrispExpr>,
+    consts: HashSet<String>
+}
+
+impl Bindings {
+    /// Takes everything that has been bound in `env`.
+    fn take(env: &mut CrispEnv) -> Self {
+        Self { data: std::mem::take(&mut env.data), consts: std::mem::take(&mut env.consts) }
+    }
+
+    /// Binds everything in `env` again.
+    fn restore(self, env: &mut CrispEnv) {
+        env.data = self.data;
+        env.consts = self.consts;
+    }
+}
+
+/// An expression that was being evaluated when a generator's body yielded.
+#[derive(Default)]
+struct Frame {
+    /// What each of the expressions that it evaluated in turn (e.g. its
+    /// arguments) evaluated to, up until the one that yielded.
+    results: Vec<CrispExpr>,
+    /// The scope of the [`Lambda`](CrispExpr) it was calling when the body
+    /// yielded, if any.
+    scope: Option<Bindings>
+}
+
+/// A generator body that is being evaluated on this thread. See
+/// [`resume_generator()`].
+#[derive(Default)]
+struct Resuming {
+    /// The frames that have yet to be re-entered, outermost first.
+    paused: VecDeque<Frame>,
+    /// The expressions being evaluated, outermost first, along with how many
+    /// of their results have been replayed.
+    path: Vec<(Frame, usize)>,
+    /// The value being yielded, while the body is being unwound.
+    yielded: Option<CrispExpr>,
+    /// The frames that have been unwound so far, innermost first.
+    unwound: Vec<Frame>,
+    /// Whether the body has been re-entered all the way to the `yield` that
+    /// it was paused at, which has yet to return.
+    at_yield: bool,
+    /// The special form that can't be paused which is being evaluated, if
+    /// any; `yield` can't be used inside of it.
+    opaque: Option<&'static str>
+}
+
+thread_local! {
+    /// The generator bodies being evaluated on this thread, innermost last.
+    static RESUMING: RefCell<Vec<Resuming>> = const { RefCell::new(Vec::new()) };
+}
+
+/// How many generator bodies are being evaluated across every thread, so that
+/// [`eval()`] can tell without looking at [`RESUMING`] that there are none.
+static RESUMING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Calls `f` with the innermost generator body being evaluated on this thread,
+/// or `None` if there isn't one.
+fn with_resuming<T>(f: impl FnOnce(Option<&mut Resuming>) -> T) -> T {
+    if RESUMING_COUNT.load(Ordering::Relaxed) == 0 {
+        return f(None);
+    }
+
+    RESUMING.with(|resuming| f(resuming.borrow_mut().last_mut()))
+}
+
+/// Evaluates a generator's `body` until it yields a value, or finishes.
+///
+/// There's no thread to pause the body on, so every time a value is asked
+/// for, the body is evaluated again from the top, in a scope nested inside of
+/// `env` (so e.g. `set` works as it would anywhere else). Only the
+/// expressions which were being evaluated when the body last yielded (a call
+/// to `yield`, the function call that it's inside of, and so on out to the
+/// body) are entered again. Whatever each of them had evaluated before then
+/// is replayed from its [`Frame`] rather than evaluated again, and the scope
+/// of any [`Lambda`](CrispExpr) that it was calling is restored; that `yield`
+/// then returns `nil`, and evaluation carries on as normal from there.
+///
+/// Builtins which call functions (e.g. `map`) are called again, so should
+/// have no other side effects. Special forms which set up anything that isn't
+/// kept in a scope (e.g. `with-output-to-string`) can't be paused, so `yield`
+/// can't be used inside of them.
+///
+/// # Returns
+///
+/// The value that was yielded, or `None` if the body has finished.
+pub fn resume_generator(
+    body: &mut GeneratorBody,
+    env: &mut CrispEnv
+) -> Result<Option<CrispExpr>, CrispError> {
+    /// Stops resuming the body again, even if evaluating it panics.
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            RESUMING.with(|resuming| resuming.borrow_mut().pop());
+            RESUMING_COUNT.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    let paused = std::mem::take(&mut body.frames).into();
+    RESUMING.with(|resuming| resuming.borrow_mut().push(Resuming { paused, ..Default::default() }));
+    RESUMING_COUNT.fetch_add(1, Ordering::Relaxed);
+    let guard = Guard;
+
+    let mut scope = env_new_scope(HashMap::new(), env);
+    std::mem::take(&mut body.scope).restore(&mut scope);
+    let result = eval(&body.body, &mut scope);
+    body.scope = Bindings::take(&mut scope);
+
+    let (yielded, unwound) = RESUMING.with(|resuming| {
+        let mut resuming = resuming.borrow_mut();
+        let resuming = resuming.last_mut().unwrap();
+        (resuming.yielded.take(), std::mem::take(&mut resuming.unwound))
+    });
+    drop(guard);
+
+    match yielded {
+        Some(value) => {
+            body.frames = unwound.into_iter().rev().collect();
+            Ok(Some(value))
+        },
+        None => result.map(|_| None)
+    }
+}
+
+/// Pauses the generator body that is being evaluated on this thread, handing
+/// `value` back to whoever asked for it, for `yield`. If the body has just
+/// been resumed from here, this returns `nil` instead.
+pub fn yield_value(value: CrispExpr) -> Result<CrispExpr, CrispError> {
+    with_resuming(|resuming| match resuming {
+        None => standard_error!("yield can only be used inside of a generator."),
+        Some(Resuming { opaque: Some(form), .. }) => {
+            standard_error!(format!("yield can't be used inside of `{}`.", form))
+        },
+        Some(resuming) if resuming.at_yield => {
+            resuming.at_yield = false;
+            Ok(CrispExpr::Nil)
+        },
+        Some(resuming) => {
+            resuming.yielded = Some(value);
+            standard_error!("yield was used where its generator couldn't be paused.")
+        }
+    })
+}
+
+/// Whether the generator body being evaluated on this thread is yielding,
+/// in which case the error that's being passed up is only unwinding it.
+fn is_yielding() -> bool {
+    with_resuming(|resuming| resuming.is_some_and(|resuming| resuming.yielded.is_some()))
+}
+
+/// Calls `f` to evaluate the special form `form`, which can't be paused if
+/// it's in a generator's body; see [`resume_generator()`].
+pub fn without_pausing<T>(form: &'static str, f: impl FnOnce() -> T) -> T {
+    let outer = with_resuming(|resuming| resuming.map(|resuming| resuming.opaque.replace(form)));
+    let result = f();
+
+    if let Some(outer) = outer {
+        with_resuming(|resuming| {
+            if let Some(resuming) = resuming {
+                resuming.opaque = outer;
+            }
+        });
+    }
+
+    result
+}
+
+/// Evaluates `expr` within a generator's body, replaying its result if it was
+/// already evaluated before the body last yielded. See [`resume_generator()`].
+/// Returns `None` if no generator body is being evaluated.
+fn eval_resumable(expr: &CrispExpr, env: &mut CrispEnv) -> Option<Result<CrispExpr, CrispError>> {
+    let replayed = with_resuming(|resuming| {
+        let resuming = resuming.filter(|resuming| resuming.opaque.is_none())?;
+
+        if let Some((frame, replayed)) = resuming.path.last_mut() {
+            if let Some(result) = frame.results.get(*replayed) {
+                *replayed += 1;
+                return Some(Some(result.clone()));
+            }
+        }
+
+        let frame = match resuming.paused.pop_front() {
+            Some(frame) => {
+                resuming.at_yield = resuming.paused.is_empty();
+                frame
+            },
+            None => Frame::default()
+        };
+
+        resuming.path.push((frame, 0));
+        Some(None)
+    })?;
+
+    if let Some(result) = replayed {
+        return Some(Ok(result));
+    }
+
+    let result = eval_expr(expr, env);
+
+    Some(with_resuming(|resuming| {
+        let resuming = resuming.unwrap();
+        let (frame, _) = resuming.path.pop().unwrap();
+
+        match (&result, resuming.yielded.is_some()) {
+            (Err(_), true) => resuming.unwound.push(frame),
+            // Something caught the error that was unwinding the body
+            (Ok(_), true) => {
+                resuming.yielded = None;
+                resuming.unwound.clear();
+                return standard_error!(format!("yield can't be used inside of {}.",
+                                               expr.inspect()));
+            },
+            (Ok(value), false) => {
+                if let Some((parent, replayed)) = resuming.path.last_mut() {
+                    parent.results.push(value.clone());
+                    *replayed += 1;
+                }
+            },
+            (Err(_), false) => ()
+        }
+
+        result
+    }))
+}
+
+/// The scope that a [`Lambda`](CrispExpr) being called within a generator's
+/// body had when the body last yielded, if the call is being re-entered.
+fn resumed_scope() -> Option<Bindings> {
+    with_resuming(|resuming| {
+        let resuming = resuming.filter(|resuming| resuming.opaque.is_none())?;
+        let (frame, replayed) = resuming.path.last_mut()?;
+
+        match *replayed == frame.results.len() && !resuming.paused.is_empty() {
+            true => frame.scope.take(),
+            false => None
+        }
+    })
+}
+
+/// Keeps the scope of a [`Lambda`](CrispExpr) whose body is yielding, to be
+/// restored when the body is resumed.
+fn pause_scope(scope: Bindings) {
+    with_resuming(|resuming| {
+        if let Some((frame, _)) = resuming.and_then(|resuming| resuming.path.last_mut()) {
+            frame.scope = Some(scope);
+        }
+    });
+}
 
 /// Evaluates an expression, resolving a node of the AST to a single value.
 pub fn eval(expr: &CrispExpr, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    if RESUMING_COUNT.load(Ordering::Relaxed) > 0 {
+        if let Some(result) = eval_resumable(expr, env) {
+            return result;
+        }
+    }
+
+    eval_expr(expr, env)
+}
+
+fn eval_expr(expr: &CrispExpr, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     match expr {
         CrispExpr::List(list) if list.is_empty() => Ok(list![]),
         CrispExpr::List(list) => {
-            match resolve(list, env) {
-                Some(evaluated_expr) => Ok(evaluated_expr?),
-                _ => Ok(CrispExpr::List(eval_across_list(&list, env)?))
+            if debugger::is_stepping() {
+                debugger::pause(Some(expr), env)?;
+            }
+
+            let result = match resolve(list, env) {
+                Some(evaluated_expr) => evaluated_expr,
+                _ => eval_across_list(list, env).map(CrispExpr::List)
+            };
+
+            if let Err(e) = &result {
+                if !is_yielding() {
+                    record_error_source(e, expr, env);
+                }
             }
+
+            result
         },
 
         // It's a symbol, check the environment for it
@@ -21,7 +456,17 @@ pub fn eval(expr: &CrispExpr, env: &mut CrispEnv) -> Result<CrispExpr, CrispErro
         CrispExpr::CrispString(_) => Ok(expr.clone()),
         CrispExpr::Nil => Ok(expr.clone()),
         CrispExpr::Number(_) => Ok(expr.clone()),
+        #[cfg(feature = "decimal")]
+        CrispExpr::Decimal(_) => Ok(expr.clone()),
         CrispExpr::Bool(_) => Ok(expr.clone()),
+        CrispExpr::Env(_) => Ok(expr.clone()),
+        CrispExpr::Task(_) => Ok(expr.clone()),
+        CrispExpr::Chan(_) => Ok(expr.clone()),
+        CrispExpr::Atom(_) => Ok(expr.clone()),
+        CrispExpr::Heap(_) => Ok(expr.clone()),
+        CrispExpr::Queue(_) => Ok(expr.clone()),
+        CrispExpr::Delay(_) => Ok(expr.clone()),
+        CrispExpr::Generator(_) => Ok(expr.clone()),
 
         CrispExpr::Func(_) => parse_error!("Found unexpected function."),
         CrispExpr::Lambda(_) => parse_error!("Found unexpected lambda.")
@@ -32,45 +477,55 @@ pub fn eval(expr: &CrispExpr, env: &mut CrispEnv) -> Result<CrispExpr, CrispErro
 /// first expression to see if it evalutates to a [`Func`](CrispExpr) or a
 /// [`Lambda`](CrispExpr)- if so, it evaluates the entire slice as a
 /// [`List`](CrispExpr) and returns the result. Otherwise returns `None`.
+///
+/// The first expression may be a keyword, a [`Symbol`](CrispExpr) naming a
+/// function, or any expression which evaluates to one, e.g.
+/// `((if negate - +) 1 2)`. A `Func` or `Lambda` itself (such as in a
+/// `List` built with `list`) is called as it is.
 pub fn resolve(
     exprs: &[CrispExpr],
     env: &mut CrispEnv,
 ) -> Option<Result<CrispExpr, CrispError>> {
-    let (head, tail) = exprs.split_first().unwrap();
+    let (head, tail) = exprs.split_first()?;
 
     match head {
         CrispExpr::Symbol(_) => {
-            match eval_keyword(head, tail, env) {
-                Some(response) => Some(response),
-                None => {
-                    let evaluated_expr = eval(head, env);
-                    match evaluated_expr {
-                        Ok(CrispExpr::Func(func)) => Some(eval_func(func, tail, env)),
-                        Ok(CrispExpr::Lambda(lambda)) => Some(eval_lambda(lambda, tail, env)),
-
-                        Ok(_) if tail.is_empty() => Some(evaluated_expr),
-                        Ok(_) => Some(join_and_eval_across_list(head, tail, env)),
-
-                        Err(_) => Some(evaluated_expr)
-                    }
-                }
+            if let Some(response) = eval_keyword(head, tail, env) {
+                return Some(response);
             }
         },
 
-        CrispExpr::List(_) => {
-            let result = match eval(head, env) {
-                Ok(CrispExpr::Lambda(lambda)) => eval_lambda(lambda, tail, env),
+        CrispExpr::List(_) => (),
 
-                Ok(expr) if tail.is_empty() => Ok(expr),
-                Ok(_) => join_and_eval_across_list(head, tail, env),
+        CrispExpr::Func(_) | CrispExpr::Lambda(_) => {
+            return Some(call_value(head, head.clone(), tail, env));
+        },
 
-                res => res
-            };
+        _ => return not_callable(head, None).map(Err)
+    }
 
-            Some(result)
-        },
+    Some(eval(head, env).and_then(|value| call_value(head, value, tail, env)))
+}
 
-        _ => None
+/// Calls `value`, which the first expression `head` of a [`List`](CrispExpr)
+/// evaluated to, with the (unevaluated) arguments `tail`. If it isn't a
+/// function, the `List` evaluates to `value` if it stands alone, or to `head`
+/// followed by the evaluated arguments, unless calls are strict.
+fn call_value(
+    head: &CrispExpr,
+    value: CrispExpr,
+    tail: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    match value {
+        CrispExpr::Func(func) => eval_func(func, tail, env),
+        CrispExpr::Lambda(lambda) => eval_lambda(&lambda, tail, env),
+
+        value => match not_callable(head, Some(&value)) {
+            Some(e) => Err(e),
+            None if tail.is_empty() => Ok(value),
+            None => join_and_eval_across_list(head, tail, env)
+        }
     }
 }
 
@@ -90,11 +545,38 @@ pub fn join_and_eval_across_list(
 }
 
 /// Iterates across a slice of expressions, [`eval()`]ing each one.
+///
+/// A symbol prefixed with `@` or `...` is spread: the list it names is
+/// evaluated and its items are spliced into the result in its place, so that
+/// e.g. `(+ @xs)` adds up the items of `xs`.
 pub fn eval_across_list(
     args: &[CrispExpr],
     env: &mut CrispEnv
 ) -> Result<Vec<CrispExpr>, CrispError> {
-    args.iter().map(|a| eval(a, env)).collect()
+    let mut evaluated = Vec::with_capacity(args.len());
+
+    for arg in args {
+        match spread_name(arg) {
+            Some(name) => match eval(&sym!(name), env)? {
+                CrispExpr::List(items) => evaluated.extend(items),
+                _ => return type_error!("List")
+            },
+            None => evaluated.push(eval(arg, env)?)
+        }
+    }
+
+    Ok(evaluated)
+}
+
+/// If `expr` is a spread symbol such as `@xs` or `...xs`, returns the name of
+/// the variable being spread (`xs`).
+pub fn spread_name(expr: &CrispExpr) -> Option<&str> {
+    let CrispExpr::Symbol(name) = expr else {
+        return None;
+    };
+
+    name.strip_prefix('@').or_else(|| name.strip_prefix("..."))
+                          .filter(|name| !name.is_empty())
 }
 
 /// Executes a built-in function `func` with the given `args`, returning
@@ -110,18 +592,45 @@ pub fn eval_func(
 /// Calls a [`Lambda`](CrispExpr) with the arguments given in `args`, returns
 /// the return value of that `Lambda` call.
 pub fn eval_lambda(
-    lambda: CrispLambda,
+    lambda: &CrispLambda,
     args: &[CrispExpr],
     env: &mut CrispEnv
 ) -> Result<CrispExpr, CrispError> {
-    eval(&lambda.func,
-         &mut env_new_for_lambda(lambda.args, &eval_across_list(args, env)?, env)?)
+    let args = eval_across_list(args, env)?;
+    apply_lambda(lambda, &args, env)
+}
+
+/// Calls a [`Lambda`](CrispExpr) with arguments that have already been
+/// evaluated, e.g. by a builtin such as `foldl`. Evaluating them again would
+/// call any [`List`](CrispExpr) among them that starts with a function, and
+/// would take time proportional to their size on every call.
+pub fn apply_lambda(
+    lambda: &CrispLambda,
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    let mut scope = env_new_for_lambda(&lambda.args, args, env)?;
+    if let Some(bindings) = resumed_scope() {
+        bindings.restore(&mut scope);
+    }
+
+    let result = match &lambda.source {
+        Some(map) => with_source_map(map, || eval(&lambda.func, &mut scope)),
+        None => eval(&lambda.func, &mut scope)
+    };
+
+    if result.is_err() && is_yielding() {
+        pause_scope(Bindings::take(&mut scope));
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{env::initialize_environment, expr::CrispExpr::*};
+    use crate::{ast::Ast, env::initialize_environment, expr::CrispExpr::*,
+                reader::{parse_into, tokenize_with_spans}};
 
     #[test]
     fn test_eval_symbol_found() {
@@ -134,6 +643,25 @@ mod tests {
         assert_eq!(result, Number(42.0));
     }
 
+    #[test]
+    fn test_eval_symbol_builtin() {
+        // Builtins are looked up in the registry rather than copied into the environment
+        let mut env = initialize_environment();
+        assert!(env.data.is_empty());
+        assert!(matches!(eval(&sym!("+"), &mut env), Ok(Func(_))));
+
+        // Including from the environment that a lambda closes over
+        let add = eval(&"\\ (a b) (+ a b)".parse().unwrap(), &mut env).unwrap();
+        let expr = list![add, Number(1.0), Number(2.0)];
+        assert_eq!(eval(&expr, &mut initialize_environment()), Ok(Number(3.0)));
+    }
+
+    #[test]
+    fn test_expr_size() {
+        // Every value is cloned on its way through `eval`, so this shouldn't grow
+        assert_eq!(std::mem::size_of::<CrispExpr>(), 24);
+    }
+
     #[test]
     fn test_eval_symbol_not_found() {
         let mut env = initialize_environment();
@@ -248,4 +776,133 @@ mod tests {
 
         assert_eq!(result, Number(80.0));
     }
+
+    #[test]
+    fn test_eval_computed_head() {
+        let mut env = initialize_environment();
+        let run = |code: &str, env: &mut CrispEnv| eval(&code.parse().unwrap(), env);
+
+        // Whichever function the `if` returns is called
+        assert_eq!(run("((if true + -) 5 2)", &mut env), Ok(Number(7.0)));
+        assert_eq!(run("((if false + -) 5 2)", &mut env), Ok(Number(3.0)));
+
+        // Functions returning functions
+        assert_eq!(run("(((\\ () (\\ n (* n 2)))) 5)", &mut env), Ok(Number(10.0)));
+        assert_eq!(run("(((\\ () (\\ (f n) (f n n)))) + 3)", &mut env), Ok(Number(6.0)));
+        assert_eq!(run("((((\\ () (\\ () -)))) 5 2)", &mut env), Ok(Number(3.0)));
+
+        // A function that was put in a list directly
+        let expr = list![env_get("+", &env).unwrap(), Number(1.0), Number(2.0)];
+        assert_eq!(eval(&expr, &mut env), Ok(Number(3.0)));
+        assert_eq!(run("eval (list * 2 3)", &mut env), Ok(Number(6.0)));
+
+        // Anything else is still a plain list
+        assert_eq!(run("((if true 1 2) 3)", &mut env), Ok(list![list![sym!("if"), Bool(true),
+                                                                    Number(1.0), Number(2.0)],
+                                                              Number(3.0)]));
+    }
+
+    #[test]
+    fn test_eval_spread() {
+        let mut env = initialize_environment();
+        env.data.insert("xs".to_string(), list![Number(2.0), Number(3.0)]);
+        env.data.insert("empty".to_string(), list![]);
+
+        let expr = list![sym!("+"), Number(1.0), sym!("@xs"), Number(4.0)];
+        assert_eq!(eval(&expr, &mut env), Ok(Number(10.0)));
+
+        let expr = list![sym!("+"), sym!("...xs"), sym!("@empty")];
+        assert_eq!(eval(&expr, &mut env), Ok(Number(5.0)));
+
+        // Lambdas get the spread arguments too
+        let lambda = list![sym!("\\"), list![sym!("a"), sym!("b")],
+                           list![sym!("-"), sym!("a"), sym!("b")]];
+        assert_eq!(eval(&list![lambda, sym!("@xs")], &mut env), Ok(Number(-1.0)));
+
+        // So do lists which aren't calls
+        let expr = list![Number(1.0), sym!("@xs")];
+        assert_eq!(eval(&expr, &mut env), Ok(list![Number(1.0), Number(2.0), Number(3.0)]));
+
+        env.data.insert("n".to_string(), Number(1.0));
+        crisp_assert_err!(eval(&list![sym!("+"), sym!("@n")], &mut env), TypeError);
+        crisp_assert_err!(eval(&list![sym!("+"), sym!("@nope")], &mut env), ParseError);
+    }
+
+    #[test]
+    fn test_use_strict_calls() {
+        assert!(!use_strict_calls(false, None));
+        assert!(use_strict_calls(true, None));
+
+        // Environment variable; an empty value doesn't count
+        assert!(use_strict_calls(false, Some(OsString::from("1"))));
+        assert!(!use_strict_calls(false, Some(OsString::new())));
+    }
+
+    #[test]
+    fn test_error_source() {
+        let mut env = initialize_environment();
+        take_error_source();
+
+        let inner = list![sym!("+"), Number(1.0), str!("a")];
+        let expr = list![sym!("*"), Number(2.0), inner.clone()];
+
+        crisp_assert_err!(eval(&expr, &mut env), TypeError);
+        assert_eq!(take_error_source(),
+                   Some(ErrorSource { expr: inner.clone(), file: None, span: None }));
+        assert_eq!(take_error_source(), None);
+
+        env.file = Some(PathBuf::from("tests/error_location.crisp"));
+        crisp_assert_err!(eval(&expr, &mut env), TypeError);
+        assert_eq!(take_error_source().and_then(|source| source.file), env.file);
+    }
+
+    #[test]
+    fn test_error_source_mapped() {
+        let mut env = initialize_environment();
+        take_error_source();
+
+        // Each of two identical calls is traced back to where it was read
+        let code = "((fn f (x) (+ x 'a'))\n (f 1)\n (+ 1 'a'))";
+        let mut ast = Ast::new();
+        let mut map = SourceMap::new();
+        map.file = Some(PathBuf::from("main.crisp"));
+        let (root, _) = parse_into(&tokenize_with_spans(code), &mut ast).unwrap();
+        let CrispExpr::List(exprs) = ast.take_expr_mapped(root, &mut map) else { panic!() };
+
+        let at = |line, column| Span { line, column };
+        let map = Arc::new(map);
+        with_source_map(&map, || {
+            eval(&exprs[0], &mut env).unwrap();
+            crisp_assert_err!(eval(&exprs[1], &mut env), TypeError);
+            // In the body of `f`, which was cloned from the code
+            let source = take_error_source().unwrap();
+            assert_eq!(source.span, Some((at(1, 12), at(1, 20))));
+            assert_eq!(source.file, map.file);
+
+            crisp_assert_err!(eval(&exprs[2], &mut env), TypeError);
+            assert_eq!(take_error_source().and_then(|source| source.span),
+                       Some((at(3, 2), at(3, 10))));
+        });
+
+        // Once it's done, the map isn't used
+        crisp_assert_err!(eval(&list![sym!("+"), Number(1.0), str!("a")], &mut env), TypeError);
+        assert_eq!(take_error_source().and_then(|source| source.span), None);
+    }
+
+    #[test]
+    fn test_resolve_empty() {
+        let mut env = initialize_environment();
+        assert!(resolve(&[], &mut env).is_none());
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        use crate::error::catch_panic;
+
+        assert_eq!(catch_panic(|| Ok(1)), Ok(1));
+        crisp_assert_err!(catch_panic(|| -> Result<(), CrispError> { type_error!("List") }),
+                          TypeError);
+        crisp_assert_err!(catch_panic(|| -> Result<(), CrispError> { panic!("oh no") }),
+                          InternalError);
+    }
 }
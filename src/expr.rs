@@ -1,24 +1,129 @@
-use std::{fmt, hash::{Hasher, Hash}, rc::Rc};
+use std::{cmp::Ordering, collections::{BinaryHeap, VecDeque}, fmt, hash::{Hasher, Hash},
+          sync::{Arc, Mutex, mpsc::{Receiver, Sender}}, thread::JoinHandle};
 
-use crate::{env::CrispEnv, error::CrispError, escape_string};
+use crate::{ast::SourceMap, env::CrispEnv, error::CrispError, escape_string, eval::GeneratorBody,
+            reader::CHAR_NAMES};
 
+/// A value in crisp, which is also a node of the AST. These are cloned all the
+/// time (every time a variable is read, for a start), so payloads bigger than
+/// a [`Vec`] are kept behind an [`Arc`] to keep the enum small, and strings and
+/// symbols, which are never changed in place, are shared `Arc<str>`s so that
+/// cloning them doesn't copy them.
 #[derive(Clone)]
 pub enum CrispExpr {
-    Symbol(String),
+    Symbol(Arc<str>),
     Char(char),
-    CrispString(String),
+    CrispString(Arc<str>),
     Nil,
     Number(f64),
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
     Bool(bool),
     List(Vec<CrispExpr>),
-    Func(fn(&[CrispExpr], &mut CrispEnv) -> Result<CrispExpr, CrispError>),
-    Lambda(CrispLambda)
+    Func(CrispFunc),
+    Lambda(Arc<CrispLambda>),
+    Env(Arc<Mutex<CrispEnv<'static>>>),
+    Task(Arc<Mutex<CrispTask>>),
+    Chan(Arc<CrispChannel>),
+    Atom(Arc<Mutex<CrispAtom>>),
+    Heap(Arc<Mutex<CrispHeap>>),
+    Queue(Arc<Mutex<VecDeque<CrispExpr>>>),
+    Delay(Arc<Mutex<CrispDelay>>),
+    Generator(Arc<Mutex<CrispGenerator>>)
 }
 
+/// A builtin function, which is given its arguments already evaluated.
+pub type CrispFunc = fn(&[CrispExpr], &mut CrispEnv) -> Result<CrispExpr, CrispError>;
+
 #[derive(Clone)]
 pub struct CrispLambda {
-    pub args: Rc<CrispExpr>,
-    pub func: Rc<CrispExpr>
+    pub args: Arc<CrispExpr>,
+    pub func: Arc<CrispExpr>,
+    /// The name it was defined with by `fn`, if any.
+    pub name: Option<Arc<str>>,
+    /// Where `func` was read from, if it was cloned from source code. See
+    /// [`map_clones()`](crate::eval::map_clones).
+    pub source: Option<Arc<SourceMap>>
+}
+
+/// The state of an expression that was `spawn`ed onto another thread. Once
+/// the thread has been joined, the result is kept so that the
+/// [`Task`](CrispExpr) can be awaited any number of times.
+pub enum CrispTask {
+    Running(JoinHandle<Result<CrispExpr, CrispError>>),
+    Done(Result<CrispExpr, CrispError>)
+}
+
+/// A channel for passing values between threads. Every copy of a
+/// [`Chan`](CrispExpr) shares the same sending and receiving ends; the sender
+/// is dropped when the channel is closed.
+pub struct CrispChannel {
+    pub sender: Mutex<Option<Sender<CrispExpr>>>,
+    pub receiver: Mutex<Receiver<CrispExpr>>
+}
+
+/// A mutable reference cell. `version` is bumped every time the value is
+/// changed, so that `swap!` can tell if another thread got there first.
+pub struct CrispAtom {
+    pub value: CrispExpr,
+    pub version: u64
+}
+
+/// A min-heap (priority queue) of values. Each value is pushed with a
+/// [`Number`](CrispExpr) as its priority: the value itself, or the result of
+/// calling `key` on it if there is one.
+pub struct CrispHeap {
+    pub entries: BinaryHeap<CrispHeapEntry>,
+    pub key: Option<CrispExpr>,
+    /// The number of values pushed so far, used to pop values of equal
+    /// priority in the order they were pushed.
+    pub pushes: u64
+}
+
+/// A value in a [`CrispHeap`], ordered so that the [`BinaryHeap`] (which is
+/// a max-heap) gives the lowest priority first, then the earliest pushed.
+pub struct CrispHeapEntry {
+    pub priority: f64,
+    pub order: u64,
+    pub value: CrispExpr
+}
+
+impl Ord for CrispHeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority).then(other.order.cmp(&self.order))
+    }
+}
+
+impl PartialOrd for CrispHeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for CrispHeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CrispHeapEntry {}
+
+/// An expression wrapped by `delay`, along with a copy of the scope it was
+/// written in. It is evaluated the first time it is `force`d, and the result
+/// is kept for any later `force`s.
+pub enum CrispDelay {
+    Pending(CrispExpr, Box<CrispEnv<'static>>),
+    Done(Result<CrispExpr, CrispError>)
+}
+
+/// A generator, which hands out values one at a time as they're asked for
+/// with `next`. See [`resume_generator()`](crate::eval::resume_generator).
+pub enum CrispGenerator {
+    /// A body of expressions which `yield` values, paused at the last one.
+    Body(GeneratorBody),
+    /// The values of a Rust iterator, e.g. a range too long to be a list.
+    Iter(Box<dyn Iterator<Item = CrispExpr> + Send>),
+    Done
 }
 
 impl PartialEq for CrispExpr {
@@ -29,8 +134,23 @@ impl PartialEq for CrispExpr {
             (CrispExpr::CrispString(s1), CrispExpr::CrispString(s2)) => s1 == s2,
             (CrispExpr::Nil, CrispExpr::Nil) => true,
             (CrispExpr::Number(n1), CrispExpr::Number(n2)) => n1 == n2,
+            #[cfg(feature = "decimal")]
+            (CrispExpr::Decimal(d1), CrispExpr::Decimal(d2)) => d1 == d2,
+            #[cfg(feature = "decimal")]
+            (CrispExpr::Number(n), CrispExpr::Decimal(d))
+            | (CrispExpr::Decimal(d), CrispExpr::Number(n)) => {
+                cmp_number_decimal(*n, d) == Ordering::Equal
+            },
             (CrispExpr::List(l1), CrispExpr::List(l2)) => l1 == l2,
             (CrispExpr::Bool(b1), CrispExpr::Bool(b2)) => b1 == b2,
+            (CrispExpr::Env(e1), CrispExpr::Env(e2)) => Arc::ptr_eq(e1, e2),
+            (CrispExpr::Task(t1), CrispExpr::Task(t2)) => Arc::ptr_eq(t1, t2),
+            (CrispExpr::Chan(c1), CrispExpr::Chan(c2)) => Arc::ptr_eq(c1, c2),
+            (CrispExpr::Atom(a1), CrispExpr::Atom(a2)) => Arc::ptr_eq(a1, a2),
+            (CrispExpr::Heap(h1), CrispExpr::Heap(h2)) => Arc::ptr_eq(h1, h2),
+            (CrispExpr::Queue(q1), CrispExpr::Queue(q2)) => Arc::ptr_eq(q1, q2),
+            (CrispExpr::Delay(d1), CrispExpr::Delay(d2)) => Arc::ptr_eq(d1, d2),
+            (CrispExpr::Generator(g1), CrispExpr::Generator(g2)) => Arc::ptr_eq(g1, g2),
             _ => false
         }
     }
@@ -38,37 +158,225 @@ impl PartialEq for CrispExpr {
 
 impl Eq for CrispExpr {}
 
+/// A total order over every value, so that any [`List`](CrispExpr) can be
+/// sorted. Values of different types are ordered by type:
+///
+/// `Nil` < `Bool` < `Number` and `Decimal` < `Char` < `String` < `Symbol` < `List` < everything else
+///
+/// Within a type, `false` comes before `true`, numbers are ordered by value
+/// (`NaN` after everything, or before if negative), with `Number`s and
+/// `Decimal`s mixed together, strings and symbols
+/// lexicographically by character, and lists by comparing their items in
+/// turn. Other values, such as functions and environments, are only ordered
+/// by where they are in memory, which is consistent within a run of the
+/// program but otherwise meaningless.
+impl Ord for CrispExpr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (CrispExpr::Symbol(s1), CrispExpr::Symbol(s2)) => s1.cmp(s2),
+            (CrispExpr::Char(c1), CrispExpr::Char(c2)) => c1.cmp(c2),
+            (CrispExpr::CrispString(s1), CrispExpr::CrispString(s2)) => s1.cmp(s2),
+            // -0 and 0 are equal, as they are with `=`
+            (CrispExpr::Number(n1), CrispExpr::Number(n2)) => {
+                n1.partial_cmp(n2).unwrap_or_else(|| n1.total_cmp(n2))
+            },
+            #[cfg(feature = "decimal")]
+            (CrispExpr::Decimal(d1), CrispExpr::Decimal(d2)) => d1.cmp(d2),
+            #[cfg(feature = "decimal")]
+            (CrispExpr::Number(n), CrispExpr::Decimal(d)) => cmp_number_decimal(*n, d),
+            #[cfg(feature = "decimal")]
+            (CrispExpr::Decimal(d), CrispExpr::Number(n)) => cmp_number_decimal(*n, d).reverse(),
+            (CrispExpr::Bool(b1), CrispExpr::Bool(b2)) => b1.cmp(b2),
+            (CrispExpr::List(l1), CrispExpr::List(l2)) => l1.cmp(l2),
+            _ => (self.type_rank(), self.address()).cmp(&(other.type_rank(), other.address()))
+        }
+    }
+}
+
+impl PartialOrd for CrispExpr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares a [`Number`](CrispExpr) with a [`Decimal`](CrispExpr) by value,
+/// converting the number as the math operators do. Infinities and `NaN`,
+/// which no `Decimal` can hold, are ordered by their sign.
+#[cfg(feature = "decimal")]
+fn cmp_number_decimal(n: f64, d: &rust_decimal::Decimal) -> Ordering {
+    match rust_decimal::Decimal::try_from(n) {
+        Ok(n) => n.cmp(d),
+        Err(_) if n.is_sign_negative() => Ordering::Less,
+        Err(_) => Ordering::Greater
+    }
+}
+
 impl fmt::Debug for CrispExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(self, f)
     }
 }
 
+impl CrispExpr {
+    /// Where this expression's type comes in the [`Ord`] for `CrispExpr`.
+    fn type_rank(&self) -> u8 {
+        match self {
+            CrispExpr::Nil => 0,
+            CrispExpr::Bool(_) => 1,
+            CrispExpr::Number(_) => 2,
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(_) => 2,
+            CrispExpr::Char(_) => 3,
+            CrispExpr::CrispString(_) => 4,
+            CrispExpr::Symbol(_) => 5,
+            CrispExpr::List(_) => 6,
+            CrispExpr::Func(_) => 7,
+            CrispExpr::Lambda(_) => 8,
+            CrispExpr::Env(_) => 9,
+            CrispExpr::Task(_) => 10,
+            CrispExpr::Chan(_) => 11,
+            CrispExpr::Atom(_) => 12,
+            CrispExpr::Heap(_) => 13,
+            CrispExpr::Queue(_) => 14,
+            CrispExpr::Delay(_) => 15,
+            CrispExpr::Generator(_) => 16
+        }
+    }
+
+    /// The address in memory of a value that is compared by identity, or `0`
+    /// for any other value.
+    fn address(&self) -> usize {
+        match self {
+            CrispExpr::Func(f) => *f as usize,
+            CrispExpr::Lambda(lambda) => Arc::as_ptr(&lambda.func) as usize,
+            CrispExpr::Env(e) => Arc::as_ptr(e) as usize,
+            CrispExpr::Task(t) => Arc::as_ptr(t) as usize,
+            CrispExpr::Chan(c) => Arc::as_ptr(c) as usize,
+            CrispExpr::Atom(a) => Arc::as_ptr(a) as usize,
+            CrispExpr::Heap(h) => Arc::as_ptr(h) as usize,
+            CrispExpr::Queue(q) => Arc::as_ptr(q) as usize,
+            CrispExpr::Delay(d) => Arc::as_ptr(d) as usize,
+            CrispExpr::Generator(g) => Arc::as_ptr(g) as usize,
+            _ => 0
+        }
+    }
+
+    /// The name of this expression's type, as used in e.g. `TypeError`s.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CrispExpr::Symbol(_) => "Symbol",
+            CrispExpr::Char(_) => "Char",
+            CrispExpr::CrispString(_) => "String",
+            CrispExpr::Nil => "Nil",
+            CrispExpr::Number(_) => "Number",
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(_) => "Decimal",
+            CrispExpr::Bool(_) => "Bool",
+            CrispExpr::List(_) => "List",
+            CrispExpr::Func(_) => "Func",
+            CrispExpr::Lambda(_) => "Lambda",
+            CrispExpr::Env(_) => "Env",
+            CrispExpr::Task(_) => "Task",
+            CrispExpr::Chan(_) => "Chan",
+            CrispExpr::Atom(_) => "Atom",
+            CrispExpr::Heap(_) => "Heap",
+            CrispExpr::Queue(_) => "Queue",
+            CrispExpr::Delay(_) => "Delay",
+            CrispExpr::Generator(_) => "Generator"
+        }
+    }
+}
+
+impl CrispExpr {
+    /// Formats the expression so that it can be read back in, e.g.
+    /// [`String`](CrispExpr)s are quoted. Values which can't be written as
+    /// code, such as [`Func`](CrispExpr)s, are formatted the same way as
+    /// they're displayed.
+    pub fn inspect(&self) -> String {
+        match self {
+            CrispExpr::Char(c) => match CHAR_NAMES.iter().find(|(_, ch)| ch == c) {
+                Some((name, _)) => format!(",{}", name),
+                None if c.is_control() => format!(",u{:04x}", *c as u32),
+                None => format!(",{}", c)
+            },
+            CrispExpr::CrispString(s) => escape_string(s),
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(d) => format!("{}d", d),
+            CrispExpr::List(list) => format!("({})",
+                list.iter().map(|e| e.inspect()).collect::<Vec<String>>().join(" ")
+            ),
+            _ => self.to_string()
+        }
+    }
+
+    /// Serializes the expression so that reading it back with
+    /// [`parse()`](str::parse) gives an identical value. Unlike
+    /// [`inspect()`](CrispExpr::inspect), this fails for values which can't be
+    /// read back, such as [`Func`](CrispExpr)s, or [`Symbol`](CrispExpr)s that
+    /// would be read as something else.
+    pub fn to_string_exact(&self) -> Result<String, CrispError> {
+        match self {
+            CrispExpr::Symbol(s) if s.parse::<CrispExpr>().ok().as_ref() != Some(self) => {
+                standard_error!(format!("Can't serialize the symbol {:?}.", s))
+            },
+
+            CrispExpr::List(list) => {
+                let items = list.iter()
+                                .map(|e| e.to_string_exact())
+                                .collect::<Result<Vec<String>, CrispError>>()?;
+                Ok(format!("({})", items.join(" ")))
+            },
+
+            CrispExpr::Symbol(_) | CrispExpr::Char(_) | CrispExpr::CrispString(_) |
+            CrispExpr::Nil | CrispExpr::Number(_) | CrispExpr::Bool(_) => Ok(self.inspect()),
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(_) => Ok(self.inspect()),
+
+            _ => standard_error!(format!("Can't serialize {}.", self))
+        }
+    }
+}
+
 impl fmt::Display for CrispExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let str = match self {
-            CrispExpr::Symbol(s) => s.clone(),
+            CrispExpr::Symbol(s) => s.to_string(),
             CrispExpr::Char(c) => format!(",{}", c),
-            CrispExpr::CrispString(s) => s.clone(),
+            CrispExpr::CrispString(s) => s.to_string(),
             CrispExpr::Nil => "nil".to_string(),
-            CrispExpr::Number(n) => n.to_string(),
+            CrispExpr::Number(n) => format_number(*n),
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(d) => d.to_string(),
             CrispExpr::Bool(b) => b.to_string(),
-            CrispExpr::List(list) => format!("({})",
-                list.iter().map(|e| {
-                    match e {
-                        CrispExpr::CrispString(s) => escape_string(s),
-                        _ => e.to_string()
-                    }
-                }).collect::<Vec<String>>().join(" ")
-            ),
+            CrispExpr::List(_) => self.inspect(),
             CrispExpr::Func(_) => "<Func>".to_string(),
-            CrispExpr::Lambda(_) => "<Lambda>".to_string()
+            CrispExpr::Lambda(_) => "<Lambda>".to_string(),
+            CrispExpr::Env(_) => "<Env>".to_string(),
+            CrispExpr::Task(_) => "<Task>".to_string(),
+            CrispExpr::Chan(_) => "<Chan>".to_string(),
+            CrispExpr::Atom(_) => "<Atom>".to_string(),
+            CrispExpr::Heap(_) => "<Heap>".to_string(),
+            CrispExpr::Queue(_) => "<Queue>".to_string(),
+            CrispExpr::Delay(_) => "<Delay>".to_string(),
+            CrispExpr::Generator(_) => "<Generator>".to_string()
         };
 
         write!(f, "{}", str)
     }
 }
 
+/// Formats a number with the fewest digits that read back as the same number,
+/// e.g. `2` rather than `2.0`. Very large and very small numbers are written
+/// in exponent form, e.g. `1e21` and `1.5e-8`, rather than with a long run of
+/// zeroes. This doesn't depend on the locale.
+pub fn format_number(n: f64) -> String {
+    if n.is_finite() && n != 0.0 && !(1e-7..1e21).contains(&n.abs()) {
+        format!("{:e}", n)
+    } else {
+        n.to_string()
+    }
+}
+
 pub trait FromCrispExpr: Sized {
     fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError>;
 }
@@ -85,7 +393,7 @@ impl FromCrispExpr for char {
 impl FromCrispExpr for String {
     fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
         match expr {
-            CrispExpr::CrispString(s) => Ok(s.clone()),
+            CrispExpr::CrispString(s) => Ok(s.to_string()),
             _ => type_error!("String"),
         }
     }
@@ -100,6 +408,16 @@ impl FromCrispExpr for f64 {
     }
 }
 
+#[cfg(feature = "decimal")]
+impl FromCrispExpr for rust_decimal::Decimal {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Decimal(d) => Ok(*d),
+            _ => type_error!("Decimal"),
+        }
+    }
+}
+
 impl FromCrispExpr for Vec<CrispExpr> {
     fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
         match expr {
@@ -118,6 +436,60 @@ impl FromCrispExpr for bool {
     }
 }
 
+impl FromCrispExpr for Arc<Mutex<CrispEnv<'static>>> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Env(e) => Ok(e.clone()),
+            _ => type_error!("Env"),
+        }
+    }
+}
+
+impl FromCrispExpr for Arc<CrispChannel> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Chan(c) => Ok(c.clone()),
+            _ => type_error!("Chan"),
+        }
+    }
+}
+
+impl FromCrispExpr for Arc<Mutex<CrispAtom>> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Atom(a) => Ok(a.clone()),
+            _ => type_error!("Atom"),
+        }
+    }
+}
+
+impl FromCrispExpr for Arc<Mutex<CrispHeap>> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Heap(h) => Ok(h.clone()),
+            _ => type_error!("Heap"),
+        }
+    }
+}
+
+impl FromCrispExpr for Arc<Mutex<VecDeque<CrispExpr>>> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Queue(q) => Ok(q.clone()),
+            _ => type_error!("Queue"),
+        }
+    }
+}
+
+impl FromCrispExpr for Arc<Mutex<CrispGenerator>> {
+    fn from_crisp_expr(expr: &CrispExpr) -> Result<Self, CrispError> {
+        match expr {
+            CrispExpr::Generator(g) => Ok(g.clone()),
+            _ => type_error!("Generator"),
+        }
+    }
+}
+
 pub trait IntoCrispExpr {
     fn into_crisp_expr(self) -> CrispExpr;
 }
@@ -130,7 +502,7 @@ impl IntoCrispExpr for char {
 
 impl IntoCrispExpr for String {
     fn into_crisp_expr(self) -> CrispExpr {
-        CrispExpr::CrispString(self)
+        CrispExpr::CrispString(self.into())
     }
 }
 
@@ -158,11 +530,34 @@ impl Hash for CrispExpr {
             CrispExpr::Symbol(s) => s.hash(state),
             CrispExpr::Char(c) => (*c as u32).hash(state),
             CrispExpr::CrispString(s) => s.hash(state),
-            // Convert the number to its IEEE 754 binary representation and hash it
-            CrispExpr::Number(n) => state.write_u64(n.to_bits()),
+            // Convert the number to its IEEE 754 binary representation and hash
+            // it. -0 and 0 are equal, so they're hashed as 0, and every NaN is
+            // hashed the same. Numbers that a Decimal can hold are hashed as
+            // one, as they're equal to it
+            CrispExpr::Number(n) => {
+                let n = if *n == 0.0 { 0.0 } else if n.is_nan() { f64::NAN } else { *n };
+                #[cfg(feature = "decimal")]
+                if let Ok(d) = rust_decimal::Decimal::try_from(n) {
+                    return d.hash(state);
+                }
+                state.write_u64(n.to_bits())
+            },
+            // Equal decimals, e.g. 1.0 and 1.00, hash the same
+            #[cfg(feature = "decimal")]
+            CrispExpr::Decimal(d) => d.hash(state),
             // Convert the boolean to a u8 (0 for false, 1 for true)
             CrispExpr::Bool(b) => state.write_u8(*b as u8),
             CrispExpr::List(list) => list.hash(state),
+            // Environments, tasks, channels, atoms, heaps, queues, delays,
+            // and generators are equal only if they're the same object
+            CrispExpr::Env(e) => (Arc::as_ptr(e) as usize).hash(state),
+            CrispExpr::Task(t) => (Arc::as_ptr(t) as usize).hash(state),
+            CrispExpr::Chan(c) => (Arc::as_ptr(c) as usize).hash(state),
+            CrispExpr::Atom(a) => (Arc::as_ptr(a) as usize).hash(state),
+            CrispExpr::Heap(h) => (Arc::as_ptr(h) as usize).hash(state),
+            CrispExpr::Queue(q) => (Arc::as_ptr(q) as usize).hash(state),
+            CrispExpr::Delay(d) => (Arc::as_ptr(d) as usize).hash(state),
+            CrispExpr::Generator(g) => (Arc::as_ptr(g) as usize).hash(state),
             // TODO: Figure out a way to hash lambdas/funcs
             _ => {}
         }
@@ -171,7 +566,7 @@ impl Hash for CrispExpr {
 
 impl Hash for CrispLambda {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        // Hash the Rc references to ensure the content is hashed
+        // Hash the Arc references to ensure the content is hashed
         self.args.as_ref().hash(state)
     }
 }
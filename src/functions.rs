@@ -1,16 +1,55 @@
 mod assert;
+mod atoms;
 mod boolean;
+mod concurrency;
+mod data;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod encoding;
+#[cfg(feature = "unsafe-ffi")]
+mod ffi;
+mod fs;
+mod heaps;
 mod io;
+mod lazy;
 mod lists;
 mod math;
+mod meta;
+mod queues;
+mod random;
+mod results;
+mod strings;
+mod system;
+#[cfg(feature = "datetime")]
+mod time;
 
 pub use assert::*;
+pub use atoms::*;
 pub use boolean::*;
+pub use concurrency::*;
+pub use data::*;
+#[cfg(feature = "decimal")]
+pub use decimal::*;
+pub use encoding::*;
+#[cfg(feature = "unsafe-ffi")]
+pub use ffi::*;
+pub use fs::*;
+pub use heaps::*;
 pub use io::*;
+pub use lazy::*;
 pub use lists::*;
 pub use math::*;
-
-use crate::{error::CrispError, expr::{CrispExpr, FromCrispExpr, IntoCrispExpr}};
+pub use meta::*;
+pub use queues::*;
+pub use random::*;
+pub use results::*;
+pub use strings::*;
+pub use system::*;
+#[cfg(feature = "datetime")]
+pub use time::*;
+
+use crate::{env::CrispEnv, error::CrispError, eval::apply_lambda,
+            expr::{CrispExpr, FromCrispExpr, IntoCrispExpr}};
 
 /// Extracts a value from a [`CrispExpr`].
 fn extract_value<T>(expr: &CrispExpr) -> Result<T, CrispError>
@@ -25,8 +64,37 @@ where T: FromCrispExpr {
     list.iter().map(|expr| extract_value::<T>(expr)).collect()
 }
 
+/// Extracts a non-negative whole [`Number`](CrispExpr) for use as an index
+/// or a count.
+pub(crate) fn extract_index(expr: &CrispExpr) -> Result<usize, CrispError> {
+    let n = extract_value::<f64>(expr)?;
+
+    if n < 0.0 || n.fract() != 0.0 {
+        return index_error!(format!("Expected a non-negative integer, got {}.", n));
+    }
+
+    Ok(n as usize)
+}
+
+/// Calls a [`Func`](CrispExpr) or [`Lambda`](CrispExpr) with arguments that
+/// have already been evaluated.
+fn call_function(
+    function: &CrispExpr,
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    match function {
+        CrispExpr::Func(func) => func(args, env),
+        CrispExpr::Lambda(lambda) => apply_lambda(lambda, args, env),
+        _ => type_error!("Func || Lambda")
+    }
+}
+
 /// For internal use with Rust functions. See [`crisp_foldl()`] for the crisp
 /// `foldl` function.
+// No builtin folds with an initial value since `&&` and `||` became special
+// forms, but this is kept alongside `backend_foldl1()` for those that will
+#[allow(dead_code)]
 fn backend_foldl<T, U>(args: &[CrispExpr], init: T,
                        mut operation: impl FnMut(T, U) -> T) -> Result<CrispExpr, CrispError>
 where
@@ -48,7 +116,7 @@ where T: FromCrispExpr + IntoCrispExpr + Copy {
     check_argument_error!(args, 2, -1);
 
     let numbers = extract_list::<T>(args)?;
-    let (first, rest) = numbers.split_first().unwrap();
+    let (first, rest) = numbers.split_first().ok_or(CrispError::ArgumentError(2, -1))?;
 
     Ok(T::into_crisp_expr(
         rest.iter().fold(*first, |acc: T, &n: &T| operation(acc, n))
@@ -91,6 +159,33 @@ mod tests {
         assert_eq!(result.unwrap(), vec![true, false]);
     }
 
+    #[test]
+    fn test_extract_index() {
+        assert_eq!(extract_index(&Number(3.0)).unwrap(), 3);
+        assert_eq!(extract_index(&Number(0.0)).unwrap(), 0);
+
+        crisp_assert_err!(extract_index(&Number(-1.0)), IndexError);
+        crisp_assert_err!(extract_index(&Number(1.5)), IndexError);
+        crisp_assert_err!(extract_index(&str!("1")), TypeError);
+    }
+
+    #[test]
+    fn test_call_function() {
+        let mut env = crate::env::initialize_environment();
+
+        let add = crate::eval::eval(&sym!("+"), &mut env).unwrap();
+        assert_eq!(call_function(&add, &[Number(1.0), Number(2.0)], &mut env).unwrap(), Number(3.0));
+
+        let double = crate::eval::eval(&list![
+            sym!("\\"),
+            sym!("n"),
+            list![sym!("*"), Number(2.0), sym!("n")]
+        ], &mut env).unwrap();
+        assert_eq!(call_function(&double, &[Number(4.0)], &mut env).unwrap(), Number(8.0));
+
+        crisp_assert_err!(call_function(&Number(1.0), &[], &mut env), TypeError);
+    }
+
     #[test]
     fn test_backend_foldl() {
         let list = num_vec![3.0, 4.0, 2.0];
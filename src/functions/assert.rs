@@ -1,19 +1,18 @@
-use std::process;
-
 use crate::{env::CrispEnv, error::CrispError, expr::CrispExpr};
 
-use super::crisp_eq;
+use super::{crisp_eq, extract_value};
 
-const FAIL_ERR_CODE: i32 = 101;
+/// The exit code used when a file is stopped by a failed assertion.
+pub const FAIL_ERR_CODE: i32 = 101;
 
 /// `assert` takes a predicate and returns `true` if the predicate evaluates
-/// to `true`, otherwise it terminates the program with an error code.
+/// to `true`, otherwise it raises an `AssertionError`.
 ///
 /// # Examples
 ///
 /// ```lisp
 /// assert (> 5 4)
-/// assert (= 3 4)  ; this would terminate the program
+/// assert (= 3 4)  ; this would raise an AssertionError
 /// assert (< 1 10)
 /// ```
 pub fn crisp_assert(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
@@ -22,7 +21,7 @@ pub fn crisp_assert(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr
     match args.first().unwrap() {
         CrispExpr::Bool(b) => {
             if !b {
-                process::exit(FAIL_ERR_CODE);
+                return assertion_error!("Expected true, got false.");
             }
 
             Ok(CrispExpr::Bool(true))
@@ -33,13 +32,13 @@ pub fn crisp_assert(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr
 }
 
 /// `assert_false` takes a predicate and returns `true` if the predicate evaluates
-/// to `false`, otherwise it terminates the program with an error code.
+/// to `false`, otherwise it raises an `AssertionError`.
 ///
 /// # Examples
 ///
 /// ```lisp
 /// assert_false (< 5 4)
-/// assert_false (= 4 4)  ; this would terminate the program
+/// assert_false (= 4 4)  ; this would raise an AssertionError
 /// assert_false (> 1 10)
 /// ```
 pub fn crisp_assert_false(
@@ -51,7 +50,7 @@ pub fn crisp_assert_false(
     match args.first().unwrap() {
         CrispExpr::Bool(b) => {
             if *b {
-                process::exit(FAIL_ERR_CODE);
+                return assertion_error!("Expected false, got true.");
             }
 
             Ok(CrispExpr::Bool(true))
@@ -62,20 +61,21 @@ pub fn crisp_assert_false(
 }
 
 /// `assert_eq` returns `true` if all aruments are equal, otherwise it
-/// terminates the program with an error code.
+/// raises an `AssertionError`.
 ///
 /// # Examples
 ///
 /// ```lisp
 /// assert_eq 5 5
-/// assert_eq 5 4   ; this would terminate the program
+/// assert_eq 5 4   ; this would raise an AssertionError
 /// assert_eq 10 10
 /// ```
 pub fn crisp_assert_eq(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     match crisp_eq(args, env)? {
         CrispExpr::Bool(b) => {
             if !b {
-                process::exit(FAIL_ERR_CODE);
+                return assertion_error!(format!("Expected values to be equal: {}",
+                                                CrispExpr::List(args.to_vec())));
             }
 
             Ok(CrispExpr::Bool(true))
@@ -86,20 +86,21 @@ pub fn crisp_assert_eq(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispEx
 }
 
 /// `assert_not_eq` returns `true` if all aruments are equal, otherwise it
-/// terminates the program with an error code.
+/// raises an `AssertionError`.
 ///
 /// # Examples
 ///
 /// ```lisp
 /// assert_not_eq 5 4
-/// assert_not_eq 5 5   ; this would terminate the program
+/// assert_not_eq 5 5   ; this would raise an AssertionError
 /// assert_not_eq 10 4
 /// ```
 pub fn crisp_assert_not_eq(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     match crisp_eq(args, env)? {
         CrispExpr::Bool(b) => {
             if b {
-                process::exit(FAIL_ERR_CODE);
+                return assertion_error!(format!("Expected values not to be equal: {}",
+                                                CrispExpr::List(args.to_vec())));
             }
 
             Ok(CrispExpr::Bool(true))
@@ -108,3 +109,117 @@ pub fn crisp_assert_not_eq(args: &[CrispExpr], env: &mut CrispEnv) -> Result<Cri
         _ => type_error!("Bool")
     }
 }
+
+/// `assert-type` returns `true` if the first argument is of the type named by
+/// the second, otherwise it raises an `AssertionError`.
+///
+/// # Examples
+///
+/// ```lisp
+/// assert-type 5 "Number"
+/// assert-type "foo" "String"
+/// assert-type 5 "String"     ; this would raise an AssertionError
+/// ```
+pub fn crisp_assert_type(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let expected = extract_value::<String>(args.get(1).unwrap())?;
+    let actual = args.first().unwrap().type_name();
+
+    if actual != expected {
+        return assertion_error!(format!("Expected {}, got {}.", expected, actual));
+    }
+
+    Ok(CrispExpr::Bool(true))
+}
+
+/// `assert-in-delta` returns `true` if the first two arguments are within
+/// `delta` of each other, otherwise it raises an `AssertionError`. This is
+/// useful for comparing the results of floating point math.
+///
+/// # Usage
+///
+/// ```lisp
+/// assert-in-delta a b delta
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// assert-in-delta (+ 0.1 0.2) 0.3 0.0001
+/// assert-in-delta 3.2 3 0.1              ; this would raise an AssertionError
+/// ```
+pub fn crisp_assert_in_delta(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let a = extract_value::<f64>(args.first().unwrap())?;
+    let b = extract_value::<f64>(args.get(1).unwrap())?;
+    let delta = extract_value::<f64>(args.get(2).unwrap())?;
+
+    if (a - b).abs() > delta {
+        return assertion_error!(format!("Expected {} and {} to be within {} of each other.",
+                                        a, b, delta));
+    }
+
+    Ok(CrispExpr::Bool(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::CrispExpr::*, env::initialize_environment};
+
+    #[test]
+    fn test_assert() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_assert(&[Bool(true)], &mut env).unwrap(), Bool(true));
+        crisp_assert_err!(crisp_assert(&[Bool(false)], &mut env), AssertionError);
+        crisp_assert_err!(crisp_assert(&[Number(1.0)], &mut env), TypeError);
+
+        assert_eq!(crisp_assert_false(&[Bool(false)], &mut env).unwrap(), Bool(true));
+        crisp_assert_err!(crisp_assert_false(&[Bool(true)], &mut env), AssertionError);
+    }
+
+    #[test]
+    fn test_assert_eq() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_assert_eq(&[Number(1.0), Number(1.0)], &mut env).unwrap(), Bool(true));
+        crisp_assert_err!(crisp_assert_eq(&[Number(1.0), Number(2.0)], &mut env),
+                          AssertionError);
+
+        assert_eq!(crisp_assert_not_eq(&[Number(1.0), Number(2.0)], &mut env).unwrap(),
+                   Bool(true));
+        crisp_assert_err!(crisp_assert_not_eq(&[Number(1.0), Number(1.0)], &mut env),
+                          AssertionError);
+    }
+
+    #[test]
+    fn test_assert_type() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_assert_type(&[Number(1.0), str!("Number")], &mut env).unwrap(),
+                   Bool(true));
+        assert_eq!(crisp_assert_type(&[str!("foo"), str!("String")], &mut env).unwrap(),
+                   Bool(true));
+        crisp_assert_err!(crisp_assert_type(&[Number(1.0), str!("String")], &mut env),
+                          AssertionError);
+        crisp_assert_err!(crisp_assert_type(&[Number(1.0), sym!("Number")], &mut env),
+                          TypeError);
+    }
+
+    #[test]
+    fn test_assert_in_delta() {
+        let mut env = initialize_environment();
+
+        let args = [Number(0.1 + 0.2), Number(0.3), Number(0.0001)];
+        assert_eq!(crisp_assert_in_delta(&args, &mut env).unwrap(), Bool(true));
+
+        let args = [Number(3.2), Number(3.0), Number(0.1)];
+        crisp_assert_err!(crisp_assert_in_delta(&args, &mut env), AssertionError);
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{error::CrispError, expr::{CrispAtom, CrispExpr}, env::CrispEnv,
+            functions::{call_function, extract_value}};
+
+/// `atom` creates an [`Atom`](CrispExpr), a reference cell holding a value
+/// which can be changed with [`reset!`](crisp_reset) or [`swap!`](crisp_swap).
+/// Every copy of an `Atom` refers to the same cell, so changes made in one
+/// place (including other threads) are seen everywhere.
+///
+/// # Examples
+///
+/// ```lisp
+/// let counter (atom 0)
+/// swap! counter (\ n (+ n 1))
+/// deref counter               ; => 1
+/// ```
+pub fn crisp_atom(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(CrispExpr::Atom(Arc::new(Mutex::new(CrispAtom {
+        value: args.first().unwrap().clone(),
+        version: 0
+    }))))
+}
+
+/// `deref` returns the current value of an [`Atom`](CrispExpr).
+///
+/// # Examples
+///
+/// ```lisp
+/// let a (atom "foo")
+/// deref a            ; => "foo"
+/// ```
+pub fn crisp_deref(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let atom = extract_value::<Arc<Mutex<CrispAtom>>>(args.first().unwrap())?;
+    let value = lock_atom(&atom).value.clone();
+
+    Ok(value)
+}
+
+/// `reset!` sets the value of an [`Atom`](CrispExpr), returning the new value.
+///
+/// # Examples
+///
+/// ```lisp
+/// let a (atom 1)
+/// reset! a 5     ; => 5
+/// deref a        ; => 5
+/// ```
+pub fn crisp_reset(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let atom = extract_value::<Arc<Mutex<CrispAtom>>>(args.first().unwrap())?;
+    let value = args.get(1).unwrap().clone();
+
+    let mut cell = lock_atom(&atom);
+    cell.value = value.clone();
+    cell.version += 1;
+
+    Ok(value)
+}
+
+/// `swap!` updates the value of an [`Atom`](CrispExpr) by calling a function
+/// with the current value (followed by any extra arguments given), and
+/// returns the new value.
+///
+/// The function is called without holding on to the `Atom`, so it may use
+/// the `Atom` itself. If the `Atom` is changed by someone else in the
+/// meantime, e.g. on another thread, the function is called again with the
+/// latest value, so it should be free of side effects.
+///
+/// # Usage
+///
+/// ```lisp
+/// swap! atom function args...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let counter (atom 0)
+/// swap! counter (\ n (+ n 1)) ; => 1
+/// swap! counter + 10          ; => 11
+/// ```
+pub fn crisp_swap(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let atom = extract_value::<Arc<Mutex<CrispAtom>>>(args.first().unwrap())?;
+    let function = args.get(1).unwrap();
+
+    loop {
+        let (value, version) = {
+            let cell = lock_atom(&atom);
+            (cell.value.clone(), cell.version)
+        };
+
+        let mut call_args = vec![value];
+        call_args.extend_from_slice(&args[2..]);
+        let new_value = call_function(function, &call_args, env)?;
+
+        let mut cell = lock_atom(&atom);
+        if cell.version == version {
+            cell.value = new_value.clone();
+            cell.version += 1;
+
+            return Ok(new_value);
+        }
+    }
+}
+
+/// Locks an [`Atom`](CrispExpr) for reading or writing.
+fn lock_atom(atom: &Mutex<CrispAtom>) -> MutexGuard<'_, CrispAtom> {
+    // A panic in another thread can't leave the value half-written
+    atom.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::slice;
+    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment, eval::eval};
+
+    #[test]
+    fn test_atom_deref() {
+        let mut env = initialize_environment();
+
+        let a = crisp_atom(&[Number(1.0)], &mut env).unwrap();
+        assert!(matches!(a, Atom(_)));
+        assert_eq!(crisp_deref(slice::from_ref(&a), &mut env).unwrap(), Number(1.0));
+
+        // Each Atom is distinct
+        assert_ne!(a, crisp_atom(&[Number(1.0)], &mut env).unwrap());
+
+        crisp_assert_err!(crisp_deref(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut env = initialize_environment();
+
+        let a = crisp_atom(&[Number(1.0)], &mut env).unwrap();
+        let copy = a.clone();
+
+        assert_eq!(crisp_reset(&[a, str!("foo")], &mut env).unwrap(), str!("foo"));
+
+        // Copies refer to the same cell
+        assert_eq!(crisp_deref(&[copy], &mut env).unwrap(), str!("foo"));
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut env = initialize_environment();
+        let a = crisp_atom(&[Number(1.0)], &mut env).unwrap();
+
+        let inc = lambda![
+            args: ["n"],
+            func: [
+                sym!("+"),
+                sym!("n"),
+                Number(1.0)
+            ]
+        ];
+        assert_eq!(crisp_swap(&[a.clone(), inc], &mut env).unwrap(), Number(2.0));
+
+        // Built-in functions and extra arguments
+        let add = eval(&sym!("+"), &mut env).unwrap();
+        assert_eq!(crisp_swap(&[a.clone(), add, Number(10.0), Number(5.0)], &mut env).unwrap(),
+                   Number(17.0));
+        assert_eq!(crisp_deref(slice::from_ref(&a), &mut env).unwrap(), Number(17.0));
+
+        crisp_assert_err!(crisp_swap(&[a, Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_swap_uses_atom() {
+        // The function can look at the Atom without deadlocking
+        let mut env = initialize_environment();
+        let a = crisp_atom(&[Number(3.0)], &mut env).unwrap();
+        env.data.insert("a".to_string(), a.clone());
+
+        let f = lambda![
+            args: ["n"],
+            func: [
+                sym!("*"),
+                sym!("n"),
+                list![sym!("deref"), sym!("a")]
+            ]
+        ];
+        assert_eq!(crisp_swap(&[a, f], &mut env).unwrap(), Number(9.0));
+    }
+}
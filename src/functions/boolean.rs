@@ -1,7 +1,9 @@
-use std::collections::HashSet;
+use std::{collections::{hash_map::DefaultHasher, HashSet}, hash::{Hash, Hasher}};
 
-use crate::{error::CrispError, expr::CrispExpr,
-            env::CrispEnv, functions::{backend_foldl, extract_value}};
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, eval::eval,
+            functions::{extract_list, extract_value}};
+#[cfg(feature = "decimal")]
+use crate::functions::to_decimal;
 
 /// The `=` operator checks if all elements of a [`List`](CrispExpr)
 /// are the same.
@@ -40,35 +42,152 @@ pub fn crisp_not_eq(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr
     Ok(CrispExpr::Bool(args.len() == uniq_values.len()))
 }
 
-/// The numeric comparison operators check if a [`List`](CrispExpr) of
-/// [`Number`](CrispExpr)s increases or decreases monotonically. These
-/// functions are set with macros:
+/// `secure-eq` checks whether two [`String`](CrispExpr)s are equal, taking
+/// the same amount of time no matter where they differ. Use this instead of
+/// [`=`](crisp_eq) to compare secrets such as tokens, so that the comparison
+/// doesn't reveal how much of a guess was correct. Only the lengths of the
+/// strings can be learned from the timing.
+///
+/// # Examples
+///
+/// ```lisp
+/// secure-eq token "s3cr3t"   ; => true if token is "s3cr3t"
+/// secure-eq "abc" "abd"      ; => false
+/// ```
+pub fn crisp_secure_eq(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let a = extract_value::<String>(args.first().unwrap())?;
+    let b = extract_value::<String>(args.get(1).unwrap())?;
+
+    if a.len() != b.len() {
+        return Ok(CrispExpr::Bool(false));
+    }
+
+    // Look at every byte rather than stopping at the first difference
+    let diff = a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y));
+
+    Ok(CrispExpr::Bool(std::hint::black_box(diff) == 0))
+}
+
+/// `hash` returns a hash of any value as a whole [`Number`](CrispExpr). Values
+/// which are [`=`](crisp_eq) have the same hash, so it can be used e.g. as a
+/// cache key. Hashes are consistent within a run of the program, but may
+/// change between versions of crisp, so they shouldn't be stored.
+///
+/// # Examples
+///
+/// ```lisp
+/// hash "foo"                        ; => e.g. 2200610390178126
+/// (= (hash (+ 1 2)) (hash 3))       ; => true
+/// ```
+pub fn crisp_hash(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let mut hasher = DefaultHasher::new();
+    args.first().unwrap().hash(&mut hasher);
+
+    // Keep it within the range of integers that can be represented exactly
+    Ok(CrispExpr::Number((hasher.finish() >> 11) as f64))
+}
+
+/// The comparison operators check if a [`List`](CrispExpr) of values
+/// increases or decreases monotonically. The values must all be of the same
+/// type, except that [`Number`](CrispExpr)s and `Decimal`s can be mixed;
+/// numbers are compared by value, and
+/// [`String`](CrispExpr)s lexicographically. See the [`Ord`] for `CrispExpr`
+/// for how other types are ordered. These functions are set with macros:
 ///
 ///  * `>`
 ///  * `>=`
 ///  * `<`
 ///  * `<=`
 ///
-/// There are also some boolean comparison operators set through this macro:
+/// # Examples
 ///
-///  * `&&`
-///  * `||`
+/// ```lisp
+/// (> 5 4)         ; => true
+/// (> 5 4 3 1)     ; => true
+/// (> 5 4 4 1)     ; => false
+/// (>= 5 4 4 1)    ; => true
+/// (> 3 10)        ; => false
+/// (< 3 10)        ; => true
+/// (<= 3 3)        ; => true
+/// (< "abc" "abd") ; => true
+/// (< "b" "abc")   ; => false
+/// ```
+macro_rules! fold_order {
+    ($name:ident, $op:tt) => {
+        /// See [`fold_order`].
+        pub fn $name(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+            check_argument_error!(args, 2, -1);
+
+            // Numbers mixed with Decimals are converted, as for the math
+            // operators
+            #[cfg(feature = "decimal")]
+            if args.iter().any(|arg| matches!(arg, CrispExpr::Decimal(_))) {
+                let decimals = args.iter().map(to_decimal)
+                                   .collect::<Result<Vec<_>, CrispError>>()?;
+                return Ok(CrispExpr::Bool(decimals.windows(2).all(|pair| pair[0] $op pair[1])));
+            }
+
+            let type_name = args.first().unwrap().type_name();
+            if args.iter().any(|arg| arg.type_name() != type_name) {
+                return type_error!(type_name);
+            }
+
+            // NaN isn't greater or less than anything when comparing numbers
+            if let Some(CrispExpr::Number(_)) = args.first() {
+                let numbers = extract_list::<f64>(args)?;
+                return Ok(CrispExpr::Bool(numbers.windows(2).all(|pair| pair[0] $op pair[1])));
+            }
+
+            Ok(CrispExpr::Bool(args.windows(2).all(|pair| pair[0] $op pair[1])))
+        }
+    };
+}
+
+fold_order!(crisp_gt, >);
+fold_order!(crisp_gte, >=);
+fold_order!(crisp_lt, <);
+fold_order!(crisp_lte, <=);
+
+/// `compare` returns `-1` if `a` comes before `b`, `1` if it comes after, or
+/// `0` if they're equal. Unlike `<` and `>`, it works on any two values,
+/// ordering them like [`sort`](crate::functions::crisp_sort) does, so it can
+/// be used as a comparator with [`sort-by`](crate::functions::crisp_sort_by).
 ///
-/// # Examples
+/// # Usage
+///
+/// ```lisp
+/// compare a b
+/// ```
 ///
-/// ### Numeric comparisons
+/// # Examples
 ///
 /// ```lisp
-/// (> 5 4)      ; => true
-/// (> 5 4 3 1)  ; => true
-/// (> 5 4 4 1)  ; => false
-/// (>= 5 4 4 1) ; => true
-/// (> 3 10)     ; => false
-/// (< 3 10)     ; => true
-/// (<= 3 3)     ; => true
+/// compare 1 2       ; => -1
+/// compare "b" "a"   ; => 1
+/// compare 3 3       ; => 0
+/// compare nil 1     ; => -1
 /// ```
+pub fn crisp_compare(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    Ok(CrispExpr::Number(args[0].cmp(&args[1]) as i8 as f64))
+}
+
+/// The boolean comparison operators are set through this macro:
+///
+///  * `&&`
+///  * `||`
+///
+/// These are special forms rather than [`Func`](CrispExpr)s: each argument is
+/// evaluated in turn, and must be a [`Bool`](CrispExpr), but they stop as soon
+/// as the result is known. `&&` stops at the first `false`, and `||` at the
+/// first `true`, so the rest aren't evaluated at all.
 ///
-/// ### Boolean comparisons
+/// # Examples
 ///
 /// `&&` is the logical AND operator, and `||` is for logical OR.
 ///
@@ -81,29 +200,26 @@ pub fn crisp_not_eq(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr
 /// (|| (> 5 4) (= 3 9))                   ; => true
 /// (|| (> 4 5) (= 3 9))                   ; => false
 /// (|| (= 10 3) (= 4 6) (= 1 2) (> 5 4))  ;=> true
+///
+/// (|| true (exit 1))                     ; => true, without exiting
 /// ````
-macro_rules! fold_compare {
-    ($name:ident, $op:tt, $type:ty) => {
-        /// See [`fold_compare`].
-        pub fn $name(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+macro_rules! short_circuit {
+    ($name:ident, $stop:expr) => {
+        /// See [`short_circuit`].
+        pub fn $name(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
             check_argument_error!(args, 2, -1);
 
-            let mut prev_value = extract_value::<$type>(args.first().unwrap())?;
+            for arg in args {
+                if extract_value::<bool>(&eval(arg, env)?)? == $stop {
+                    return Ok(CrispExpr::Bool($stop));
+                }
+            }
 
-            backend_foldl::<bool, $type>(&args[1..], true, |acc, n| {
-                let result = acc && prev_value $op n;
-                prev_value = n;
-                result
-            })
+            Ok(CrispExpr::Bool(!$stop))
         }
     };
 }
 
-fold_compare!(crisp_gt, >, f64);
-fold_compare!(crisp_gte, >=, f64);
-fold_compare!(crisp_lt, <, f64);
-fold_compare!(crisp_lte, <=, f64);
-
 /// The `!` operator inverts one or more [`Bool`](CrispExpr)s. If one argument
 /// is provided, a `Bool` will be returned, otherwise the results will be
 /// mapped into a [`List`](CrispExpr) of `Bool`s.
@@ -124,17 +240,14 @@ pub fn crisp_not(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, C
             _ => type_error!("Bool"),
         })
         .collect::<Result<Vec<CrispExpr>, CrispError>>()
-        .map(|list| {
-            if list.len() == 1 {
-                list.into_iter().next().unwrap()
-            } else {
-                CrispExpr::List(list)
-            }
+        .map(|mut list| match list.len() {
+            1 => list.remove(0),
+            _ => CrispExpr::List(list)
         })
 }
 
-fold_compare!(crisp_and, &&, bool);
-fold_compare!(crisp_or, ||, bool);
+short_circuit!(crisp_and, false);
+short_circuit!(crisp_or, true);
 
 #[cfg(test)]
 mod tests {
@@ -148,6 +261,7 @@ mod tests {
         crisp_assert!(crisp_eq(&num_vec![5.0, 5.0], &mut env));
         crisp_assert!(crisp_eq(&num_vec![30.0, 30.0, 30.0], &mut env));
         crisp_assert!(crisp_eq(&string_vec!["foo", "foo"], &mut env));
+        crisp_assert!(crisp_eq(&[Number(0.0), Number(-0.0)], &mut env));
 
         crisp_assert_false!(crisp_eq(&num_vec![5.0, 4.0], &mut env));
         crisp_assert_false!(crisp_eq(&num_vec![5.0, 4.0, 5.0], &mut env));
@@ -169,6 +283,41 @@ mod tests {
         crisp_assert_false!(crisp_not_eq(&string_vec!["foo", "foo"], &mut env));
     }
 
+    #[test]
+    fn test_secure_eq() {
+        let mut env = initialize_environment();
+
+        crisp_assert!(crisp_secure_eq(&[str!("s3cr3t"), str!("s3cr3t")], &mut env));
+        crisp_assert!(crisp_secure_eq(&[str!(""), str!("")], &mut env));
+        crisp_assert_false!(crisp_secure_eq(&[str!("s3cr3t"), str!("s3cr3T")], &mut env));
+        crisp_assert_false!(crisp_secure_eq(&[str!("s3cr3t"), str!("s3cr3")], &mut env));
+        crisp_assert_err!(crisp_secure_eq(&[str!("1"), Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut env = initialize_environment();
+
+        let hash = |expr: CrispExpr, env: &mut CrispEnv| crisp_hash(&[expr], env).unwrap();
+
+        assert_eq!(hash(str!("foo"), &mut env), hash(str!("foo"), &mut env));
+        assert_ne!(hash(str!("foo"), &mut env), hash(str!("bar"), &mut env));
+        assert_eq!(hash(list![Number(1.0), str!("a")], &mut env),
+                   hash(list![Number(1.0), str!("a")], &mut env));
+
+        // Numbers that are `=` hash the same
+        assert_eq!(hash(Number(0.0), &mut env), hash(Number(-0.0), &mut env));
+        assert_eq!(hash(Number(f64::NAN), &mut env), hash(Number(-f64::NAN), &mut env));
+        assert_eq!(hash(Number(f64::NAN), &mut env),
+                   hash(Number(f64::from_bits(0x7ff8_0000_0000_0001)), &mut env));
+        assert_eq!(hash(list![Number(-0.0)], &mut env), hash(list![Number(0.0)], &mut env));
+
+        match hash(Number(5.0), &mut env) {
+            Number(n) => assert!(n.fract() == 0.0 && n >= 0.0 && n < 2f64.powi(53)),
+            _ => panic!("Expected a Number.")
+        }
+    }
+
     #[test]
     fn test_gt() {
         let mut env = initialize_environment();
@@ -215,6 +364,53 @@ mod tests {
         crisp_assert_false!(crisp_lte(&num_vec![5.0, 7.0, 8.0, 7.5], &mut env));
     }
 
+    #[test]
+    fn test_compare_strings() {
+        let mut env = initialize_environment();
+
+        crisp_assert!(crisp_lt(&[str!("abc"), str!("abd"), str!("b")], &mut env));
+        crisp_assert!(crisp_lte(&[str!("a"), str!("a"), str!("ab")], &mut env));
+        crisp_assert!(crisp_gt(&[str!("b"), str!("abc"), str!("")], &mut env));
+        crisp_assert!(crisp_gte(&[str!("b"), str!("b")], &mut env));
+
+        crisp_assert_false!(crisp_lt(&[str!("b"), str!("abc")], &mut env));
+        crisp_assert_false!(crisp_gt(&[str!("B"), str!("a")], &mut env));
+    }
+
+    #[test]
+    fn test_compare_mixed_types() {
+        let mut env = initialize_environment();
+
+        crisp_assert!(crisp_lt(&[list![Number(1.0)], list![Number(1.0), Number(0.0)]], &mut env));
+        crisp_assert!(crisp_lt(&[Char('a'), Char('b')], &mut env));
+
+        crisp_assert_err!(crisp_lt(&[Number(1.0), str!("a")], &mut env), TypeError);
+        crisp_assert_err!(crisp_gt(&[str!("a"), str!("b"), Number(1.0)], &mut env), TypeError);
+
+        // NaN isn't ordered against other numbers
+        crisp_assert_false!(crisp_lt(&[Number(1.0), Number(f64::NAN)], &mut env));
+        crisp_assert_false!(crisp_gte(&[Number(f64::NAN), Number(1.0)], &mut env));
+    }
+
+    #[test]
+    fn test_total_order() {
+        let mut values = vec![
+            list![Number(1.0)], sym!("a"), str!("b"), Char('c'), Number(2.0), Bool(true),
+            Number(-1.0), Bool(false), Nil, str!("a")
+        ];
+        values.sort();
+
+        assert_eq!(values, vec![
+            Nil, Bool(false), Bool(true), Number(-1.0), Number(2.0), Char('c'), str!("a"),
+            str!("b"), sym!("a"), list![Number(1.0)]
+        ]);
+
+        assert_eq!(Number(-0.0).cmp(&Number(0.0)), std::cmp::Ordering::Equal);
+        assert!(Number(f64::NAN) > Number(f64::INFINITY));
+        assert!(list![Number(1.0), Number(2.0)] < list![Number(1.0), Number(3.0)]);
+        assert!(list![] < list![Nil]);
+    }
+
     #[test]
     fn test_not() {
         let mut env = initialize_environment();
@@ -251,4 +447,32 @@ mod tests {
         crisp_assert_false!(crisp_or(&bool_vec![false, false], &mut env));
         crisp_assert_false!(crisp_or(&bool_vec![false, false, false, false], &mut env));
     }
+
+    #[test]
+    fn test_compare() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_compare(&[Number(1.0), Number(2.0)], &mut env).unwrap(), Number(-1.0));
+        assert_eq!(crisp_compare(&[str!("b"), str!("a")], &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_compare(&[Number(3.0), Number(3.0)], &mut env).unwrap(), Number(0.0));
+        // Values of different types can be compared too
+        assert_eq!(crisp_compare(&[Nil, Number(1.0)], &mut env).unwrap(), Number(-1.0));
+
+        crisp_assert_err!(crisp_compare(&[Number(1.0)], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_and_or_short_circuit() {
+        let mut env = initialize_environment();
+        let fail = list![sym!("+"), Number(1.0), str!("a")];
+
+        // The arguments after the first that decides the result aren't evaluated
+        crisp_assert_false!(crisp_and(&[Bool(true), Bool(false), fail.clone()], &mut env));
+        crisp_assert!(crisp_or(&[Bool(false), Bool(true), fail.clone()], &mut env));
+        crisp_assert!(eval(&"|| (= 1 1) (+ 1 'a')".parse().unwrap(), &mut env));
+
+        crisp_assert_err!(crisp_and(&[Bool(true), fail], &mut env), TypeError);
+        crisp_assert_err!(crisp_or(&[Bool(false), Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_and(&[Bool(true)], &mut env), ArgumentError);
+    }
 }
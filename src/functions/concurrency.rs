@@ -0,0 +1,365 @@
+use std::{mem, sync::{Arc, Mutex, mpsc}, thread};
+
+use crate::{error::CrispError, expr::{CrispChannel, CrispExpr, CrispTask},
+            env::{CrispEnv, env_snapshot}, eval::apply_lambda, functions::extract_value};
+
+/// `await` waits for a [`Task`](CrispExpr) started with `spawn` to finish,
+/// and returns its result. If the spawned expression failed, the error is
+/// raised here. A `Task` may be awaited more than once.
+///
+/// # Examples
+///
+/// ```lisp
+/// let task (spawn (+ 1 2))
+/// await task               ; => 3
+/// ```
+pub fn crisp_await(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    await_task(args.first().unwrap())
+}
+
+/// `join` waits for all of the given [`Task`](CrispExpr)s to finish, and
+/// returns a [`List`](CrispExpr) of their results in order.
+///
+/// # Examples
+///
+/// ```lisp
+/// join (spawn (+ 1 2)) (spawn (* 3 4)) ; => (3 12)
+/// ```
+pub fn crisp_join(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    args.iter().map(await_task).collect::<Result<Vec<CrispExpr>, CrispError>>()
+                               .map(CrispExpr::List)
+}
+
+/// Waits for a [`Task`](CrispExpr) and returns its result. See
+/// [`crisp_await()`].
+fn await_task(expr: &CrispExpr) -> Result<CrispExpr, CrispError> {
+    let task = match expr {
+        CrispExpr::Task(task) => task,
+        _ => return type_error!("Task")
+    };
+
+    // If another thread is awaiting this task, this will block until it has
+    // been joined
+    let mut state = task.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let result = match mem::replace(&mut *state, CrispTask::Done(Ok(CrispExpr::Nil))) {
+        CrispTask::Running(handle) => handle.join().unwrap_or_else(|_|
+            standard_error!("Task panicked.")
+        ),
+
+        CrispTask::Done(result) => result
+    };
+
+    *state = CrispTask::Done(result.clone());
+    result
+}
+
+/// `par-map` works like [`map`](crate::functions::crisp_map), but splits the
+/// [`List`](CrispExpr) up and maps across the pieces on multiple threads at
+/// once. The results are returned in the same order as with `map`. Each
+/// thread evaluates the `Lambda` in its own copy of the current scope.
+///
+/// # Examples
+///
+/// ```lisp
+/// fn fib n (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))
+/// par-map fib (20 21 22 23) ; => (6765 10946 17711 28657)
+/// ```
+pub fn crisp_par_map(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let lambda = match args.first().unwrap() {
+        CrispExpr::Lambda(lambda) => lambda,
+        _ => return type_error!("Lambda")
+    };
+
+    let n_args = match lambda.args.as_ref() {
+        CrispExpr::List(list) => list.len(),
+        _ => return type_error!("Symbol || List<Symbol>")
+    };
+
+    let list = match args.get(1).unwrap() {
+        CrispExpr::List(list) => list,
+        _ => return type_error!("List")
+    };
+
+    // Group up the arguments for each call as `map` does, then divide the
+    // calls evenly between the threads
+    let calls: Vec<Vec<CrispExpr>> = list.chunks(n_args.max(1)).map(|c| c.to_vec()).collect();
+    let n_threads = thread::available_parallelism().map_or(1, |n| n.get()).min(calls.len()).max(1);
+    let calls = Arc::new(calls);
+
+    let handles: Vec<_> = (0..n_threads).map(|i| {
+        let calls = Arc::clone(&calls);
+        let lambda = lambda.clone();
+        let mut snapshot = env_snapshot(env);
+
+        thread::spawn(move || {
+            let per_thread = calls.len().div_ceil(n_threads);
+            let start = (i * per_thread).min(calls.len());
+            let end = ((i + 1) * per_thread).min(calls.len());
+
+            calls[start..end].iter()
+                             .map(|call| apply_lambda(&lambda, call, &mut snapshot))
+                             .collect::<Result<Vec<CrispExpr>, CrispError>>()
+        })
+    }).collect();
+
+    let mut result = Vec::new();
+    for handle in handles {
+        match handle.join() {
+            Ok(values) => result.extend(values?),
+            Err(_) => return standard_error!("Thread panicked during `par-map`.")
+        }
+    }
+
+    Ok(CrispExpr::List(result))
+}
+
+/// `chan` creates a new [`Chan`](CrispExpr), which can be used to pass values
+/// between `spawn`ed tasks with [`send!`](crisp_send) and
+/// [`recv!`](crisp_recv).
+///
+/// # Examples
+///
+/// ```lisp
+/// let c (chan)
+/// spawn (map (\ n (send! c (* n n))) (1 2 3))
+/// recv! c                                     ; => 1
+/// ```
+pub fn crisp_chan(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    let (sender, receiver) = mpsc::channel();
+
+    Ok(CrispExpr::Chan(Arc::new(CrispChannel {
+        sender: Mutex::new(Some(sender)),
+        receiver: Mutex::new(receiver)
+    })))
+}
+
+/// `send!` sends a value over a [`Chan`](CrispExpr), returning the value. It
+/// is an error to send over a channel that has been [closed](crisp_close).
+///
+/// # Examples
+///
+/// ```lisp
+/// let c (chan)
+/// send! c "hello" ; => "hello"
+/// ```
+pub fn crisp_send(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let channel = extract_value::<Arc<CrispChannel>>(args.first().unwrap())?;
+    let value = args.get(1).unwrap().clone();
+
+    let sender = channel.sender.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    match sender.as_ref().map(|sender| sender.send(value.clone())) {
+        Some(Ok(_)) => Ok(value),
+        _ => standard_error!("Can't send on a closed Chan.")
+    }
+}
+
+/// `recv!` receives the next value from a [`Chan`](CrispExpr), waiting for
+/// one to be sent if need be. Once the channel has been [closed](crisp_close)
+/// and every value sent before that has been received, `recv!` returns `nil`.
+///
+/// # Examples
+///
+/// ```lisp
+/// let c (chan)
+/// send! c 1
+/// close! c
+/// recv! c   ; => 1
+/// recv! c   ; => nil
+/// ```
+pub fn crisp_recv(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let channel = extract_value::<Arc<CrispChannel>>(args.first().unwrap())?;
+    let receiver = channel.receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    Ok(receiver.recv().unwrap_or(CrispExpr::Nil))
+}
+
+/// `close!` closes a [`Chan`](CrispExpr), so that nothing more can be sent on
+/// it. Values that were already sent can still be received. Returns `true` if
+/// the channel was open.
+///
+/// # Examples
+///
+/// ```lisp
+/// let c (chan)
+/// close! c     ; => true
+/// close! c     ; => false
+/// ```
+pub fn crisp_close(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let channel = extract_value::<Arc<CrispChannel>>(args.first().unwrap())?;
+    let mut sender = channel.sender.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    Ok(CrispExpr::Bool(sender.take().is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{slice, sync::Mutex};
+    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment, eval::eval,
+                functions::crisp_map};
+
+    fn spawn(expr: CrispExpr, env: &mut CrispEnv) -> CrispExpr {
+        eval(&list![sym!("spawn"), expr], env).unwrap()
+    }
+
+    #[test]
+    fn test_await() {
+        let mut env = initialize_environment();
+
+        let task = spawn(list![sym!("*"), Number(6.0), Number(7.0)], &mut env);
+        assert_eq!(crisp_await(slice::from_ref(&task), &mut env).unwrap(), Number(42.0));
+
+        // Can be awaited again
+        assert_eq!(crisp_await(&[task], &mut env).unwrap(), Number(42.0));
+
+        // Errors are raised when awaited
+        let task = spawn(list![sym!("+"), Number(1.0), str!("foo")], &mut env);
+        crisp_assert_err!(crisp_await(&[task], &mut env), TypeError);
+
+        crisp_assert_err!(crisp_await(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_await_finished() {
+        let mut env = initialize_environment();
+
+        let task = Task(Arc::new(Mutex::new(CrispTask::Done(Ok(Number(1.0))))));
+        assert_eq!(crisp_await(&[task], &mut env).unwrap(), Number(1.0));
+    }
+
+    #[test]
+    fn test_join() {
+        let mut env = initialize_environment();
+
+        let tasks = vec![
+            spawn(list![sym!("+"), Number(1.0), Number(2.0)], &mut env),
+            spawn(list![sym!("*"), Number(3.0), Number(4.0)], &mut env)
+        ];
+
+        assert_eq!(crisp_join(&tasks, &mut env).unwrap(), num_list![3.0, 12.0]);
+    }
+
+    #[test]
+    fn test_par_map() {
+        let mut env = initialize_environment();
+        env.data.insert("factor".to_string(), Number(3.0));
+
+        let args = vec![
+            lambda![
+                args: ["a"],
+                func: [
+                    sym!("*"),
+                    sym!("a"),
+                    sym!("factor")
+                ]
+            ],
+            num_list![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0]
+        ];
+
+        assert_eq!(crisp_par_map(&args, &mut env).unwrap(),
+                   crisp_map(&args, &mut env).unwrap());
+
+        let args = vec![
+            lambda![
+                args: ["a", "b"],
+                func: [
+                    sym!("+"),
+                    sym!("a"),
+                    sym!("b")
+                ]
+            ],
+            num_list![1.0, 2.0, 10.0, 20.0, 100.0, 200.0]
+        ];
+
+        assert_eq!(crisp_par_map(&args, &mut env).unwrap(),
+                   num_list![3.0, 30.0, 300.0]);
+
+        let args = vec![args.first().unwrap().clone(), list![]];
+        assert_eq!(crisp_par_map(&args, &mut env).unwrap(), list![]);
+    }
+
+    #[test]
+    fn test_chan() {
+        let mut env = initialize_environment();
+
+        let c = crisp_chan(&[], &mut env).unwrap();
+        assert!(matches!(c, Chan(_)));
+        assert_eq!(c, c.clone());
+        assert_ne!(c, crisp_chan(&[], &mut env).unwrap());
+    }
+
+    #[test]
+    fn test_send_recv() {
+        let mut env = initialize_environment();
+        let c = crisp_chan(&[], &mut env).unwrap();
+
+        assert_eq!(crisp_send(&[c.clone(), Number(1.0)], &mut env).unwrap(), Number(1.0));
+        crisp_send(&[c.clone(), str!("two")], &mut env).unwrap();
+
+        assert_eq!(crisp_recv(slice::from_ref(&c), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_recv(slice::from_ref(&c), &mut env).unwrap(), str!("two"));
+
+        crisp_assert_err!(crisp_send(&[Number(1.0), Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_recv(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_close() {
+        let mut env = initialize_environment();
+        let c = crisp_chan(&[], &mut env).unwrap();
+
+        crisp_send(&[c.clone(), Number(1.0)], &mut env).unwrap();
+
+        crisp_assert!(crisp_close(slice::from_ref(&c), &mut env));
+        crisp_assert_false!(crisp_close(slice::from_ref(&c), &mut env));
+
+        crisp_assert_err!(crisp_send(&[c.clone(), Number(2.0)], &mut env), StandardError);
+
+        // Values sent before closing can still be received
+        assert_eq!(crisp_recv(slice::from_ref(&c), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_recv(&[c], &mut env).unwrap(), Nil);
+    }
+
+    #[test]
+    fn test_chan_between_tasks() {
+        let mut env = initialize_environment();
+        let c = crisp_chan(&[], &mut env).unwrap();
+        env.data.insert("c".to_string(), c.clone());
+        env.data.insert("send-square".to_string(), lambda![
+            args: ["n"],
+            func: [
+                sym!("send!"),
+                sym!("c"),
+                list![sym!("*"), sym!("n"), sym!("n")]
+            ]
+        ]);
+
+        let task = spawn(list![
+            sym!("map"),
+            sym!("send-square"),
+            num_list![1.0, 2.0, 3.0]
+        ], &mut env);
+
+        assert_eq!(crisp_recv(slice::from_ref(&c), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_recv(slice::from_ref(&c), &mut env).unwrap(), Number(4.0));
+        assert_eq!(crisp_recv(&[c], &mut env).unwrap(), Number(9.0));
+
+        crisp_await(&[task], &mut env).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::extract_value};
+
+/// Splits CSV text into rows of fields. Fields may be wrapped in double
+/// quotes, in which case they can contain commas, line breaks, and quotes
+/// (written as `""`). Both `\n` and `\r\n` line endings are accepted, and a
+/// trailing line ending doesn't produce an empty row.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, CrispError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                },
+                '"' => in_quotes = false,
+                _ => field.push(ch)
+            }
+
+            continue;
+        }
+
+        match ch {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            _ => field.push(ch)
+        }
+    }
+
+    if in_quotes {
+        return parse_error!("Unterminated quoted field in CSV.");
+    }
+
+    // The last row might not end with a line ending
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+/// Formats a single value as a CSV field, quoting it if need be.
+fn csv_field(value: &CrispExpr) -> String {
+    let field = value.to_string();
+
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// `csv-parse` parses a [`String`](CrispExpr) of CSV data into a
+/// [`List`](CrispExpr) of rows, each of which is a `List` of `String` fields.
+///
+/// If the second argument is `true`, the first row is treated as a header:
+/// each of the following rows becomes a `List` of `(header value)` pairs
+/// instead, so that fields can be looked up by name.
+///
+/// # Usage
+///
+/// ```lisp
+/// csv-parse text [headers]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// csv-parse "a,b\n1,\"x, y\"\n"        ; => (('a' 'b') ('1' 'x, y'))
+/// csv-parse "name,age\nAnn,30\n" true  ; => ((('name' 'Ann') ('age' '30')))
+/// ```
+pub fn crisp_csv_parse(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+
+    let text = extract_value::<String>(args.first().unwrap())?;
+    let headers = match args.get(1) {
+        Some(expr) => extract_value::<bool>(expr)?,
+        None => false
+    };
+
+    let mut rows = parse_csv(&text)?.into_iter();
+    let to_list = |fields: Vec<String>| {
+        CrispExpr::List(fields.into_iter().map(|s| CrispExpr::CrispString(s.into())).collect())
+    };
+
+    if !headers {
+        return Ok(CrispExpr::List(rows.map(to_list).collect()));
+    }
+
+    let header = rows.next().unwrap_or_default();
+    Ok(CrispExpr::List(rows.map(|row| {
+        CrispExpr::List(header.iter().zip(row).map(|(name, value)| {
+            list![str!(name), str!(value)]
+        }).collect())
+    }).collect()))
+}
+
+/// `csv-write` formats a [`List`](CrispExpr) of rows, each of which is a
+/// `List` of values, as CSV. Fields are quoted where necessary, and each row
+/// ends with a line break.
+///
+/// # Examples
+///
+/// ```lisp
+/// csv-write (csv-parse "a,b\n1,2")   ; => "a,b\n1,2\n"
+/// csv-write (read "((1 'x, y'))")    ; => "1,\"x, y\"\n"
+/// ```
+pub fn crisp_csv_write(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let rows = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+
+    let mut csv = String::new();
+    for row in rows {
+        let fields = extract_value::<Vec<CrispExpr>>(&row)?;
+        csv.push_str(&fields.iter().map(csv_field).collect::<Vec<String>>().join(","));
+        csv.push('\n');
+    }
+
+    Ok(str!(csv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
+
+    #[test]
+    fn test_parse_csv() {
+        assert_eq!(parse_csv("a,b\n1,2\n").unwrap(), [["a", "b"], ["1", "2"]]);
+        assert_eq!(parse_csv("a,b\r\n1,2").unwrap(), [["a", "b"], ["1", "2"]]);
+        assert_eq!(parse_csv("\"x, y\",\"say \"\"hi\"\"\",\"two\nlines\"").unwrap(),
+                   [["x, y", "say \"hi\"", "two\nlines"]]);
+        assert_eq!(parse_csv("a,,\n").unwrap(), [["a", "", ""]]);
+        assert!(parse_csv("").unwrap().is_empty());
+
+        crisp_assert_err!(parse_csv("\"unterminated\n"), ParseError);
+    }
+
+    #[test]
+    fn test_csv_parse() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_csv_parse(&[str!("a,b\n1,2\n")], &mut env).unwrap(),
+                   list![list![str!("a"), str!("b")], list![str!("1"), str!("2")]]);
+
+        assert_eq!(crisp_csv_parse(&[str!("name,age\nAnn,30\nBo,4\n"), Bool(true)], &mut env)
+                       .unwrap(),
+                   list![
+                       list![list![str!("name"), str!("Ann")], list![str!("age"), str!("30")]],
+                       list![list![str!("name"), str!("Bo")], list![str!("age"), str!("4")]]
+                   ]);
+        assert_eq!(crisp_csv_parse(&[str!(""), Bool(true)], &mut env).unwrap(), list![]);
+
+        crisp_assert_err!(crisp_csv_parse(&[Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_csv_parse(&[str!("a"), Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_csv_write() {
+        let mut env = initialize_environment();
+
+        let rows = list![
+            list![str!("a"), Number(1.0)],
+            list![str!("x, y"), str!("say \"hi\"")]
+        ];
+        assert_eq!(crisp_csv_write(std::slice::from_ref(&rows), &mut env).unwrap(),
+                   str!("a,1\n\"x, y\",\"say \"\"hi\"\"\"\n"));
+
+        // Round trip
+        let csv = str!("a,b\n\"1,2\",3\n");
+        let parsed = crisp_csv_parse(std::slice::from_ref(&csv), &mut env).unwrap();
+        assert_eq!(crisp_csv_write(&[parsed], &mut env).unwrap(), csv);
+
+        crisp_assert_err!(crisp_csv_write(&[list![Number(1.0)]], &mut env), TypeError);
+    }
+}
@@ -0,0 +1,149 @@
+use rust_decimal::Decimal;
+
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv};
+
+/// `decimal` converts a [`Number`](CrispExpr) or a [`String`](CrispExpr) to
+/// a [`Decimal`](CrispExpr), a base-10 number which, unlike a `Number`, can
+/// hold values such as `0.1` exactly. This makes it suitable for e.g. amounts
+/// of money. `Decimal`s can also be written directly, as a number followed by
+/// `d`, e.g. `1.10d`.
+///
+/// `Decimal`s work with the math operators, giving a `Decimal`; any `Number`s
+/// mixed in are converted first. They keep the number of decimal places they
+/// were written with, e.g. `1.10d` displays as `1.10`, but compare equal to
+/// the same value written with more or fewer places, or as a `Number`.
+///
+/// # Examples
+///
+/// ```lisp
+/// decimal "19.99"  ; => 19.99d
+/// decimal 0.5      ; => 0.5d
+/// + 0.1d 0.2d      ; => 0.3d
+/// = 1.10d 1.1d     ; => true
+/// ```
+pub fn crisp_decimal(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    match &args[0] {
+        CrispExpr::CrispString(s) => match s.trim().parse() {
+            Ok(d) => Ok(CrispExpr::Decimal(d)),
+            Err(_) => parse_error!(format!("Invalid decimal: {:?}", s))
+        },
+        expr => to_decimal(expr).map(CrispExpr::Decimal)
+    }
+}
+
+/// Converts a [`Decimal`](CrispExpr) or [`Number`](CrispExpr) to a
+/// [`Decimal`].
+pub(crate) fn to_decimal(expr: &CrispExpr) -> Result<Decimal, CrispError> {
+    match expr {
+        CrispExpr::Decimal(d) => Ok(*d),
+        CrispExpr::Number(n) => match Decimal::try_from(*n) {
+            Ok(d) => Ok(d),
+            Err(_) => standard_error!(format!("Can't convert {} to a Decimal.", n))
+        },
+        _ => type_error!("Decimal")
+    }
+}
+
+/// Folds a math operator across its arguments as [`Decimal`]s, for when any
+/// of them is one. `operation` is one of the checked operations on `Decimal`,
+/// which gives `None` if the result overflows or it divides by zero, either of
+/// which is a `MathError`.
+pub(crate) fn fold_decimal(
+    args: &[CrispExpr],
+    operation: fn(Decimal, Decimal) -> Option<Decimal>
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let decimals = args.iter().map(to_decimal).collect::<Result<Vec<Decimal>, CrispError>>()?;
+    let (first, rest) = decimals.split_first().ok_or(CrispError::ArgumentError(2, -1))?;
+
+    match rest.iter().try_fold(*first, |acc, &d| operation(acc, d)) {
+        Some(result) => Ok(CrispExpr::Decimal(result)),
+        None => math_error!("Decimal overflow or division by zero.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, eval::eval, expr::CrispExpr::{Bool, Nil, Number}};
+
+    /// A [`Decimal`](CrispExpr) of `n` with `scale` decimal places.
+    fn dec(n: i64, scale: u32) -> CrispExpr {
+        CrispExpr::Decimal(Decimal::new(n, scale))
+    }
+
+    /// Reads and evaluates `code`.
+    fn run(code: &str) -> Result<CrispExpr, CrispError> {
+        eval(&code.parse().unwrap(), &mut initialize_environment())
+    }
+
+    #[test]
+    fn test_decimal() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_decimal(&[str!("19.99")], &mut env).unwrap(), dec(1999, 2));
+        assert_eq!(crisp_decimal(&[Number(0.5)], &mut env).unwrap(), dec(5, 1));
+        assert_eq!(crisp_decimal(&[dec(1, 0)], &mut env).unwrap(), dec(1, 0));
+
+        crisp_assert_err!(crisp_decimal(&[str!("abc")], &mut env), ParseError);
+        crisp_assert_err!(crisp_decimal(&[Number(f64::NAN)], &mut env), StandardError);
+        crisp_assert_err!(crisp_decimal(&[Nil], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_decimal_literals() {
+        assert_eq!("1.10d".parse::<CrispExpr>().unwrap(), dec(110, 2));
+        assert_eq!("-3d".parse::<CrispExpr>().unwrap(), dec(-3, 0));
+        assert_eq!("d".parse::<CrispExpr>().unwrap(), sym!("d"));
+        assert_eq!("add".parse::<CrispExpr>().unwrap(), sym!("add"));
+
+        // The places written are kept
+        assert_eq!(dec(110, 2).to_string(), "1.10");
+        assert_eq!(dec(110, 2).inspect(), "1.10d");
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        assert_eq!(run("+ 0.1d 0.2d").unwrap(), dec(3, 1));
+        assert_eq!(run("- 1.00d 0.01d").unwrap(), dec(99, 2));
+        assert_eq!(run("* 19.99d 3").unwrap(), dec(5997, 2));
+        assert_eq!(run("/ 1d 4").unwrap(), dec(25, 2));
+        assert_eq!(run("mod 10.5d 3d").unwrap(), dec(15, 1));
+        // Numbers alone are unaffected
+        assert_eq!(run("+ 0.5 0.25").unwrap(), Number(0.75));
+
+        crisp_assert_err!(run("/ 1d 0"), MathError);
+        crisp_assert_err!(run("+ 1d 'a'"), TypeError);
+    }
+
+    #[test]
+    fn test_decimal_comparison() {
+        assert_eq!(run("= 1.10d 1.1d").unwrap(), Bool(true));
+        assert_eq!(run("< 0.1d 0.2d 0.3d").unwrap(), Bool(true));
+        assert_eq!(run(">= 0.3d (+ 0.1d 0.2d)").unwrap(), Bool(true));
+        crisp_assert_err!(run("< 1d 'a'"), TypeError);
+        crisp_assert_err!(crate::functions::crisp_lt(&[dec(1, 0), Number(f64::NAN)],
+                                                     &mut initialize_environment()),
+                          StandardError);
+    }
+
+    #[test]
+    fn test_mixed_comparison() {
+        // Numbers are converted, as they are for the math operators
+        assert_eq!(run("= 1d 1").unwrap(), Bool(true));
+        assert_eq!(run("= 0.1 0.1d 0.10d").unwrap(), Bool(true));
+        assert_eq!(run("= 1d 2").unwrap(), Bool(false));
+        assert_eq!(run("!= 1 1.00d").unwrap(), Bool(false));
+        assert_eq!(run("< 1d 2").unwrap(), Bool(true));
+        assert_eq!(run("< 1 1.5d 2").unwrap(), Bool(true));
+        assert_eq!(run(">= 2 2d 1.9d").unwrap(), Bool(true));
+        assert_eq!(run("> 1d 2").unwrap(), Bool(false));
+
+        assert_eq!(run("sort (2 1.5d 1)").unwrap(), list![Number(1.0), dec(15, 1), Number(2.0)]);
+        // Equal values hash the same
+        assert_eq!(run("= (hash 1.5) (hash 1.50d)").unwrap(), Bool(true));
+    }
+}
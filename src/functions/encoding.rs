@@ -0,0 +1,192 @@
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::extract_value};
+
+/// Interprets decoded bytes as the contents of a [`String`](CrispExpr).
+fn decoded_string(bytes: Vec<u8>) -> Result<CrispExpr, CrispError> {
+    String::from_utf8(bytes)
+        .map(|s| CrispExpr::CrispString(s.into()))
+        .map_err(|_| standard_error_unwrapped!("Decoded data is not valid UTF-8."))
+}
+
+/// `base64-encode` encodes a [`String`](CrispExpr) as base64.
+///
+/// # Examples
+///
+/// ```lisp
+/// base64-encode "Hello, world!"   ; => "SGVsbG8sIHdvcmxkIQ=="
+/// ```
+pub fn crisp_base64_encode(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    Ok(str!(BASE64.encode(str)))
+}
+
+/// `base64-decode` decodes a base64 [`String`](CrispExpr), the inverse of
+/// [`base64-encode`](crisp_base64_encode). It's an error if the input isn't
+/// valid base64, or if it doesn't decode to valid UTF-8 text.
+///
+/// # Examples
+///
+/// ```lisp
+/// base64-decode "SGVsbG8sIHdvcmxkIQ=="   ; => "Hello, world!"
+/// ```
+pub fn crisp_base64_decode(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    match BASE64.decode(str) {
+        Ok(bytes) => decoded_string(bytes),
+        Err(e) => standard_error!(format!("Invalid base64: {}", e))
+    }
+}
+
+/// `hex-encode` encodes a [`String`](CrispExpr) as lowercase hexadecimal,
+/// two digits per byte.
+///
+/// # Examples
+///
+/// ```lisp
+/// hex-encode "crisp"   ; => "6372697370"
+/// ```
+pub fn crisp_hex_encode(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    Ok(str!(hex::encode(str)))
+}
+
+/// `hex-decode` decodes a hexadecimal [`String`](CrispExpr) (in either case),
+/// the inverse of [`hex-encode`](crisp_hex_encode).
+///
+/// # Examples
+///
+/// ```lisp
+/// hex-decode "6372697370"   ; => "crisp"
+/// ```
+pub fn crisp_hex_decode(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    match hex::decode(str) {
+        Ok(bytes) => decoded_string(bytes),
+        Err(e) => standard_error!(format!("Invalid hex: {}", e))
+    }
+}
+
+/// Generates a builtin which returns the hex digest of a
+/// [`String`](CrispExpr) using the hash function `$hasher` from the RustCrypto
+/// crate `$krate`.
+#[cfg(feature = "digest")]
+macro_rules! digest_function {
+    ($(#[$meta:meta])* $name:ident, $krate:ident::$hasher:ident) => {
+        $(#[$meta])*
+        pub fn $name(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+            use $krate::Digest;
+
+            check_argument_error!(args, 1, 1);
+
+            let str = extract_value::<String>(args.first().unwrap())?;
+
+            Ok(str!(hex::encode($krate::$hasher::digest(str))))
+        }
+    }
+}
+
+#[cfg(feature = "digest")]
+digest_function!(
+    /// `sha256` returns the SHA-256 digest of a [`String`](CrispExpr) in
+    /// hexadecimal. Only available when crisp is built with the `digest`
+    /// feature.
+    ///
+    /// # Examples
+    ///
+    /// ```lisp
+    /// sha256 "crisp"   ; => "06df90ccef78b5002bf5d2b2c5a9ab54c58ba70be98b839161928e8d76a0d478"
+    /// ```
+    crisp_sha256, sha2::Sha256
+);
+
+#[cfg(feature = "digest")]
+digest_function!(
+    /// `sha512` returns the SHA-512 digest of a [`String`](CrispExpr) in
+    /// hexadecimal. Only available when crisp is built with the `digest`
+    /// feature.
+    crisp_sha512, sha2::Sha512
+);
+
+#[cfg(feature = "digest")]
+digest_function!(
+    /// `md5` returns the MD5 digest of a [`String`](CrispExpr) in hexadecimal.
+    /// MD5 is long broken, so this is only for checking against digests that
+    /// something else gives; use [`sha256`](crisp_sha256) for anything new.
+    /// Only available when crisp is built with the `digest` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```lisp
+    /// md5 "crisp"   ; => "cd92cb505429e1421e0f56000f17dc59"
+    /// ```
+    crisp_md5, md5::Md5
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
+
+    #[test]
+    fn test_base64() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_base64_encode(&[str!("Hello, world!")], &mut env).unwrap(),
+                   str!("SGVsbG8sIHdvcmxkIQ=="));
+        assert_eq!(crisp_base64_encode(&[str!("")], &mut env).unwrap(), str!(""));
+        assert_eq!(crisp_base64_decode(&[str!("SGVsbG8sIHdvcmxkIQ==")], &mut env).unwrap(),
+                   str!("Hello, world!"));
+
+        crisp_assert_err!(crisp_base64_decode(&[str!("not base64!")], &mut env), StandardError);
+        crisp_assert_err!(crisp_base64_decode(&[str!("/w==")], &mut env), StandardError);
+        crisp_assert_err!(crisp_base64_encode(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_hex() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_hex_encode(&[str!("crisp")], &mut env).unwrap(), str!("6372697370"));
+        assert_eq!(crisp_hex_decode(&[str!("6372697370")], &mut env).unwrap(), str!("crisp"));
+        assert_eq!(crisp_hex_decode(&[str!("C3A9")], &mut env).unwrap(), str!("é"));
+
+        crisp_assert_err!(crisp_hex_decode(&[str!("abc")], &mut env), StandardError);
+        crisp_assert_err!(crisp_hex_decode(&[str!("zz")], &mut env), StandardError);
+        crisp_assert_err!(crisp_hex_decode(&[str!("ff")], &mut env), StandardError);
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digests() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_sha256(&[str!("")], &mut env).unwrap(),
+                   str!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+        assert_eq!(crisp_sha256(&[str!("crisp")], &mut env).unwrap(),
+                   str!("06df90ccef78b5002bf5d2b2c5a9ab54c58ba70be98b839161928e8d76a0d478"));
+        assert_eq!(crisp_md5(&[str!("crisp")], &mut env).unwrap(),
+                   str!("cd92cb505429e1421e0f56000f17dc59"));
+        assert_eq!(crisp_sha512(&[str!("")], &mut env).unwrap(),
+                   str!("cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+                         47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"));
+    }
+}
@@ -0,0 +1,320 @@
+use std::{ffi::{CStr, CString, c_char, c_void}, sync::Arc};
+
+use crate::{env::{CrispEnv, env_bind, env_host},
+            error::CrispError,
+            expr::{CrispExpr, CrispLambda}};
+
+use super::{crisp_host_call, crisp_list, extract_value};
+
+/// The symbol that a native extension must export for
+/// [`extern-load`](crisp_extern_load) to find.
+const REGISTER_SYMBOL: &[u8] = b"crisp_register\0";
+
+/// [`CrispValue::tag`] for `nil`.
+pub const CRISP_NIL: u32 = 0;
+/// [`CrispValue::tag`] for a boolean, stored in `number` as `0` or `1`.
+pub const CRISP_BOOL: u32 = 1;
+/// [`CrispValue::tag`] for a number.
+pub const CRISP_NUMBER: u32 = 2;
+/// [`CrispValue::tag`] for a string, pointed to by `string`.
+pub const CRISP_STRING: u32 = 3;
+/// [`CrispValue::tag`] for an error, only as a return value. The message is
+/// pointed to by `string`, and is raised as a `StandardError`.
+pub const CRISP_ERROR: u32 = 4;
+
+/// A value passed between crisp and a native extension. Only `nil`, booleans,
+/// numbers and strings can be passed; anything else is a `TypeError`.
+///
+/// Strings are NUL-terminated UTF-8. Those passed to an extension are only
+/// valid for the duration of the call; those it returns are copied straight
+/// away, so e.g. a static buffer will do.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct CrispValue {
+    pub tag: u32,
+    pub number: f64,
+    pub string: *const c_char
+}
+
+/// A function provided by a native extension, called with a pointer to its
+/// arguments and how many there are.
+pub type CrispExternFn = unsafe extern "C" fn(args: *const CrispValue, len: usize) -> CrispValue;
+
+/// The callback that a native extension is given to define each of its
+/// functions, with the name it's bound to and the number of arguments it
+/// takes. `ctx` must be passed through unchanged.
+pub type CrispDefineFn = unsafe extern "C" fn(
+    ctx: *mut c_void,
+    name: *const c_char,
+    arity: usize,
+    func: Option<CrispExternFn>
+);
+
+/// The type of the `crisp_register` function exported by a native extension.
+/// It calls `define` for each of the functions it provides, e.g. in C:
+///
+/// ```c
+/// typedef struct { uint32_t tag; double number; const char *string; } CrispValue;
+/// typedef CrispValue (*CrispExternFn)(const CrispValue *args, size_t len);
+/// typedef void (*CrispDefineFn)(void *ctx, const char *name, size_t arity,
+///                               CrispExternFn func);
+///
+/// static CrispValue fast_sum(const CrispValue *args, size_t len) {
+///     return (CrispValue) { 2, args[0].number + args[1].number, NULL };
+/// }
+///
+/// void crisp_register(void *ctx, CrispDefineFn define) {
+///     define(ctx, "fast-sum", 2, fast_sum);
+/// }
+/// ```
+pub type CrispRegisterFn = unsafe extern "C" fn(ctx: *mut c_void, define: CrispDefineFn);
+
+/// A function defined by a native extension, as collected by [`define()`].
+struct ExternDefinition {
+    name: String,
+    arity: usize,
+    func: Option<CrispExternFn>
+}
+
+/// The [`CrispDefineFn`] given to `crisp_register`; `ctx` is the
+/// `Vec<ExternDefinition>` being collected.
+unsafe extern "C" fn define(
+    ctx: *mut c_void,
+    name: *const c_char,
+    arity: usize,
+    func: Option<CrispExternFn>
+) {
+    if ctx.is_null() || name.is_null() {
+        return;
+    }
+
+    let definitions = &mut *(ctx as *mut Vec<ExternDefinition>);
+    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+    definitions.push(ExternDefinition { name, arity, func });
+}
+
+/// `extern-load` loads a native extension from a shared library and binds
+/// the functions it defines in the current scope, returning a
+/// [`List`](CrispExpr) of their names. See [`CrispRegisterFn`] for what the
+/// library must provide. Like `let`, it can't rebind a builtin or a constant.
+///
+/// This is only available when crisp is built with the `unsafe-ffi` feature,
+/// as the library is trusted completely; nothing it does can be checked. Like
+/// the other functions that use the filesystem, it isn't allowed by a
+/// [`SandboxHost`](crate::host::SandboxHost).
+///
+/// # Examples
+///
+/// ```lisp
+/// extern-load "./libfastmath.so"   ; => ("fast-product" "fast-sum")
+/// fast-sum 1 2
+/// ```
+pub fn crisp_extern_load(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+    env_host(env).check_file_access()?;
+
+    let path = extract_value::<String>(args.first().unwrap())?;
+    let c_path = CString::new(path.clone())
+        .map_err(|_| standard_error_unwrapped!("Library path can't contain a null byte."))?;
+
+    // SAFETY: the library is trusted to be a crisp extension. It's never
+    // unloaded, so the functions it defines remain valid.
+    let mut definitions: Vec<ExternDefinition> = Vec::new();
+    unsafe {
+        let library = libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+        if library.is_null() {
+            return load_error!(path);
+        }
+
+        let symbol = libc::dlsym(library, REGISTER_SYMBOL.as_ptr() as *const c_char);
+        if symbol.is_null() {
+            return standard_error!(format!("{} is not a crisp extension \
+                                            (it doesn't export `crisp_register`).", path));
+        }
+
+        let register = std::mem::transmute::<*mut c_void, CrispRegisterFn>(symbol);
+        register(&mut definitions as *mut Vec<ExternDefinition> as *mut c_void, define);
+    }
+
+    let mut names = Vec::new();
+    for ExternDefinition { name, arity, func } in definitions {
+        let Some(func) = func else {
+            return standard_error!(format!("{} defined `{}` without a function.", path, name));
+        };
+
+        env_bind(name.clone(), extern_lambda(&name, arity, func, env), env)?;
+        names.push(name);
+    }
+
+    names.sort();
+    Ok(CrispExpr::List(names.into_iter().map(|s| CrispExpr::CrispString(s.into())).collect()))
+}
+
+/// Makes a [`Lambda`](CrispExpr) taking `arity` arguments, which calls `func`.
+/// Since a [`Func`](CrispExpr) can't hold on to `func`, it's added to `env` as
+/// a host function (see [`host-call`](crisp_host_call)), which the `Lambda`
+/// calls.
+fn extern_lambda(name: &str, arity: usize, func: CrispExternFn, env: &mut CrispEnv) -> CrispExpr {
+    let host_fn_name = format!("extern:{}", name);
+    env.host_fns.insert(host_fn_name.clone(), Arc::new(move |args| call_extern(func, args)));
+
+    let params: Vec<CrispExpr> = (0..arity).map(|i| sym!(format!("arg{}", i))).collect();
+    let mut call_args = vec![CrispExpr::Func(crisp_list)];
+    call_args.extend(params.iter().cloned());
+
+    CrispExpr::Lambda(Arc::new(CrispLambda {
+        args: Arc::new(CrispExpr::List(params)),
+        func: Arc::new(list![CrispExpr::Func(crisp_host_call), str!(host_fn_name),
+                             CrispExpr::List(call_args)]),
+        name: Some(name.into()),
+        source: None
+    }))
+}
+
+/// Converts `args` to [`CrispValue`]s, calls `func` with them, and converts
+/// the value it returns.
+fn call_extern(func: CrispExternFn, args: &[CrispExpr]) -> Result<CrispExpr, CrispError> {
+    // The strings must outlive the call
+    let mut strings = Vec::new();
+    let mut values = Vec::with_capacity(args.len());
+
+    for arg in args {
+        values.push(match arg {
+            CrispExpr::Nil => CrispValue { tag: CRISP_NIL, number: 0.0, string: std::ptr::null() },
+            CrispExpr::Bool(b) => CrispValue {
+                tag: CRISP_BOOL,
+                number: if *b { 1.0 } else { 0.0 },
+                string: std::ptr::null()
+            },
+            CrispExpr::Number(n) => CrispValue {
+                tag: CRISP_NUMBER,
+                number: *n,
+                string: std::ptr::null()
+            },
+            CrispExpr::CrispString(s) => {
+                let s = CString::new(s.as_bytes()).map_err(|_| {
+                    standard_error_unwrapped!("Strings passed to a native extension \
+                                               can't contain a null byte.")
+                })?;
+                strings.push(s);
+                CrispValue { tag: CRISP_STRING, number: 0.0, string: strings.last().unwrap().as_ptr() }
+            },
+            _ => return type_error!("Nil || Bool || Number || String")
+        });
+    }
+
+    // SAFETY: the extension is trusted to read no more than `len` arguments,
+    // and to return a valid `CrispValue`.
+    let result = unsafe { func(values.as_ptr(), values.len()) };
+    let string = || -> Result<String, CrispError> {
+        if result.string.is_null() {
+            return standard_error!("A native extension returned a null string.");
+        }
+
+        // SAFETY: as above, a non-null string is valid and NUL-terminated
+        Ok(unsafe { CStr::from_ptr(result.string) }.to_string_lossy().into_owned())
+    };
+
+    match result.tag {
+        CRISP_NIL => Ok(CrispExpr::Nil),
+        CRISP_BOOL => Ok(CrispExpr::Bool(result.number != 0.0)),
+        CRISP_NUMBER => Ok(CrispExpr::Number(result.number)),
+        CRISP_STRING => Ok(CrispExpr::CrispString(string()?.into())),
+        CRISP_ERROR => standard_error!(string()?),
+        tag => standard_error!(format!("A native extension returned an unknown tag: {}", tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, host::SandboxHost};
+
+    use std::{fs, path::{Path, PathBuf}, process::Command};
+
+    /// Compiles the C `source` into a shared library in `dir`, returning its
+    /// path.
+    fn build_extension(dir: &Path, name: &str, source: &str) -> PathBuf {
+        let header = "#include <stddef.h>\n\
+                      typedef struct { unsigned int tag; double number; const char *string; } CrispValue;\n\
+                      typedef CrispValue (*CrispExternFn)(const CrispValue *, size_t);\n\
+                      typedef void (*CrispDefineFn)(void *, const char *, size_t, CrispExternFn);\n";
+
+        let c_file = dir.join(format!("{}.c", name));
+        let library = dir.join(format!("lib{}.so", name));
+        fs::write(&c_file, format!("{}{}", header, source)).unwrap();
+
+        let status = Command::new("cc")
+            .args(["-shared", "-fPIC", "-o"]).arg(&library).arg(&c_file)
+            .status().unwrap();
+        assert!(status.success());
+
+        library
+    }
+
+    #[test]
+    fn test_extern_load_errors() {
+        let mut env = initialize_environment();
+
+        crisp_assert_err!(crisp_extern_load(&[str!("./does_not_exist.so")], &mut env), LoadError);
+        crisp_assert_err!(crisp_extern_load(&[str!("libc.so.6")], &mut env), StandardError);
+        crisp_assert_err!(crisp_extern_load(&[str!("a\0b")], &mut env), StandardError);
+        crisp_assert_err!(crisp_extern_load(&[CrispExpr::Number(1.0)], &mut env), TypeError);
+
+        // Nothing is loaded in the sandbox
+        let mut env = initialize_environment().with_host(Arc::new(SandboxHost));
+        crisp_assert_err!(crisp_extern_load(&[str!("./does_not_exist.so")], &mut env),
+                          StandardError);
+    }
+
+    #[test]
+    fn test_extern_load() {
+        let dir = std::env::temp_dir().join(format!("crisp-ffi-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let library = build_extension(&dir, "crisptest", r#"
+            static CrispValue add(const CrispValue *args, size_t len) {
+                if (args[0].tag != 2 || args[1].tag != 2)
+                    return (CrispValue) { 4, 0, "add expects numbers" };
+                return (CrispValue) { 2, args[0].number + args[1].number, NULL };
+            }
+
+            static CrispValue greet(const CrispValue *args, size_t len) {
+                return (CrispValue) { 3, 0, "hello" };
+            }
+
+            void crisp_register(void *ctx, CrispDefineFn define) {
+                define(ctx, "c-add", 2, add);
+                define(ctx, "c-greet", 0, greet);
+            }
+        "#);
+
+        let mut env = initialize_environment();
+        let path = str!(library.to_str().unwrap());
+        assert_eq!(crisp_extern_load(&[path], &mut env),
+                   Ok(list![str!("c-add"), str!("c-greet")]));
+
+        let mut eval = |code: &str| crate::eval::eval(&code.parse().unwrap(), &mut env);
+        assert_eq!(eval("c-add 1 2"), Ok(CrispExpr::Number(3.0)));
+        assert_eq!(eval("(c-greet)"), Ok(str!("hello")));
+        crisp_assert_err!(eval("c-add 1 \"2\""), StandardError);
+        crisp_assert_err!(eval("c-add 1 (1 2)"), TypeError);
+        crisp_assert_err!(eval("c-add 1"), ArgumentError);
+
+        // Builtins and constants can't be rebound, just like with `let`
+        let library = build_extension(&dir, "crispmap", r#"
+            static CrispValue nothing(const CrispValue *args, size_t len) {
+                return (CrispValue) { 0, 0, NULL };
+            }
+
+            void crisp_register(void *ctx, CrispDefineFn define) {
+                define(ctx, "map", 0, nothing);
+            }
+        "#);
+        let mut env = initialize_environment();
+        crisp_assert_err!(crisp_extern_load(&[str!(library.to_str().unwrap())], &mut env),
+                          StandardError);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::{error::CrispError, expr::CrispExpr, env::{CrispEnv, env_host},
+            functions::extract_value};
+
+/// Converts an IO error on `path` into a [`CrispError`].
+fn io_error(e: io::Error, path: &str) -> CrispError {
+    match e.kind() {
+        io::ErrorKind::NotFound => CrispError::LoadError(path.to_string()),
+        _ => standard_error_unwrapped!(format!("{}: {}", path, e))
+    }
+}
+
+/// Extracts the optional `recursive` flag given to e.g. [`mkdir`](crisp_mkdir).
+fn recursive_flag(args: &[CrispExpr]) -> Result<bool, CrispError> {
+    match args.get(1) {
+        Some(expr) => extract_value::<bool>(expr),
+        None => Ok(false)
+    }
+}
+
+/// `ls` lists the names of the entries in a directory (the current directory
+/// by default) as a sorted [`List`](CrispExpr) of [`String`](CrispExpr)s.
+///
+/// # Usage
+///
+/// ```lisp
+/// ls [path]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// ls          ; => ('Cargo.toml' 'src' 'tests')
+/// ls "src"    ; => ('env.rs' 'eval.rs' ...)
+/// ```
+pub fn crisp_ls(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 1);
+    env_host(env).check_file_access()?;
+
+    let path = match args.first() {
+        Some(expr) => extract_value::<String>(expr)?,
+        None => ".".to_string()
+    };
+
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries.map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                   .collect::<io::Result<Vec<String>>>()
+        })
+        .map_err(|e| io_error(e, &path))?;
+    names.sort();
+
+    Ok(CrispExpr::List(names.into_iter().map(|s| CrispExpr::CrispString(s.into())).collect()))
+}
+
+/// `mkdir` creates a directory. If the second argument is `true`, any missing
+/// parent directories are created too, and it's not an error if the directory
+/// already exists.
+///
+/// # Usage
+///
+/// ```lisp
+/// mkdir path [recursive]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// mkdir "out"
+/// mkdir "out/reports/2024" true
+/// ```
+pub fn crisp_mkdir(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+    env_host(env).check_file_access()?;
+
+    let path = extract_value::<String>(args.first().unwrap())?;
+
+    let result = if recursive_flag(args)? {
+        fs::create_dir_all(&path)
+    } else {
+        fs::create_dir(&path)
+    };
+
+    result.map(|_| CrispExpr::Nil).map_err(|e| io_error(e, &path))
+}
+
+/// `rm` removes a file or an empty directory. If the second argument is
+/// `true`, directories are removed along with everything in them.
+///
+/// # Usage
+///
+/// ```lisp
+/// rm path [recursive]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// rm "out/old.txt"
+/// rm "out" true
+/// ```
+pub fn crisp_rm(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+    env_host(env).check_file_access()?;
+
+    let path = extract_value::<String>(args.first().unwrap())?;
+    let recursive = recursive_flag(args)?;
+
+    let result = match fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.is_dir() && recursive => fs::remove_dir_all(&path),
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir(&path),
+        Ok(_) => fs::remove_file(&path),
+        Err(e) => Err(e)
+    };
+
+    result.map(|_| CrispExpr::Nil).map_err(|e| io_error(e, &path))
+}
+
+/// Copies the directory `from` to `to`, along with everything in it. `to`
+/// can't be inside `from`, or the copy would never end.
+///
+/// # Returns
+///
+/// On failure, the error along with the path that it happened on.
+pub fn copy_dir(from: &Path, to: &Path) -> Result<(), (io::Error, PathBuf)> {
+    let source = from.canonicalize().map_err(|e| (e, from.to_path_buf()))?;
+    if canonical_target(to).starts_with(source) {
+        let e = io::Error::new(io::ErrorKind::InvalidInput, "can't copy a directory into itself");
+        return Err((e, to.to_path_buf()));
+    }
+
+    copy_tree(from, to)
+}
+
+/// Does the copying for [`copy_dir()`], once the destination is known to be
+/// outside of the source.
+fn copy_tree(from: &Path, to: &Path) -> Result<(), (io::Error, PathBuf)> {
+    fs::create_dir_all(to).map_err(|e| (e, to.to_path_buf()))?;
+
+    for entry in fs::read_dir(from).map_err(|e| (e, from.to_path_buf()))? {
+        let entry = entry.map_err(|e| (e, from.to_path_buf()))?;
+        let path = entry.path();
+        let target = to.join(entry.file_name());
+
+        if entry.file_type().map_err(|e| (e, path.clone()))?.is_dir() {
+            copy_tree(&path, &target)?;
+        } else {
+            fs::copy(&path, &target).map_err(|e| (e, failed_path(&path, &target).to_path_buf()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The canonical form of `path`, which may not exist yet: the canonical form
+/// of its closest ancestor that does, followed by the rest of it.
+fn canonical_target(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => path.to_path_buf()
+    };
+
+    for ancestor in path.ancestors() {
+        if let Ok(canonical) = ancestor.canonicalize() {
+            return canonical.join(path.strip_prefix(ancestor).unwrap());
+        }
+    }
+
+    path
+}
+
+/// Works out which of `from` and `to` a failed copy or move between them
+/// failed on: `from` if it isn't there, and otherwise `to`.
+fn failed_path<'a>(from: &'a Path, to: &'a Path) -> &'a Path {
+    match fs::symlink_metadata(from) {
+        Ok(_) => to,
+        Err(_) => from
+    }
+}
+
+/// `cp` copies a file, replacing the destination if it exists. Directories
+/// are copied along with everything in them.
+///
+/// # Examples
+///
+/// ```lisp
+/// cp "config.example" "config"
+/// cp "assets" "out/assets"
+/// ```
+pub fn crisp_cp(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+    env_host(env).check_file_access()?;
+
+    let from = extract_value::<String>(args.first().unwrap())?;
+    let to = extract_value::<String>(args.get(1).unwrap())?;
+
+    let (from, to) = (Path::new(&from), Path::new(&to));
+    let result = if from.is_dir() {
+        copy_dir(from, to)
+    } else {
+        fs::copy(from, to).map(|_| ()).map_err(|e| (e, failed_path(from, to).to_path_buf()))
+    };
+
+    result.map(|_| CrispExpr::Nil).map_err(|(e, path)| io_error(e, &path.display().to_string()))
+}
+
+/// `mv` moves (renames) a file or directory, replacing the destination if it
+/// is a file that exists.
+///
+/// # Examples
+///
+/// ```lisp
+/// mv "report.tmp" "report.txt"
+/// ```
+pub fn crisp_mv(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+    env_host(env).check_file_access()?;
+
+    let from = extract_value::<String>(args.first().unwrap())?;
+    let to = extract_value::<String>(args.get(1).unwrap())?;
+
+    let (from, to) = (Path::new(&from), Path::new(&to));
+    fs::rename(from, to).map(|_| CrispExpr::Nil).map_err(|e| {
+        io_error(e, &failed_path(from, to).display().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
+
+    /// Creates an empty scratch directory for a test, unique to `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("crisp-fs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn path_str(path: &Path) -> CrispExpr {
+        str!(path.display())
+    }
+
+    #[test]
+    fn test_ls() {
+        let mut env = initialize_environment();
+
+        let listing = crisp_ls(&[str!("tests/runner")], &mut env).unwrap();
+        assert_eq!(listing, list![str!("fail"), str!("pass")]);
+
+        crisp_assert_err!(crisp_ls(&[str!("tests/does_not_exist")], &mut env), LoadError);
+        crisp_assert_err!(crisp_ls(&[str!("Cargo.toml")], &mut env), StandardError);
+    }
+
+    #[test]
+    fn test_mkdir_and_rm() {
+        let mut env = initialize_environment();
+        let dir = scratch_dir("mkdir");
+
+        let nested = dir.join("a/b");
+        crisp_assert_err!(crisp_mkdir(&[path_str(&nested)], &mut env), LoadError);
+        assert_eq!(crisp_mkdir(&[path_str(&nested), Bool(true)], &mut env).unwrap(), Nil);
+        assert!(nested.is_dir());
+
+        // Recursive `mkdir` doesn't mind if the directory exists
+        assert_eq!(crisp_mkdir(&[path_str(&nested), Bool(true)], &mut env).unwrap(), Nil);
+        crisp_assert_err!(crisp_mkdir(&[path_str(&nested)], &mut env), StandardError);
+
+        fs::write(nested.join("file.txt"), "hi").unwrap();
+        assert_eq!(crisp_rm(&[path_str(&nested.join("file.txt"))], &mut env).unwrap(), Nil);
+        assert!(!nested.join("file.txt").exists());
+
+        fs::write(nested.join("file.txt"), "hi").unwrap();
+        crisp_assert_err!(crisp_rm(&[path_str(&dir.join("a"))], &mut env), StandardError);
+        assert_eq!(crisp_rm(&[path_str(&dir.join("a")), Bool(true)], &mut env).unwrap(), Nil);
+        assert!(!dir.join("a").exists());
+
+        crisp_assert_err!(crisp_rm(&[path_str(&dir.join("a"))], &mut env), LoadError);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cp_and_mv() {
+        let mut env = initialize_environment();
+        let dir = scratch_dir("cp");
+
+        fs::create_dir_all(dir.join("src/inner")).unwrap();
+        fs::write(dir.join("src/one.txt"), "one").unwrap();
+        fs::write(dir.join("src/inner/two.txt"), "two").unwrap();
+
+        let args = [path_str(&dir.join("src/one.txt")), path_str(&dir.join("copy.txt"))];
+        assert_eq!(crisp_cp(&args, &mut env).unwrap(), Nil);
+        assert_eq!(fs::read_to_string(dir.join("copy.txt")).unwrap(), "one");
+
+        let args = [path_str(&dir.join("src")), path_str(&dir.join("dest"))];
+        assert_eq!(crisp_cp(&args, &mut env).unwrap(), Nil);
+        assert_eq!(fs::read_to_string(dir.join("dest/inner/two.txt")).unwrap(), "two");
+
+        let args = [path_str(&dir.join("copy.txt")), path_str(&dir.join("moved.txt"))];
+        assert_eq!(crisp_mv(&args, &mut env).unwrap(), Nil);
+        assert!(!dir.join("copy.txt").exists());
+        assert_eq!(fs::read_to_string(dir.join("moved.txt")).unwrap(), "one");
+
+        crisp_assert_err!(crisp_mv(&args, &mut env), LoadError);
+        crisp_assert_err!(crisp_cp(&args, &mut env), LoadError);
+
+        // The error names the path that failed, which here is the destination
+        let missing = dir.join("missing/moved.txt");
+        let args = [path_str(&dir.join("moved.txt")), path_str(&missing)];
+        assert_eq!(crisp_mv(&args, &mut env).unwrap_err(),
+                   CrispError::LoadError(missing.display().to_string()));
+        assert_eq!(crisp_cp(&args, &mut env).unwrap_err(),
+                   CrispError::LoadError(missing.display().to_string()));
+
+        // A directory can't be copied into itself
+        let args = [path_str(&dir.join("src")), path_str(&dir.join("src/inner/copy"))];
+        crisp_assert_err!(crisp_cp(&args, &mut env), StandardError);
+        assert!(!dir.join("src/inner/copy").exists());
+        let args = [path_str(&dir.join("src")), path_str(&dir.join("dest/../src"))];
+        crisp_assert_err!(crisp_cp(&args, &mut env), StandardError);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sandbox() {
+        use crate::host::SandboxHost;
+        use std::sync::Arc;
+
+        let mut env = initialize_environment().with_host(Arc::new(SandboxHost));
+        crisp_assert_err!(crisp_ls(&[], &mut env), StandardError);
+        crisp_assert_err!(crisp_rm(&[str!("Cargo.toml")], &mut env), StandardError);
+    }
+}
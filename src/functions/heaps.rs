@@ -0,0 +1,198 @@
+use std::{collections::BinaryHeap, sync::{Arc, Mutex, MutexGuard}};
+
+use crate::{error::CrispError, expr::{CrispExpr, CrispHeap, CrispHeapEntry}, env::CrispEnv,
+            functions::{call_function, extract_value}};
+
+/// `heap-new` creates an empty [`Heap`](CrispExpr), a priority queue which
+/// always gives back its smallest value first. Values are pushed with
+/// [`heap-push!`](crisp_heap_push) and taken back out with
+/// [`heap-pop!`](crisp_heap_pop).
+///
+/// Without a `key`, the values must be [`Number`](CrispExpr)s. Otherwise,
+/// `key` is called with each value as it is pushed, and should return the
+/// `Number` to order it by. Values with equal priority come out in the order
+/// they were pushed.
+///
+/// Like an [`Atom`](CrispExpr), every copy of a `Heap` refers to the same
+/// queue.
+///
+/// # Usage
+///
+/// ```lisp
+/// heap-new [key]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let h (heap-new)
+/// heap-push! h 3
+/// heap-push! h 1
+/// heap-pop! h     ; => 1
+///
+/// ; A max-heap
+/// let h (heap-new (\ n (* -1 n)))
+/// heap-push! h 1
+/// heap-push! h 3
+/// heap-pop! h     ; => 3
+/// ```
+pub fn crisp_heap_new(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 1);
+
+    if let Some(key) = args.first() {
+        if !matches!(key, CrispExpr::Func(_) | CrispExpr::Lambda(_)) {
+            return type_error!("Func || Lambda");
+        }
+    }
+
+    Ok(CrispExpr::Heap(Arc::new(Mutex::new(CrispHeap {
+        entries: BinaryHeap::new(),
+        key: args.first().cloned(),
+        pushes: 0
+    }))))
+}
+
+/// `heap-push!` adds a value to a [`Heap`](CrispExpr), returning the value.
+///
+/// # Usage
+///
+/// ```lisp
+/// heap-push! heap value
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let h (heap-new)
+/// heap-push! h 5   ; => 5
+/// ```
+pub fn crisp_heap_push(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let heap = extract_value::<Arc<Mutex<CrispHeap>>>(&args[0])?;
+    let value = args[1].clone();
+
+    // The key is called without holding on to the Heap, so it may use it
+    let key = lock_heap(&heap).key.clone();
+    let priority = match key {
+        Some(key) => call_function(&key, std::slice::from_ref(&value), env)?,
+        None => value.clone()
+    };
+    let priority = extract_value::<f64>(&priority)?;
+
+    let mut heap = lock_heap(&heap);
+    let order = heap.pushes;
+    heap.pushes += 1;
+    heap.entries.push(CrispHeapEntry { priority, order, value: value.clone() });
+
+    Ok(value)
+}
+
+/// `heap-pop!` removes and returns the smallest value of a
+/// [`Heap`](CrispExpr), or `nil` if it's empty.
+///
+/// # Examples
+///
+/// ```lisp
+/// let h (heap-new)
+/// heap-push! h 2
+/// heap-push! h 1
+/// heap-pop! h     ; => 1
+/// heap-pop! h     ; => 2
+/// heap-pop! h     ; => nil
+/// ```
+pub fn crisp_heap_pop(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let heap = extract_value::<Arc<Mutex<CrispHeap>>>(&args[0])?;
+    let entry = lock_heap(&heap).entries.pop();
+
+    Ok(entry.map_or(CrispExpr::Nil, |entry| entry.value))
+}
+
+/// `heap-peek` returns the smallest value of a [`Heap`](CrispExpr) without
+/// removing it, or `nil` if it's empty.
+///
+/// # Examples
+///
+/// ```lisp
+/// let h (heap-new)
+/// heap-peek h      ; => nil
+/// heap-push! h 1
+/// heap-peek h      ; => 1
+/// ```
+pub fn crisp_heap_peek(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let heap = extract_value::<Arc<Mutex<CrispHeap>>>(&args[0])?;
+    let value = lock_heap(&heap).entries.peek().map(|entry| entry.value.clone());
+
+    Ok(value.unwrap_or(CrispExpr::Nil))
+}
+
+/// Locks a [`Heap`](CrispExpr) for reading or writing.
+fn lock_heap(heap: &Mutex<CrispHeap>) -> MutexGuard<'_, CrispHeap> {
+    // A panic in another thread can't leave the heap half-written
+    heap.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment};
+
+    #[test]
+    fn test_heap() {
+        let mut env = initialize_environment();
+        let heap = crisp_heap_new(&[], &mut env).unwrap();
+
+        for n in [5.0, 1.0, 4.0, 1.0, 3.0] {
+            assert_eq!(crisp_heap_push(&[heap.clone(), Number(n)], &mut env).unwrap(), Number(n));
+        }
+
+        assert_eq!(crisp_heap_peek(std::slice::from_ref(&heap), &mut env).unwrap(), Number(1.0));
+        let popped: Vec<CrispExpr> = (0..6).map(|_| {
+            crisp_heap_pop(std::slice::from_ref(&heap), &mut env).unwrap()
+        }).collect();
+        assert_eq!(popped, [Number(1.0), Number(1.0), Number(3.0), Number(4.0), Number(5.0), Nil]);
+        assert_eq!(crisp_heap_peek(std::slice::from_ref(&heap), &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(crisp_heap_push(&[heap, str!("a")], &mut env), TypeError);
+        crisp_assert_err!(crisp_heap_pop(&[Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_heap_new(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_heap_key() {
+        let mut env = initialize_environment();
+        let negate = lambda![args: ["n"], func: [sym!("*"), Number(-1.0), sym!("n")]];
+        let heap = crisp_heap_new(&[negate], &mut env).unwrap();
+
+        for n in [2.0, 7.0, -1.0] {
+            crisp_heap_push(&[heap.clone(), Number(n)], &mut env).unwrap();
+        }
+
+        let popped: Vec<CrispExpr> = (0..3).map(|_| {
+            crisp_heap_pop(std::slice::from_ref(&heap), &mut env).unwrap()
+        }).collect();
+        assert_eq!(popped, [Number(7.0), Number(2.0), Number(-1.0)]);
+
+        // Equal priorities come out in the order they were pushed
+        let constant = lambda![args: ["s"], func: [sym!("+"), Number(0.0), Number(1.0)]];
+        let heap = crisp_heap_new(&[constant], &mut env).unwrap();
+        for s in ["b", "c", "a"] {
+            crisp_heap_push(&[heap.clone(), str!(s)], &mut env).unwrap();
+        }
+
+        let popped: Vec<CrispExpr> = (0..3).map(|_| {
+            crisp_heap_pop(std::slice::from_ref(&heap), &mut env).unwrap()
+        }).collect();
+        assert_eq!(popped, [str!("b"), str!("c"), str!("a")]);
+
+        // The key must give a Number
+        let identity = lambda![args: ["s"], func: [sym!("str-concat"), sym!("s")]];
+        let heap = crisp_heap_new(&[identity], &mut env).unwrap();
+        crisp_assert_err!(crisp_heap_push(&[heap, str!("x")], &mut env), TypeError);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv};
+use std::{io::{self, BufRead, Write}, sync::MutexGuard};
+
+use crate::{error::CrispError, expr::CrispExpr,
+            env::{CrispEnv, CrispWriter, env_stderr, env_stdin, env_stdout}};
 
 use dyn_fmt::AsStrFormatExt;
 
@@ -8,23 +11,86 @@ use dyn_fmt::AsStrFormatExt;
 /// and `}}` to escape the `{` and `}` characters in strings that are being
 /// interpolated.
 ///
+/// A [`List`](CrispExpr) can be interpolated with `{*sep}` rather than `{}`,
+/// which gives its items separated by `sep` instead of the whole list. `{*}`
+/// separates them with `, `.
+///
+/// Variables can also be interpolated by name in double-quoted strings, which
+/// are read as calls to `format`: `"$x and ${y}"` is the same as
+/// `(format "{} and {}" x y)`.
+///
 /// # Examples
 ///
 /// ```lisp
-/// format "{}" 5         ; => "5"
-/// format "{}: {}" "n" 5 ; => "n: 5"
+/// format "{}" 5                ; => "5"
+/// format "{}: {}" "n" 5        ; => "n: 5"
+/// format "{}" (1 "a")          ; => "(1 'a')"
+/// format "[{*}]" (1 2 3)       ; => "[1, 2, 3]"
+/// format "{* | }" ("a" "b" "c") ; => "a | b | c"
 /// ```
 pub fn crisp_format(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     if let Some((format_str, format_args)) = args.split_first() {
         return Ok(str!(match format_args {
             [] => format!("{}", format_str),
-            _ => format_str.to_string().format(format_args)
+            _ => {
+                let (format_str, format_args) = splice_lists(&format_str.to_string(),
+                                                             format_args)?;
+                format_str.format(&format_args)
+            }
         }));
     }
 
     argument_error!(1, -1)
 }
 
+/// Replaces each `{*sep}` placeholder in `format_str` with a plain `{}`, and
+/// the [`List`](CrispExpr) it refers to in `args` with its items joined by
+/// `sep`, ready to be formatted. See [`crisp_format()`].
+fn splice_lists(
+    format_str: &str,
+    args: &[CrispExpr]
+) -> Result<(String, Vec<CrispExpr>), CrispError> {
+    let (mut spliced, mut args) = (String::new(), args.to_vec());
+    let (mut rest, mut index) = (format_str, 0);
+
+    while let Some(start) = rest.find('{') {
+        let (before, placeholder) = rest.split_at(start);
+        spliced.push_str(before);
+
+        let splice = placeholder.strip_prefix("{*").and_then(|splice| splice.split_once('}'));
+
+        rest = if let Some(after) = placeholder.strip_prefix("{{") {
+            spliced.push_str("{{");
+            after
+        } else if let Some((sep, after)) = splice {
+            let sep = if sep.is_empty() { ", " } else { sep };
+
+            if let Some(arg) = args.get_mut(index) {
+                let CrispExpr::List(items) = arg else {
+                    return type_error!("List");
+                };
+
+                let items: Vec<String> = items.iter().map(CrispExpr::to_string).collect();
+                *arg = str!(items.join(sep));
+            }
+
+            spliced.push_str("{}");
+            index += 1;
+            after
+        } else if let Some(after) = placeholder.strip_prefix("{}") {
+            spliced.push_str("{}");
+            index += 1;
+            after
+        } else {
+            spliced.push('{');
+            &placeholder[1..]
+        };
+    }
+
+    spliced.push_str(rest);
+    Ok((spliced, args))
+}
+
 /// `puts` prints the specified value followed by a newline. It takes
 /// format parameters similar to [`format`](crisp_format).
 ///
@@ -36,11 +102,11 @@ pub fn crisp_format(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr
 /// ```
 pub fn crisp_puts(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     if args.is_empty() {
-        println!();
+        write_stdout("\n", env)?;
         Ok(CrispExpr::Nil)
     } else {
         let value = crisp_format(args, env)?;
-        println!("{}", value);
+        write_stdout(&format!("{}\n", value), env)?;
 
         Ok(value)
     }
@@ -58,15 +124,293 @@ pub fn crisp_puts(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, C
 /// ```
 pub fn crisp_print(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     let value = crisp_format(args, env)?;
-    print!("{}", value);
+    write_stdout(&value.to_string(), env)?;
+
+    Ok(value)
+}
+
+/// `write` prints the specified values in a form that can be read back in, as
+/// returned by [`inspect`](crisp_inspect), separated by spaces and with no
+/// newline.
+///
+/// # Examples
+///
+/// ```lisp
+/// write "foo"       ; Prints: 'foo'
+/// write ("a b" ,c)  ; Prints: ("a b" ,c)
+/// ```
+pub fn crisp_write(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let written = args.iter().map(|arg| arg.inspect()).collect::<Vec<String>>().join(" ");
+    write_stdout(&written, env)?;
+
+    Ok(str!(written))
+}
+
+/// `inspect` returns a [`String`](CrispExpr) containing a value in a form that
+/// can be read back in, e.g. with quotes around `String`s. Compare to
+/// [`format`](crisp_format), which returns `String`s as they are.
+///
+/// # Examples
+///
+/// ```lisp
+/// inspect "foo"          ; => "'foo'"
+/// inspect ("foo" 1 ,c)   ; => "('foo' 1 ,c)"
+/// format "{}" "foo"      ; => "foo"
+/// ```
+pub fn crisp_inspect(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(str!(args.first().unwrap().inspect()))
+}
+
+/// `eputs` works like [`puts`](crisp_puts), but prints to stderr.
+///
+/// # Examples
+///
+/// ```lisp
+/// eputs "Something went wrong!"
+/// eputs "Line: {}" 5
+/// ```
+pub fn crisp_eputs(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    if args.is_empty() {
+        write_stderr("\n", env)?;
+        Ok(CrispExpr::Nil)
+    } else {
+        let value = crisp_format(args, env)?;
+        write_stderr(&format!("{}\n", value), env)?;
+
+        Ok(value)
+    }
+}
+
+/// `eprint` works like [`print`](crisp_print), but prints to stderr.
+///
+/// # Examples
+///
+/// ```lisp
+/// eprint "Warning: "
+/// eputs "disk is almost full"
+/// ```
+pub fn crisp_eprint(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let value = crisp_format(args, env)?;
+    write_stderr(&value.to_string(), env)?;
 
     Ok(value)
 }
 
+/// `flush` flushes stdout and stderr, so that anything written with
+/// [`print`](crisp_print) or [`eprint`](crisp_eprint) is displayed right away.
+///
+/// # Examples
+///
+/// ```lisp
+/// print "Working... "
+/// flush
+/// ```
+pub fn crisp_flush(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    let stdout = match env_stdout(env) {
+        Some(writer) => lock_writer(&writer).flush(),
+        None => io::stdout().flush()
+    };
+    let stderr = match env_stderr(env) {
+        Some(writer) => lock_writer(&writer).flush(),
+        None => io::stderr().flush()
+    };
+
+    stdout.and(stderr)
+          .map_err(|e| standard_error_unwrapped!(format!("Couldn't flush output: {}", e)))?;
+
+    Ok(CrispExpr::Nil)
+}
+
+/// `gets` reads a line of input from stdin, returning it as a
+/// [`String`](CrispExpr) without the line ending. At the end of the input,
+/// `gets` returns `nil`.
+///
+/// # Examples
+///
+/// ```lisp
+/// print "What's your name? "
+/// flush
+/// puts "Hello, {}!" (gets)
+/// ```
+pub fn crisp_gets(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    let mut line = String::new();
+    let read = match env_stdin(env) {
+        Some(reader) => reader.lock()
+                              .unwrap_or_else(|poisoned| poisoned.into_inner())
+                              .read_line(&mut line),
+        None => io::stdin().lock().read_line(&mut line)
+    };
+
+    match read {
+        Ok(0) => Ok(CrispExpr::Nil),
+        Ok(_) => {
+            let len = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(len);
+
+            Ok(str!(line))
+        },
+        Err(e) => standard_error!(format!("Couldn't read input: {}", e))
+    }
+}
+
+/// Writes `text` to stdout, or wherever it has been redirected to within the
+/// scope `env`.
+pub(crate) fn write_stdout(text: &str, env: &CrispEnv) -> Result<(), CrispError> {
+    match env_stdout(env) {
+        Some(writer) => write_to(&writer, text),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Writes `text` to stderr, or wherever it has been redirected to within the
+/// scope `env`.
+fn write_stderr(text: &str, env: &CrispEnv) -> Result<(), CrispError> {
+    match env_stderr(env) {
+        Some(writer) => write_to(&writer, text),
+        None => {
+            eprint!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Writes `text` to a redirected output stream.
+fn write_to(writer: &CrispWriter, text: &str) -> Result<(), CrispError> {
+    lock_writer(writer)
+        .write_all(text.as_bytes())
+        .map_err(|e| standard_error_unwrapped!(format!("Couldn't write output: {}", e)))
+}
+
+/// Locks a redirected output stream for writing.
+fn lock_writer(writer: &CrispWriter) -> MutexGuard<'_, dyn Write + Send + 'static> {
+    // A panic while writing doesn't leave the writer unusable
+    writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{expr::CrispExpr::*, env::initialize_environment};
+    use crate::{expr::CrispExpr::*, env::initialize_environment, functions::extract_value};
+    use std::{slice, sync::{Arc, Mutex}};
+
+    type Buffer = Arc<Mutex<Vec<u8>>>;
+
+    /// Redirects stdout and stderr in `env` to buffers, which are returned.
+    fn capture(env: &mut CrispEnv) -> (Buffer, Buffer) {
+        let (stdout, stderr) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        env.stdout = Some(stdout.clone());
+        env.stderr = Some(stderr.clone());
+
+        (stdout, stderr)
+    }
+
+    fn contents(buffer: &Mutex<Vec<u8>>) -> String {
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn test_puts_print() {
+        let mut env = initialize_environment();
+        let (stdout, stderr) = capture(&mut env);
+
+        assert_eq!(crisp_puts(&[str!("{} {}"), str!("foo"), Number(1.0)], &mut env).unwrap(),
+                   str!("foo 1"));
+        crisp_puts(&[], &mut env).unwrap();
+        assert_eq!(crisp_print(&[str!("bar")], &mut env).unwrap(), str!("bar"));
+
+        assert_eq!(contents(&stdout), "foo 1\n\nbar");
+        assert_eq!(contents(&stderr), "");
+    }
+
+    #[test]
+    fn test_eputs_eprint() {
+        let mut env = initialize_environment();
+        let (stdout, stderr) = capture(&mut env);
+
+        crisp_eputs(&[str!("foo")], &mut env).unwrap();
+        crisp_eprint(&[str!("bar")], &mut env).unwrap();
+        crisp_flush(&[], &mut env).unwrap();
+
+        assert_eq!(contents(&stdout), "");
+        assert_eq!(contents(&stderr), "foo\nbar");
+    }
+
+    #[test]
+    fn test_write_inspect() {
+        let mut env = initialize_environment();
+        let (stdout, _) = capture(&mut env);
+
+        assert_eq!(crisp_inspect(&[str!("foo")], &mut env).unwrap(), str!("'foo'"));
+        assert_eq!(crisp_inspect(&[str!("a'b")], &mut env).unwrap(), str!("\"a'b\""));
+        assert_eq!(crisp_inspect(&[list![str!("foo"), Number(1.0), Char('c'), Nil]], &mut env)
+                       .unwrap(),
+                   str!("('foo' 1 ,c nil)"));
+        assert_eq!(crisp_inspect(&[list![list![str!("nested")]]], &mut env).unwrap(),
+                   str!("(('nested'))"));
+        assert_eq!(crisp_inspect(&[sym!("foo")], &mut env).unwrap(), str!("foo"));
+        assert_eq!(crisp_inspect(&[list![Char(' '), Char('\n'), Char('\u{7}'), Char('é')]],
+                                 &mut env).unwrap(),
+                   str!("(,space ,newline ,u0007 ,é)"));
+
+        crisp_write(&[str!("foo"), Number(2.0)], &mut env).unwrap();
+        assert_eq!(contents(&stdout), "'foo' 2");
+    }
+
+    #[test]
+    fn test_inspect_reads_back() {
+        let mut env = initialize_environment();
+
+        let value = list![
+            str!("foo bar"),
+            str!("it's"),
+            Number(-1.5),
+            Bool(true),
+            Char('x'),
+            Char(' '),
+            Char('\u{1b}')
+        ];
+        let inspected = extract_value::<String>(&crisp_inspect(slice::from_ref(&value), &mut env)
+                                                     .unwrap()).unwrap();
+
+        let tokens = crate::reader::tokenize(inspected);
+        let (parsed, _) = crate::reader::parse(&tokens).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    #[test]
+    fn test_gets() {
+        let input = io::Cursor::new("foo\r\n\nbar".as_bytes().to_vec());
+        let mut env = initialize_environment().with_stdin(Arc::new(Mutex::new(input)));
+
+        assert_eq!(crisp_gets(&[], &mut env).unwrap(), str!("foo"));
+        assert_eq!(crisp_gets(&[], &mut env).unwrap(), str!(""));
+        assert_eq!(crisp_gets(&[], &mut env).unwrap(), str!("bar"));
+        assert_eq!(crisp_gets(&[], &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(crisp_gets(&[Nil], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_output_in_nested_scope() {
+        let mut env = initialize_environment();
+        let (stdout, _) = capture(&mut env);
+
+        let mut child = crate::env::env_new_scope(Default::default(), &env);
+        crisp_puts(&[str!("foo")], &mut child).unwrap();
+
+        assert_eq!(contents(&stdout), "foo\n");
+    }
 
     #[test]
     fn test_format() {
@@ -110,6 +454,32 @@ mod tests {
         assert_eq!(result, str!("1 a 2 b true"));
     }
 
+    #[test]
+    fn test_format_list() {
+        let mut env = initialize_environment();
+        let list = list![Number(1.0), str!("a"), list![Bool(true)]];
+
+        assert_eq!(crisp_format(&[str!("{}"), list.clone()], &mut env).unwrap(),
+                   str!("(1 'a' (true))"));
+        assert_eq!(crisp_format(&[str!("[{*}]"), list.clone()], &mut env).unwrap(),
+                   str!("[1, a, (true)]"));
+        assert_eq!(crisp_format(&[str!("{} {* | } {}"), Number(0.0), list.clone(), Number(2.0)],
+                                &mut env).unwrap(),
+                   str!("0 1 | a | (true) 2"));
+        assert_eq!(crisp_format(&[str!("({*})"), list![]], &mut env).unwrap(),
+                   str!("()"));
+
+        // Escaped braces aren't placeholders
+        assert_eq!(crisp_format(&[str!("{{*}} {*-}"), num_list![1.0, 2.0]], &mut env).unwrap(),
+                   str!("{*} 1-2"));
+
+        // Missing arguments are left empty, as with `{}`
+        assert_eq!(crisp_format(&[str!("{} {*}"), Number(1.0)], &mut env).unwrap(),
+                   str!("1 "));
+
+        crisp_assert_err!(crisp_format(&[str!("{*}"), Number(1.0)], &mut env), TypeError);
+    }
+
     #[test]
     fn test_format_escape() {
         let mut env = initialize_environment();
@@ -0,0 +1,308 @@
+use std::{mem, sync::{Arc, Mutex, TryLockError}};
+
+use crate::{error::CrispError, expr::{CrispDelay, CrispExpr, CrispGenerator}, env::CrispEnv,
+            eval::{eval, resume_generator, yield_value}, functions::extract_value};
+
+/// `force` evaluates a [`Delay`](CrispExpr) made by
+/// [`delay`](crate::keywords), returning the result. The expression is only
+/// evaluated the first time; after that, the same result is returned again.
+/// Any other value is returned as-is.
+///
+/// # Examples
+///
+/// ```lisp
+/// let d (delay (+ 1 2))
+/// force d               ; => 3
+/// force 5               ; => 5
+/// ```
+pub fn crisp_force(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let delay = match args.first().unwrap() {
+        CrispExpr::Delay(delay) => delay,
+        expr => return Ok(expr.clone())
+    };
+
+    // Fails rather than waiting, so that a Delay which forces itself doesn't
+    // deadlock
+    let mut state = match delay.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return standard_error!("Delay is already being forced.")
+    };
+
+    let result = match mem::replace(&mut *state, CrispDelay::Done(Ok(CrispExpr::Nil))) {
+        CrispDelay::Pending(expr, mut snapshot) => eval(&expr, &mut snapshot),
+        CrispDelay::Done(result) => result
+    };
+
+    *state = CrispDelay::Done(result.clone());
+    result
+}
+
+/// `next` asks a [`Generator`](CrispExpr) for its next value, evaluating its
+/// body until it reaches a [`yield`](crisp_yield). Once the body has
+/// finished, `next` returns `nil`.
+///
+/// # Examples
+///
+/// ```lisp
+/// let g (generator (yield 1))
+/// next g                      ; => 1
+/// next g                      ; => nil
+/// ```
+pub fn crisp_next(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let generator = extract_value::<Arc<Mutex<CrispGenerator>>>(args.first().unwrap())?;
+
+    Ok(generator_next(&generator, env)?.unwrap_or(CrispExpr::Nil))
+}
+
+/// `yield` hands a value back to whoever asked the current
+/// [`Generator`](CrispExpr) for one, pausing its body until it is asked for
+/// another, at which point `yield` returns `nil`. It is an error to `yield`
+/// anywhere but inside of a generator, or inside of a special form which
+/// can't be paused (see [`RESUMABLE_FORMS`](crate::keywords::RESUMABLE_FORMS)).
+///
+/// # Examples
+///
+/// ```lisp
+/// let squares (generator (map (\ n (yield (* n n))) (1 2 3)))
+/// next squares                                                ; => 1
+/// next squares                                                ; => 4
+/// ```
+pub fn crisp_yield(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    yield_value(args.first().unwrap().clone())
+}
+
+/// Gets the next value from a [`Generator`](CrispExpr), or `None` if it has
+/// finished. A body is evaluated in a scope nested inside of `env`.
+pub(super) fn generator_next(
+    generator: &Mutex<CrispGenerator>,
+    env: &mut CrispEnv
+) -> Result<Option<CrispExpr>, CrispError> {
+    // Fails rather than waiting, so that a generator which asks itself for a
+    // value doesn't deadlock
+    let mut state = match generator.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return standard_error!("Generator is already running.")
+    };
+
+    let value = match &mut *state {
+        CrispGenerator::Body(body) => resume_generator(body, env),
+        CrispGenerator::Iter(values) => Ok(values.next()),
+        CrispGenerator::Done => Ok(None)
+    };
+
+    if !matches!(value, Ok(Some(_))) {
+        *state = CrispGenerator::Done;
+    }
+
+    value
+}
+
+/// Makes a [`Generator`](CrispExpr) which hands out the values of `values`, a
+/// Rust iterator, one at a time as they're asked for.
+pub(super) fn generator_from_iter(
+    values: impl Iterator<Item = CrispExpr> + Send + 'static
+) -> CrispExpr {
+    CrispExpr::Generator(Arc::new(Mutex::new(CrispGenerator::Iter(Box::new(values)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{slice, sync::{Arc, Mutex}};
+    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment};
+
+    fn generator(body: &[CrispExpr], env: &mut CrispEnv) -> CrispExpr {
+        let mut list = vec![sym!("generator")];
+        list.extend_from_slice(body);
+        eval(&CrispExpr::List(list), env).unwrap()
+    }
+
+    fn delay(expr: CrispExpr, env: &CrispEnv) -> CrispExpr {
+        let snapshot = Box::new(crate::env::env_snapshot(env));
+        Delay(Arc::new(Mutex::new(CrispDelay::Pending(expr, snapshot))))
+    }
+
+    #[test]
+    fn test_force() {
+        let mut env = initialize_environment();
+
+        let d = delay(list![sym!("+"), Number(1.0), Number(2.0)], &env);
+        assert_eq!(crisp_force(slice::from_ref(&d), &mut env).unwrap(), Number(3.0));
+        assert_eq!(crisp_force(&[d], &mut env).unwrap(), Number(3.0));
+
+        // Other values are returned as-is
+        assert_eq!(crisp_force(&[Number(5.0)], &mut env).unwrap(), Number(5.0));
+
+        crisp_assert_err!(crisp_force(&[], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_force_evaluates_once() {
+        let mut env = initialize_environment();
+        let counter = crate::functions::crisp_atom(&[Number(0.0)], &mut env).unwrap();
+        env.data.insert("counter".to_string(), counter.clone());
+
+        let d = delay(list![sym!("swap!"), sym!("counter"), sym!("+"), Number(1.0)], &env);
+        crisp_force(slice::from_ref(&d), &mut env).unwrap();
+        crisp_force(slice::from_ref(&d), &mut env).unwrap();
+
+        assert_eq!(crate::functions::crisp_deref(&[counter], &mut env).unwrap(), Number(1.0));
+    }
+
+    #[test]
+    fn test_force_errors() {
+        let mut env = initialize_environment();
+
+        // Errors are kept, too
+        let d = delay(list![sym!("undefined-function")], &env);
+        crisp_assert_err!(crisp_force(slice::from_ref(&d), &mut env), ParseError);
+        crisp_assert_err!(crisp_force(&[d], &mut env), ParseError);
+    }
+
+    #[test]
+    fn test_next() {
+        let mut env = initialize_environment();
+
+        let g = generator(&[
+            list![sym!("yield"), str!("foo")],
+            list![sym!("yield"), list![sym!("+"), Number(1.0), Number(2.0)]]
+        ], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), str!("foo"));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(3.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(crisp_next(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_next_error() {
+        let mut env = initialize_environment();
+
+        // An error in the body is passed along, after which it's finished
+        let g = generator(&[
+            list![sym!("yield"), Number(1.0)],
+            list![sym!("undefined-function")]
+        ], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(1.0));
+        crisp_assert_err!(crisp_next(slice::from_ref(&g), &mut env), ParseError);
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Nil);
+    }
+
+    #[test]
+    fn test_yield() {
+        let mut env = initialize_environment();
+        crisp_assert_err!(crisp_yield(&[Number(1.0)], &mut env), StandardError);
+
+        // From inside of a Lambda called by the generator
+        let square = lambda![
+            args: ["n"],
+            func: [
+                sym!("yield"),
+                list![sym!("*"), sym!("n"), sym!("n")]
+            ]
+        ];
+        env.data.insert("yield-square".to_string(), square);
+
+        let g = generator(&[
+            list![sym!("map"), sym!("yield-square"), num_list![2.0, 3.0]]
+        ], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(4.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(9.0));
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Nil);
+    }
+
+    #[test]
+    fn test_next_in_callers_scope() {
+        let mut env = initialize_environment();
+
+        // The body sees `x` as it is whenever a value is asked for
+        let g = generator(&[
+            list![sym!("yield"), sym!("x")],
+            list![sym!("yield"), sym!("x")]
+        ], &mut env);
+        env.data.insert("x".to_string(), Number(1.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(1.0));
+        env.data.insert("x".to_string(), Number(2.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(2.0));
+
+        // Its own bindings are kept between values, but don't leak out
+        let g = generator(&[
+            list![sym!("let"), sym!("y"), Number(5.0)],
+            list![sym!("yield"), sym!("y")],
+            list![sym!("yield"), list![sym!("+"), sym!("y"), Number(1.0)]]
+        ], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(5.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(6.0));
+        assert!(!env.data.contains_key("y"));
+    }
+
+    #[test]
+    fn test_yield_keeps_lambda_scope() {
+        let mut env = initialize_environment();
+
+        // What the Lambda bound before yielding is still there once resumed,
+        // and what it evaluated before then isn't evaluated again
+        let counter = crate::functions::crisp_atom(&[Number(0.0)], &mut env).unwrap();
+        env.data.insert("counter".to_string(), counter.clone());
+        let twice = lambda![
+            args: ["n"],
+            func: [
+                sym!("do"),
+                list![sym!("swap!"), sym!("counter"), sym!("+"), Number(1.0)],
+                list![sym!("let"), sym!("m"), list![sym!("*"), sym!("n"), Number(10.0)]],
+                list![sym!("yield"), sym!("m")],
+                list![sym!("yield"), list![sym!("+"), sym!("m"), sym!("n")]]
+            ]
+        ];
+        env.data.insert("twice".to_string(), twice);
+
+        let g = generator(&[list![sym!("twice"), Number(2.0)]], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(20.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(22.0));
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Nil);
+        assert_eq!(crate::functions::crisp_deref(&[counter], &mut env).unwrap(), Number(1.0));
+    }
+
+    #[test]
+    fn test_yield_in_special_form() {
+        let mut env = initialize_environment();
+
+        // Special forms which can't be paused
+        let g = generator(&[
+            list![sym!("with-output-to-string"), list![sym!("yield"), Number(1.0)]]
+        ], &mut env);
+        crisp_assert_err!(crisp_next(slice::from_ref(&g), &mut env), StandardError);
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Nil);
+
+        // Those which can
+        let g = generator(&[
+            list![sym!("if"), Bool(true), list![sym!("yield"), Number(1.0)], Nil],
+            list![sym!("&&"), list![sym!("yield"), Number(2.0)], Bool(true)]
+        ], &mut env);
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(2.0));
+    }
+
+    #[test]
+    fn test_generator_from_iter() {
+        let mut env = initialize_environment();
+
+        let g = generator_from_iter((1..=2).map(|n| Number(n as f64)));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Number(2.0));
+        assert_eq!(crisp_next(slice::from_ref(&g), &mut env).unwrap(), Nil);
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Nil);
+
+        // Values are only made as they're asked for
+        let g = generator_from_iter((0..).map(|n| Number(n as f64)));
+        assert_eq!(crisp_next(&[g], &mut env).unwrap(), Number(0.0));
+    }
+}
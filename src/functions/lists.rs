@@ -1,4 +1,110 @@
-use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, eval::eval_lambda};
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, eval::apply_lambda,
+            functions::{call_function, extract_index, extract_value, function_arity,
+                        generator_from_iter, generator_next}};
+
+use std::cmp::Ordering;
+
+/// Ranges longer than this are made as [`Generator`](CrispExpr)s rather than
+/// [`List`](CrispExpr)s, so that they don't all have to be held in memory.
+const RANGE_LIST_LIMIT: usize = 100_000;
+
+/// `range` returns the [`Number`](CrispExpr)s counting up from `start` to just
+/// before `end`, by `step` if given (which may be negative, to count down),
+/// otherwise by `1`. The reader turns `start..end` into a call to `range`.
+///
+/// The numbers are returned as a [`List`](CrispExpr), unless there are more
+/// than 100,000 of them, in which case they're returned as a
+/// [`Generator`](CrispExpr) which makes each number as it's asked for, so
+/// that very long ranges can still be used with e.g. `take`.
+///
+/// # Usage
+///
+/// ```lisp
+/// range start end
+/// range start end step
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// range 1 5        ; => (1 2 3 4)
+/// 1..5             ; => (1 2 3 4)
+/// range 0 10 3     ; => (0 3 6 9)
+/// range 5 0 -2     ; => (5 3 1)
+/// take 2 0..1e9    ; => (0 1)
+/// ```
+pub fn crisp_range(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    make_range(args, false)
+}
+
+/// `range-inclusive` is like [`range`](crisp_range), but `end` is included
+/// if it's reached. The reader turns `start..=end` into a call to
+/// `range-inclusive`.
+///
+/// # Usage
+///
+/// ```lisp
+/// range-inclusive start end
+/// range-inclusive start end step
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// range-inclusive 1 5    ; => (1 2 3 4 5)
+/// 1..=5                  ; => (1 2 3 4 5)
+/// range-inclusive 0 7 3  ; => (0 3 6)
+/// ```
+pub fn crisp_range_inclusive(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    make_range(args, true)
+}
+
+/// Makes the range for [`crisp_range()`] or [`crisp_range_inclusive()`].
+fn make_range(args: &[CrispExpr], inclusive: bool) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 3);
+
+    let start = extract_value::<f64>(&args[0])?;
+    let end = extract_value::<f64>(&args[1])?;
+    let step = match args.get(2) {
+        Some(step) => extract_value::<f64>(step)?,
+        None => 1.0
+    };
+
+    if step == 0.0 || !step.is_finite() || !start.is_finite() || end.is_nan() {
+        return standard_error!("A range must have a finite start and a non-zero step.");
+    }
+
+    let steps = (end - start) / step;
+    let len = if inclusive { steps.floor() + 1.0 } else { steps.ceil() }.max(0.0);
+    let numbers = (0..).map(move |i| CrispExpr::Number(start + i as f64 * step));
+
+    if len > RANGE_LIST_LIMIT as f64 {
+        // An infinite end can't be reached, so this goes on forever
+        Ok(generator_from_iter(numbers.take(len.min(u64::MAX as f64) as usize)))
+    } else {
+        Ok(CrispExpr::List(numbers.take(len as usize).collect()))
+    }
+}
+
+/// `list` returns its arguments as a [`List`](CrispExpr). Unlike writing the
+/// `List` out as e.g. `(1 2 3)`, this never treats the first element as a
+/// function to call, so it works the same under `--strict-calls`. `[1 2 3]` is
+/// short for `(list 1 2 3)`.
+///
+/// # Examples
+///
+/// ```lisp
+/// list 1 2 3      ; => (1 2 3)
+/// list (+ 1 2) x  ; => (3 5), if x is 5
+/// list            ; => ()
+/// [puts "a"]      ; => (<Func> 'a')
+/// ```
+pub fn crisp_list(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    Ok(CrispExpr::List(args.to_vec()))
+}
 
 /// `cons` adds an element to the beginning of a [`List`](CrispExpr).
 ///
@@ -27,12 +133,19 @@ pub fn crisp_cons(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr,
 
 /// `map` iterates across a [`List`](CrispExpr), applying a function to each
 /// element (or chunk of elements, if the function makes multiple arguments)
-/// and returning a new `List` with the results of those functions.
+/// and returning a new `List` with the results of those functions. A
+/// [`Generator`](CrispExpr) can be mapped over as well, once it has run to
+/// the end.
+///
+/// Given more than one `List`, `map` goes through them in lockstep instead,
+/// calling the function with one element from each. The `List`s must all be
+/// the same length.
 ///
 /// # usage
 ///
 /// ```lisp
 /// map lambda list
+/// map function list1 list2...
 /// ```
 ///
 /// # examples
@@ -41,35 +154,81 @@ pub fn crisp_cons(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr,
 /// fn double n (* 2 n)
 /// map double (1 2 3 4 5)                 ; => (2 4 6 8 10)
 /// map (\ (a b) (+ a b)) (1 10 2 20 3 40) ; => (11 22 33)
+/// map double 1..4                        ; => (2 4 6)
+/// map + (1 2 3) (10 20 30)               ; => (11 22 33)
+/// map (\ (k v) [k v]) ("a" "b") (1 2)    ; => (('a' 1) ('b' 2))
 /// ```
 pub fn crisp_map(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
-    check_argument_error!(args, 2, 2);
+    check_argument_error!(args, 2, -1);
 
-    match args.first().unwrap() {
-        CrispExpr::Lambda(lambda) => {
-            let n_args = match lambda.args.as_ref() {
-                // The Symbol case will have already been handled when the list was
-                // `eval_keyword_lambda()`ed into a CrispExpr, but we'll still print
-                // it in the error since a Symbol is an acceptable input to a lambda
-                CrispExpr::List(list) => list.len(),
-                _ => return type_error!("Symbol || List<Symbol>")
-            };
+    let (function, lists) = args.split_first().unwrap();
+    let lists = lists.iter().map(|list| map_items(list, env)).collect::<Result<Vec<Vec<CrispExpr>>, CrispError>>()?;
 
-            match args.get(1).unwrap() {
-                CrispExpr::List(list) => {
-                    let mut result = Vec::new();
-                    for chunk in list.chunks(n_args) {
-                        result.push(eval_lambda(lambda.clone(), chunk, env)?);
-                    }
+    if let [list] = &lists[..] {
+        return map_chunks(function, list, env);
+    }
 
-                    Ok(CrispExpr::List(result))
-                },
+    if !matches!(function, CrispExpr::Func(_) | CrispExpr::Lambda(_)) {
+        return type_error!("Func || Lambda");
+    }
 
-                _ => type_error!("List")
+    let len = lists.first().unwrap().len();
+    if let Some(list) = lists.iter().find(|list| list.len() != len) {
+        return index_error!(format!("Can't map over Lists of different lengths ({} and {}).",
+                                    len, list.len()));
+    }
+
+    let mut result = Vec::new();
+    for i in 0..len {
+        let row: Vec<CrispExpr> = lists.iter().map(|list| list[i].clone()).collect();
+        result.push(call_function(function, &row, env)?);
+    }
+
+    Ok(CrispExpr::List(result))
+}
+
+/// Maps a [`Lambda`](CrispExpr) over a single list, in chunks as big as the
+/// number of arguments it takes. See [`crisp_map()`].
+fn map_chunks(
+    function: &CrispExpr,
+    list: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    let lambda = match function {
+        CrispExpr::Lambda(lambda) => lambda,
+        _ => return type_error!("Lambda")
+    };
+
+    let n_args = match lambda.args.as_ref() {
+        // The Symbol case will have already been handled when the list was
+        // `eval_keyword_lambda()`ed into a CrispExpr, but we'll still print
+        // it in the error since a Symbol is an acceptable input to a lambda
+        CrispExpr::List(list) => list.len(),
+        _ => return type_error!("Symbol || List<Symbol>")
+    };
+
+    let mut result = Vec::new();
+    for chunk in list.chunks(n_args) {
+        result.push(apply_lambda(lambda, chunk, env)?);
+    }
+
+    Ok(CrispExpr::List(result))
+}
+
+/// The elements to [`map`](crisp_map) over: those of a [`List`](CrispExpr),
+/// or all of the values of a [`Generator`](CrispExpr).
+fn map_items(expr: &CrispExpr, env: &mut CrispEnv) -> Result<Vec<CrispExpr>, CrispError> {
+    match expr {
+        CrispExpr::Generator(generator) => {
+            let mut items = Vec::new();
+            while let Some(item) = generator_next(generator, env)? {
+                items.push(item);
             }
+
+            Ok(items)
         },
 
-        _ => type_error!("Lambda")
+        _ => extract_value::<Vec<CrispExpr>>(expr)
     }
 }
 
@@ -108,7 +267,7 @@ pub fn crisp_foldl(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr,
             match args.get(2).unwrap() {
                 CrispExpr::List(list) => {
                     for elem in list {
-                        acc = eval_lambda(lambda.clone(), &vec![acc, elem.clone()], env)?.clone();
+                        acc = apply_lambda(lambda, &[acc, elem.clone()], env)?;
                     }
 
                     Ok(acc)
@@ -162,11 +321,655 @@ pub fn crisp_foldl1(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr,
     }
 }
 
+/// `iterate` returns a [`List`](CrispExpr) of `n` values, starting with `x`,
+/// where each is the result of calling the function `f` with the one before.
+///
+/// # Usage
+///
+/// ```lisp
+/// iterate f x n
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// iterate (\ x (* 2 x)) 1 5 ; => (1 2 4 8 16)
+/// iterate (\ x (* 2 x)) 1 0 ; => ()
+/// ```
+pub fn crisp_iterate(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let f = args.first().unwrap();
+    let n = extract_index(args.get(2).unwrap())?;
+
+    let mut values = Vec::with_capacity(n);
+    let mut value = args.get(1).unwrap().clone();
+    for i in 0..n {
+        // Don't call `f` once more than is needed
+        if i > 0 {
+            value = call_function(f, std::slice::from_ref(&value), env)?;
+        }
+        values.push(value.clone());
+    }
+
+    Ok(CrispExpr::List(values))
+}
+
+/// `unfold` builds a [`List`](CrispExpr) from a seed value. Until the
+/// predicate `stop?` returns `true` for the seed, `f` is called with the seed
+/// to make the next element, and `next` is called with it to make the next
+/// seed.
+///
+/// # Usage
+///
+/// ```lisp
+/// unfold stop? f next seed
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// unfold (\ n (> n 5)) (\ n (* n n)) (\ n (+ n 1)) 1 ; => (1 4 9 16 25)
+/// unfold (\ n (= n 0)) (\ n n) (\ n (- n 1)) 3       ; => (3 2 1)
+/// ```
+pub fn crisp_unfold(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 4, 4);
+
+    let (stop, f, next) = (&args[0], &args[1], &args[2]);
+    let mut seed = args[3].clone();
+
+    let mut values = Vec::new();
+    loop {
+        match call_function(stop, std::slice::from_ref(&seed), env)? {
+            CrispExpr::Bool(true) => break,
+            CrispExpr::Bool(false) => {},
+            _ => return type_error!("Bool")
+        }
+
+        values.push(call_function(f, std::slice::from_ref(&seed), env)?);
+        seed = call_function(next, &[seed], env)?;
+    }
+
+    Ok(CrispExpr::List(values))
+}
+
+/// `take` returns the first `n` elements of a [`List`](CrispExpr). It is an
+/// error to take more elements than the `List` contains.
+///
+/// `take` can also be given a [`Generator`](CrispExpr), in which case it asks
+/// the generator for up to `n` values, stopping early if it finishes.
+///
+/// # Usage
+///
+/// ```lisp
+/// take n list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// take 2 (1 2 3 4) ; => (1 2)
+/// take 0 (1 2 3 4) ; => ()
+/// ```
+pub fn crisp_take(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let n = extract_index(args.first().unwrap())?;
+
+    if let CrispExpr::Generator(generator) = args.get(1).unwrap() {
+        let mut values = Vec::new();
+        while values.len() < n {
+            match generator_next(generator, env)? {
+                Some(value) => values.push(value),
+                None => break
+            }
+        }
+
+        return Ok(CrispExpr::List(values));
+    }
+    let list = extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?;
+
+    if n > list.len() {
+        return index_error!(format!("Can't take {} elements from a List of length {}.",
+                                    n, list.len()));
+    }
+
+    Ok(CrispExpr::List(list[..n].to_vec()))
+}
+
+/// `drop` returns a [`List`](CrispExpr) with the first `n` elements removed.
+/// It is an error to drop more elements than the `List` contains.
+///
+/// # Usage
+///
+/// ```lisp
+/// drop n list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// drop 2 (1 2 3 4) ; => (3 4)
+/// drop 4 (1 2 3 4) ; => ()
+/// ```
+pub fn crisp_drop(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let n = extract_index(args.first().unwrap())?;
+    let list = extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?;
+
+    if n > list.len() {
+        return index_error!(format!("Can't drop {} elements from a List of length {}.",
+                                    n, list.len()));
+    }
+
+    Ok(CrispExpr::List(list[n..].to_vec()))
+}
+
+/// `slice` returns the elements of a [`List`](CrispExpr) starting at index
+/// `from` (inclusive) up until index `to` (exclusive).
+///
+/// # Usage
+///
+/// ```lisp
+/// slice from to list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// slice 1 3 (1 2 3 4) ; => (2 3)
+/// slice 2 2 (1 2 3 4) ; => ()
+/// ```
+pub fn crisp_slice(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let from = extract_index(args.first().unwrap())?;
+    let to = extract_index(args.get(1).unwrap())?;
+    let list = extract_value::<Vec<CrispExpr>>(args.get(2).unwrap())?;
+
+    if from > to || to > list.len() {
+        return index_error!(format!("Slice {}..{} is out of bounds for a List of length {}.",
+                                    from, to, list.len()));
+    }
+
+    Ok(CrispExpr::List(list[from..to].to_vec()))
+}
+
+/// `chunks` splits a [`List`](CrispExpr) into `List`s of `n` elements each,
+/// in order. The last chunk is shorter if there aren't enough elements left
+/// to fill it.
+///
+/// # Usage
+///
+/// ```lisp
+/// chunks n list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// chunks 2 (1 2 3 4 5) ; => ((1 2) (3 4) (5))
+/// chunks 2 ()          ; => ()
+/// ```
+pub fn crisp_chunks(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let (n, list) = partition_args(args)?;
+
+    Ok(CrispExpr::List(list.chunks(n).map(|chunk| CrispExpr::List(chunk.to_vec())).collect()))
+}
+
+/// `windows` returns every run of `n` consecutive elements of a
+/// [`List`](CrispExpr), in order. If the `List` has fewer than `n` elements,
+/// there are none.
+///
+/// # Usage
+///
+/// ```lisp
+/// windows n list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// windows 2 (1 2 3 4) ; => ((1 2) (2 3) (3 4))
+/// windows 3 (1 2)     ; => ()
+/// ```
+pub fn crisp_windows(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let (n, list) = partition_args(args)?;
+
+    Ok(CrispExpr::List(list.windows(n).map(|window| CrispExpr::List(window.to_vec())).collect()))
+}
+
+/// Gets the size and the [`List`](CrispExpr) given to
+/// [`chunks`](crisp_chunks) or [`windows`](crisp_windows). The size must be
+/// at least `1`.
+fn partition_args(args: &[CrispExpr]) -> Result<(usize, Vec<CrispExpr>), CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let n = extract_index(args.first().unwrap())?;
+    if n == 0 {
+        return index_error!("Expected a size of at least 1, got 0.");
+    }
+
+    Ok((n, extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?))
+}
+
+/// `split-at` splits a [`List`](CrispExpr) in two after the first `n`
+/// elements, returning a `List` of the two halves: the same as
+/// [`take`](crisp_take) and [`drop`](crisp_drop) together. It is an error to
+/// split at an index past the end of the `List`.
+///
+/// # Usage
+///
+/// ```lisp
+/// split-at n list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// split-at 2 (1 2 3 4) ; => ((1 2) (3 4))
+/// split-at 0 (1 2)     ; => (() (1 2))
+/// ```
+pub fn crisp_split_at(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let n = extract_index(args.first().unwrap())?;
+    let mut list = extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?;
+
+    if n > list.len() {
+        return index_error!(format!("Can't split a List of length {} at {}.", list.len(), n));
+    }
+
+    let rest = list.split_off(n);
+
+    Ok(list![CrispExpr::List(list), CrispExpr::List(rest)])
+}
+
+/// `reverse` returns a [`List`](CrispExpr) with its elements in reverse order.
+///
+/// # Examples
+///
+/// ```lisp
+/// reverse (1 2 3) ; => (3 2 1)
+/// reverse ()      ; => ()
+/// ```
+pub fn crisp_reverse(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let mut list = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+    list.reverse();
+
+    Ok(CrispExpr::List(list))
+}
+
+/// `sort` returns a [`List`](CrispExpr) with its elements in ascending
+/// order. Any values can be sorted together: [`Number`](CrispExpr)s are
+/// ordered by value and [`String`](CrispExpr)s lexicographically, and values
+/// of different types are grouped by type, e.g. numbers before strings. See
+/// the [`Ord`] for `CrispExpr` for the full order.
+///
+/// # Examples
+///
+/// ```lisp
+/// sort (3 1 2)           ; => (1 2 3)
+/// sort ("b" "c" "a")     ; => ('a' 'b' 'c')
+/// sort ("a" 2 nil (1))   ; => (nil 2 'a' (1))
+/// ```
+pub fn crisp_sort(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let mut list = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+    list.sort();
+
+    Ok(CrispExpr::List(list))
+}
+
+/// `sort-by` sorts a [`List`](CrispExpr) using a function, which is either a
+/// key or a comparator, depending on how many arguments it takes:
+///
+///  * A key takes one element and returns the value to sort it by, which are
+///    compared like with [`sort`](crisp_sort). It's only called once for each
+///    element.
+///  * A comparator takes two elements, `a` and `b`, and returns a
+///    [`Number`](CrispExpr): negative if `a` comes first, positive if `b`
+///    does, or `0` if it doesn't matter, like [`compare`](crisp_compare).
+///
+/// Either way, the sort is stable: elements that are tied stay in the order
+/// they were in.
+///
+/// # Usage
+///
+/// ```lisp
+/// sort-by key list
+/// sort-by comparator list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// sort-by (\ n (mod n 3)) (5 3 4)         ; => (3 4 5)
+/// sort-by capitalize ("b" "A" "a")        ; => ('A' 'a' 'b')
+/// sort-by (\ (a b) (compare b a)) (1 3 2) ; => (3 2 1)
+/// ```
+pub fn crisp_sort_by(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let func = args.first().unwrap();
+    let list = extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?;
+
+    let takes = |n| match function_arity(func) {
+        Ok((min, max)) => min <= n && max.is_none_or(|max| n <= max),
+        Err(_) => false
+    };
+
+    if takes(1) {
+        let mut keyed = list.into_iter().map(|item| {
+            Ok((call_function(func, std::slice::from_ref(&item), env)?, item))
+        }).collect::<Result<Vec<(CrispExpr, CrispExpr)>, CrispError>>()?;
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        return Ok(CrispExpr::List(keyed.into_iter().map(|(_, item)| item).collect()));
+    }
+
+    if takes(2) {
+        return merge_sort_by(list, &mut |a, b| {
+            let order = call_function(func, &[a.clone(), b.clone()], env)?;
+            let order = extract_value::<f64>(&order)?;
+
+            Ok(if order < 0.0 { Ordering::Less } else if order > 0.0 {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            })
+        }).map(CrispExpr::List);
+    }
+
+    function_arity(func)?;
+    standard_error!("sort-by needs a key (of 1 argument) or a comparator (of 2 arguments).")
+}
+
+/// A stable merge sort of `list` with a comparator that can fail. Unlike
+/// [`slice::sort_by()`], it copes with comparators that aren't consistent,
+/// which crisp code may well give it. See [`crisp_sort_by()`].
+fn merge_sort_by(
+    mut list: Vec<CrispExpr>,
+    compare: &mut impl FnMut(&CrispExpr, &CrispExpr) -> Result<Ordering, CrispError>
+) -> Result<Vec<CrispExpr>, CrispError> {
+    if list.len() < 2 {
+        return Ok(list);
+    }
+
+    let right = merge_sort_by(list.split_off(list.len() / 2), compare)?;
+    let left = merge_sort_by(list, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Ties go to the left, keeping the sort stable
+        let next = if compare(a, b)? == Ordering::Greater { &mut right } else { &mut left };
+        merged.extend(next.next());
+    }
+    merged.extend(left.chain(right));
+
+    Ok(merged)
+}
+
+/// `min-by` returns the element of a [`List`](CrispExpr) for which the
+/// function `key` returns the smallest value, or `nil` if the `List` is empty.
+/// If there's a tie, the first of those elements is returned. Keys are
+/// compared like with [`sort`](crisp_sort).
+///
+/// # Usage
+///
+/// ```lisp
+/// min-by key list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// min-by (\ n (mod n 3)) (5 3 4) ; => 3
+/// min-by (\ n (- 0 n)) (1 3 2)   ; => 3
+/// ```
+pub fn crisp_min_by(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    find_by_key(args, Ordering::Less, env)
+}
+
+/// `max-by` returns the element of a [`List`](CrispExpr) for which the
+/// function `key` returns the largest value, or `nil` if the `List` is empty.
+/// If there's a tie, the first of those elements is returned. Keys are
+/// compared like with [`sort`](crisp_sort).
+///
+/// # Usage
+///
+/// ```lisp
+/// max-by key list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// max-by (\ n (mod n 3)) (3 4 5 8) ; => 5
+/// max-by (\ n (- 0 n)) (1 3 2)     ; => 1
+/// ```
+pub fn crisp_max_by(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    find_by_key(args, Ordering::Greater, env)
+}
+
+/// Finds the element of the list in `args` whose key, given by the function
+/// in `args`, is the furthest in the direction of `wanted`. See
+/// [`crisp_min_by()`] and [`crisp_max_by()`].
+fn find_by_key(
+    args: &[CrispExpr],
+    wanted: Ordering,
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let key = args.first().unwrap();
+    let list = extract_value::<Vec<CrispExpr>>(args.get(1).unwrap())?;
+
+    let mut best: Option<(CrispExpr, CrispExpr)> = None;
+    for item in list {
+        let item_key = call_function(key, std::slice::from_ref(&item), env)?;
+
+        if best.as_ref().is_none_or(|(_, best_key)| item_key.cmp(best_key) == wanted) {
+            best = Some((item, item_key));
+        }
+    }
+
+    Ok(best.map_or(CrispExpr::Nil, |(item, _)| item))
+}
+
+/// `extent` returns the smallest and largest elements of a
+/// [`List`](CrispExpr) as a `List` of two, finding both in one pass, or `nil`
+/// if the `List` is empty. Elements are compared like with
+/// [`sort`](crisp_sort).
+///
+/// # Examples
+///
+/// ```lisp
+/// extent (3 1 4 1 5) ; => (1 5)
+/// extent ("b" "a")   ; => ('a' 'b')
+/// extent ()          ; => nil
+/// ```
+pub fn crisp_extent(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let list = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+    let Some(first) = list.first() else {
+        return Ok(CrispExpr::Nil);
+    };
+
+    let (min, max) = list.iter().fold((first, first), |(min, max), item| {
+        (if item < min { item } else { min }, if item > max { item } else { max })
+    });
+
+    Ok(list![min.clone(), max.clone()])
+}
+
+/// One step of a [`diff`](crisp_diff), by the index of the item it refers to:
+/// in the old sequence for `Keep` and `Delete`, or the new one for `Insert`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep(usize),
+    Delete(usize),
+    Insert(usize)
+}
+
+impl Edit {
+    /// The tag used for the edit in the result of [`diff`](crisp_diff).
+    fn name(&self) -> &'static str {
+        match self {
+            Edit::Keep(_) => "keep",
+            Edit::Delete(_) => "delete",
+            Edit::Insert(_) => "insert"
+        }
+    }
+}
+
+/// Finds the shortest list of edits turning `old` into `new`, using Myers'
+/// O(ND) algorithm (where D is the number of insertions and deletions).
+/// Deletions come before insertions where there's a choice.
+fn myers_diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    // The furthest x reached on each diagonal k = x - y, offset by `max`
+    let mut v = vec![0; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    let at = |k: isize| (k + max) as usize;
+
+    // Whether the path onto diagonal k comes down from k + 1 (an insertion)
+    // rather than across from k - 1 (a deletion)
+    let down = |v: &[isize], k: isize, d: isize| k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]);
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if down(&v, k, d) { v[at(k + 1)] } else { v[at(k - 1)] + 1 };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back through the trace from the end to find the path taken
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let previous_k = if down(v, k, d) { k + 1 } else { k - 1 };
+        let previous_x = v[at(previous_k)];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Keep(x as usize));
+        }
+
+        if d > 0 {
+            edits.push(if x == previous_x {
+                Edit::Insert(previous_y as usize)
+            } else {
+                Edit::Delete(previous_x as usize)
+            });
+        }
+
+        (x, y) = (previous_x, previous_y);
+    }
+
+    edits.reverse();
+    edits
+}
+
+/// `diff` finds the shortest list of edits that turns one sequence into
+/// another: a [`List`](CrispExpr) of `(keep item)`, `(delete item)` and
+/// `(insert item)` steps, in order, which give the old sequence when the
+/// insertions are left out and the new one when the deletions are.
+///
+/// Both sequences may be `List`s, whose items are compared with `=`, or
+/// [`String`](CrispExpr)s, which are compared by character; in that case, each
+/// run of characters with the same kind of edit is given as one `String`. To
+/// diff text by line, split it with [`lines`](crate::functions::crisp_lines)
+/// first.
+///
+/// # Usage
+///
+/// ```lisp
+/// diff old new
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// diff (1 2 3) (1 3 4)  ; => ((keep 1) (delete 2) (keep 3) (insert 4))
+/// diff "kitten" "sitting"
+/// ; => ((delete 'k') (insert 's') (keep 'itt') (delete 'e') (insert 'i') (keep 'n')
+/// ;     (insert 'g'))
+/// ```
+pub fn crisp_diff(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let tagged = |edit: Edit, item: CrispExpr| {
+        list![sym!(edit.name()), item]
+    };
+
+    match (&args[0], &args[1]) {
+        (CrispExpr::CrispString(old), CrispExpr::CrispString(new)) => {
+            let (old, new): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+            let mut runs: Vec<(Edit, String)> = Vec::new();
+
+            for edit in myers_diff(&old, &new) {
+                let ch = match edit {
+                    Edit::Keep(i) | Edit::Delete(i) => old[i],
+                    Edit::Insert(i) => new[i]
+                };
+
+                match runs.last_mut() {
+                    Some((last, run)) if last.name() == edit.name() => run.push(ch),
+                    _ => runs.push((edit, ch.to_string()))
+                }
+            }
+
+            Ok(CrispExpr::List(runs.into_iter()
+                                   .map(|(edit, run)| tagged(edit, str!(run)))
+                                   .collect()))
+        },
+
+        (old, new) => {
+            let old = extract_value::<Vec<CrispExpr>>(old)?;
+            let new = extract_value::<Vec<CrispExpr>>(new)?;
+
+            Ok(CrispExpr::List(myers_diff(&old, &new).into_iter().map(|edit| {
+                let item = match edit {
+                    Edit::Keep(i) | Edit::Delete(i) => &old[i],
+                    Edit::Insert(i) => &new[i]
+                };
+
+                tagged(edit, item.clone())
+            }).collect()))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::rc::Rc;
-    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment, eval::eval};
+    use std::sync::Arc;
+    use crate::{expr::{CrispExpr::*, CrispGenerator, CrispLambda}, env::{env_get, initialize_environment},
+                eval::eval};
 
     #[test]
     fn test_cons() {
@@ -253,6 +1056,74 @@ mod tests {
         assert_eq!(result, num_list![4.0, 6.0, 8.0]);
     }
 
+    #[test]
+    fn test_map_multiple_lists() {
+        let mut env = initialize_environment();
+        let add = env_get("+", &env).unwrap();
+
+        assert_eq!(crisp_map(&[add.clone(), num_list![1.0, 2.0, 3.0], num_list![10.0, 20.0, 30.0]],
+                             &mut env).unwrap(),
+                   num_list![11.0, 22.0, 33.0]);
+        assert_eq!(crisp_map(&[add.clone(), list![], list![], list![]], &mut env).unwrap(),
+                   list![]);
+
+        let pair = lambda![
+            args: ["k", "v"],
+            func: [sym!("list"), sym!("k"), sym!("v")]
+        ];
+        assert_eq!(crisp_map(&[pair, string_list!["a", "b"], num_list![1.0, 2.0]], &mut env)
+                       .unwrap(),
+                   list![list![str!("a"), Number(1.0)], list![str!("b"), Number(2.0)]]);
+
+        crisp_assert_err!(crisp_map(&[add.clone(), num_list![1.0, 2.0], num_list![1.0]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_map(&[add, num_list![1.0], Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_map(&[Number(1.0), num_list![1.0], num_list![1.0]], &mut env),
+                          TypeError);
+    }
+
+    #[test]
+    fn test_iterate() {
+        let mut env = initialize_environment();
+        let double = lambda![
+            args: ["x"],
+            func: [sym!("*"), Number(2.0), sym!("x")]
+        ];
+
+        assert_eq!(crisp_iterate(&[double.clone(), Number(1.0), Number(5.0)], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 4.0, 8.0, 16.0]);
+        assert_eq!(crisp_iterate(&[double.clone(), Number(1.0), Number(0.0)], &mut env).unwrap(),
+                   list![]);
+
+        // The function isn't called for the first value
+        assert_eq!(crisp_iterate(&[Number(1.0), str!("x"), Number(1.0)], &mut env).unwrap(),
+                   string_list!["x"]);
+
+        crisp_assert_err!(crisp_iterate(&[Number(1.0), Number(1.0), Number(2.0)], &mut env),
+                          TypeError);
+        crisp_assert_err!(crisp_iterate(&[double, Number(1.0), Number(-1.0)], &mut env),
+                          IndexError);
+    }
+
+    #[test]
+    fn test_unfold() {
+        let mut env = initialize_environment();
+        let over_5 = lambda![args: ["n"], func: [sym!(">"), sym!("n"), Number(5.0)]];
+        let square = lambda![args: ["n"], func: [sym!("*"), sym!("n"), sym!("n")]];
+        let inc = lambda![args: ["n"], func: [sym!("+"), sym!("n"), Number(1.0)]];
+
+        assert_eq!(crisp_unfold(&[over_5.clone(), square.clone(), inc.clone(), Number(1.0)],
+                                &mut env).unwrap(),
+                   num_list![1.0, 4.0, 9.0, 16.0, 25.0]);
+        assert_eq!(crisp_unfold(&[over_5.clone(), square.clone(), inc.clone(), Number(6.0)],
+                                &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_unfold(&[square.clone(), square, inc, Number(1.0)], &mut env),
+                          TypeError);
+        crisp_assert_err!(crisp_unfold(&[over_5, Number(1.0)], &mut env), ArgumentError);
+    }
+
     #[test]
     fn test_foldl() {
         let mut env = initialize_environment();
@@ -293,4 +1164,354 @@ mod tests {
 
         assert_eq!(result, Number(6.0));
     }
+
+    #[test]
+    fn test_take() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_take(&[Number(2.0), num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   num_list![1.0, 2.0]);
+        assert_eq!(crisp_take(&[Number(0.0), num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   list![]);
+        assert_eq!(crisp_take(&[Number(3.0), num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 3.0]);
+
+        crisp_assert_err!(crisp_take(&[Number(4.0), num_list![1.0, 2.0, 3.0]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_take(&[Number(-1.0), num_list![1.0, 2.0, 3.0]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_take(&[Number(1.0), Number(2.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_take_generator() {
+        let mut env = initialize_environment();
+
+        let list = list![
+            sym!("generator"),
+            list![sym!("yield"), Number(1.0)],
+            list![sym!("yield"), Number(2.0)],
+            list![sym!("yield"), Number(3.0)]
+        ];
+        let generator = crate::eval::eval(&list, &mut env).unwrap();
+
+        assert_eq!(crisp_take(&[Number(2.0), generator.clone()], &mut env).unwrap(),
+                   num_list![1.0, 2.0]);
+
+        // Stops early once the generator has finished
+        assert_eq!(crisp_take(&[Number(2.0), generator], &mut env).unwrap(),
+                   num_list![3.0]);
+    }
+
+    #[test]
+    fn test_drop() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_drop(&[Number(2.0), num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   num_list![3.0]);
+        assert_eq!(crisp_drop(&[Number(3.0), num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_drop(&[Number(4.0), num_list![1.0, 2.0, 3.0]], &mut env),
+                          IndexError);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut env = initialize_environment();
+
+        let list = num_list![1.0, 2.0, 3.0, 4.0];
+
+        assert_eq!(crisp_slice(&[Number(1.0), Number(3.0), list.clone()], &mut env).unwrap(),
+                   num_list![2.0, 3.0]);
+        assert_eq!(crisp_slice(&[Number(2.0), Number(2.0), list.clone()], &mut env).unwrap(),
+                   list![]);
+        assert_eq!(crisp_slice(&[Number(0.0), Number(4.0), list.clone()], &mut env).unwrap(),
+                   list);
+
+        crisp_assert_err!(crisp_slice(&[Number(3.0), Number(1.0), list.clone()], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_slice(&[Number(0.0), Number(5.0), list.clone()], &mut env),
+                          IndexError);
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_chunks(&[Number(2.0), num_list![1.0, 2.0, 3.0, 4.0, 5.0]], &mut env)
+                       .unwrap(),
+                   list![num_list![1.0, 2.0], num_list![3.0, 4.0], num_list![5.0]]);
+        assert_eq!(crisp_chunks(&[Number(3.0), num_list![1.0, 2.0]], &mut env).unwrap(),
+                   list![num_list![1.0, 2.0]]);
+        assert_eq!(crisp_chunks(&[Number(2.0), list![]], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_chunks(&[Number(0.0), num_list![1.0]], &mut env), IndexError);
+        crisp_assert_err!(crisp_chunks(&[Number(1.5), num_list![1.0]], &mut env), IndexError);
+        crisp_assert_err!(crisp_chunks(&[Number(1.0), Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_windows() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_windows(&[Number(2.0), num_list![1.0, 2.0, 3.0, 4.0]], &mut env).unwrap(),
+                   list![num_list![1.0, 2.0], num_list![2.0, 3.0], num_list![3.0, 4.0]]);
+        assert_eq!(crisp_windows(&[Number(3.0), num_list![1.0, 2.0]], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_windows(&[Number(0.0), num_list![1.0]], &mut env), IndexError);
+    }
+
+    #[test]
+    fn test_split_at() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_split_at(&[Number(2.0), num_list![1.0, 2.0, 3.0, 4.0]], &mut env)
+                       .unwrap(),
+                   list![num_list![1.0, 2.0], num_list![3.0, 4.0]]);
+        assert_eq!(crisp_split_at(&[Number(0.0), num_list![1.0, 2.0]], &mut env).unwrap(),
+                   list![list![], num_list![1.0, 2.0]]);
+        assert_eq!(crisp_split_at(&[Number(2.0), num_list![1.0, 2.0]], &mut env).unwrap(),
+                   list![num_list![1.0, 2.0], list![]]);
+
+        crisp_assert_err!(crisp_split_at(&[Number(3.0), num_list![1.0, 2.0]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_split_at(&[Number(1.0)], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_reverse(&[num_list![1.0, 2.0, 3.0]], &mut env).unwrap(),
+                   num_list![3.0, 2.0, 1.0]);
+        assert_eq!(crisp_reverse(&[list![]], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_reverse(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_sort(&[num_list![3.0, 1.0, 2.0]], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 3.0]);
+        assert_eq!(crisp_sort(&[string_list!["b", "c", "a"]], &mut env).unwrap(),
+                   string_list!["a", "b", "c"]);
+        assert_eq!(crisp_sort(&[list![str!("a"), Number(2.0), Nil, num_list![1.0]]], &mut env)
+                       .unwrap(),
+                   list![Nil, Number(2.0), str!("a"), num_list![1.0]]);
+        assert_eq!(crisp_sort(&[list![]], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_sort(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut env = initialize_environment();
+        let mod_3 = eval(&list![sym!("\\"), sym!("n"), list![sym!("mod"), sym!("n"), Number(3.0)]],
+                         &mut env).unwrap();
+        let descending = eval(&list![sym!("\\"), list![sym!("a"), sym!("b")],
+                                     list![sym!("compare"), sym!("b"), sym!("a")]],
+                              &mut env).unwrap();
+
+        // A key, which keeps ties in order
+        assert_eq!(crisp_sort_by(&[mod_3.clone(), num_list![5.0, 3.0, 4.0, 6.0]], &mut env)
+                       .unwrap(),
+                   num_list![3.0, 6.0, 4.0, 5.0]);
+        assert_eq!(crisp_sort_by(&[CrispExpr::Func(crate::functions::crisp_capitalize),
+                                   string_list!["b", "A", "a"]], &mut env).unwrap(),
+                   string_list!["A", "a", "b"]);
+
+        // A comparator
+        assert_eq!(crisp_sort_by(&[descending.clone(), num_list![1.0, 3.0, 2.0]], &mut env)
+                       .unwrap(),
+                   num_list![3.0, 2.0, 1.0]);
+        assert_eq!(crisp_sort_by(&[CrispExpr::Func(crate::functions::crisp_compare),
+                                   num_list![2.0, 1.0, 3.0]], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 3.0]);
+        assert_eq!(crisp_sort_by(&[descending.clone(), list![]], &mut env).unwrap(), list![]);
+
+        // The comparator must give a Number
+        let bad = eval(&list![sym!("\\"), list![sym!("a"), sym!("b")], str!("x")],
+                       &mut env).unwrap();
+        crisp_assert_err!(crisp_sort_by(&[bad, num_list![1.0, 2.0]], &mut env), TypeError);
+
+        let three = eval(&list![sym!("\\"), list![sym!("a"), sym!("b"), sym!("c")], Nil],
+                         &mut env).unwrap();
+        crisp_assert_err!(crisp_sort_by(&[three, num_list![1.0]], &mut env), StandardError);
+        crisp_assert_err!(crisp_sort_by(&[Number(1.0), num_list![1.0]], &mut env), TypeError);
+        crisp_assert_err!(crisp_sort_by(&[mod_3, Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_min_by_max_by() {
+        let mut env = initialize_environment();
+        let negate = eval(&list![sym!("\\"), sym!("n"), list![sym!("-"), Number(0.0), sym!("n")]],
+                          &mut env).unwrap();
+
+        assert_eq!(crisp_min_by(&[negate.clone(), num_list![1.0, 3.0, 2.0]], &mut env).unwrap(),
+                   Number(3.0));
+        assert_eq!(crisp_max_by(&[negate.clone(), num_list![1.0, 3.0, 2.0]], &mut env).unwrap(),
+                   Number(1.0));
+        assert_eq!(crisp_min_by(&[negate.clone(), list![]], &mut env).unwrap(),
+                   Nil);
+
+        // Ties go to the first element
+        let mod_3 = eval(&list![sym!("\\"), sym!("n"), list![sym!("mod"), sym!("n"), Number(3.0)]],
+                         &mut env).unwrap();
+        assert_eq!(crisp_max_by(&[mod_3.clone(), num_list![3.0, 5.0, 8.0]], &mut env).unwrap(),
+                   Number(5.0));
+        assert_eq!(crisp_min_by(&[mod_3, num_list![4.0, 3.0, 6.0]], &mut env).unwrap(),
+                   Number(3.0));
+
+        crisp_assert_err!(crisp_min_by(&[negate.clone(), Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_max_by(&[negate, string_list!["a"]], &mut env), TypeError);
+        crisp_assert_err!(crisp_max_by(&[Number(1.0), num_list![1.0]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_extent() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_extent(&[num_list![3.0, 1.0, 4.0, 1.0, 5.0]], &mut env).unwrap(),
+                   num_list![1.0, 5.0]);
+        assert_eq!(crisp_extent(&[string_list!["b", "a"]], &mut env).unwrap(),
+                   string_list!["a", "b"]);
+        assert_eq!(crisp_extent(&[num_list![2.0]], &mut env).unwrap(),
+                   num_list![2.0, 2.0]);
+        assert_eq!(crisp_extent(&[list![]], &mut env).unwrap(),
+                   Nil);
+
+        crisp_assert_err!(crisp_extent(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_list() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_list(&[Number(1.0), str!("a")], &mut env).unwrap(),
+                   list![Number(1.0), str!("a")]);
+        assert_eq!(crisp_list(&[], &mut env).unwrap(),
+                   list![]);
+
+        let expr = "[(+ 1 2) [,a] []]".parse::<CrispExpr>().unwrap();
+        assert_eq!(eval(&expr, &mut env).unwrap(),
+                   list![Number(3.0), list![Char('a')], list![]]);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_range(&[Number(1.0), Number(5.0)], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(crisp_range(&[Number(0.0), Number(10.0), Number(3.0)], &mut env).unwrap(),
+                   num_list![0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(crisp_range(&[Number(5.0), Number(0.0), Number(-2.0)], &mut env).unwrap(),
+                   num_list![5.0, 3.0, 1.0]);
+        assert_eq!(crisp_range(&[Number(0.0), Number(1.0), Number(0.25)], &mut env).unwrap(),
+                   num_list![0.0, 0.25, 0.5, 0.75]);
+        assert_eq!(crisp_range(&[Number(5.0), Number(5.0)], &mut env).unwrap(),
+                   list![]);
+        assert_eq!(crisp_range(&[Number(5.0), Number(1.0)], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_range(&[Number(1.0), Number(5.0), Number(0.0)], &mut env),
+                          StandardError);
+        crisp_assert_err!(crisp_range(&[Number(1.0), str!("5")], &mut env), TypeError);
+        crisp_assert_err!(crisp_range(&[Number(1.0)], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_range_inclusive() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_range_inclusive(&[Number(1.0), Number(5.0)], &mut env).unwrap(),
+                   num_list![1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(crisp_range_inclusive(&[Number(0.0), Number(7.0), Number(3.0)], &mut env)
+                       .unwrap(),
+                   num_list![0.0, 3.0, 6.0]);
+        assert_eq!(crisp_range_inclusive(&[Number(5.0), Number(5.0)], &mut env).unwrap(),
+                   num_list![5.0]);
+        assert_eq!(crisp_range_inclusive(&[Number(5.0), Number(4.0)], &mut env).unwrap(),
+                   list![]);
+    }
+
+    #[test]
+    fn test_range_generator() {
+        let mut env = initialize_environment();
+
+        let range = crisp_range(&[Number(0.0), Number(f64::INFINITY)], &mut env).unwrap();
+        assert!(matches!(range, Generator(_)));
+        assert_eq!(crisp_take(&[Number(3.0), range], &mut env).unwrap(),
+                   num_list![0.0, 1.0, 2.0]);
+
+        // Made straight from an iterator, rather than evaluating a body
+        let range = crisp_range(&[Number(0.0), Number(1e6)], &mut env).unwrap();
+        let Generator(generator) = range else {
+            panic!("Expected a Generator.");
+        };
+        assert!(matches!(*generator.lock().unwrap(), CrispGenerator::Iter(_)));
+    }
+
+    #[test]
+    fn test_map_generator() {
+        let mut env = initialize_environment();
+
+        let double = eval(&list![sym!("\\"), sym!("n"), list![sym!("*"), Number(2.0), sym!("n")]],
+                          &mut env).unwrap();
+        let range = crisp_range(&[Number(0.0), Number(200_000.0), Number(50_000.0)], &mut env)
+            .unwrap();
+
+        assert_eq!(crisp_map(&[double, range], &mut env).unwrap(),
+                   num_list![0.0, 100_000.0, 200_000.0, 300_000.0]);
+    }
+
+    #[test]
+    fn test_myers_diff() {
+        use Edit::*;
+
+        assert_eq!(myers_diff(&[1, 2, 3], &[1, 3, 4]),
+                   [Keep(0), Delete(1), Keep(2), Insert(2)]);
+        assert_eq!(myers_diff(&[1, 2], &[1, 2]), [Keep(0), Keep(1)]);
+        assert_eq!(myers_diff(&[1, 2], &[3]), [Delete(0), Delete(1), Insert(0)]);
+        assert_eq!(myers_diff::<i32>(&[], &[1]), [Insert(0)]);
+        assert_eq!(myers_diff::<i32>(&[1], &[]), [Delete(0)]);
+        assert!(myers_diff::<i32>(&[], &[]).is_empty());
+
+        // The classic example from Myers' paper, with D = 5
+        let (old, new): (Vec<char>, Vec<char>) = ("abcabba".chars().collect(),
+                                                  "cbabac".chars().collect());
+        let edits = myers_diff(&old, &new);
+        assert_eq!(edits.iter().filter(|edit| !matches!(edit, Keep(_))).count(), 5);
+        assert_eq!(edits.iter().filter(|edit| matches!(edit, Keep(_))).count(), 4);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut env = initialize_environment();
+        let edit = |name: &str, item| list![sym!(name), item];
+
+        assert_eq!(crisp_diff(&[num_list![1.0, 2.0, 3.0], num_list![1.0, 3.0, 4.0]], &mut env)
+                       .unwrap(),
+                   list![edit("keep", Number(1.0)), edit("delete", Number(2.0)),
+                         edit("keep", Number(3.0)), edit("insert", Number(4.0))]);
+        assert_eq!(crisp_diff(&[list![], list![]], &mut env).unwrap(), list![]);
+
+        assert_eq!(crisp_diff(&[str!("kitten"), str!("sitting")], &mut env).unwrap(),
+                   list![edit("delete", str!("k")), edit("insert", str!("s")),
+                         edit("keep", str!("itt")), edit("delete", str!("e")),
+                         edit("insert", str!("i")), edit("keep", str!("n")),
+                         edit("insert", str!("g"))]);
+        assert_eq!(crisp_diff(&[str!("né"), str!("née")], &mut env).unwrap(),
+                   list![edit("keep", str!("né")), edit("insert", str!("e"))]);
+
+        crisp_assert_err!(crisp_diff(&[str!("a"), num_list![1.0]], &mut env), TypeError);
+        crisp_assert_err!(crisp_diff(&[list![]], &mut env), ArgumentError);
+    }
 }
@@ -1,4 +1,10 @@
-use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::backend_foldl1};
+use std::{env, ffi::OsString, sync::atomic::{AtomicBool, Ordering}};
+
+use crate::{error::CrispError, expr::{CrispExpr, format_number}, env::CrispEnv,
+            functions::{backend_foldl1, extract_index, extract_list, extract_value}};
+
+#[cfg(feature = "decimal")]
+use crate::functions::fold_decimal;
 
 /// The math operators fold across the [`List`](CrispExpr) from left-to-right,
 /// applying the operator to the next element. The result is that `+` is more
@@ -20,25 +26,395 @@ use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::backen
 /// (/ 9 2)   ; => 4.5
 /// (mod 9 2) ; => 1
 /// ```
+///
+/// With the `decimal` feature, if any of the arguments is a
+/// [`Decimal`](CrispExpr), the result is a `Decimal` too. See
+/// [`decimal`](crate::functions::crisp_decimal).
+///
+/// Dividing by zero with `/` or `mod` raises a `MathError`, rather than giving
+/// infinity or `NaN`.
 macro_rules! fold_operator {
-    ($name:ident, $op:tt) => {
+    ($name:ident, $op:tt, $checked:ident) => {
+        fold_operator!($name, $op, $checked, false);
+    };
+
+    ($name:ident, $op:tt, $checked:ident, $divides:expr) => {
         /// See [`fold_operator`].
         pub fn $name(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+            #[cfg(feature = "decimal")]
+            if args.iter().any(|arg| matches!(arg, CrispExpr::Decimal(_))) {
+                return fold_decimal(args, rust_decimal::Decimal::$checked);
+            }
+
+            if $divides {
+                check_divisors(args)?;
+            }
+
             backend_foldl1::<f64>(args, |acc, n| acc $op n)
         }
     };
 }
 
-fold_operator!(crisp_add, +);
-fold_operator!(crisp_sub, -);
-fold_operator!(crisp_mult, *);
-fold_operator!(crisp_div, /);
-fold_operator!(crisp_mod, %);
+fold_operator!(crisp_add, +, checked_add);
+fold_operator!(crisp_sub, -, checked_sub);
+fold_operator!(crisp_mult, *, checked_mul);
+fold_operator!(crisp_div, /, checked_div, true);
+fold_operator!(crisp_mod, %, checked_rem, true);
+
+/// Raises a `MathError` if any of the divisors given to `/` or `mod` (every
+/// argument after the first) is zero.
+fn check_divisors(args: &[CrispExpr]) -> Result<(), CrispError> {
+    if args.iter().skip(1).any(|arg| matches!(arg, CrispExpr::Number(n) if *n == 0.0)) {
+        return math_error!("Division by zero.");
+    }
+
+    Ok(())
+}
+
+/// Whether math outside of a function's domain gives `NaN` (or infinity)
+/// rather than raising an error. See [`configure_lenient_math()`].
+static LENIENT_MATH: AtomicBool = AtomicBool::new(false);
+
+/// Configures whether math is lenient for the rest of the program. Normally,
+/// asking for something that isn't defined, such as the
+/// [`sqrt`](crisp_sqrt) of a negative number or the [`log`](crisp_log) of
+/// `0`, raises a `MathError`. With lenient math, the result is `NaN` or
+/// infinity instead, as with floating point numbers in most languages. This is
+/// the case if `lenient` is set (e.g. by the `--lenient-math` flag), or if the
+/// `CRISP_LENIENT_MATH` environment variable is set to a non-empty value.
+/// Dividing by zero is always an error.
+pub fn configure_lenient_math(lenient: bool) {
+    LENIENT_MATH.store(use_lenient_math(lenient, env::var_os("CRISP_LENIENT_MATH")),
+                       Ordering::Relaxed);
+}
+
+/// Decides whether math should be lenient. See [`configure_lenient_math()`].
+fn use_lenient_math(lenient: bool, lenient_env: Option<OsString>) -> bool {
+    lenient || lenient_env.is_some_and(|value| !value.is_empty())
+}
+
+/// Raises a `MathError` with `message` if an argument is outside of a
+/// function's domain (i.e. not `valid`), unless math is lenient.
+fn check_domain(valid: bool, message: impl FnOnce() -> String) -> Result<(), CrispError> {
+    if !valid && !LENIENT_MATH.load(Ordering::Relaxed) {
+        return math_error!(message());
+    }
+
+    Ok(())
+}
+
+/// `sqrt` returns the square root of a [`Number`](CrispExpr). The number
+/// can't be negative; see [`configure_lenient_math()`].
+///
+/// # Examples
+///
+/// ```lisp
+/// sqrt 16   ; => 4
+/// sqrt 2    ; => 1.4142135623730951
+/// sqrt -1   ; MathError
+/// ```
+pub fn crisp_sqrt(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let n = extract_value::<f64>(&args[0])?;
+    check_domain(n >= 0.0, || {
+        format!("Can't take the square root of {}.", format_number(n))
+    })?;
+
+    Ok(CrispExpr::Number(n.sqrt()))
+}
+
+/// `log` returns the logarithm of a [`Number`](CrispExpr), which must be
+/// positive. Without a `base`, it's the natural logarithm. The `base` must
+/// also be positive, and can't be `1`; see [`configure_lenient_math()`].
+///
+/// # Usage
+///
+/// ```lisp
+/// log n [base]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// log 1        ; => 0
+/// log 100 10   ; => 2
+/// log 8 2      ; => 3
+/// log 0        ; MathError
+/// ```
+pub fn crisp_log(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+
+    let n = extract_value::<f64>(&args[0])?;
+    check_domain(n > 0.0, || format!("Can't take the logarithm of {}.", format_number(n)))?;
+
+    match args.get(1) {
+        Some(base) => {
+            let base = extract_value::<f64>(base)?;
+            check_domain(base > 0.0 && base != 1.0, || {
+                format!("Can't take a logarithm in base {}.", format_number(base))
+            })?;
+
+            Ok(CrispExpr::Number(n.log(base)))
+        },
+        None => Ok(CrispExpr::Number(n.ln()))
+    }
+}
+
+/// `num-format` formats a [`Number`](CrispExpr) as a [`String`](CrispExpr),
+/// according to a spec made up of the following parts, in order, each of
+/// which may be left out:
+///
+///  * `0`: Pad with zeroes rather than spaces
+///  * A width: Pad the number on the left to at least this many characters
+///  * `,` or `_`: Separate each group of three digits before the decimal
+///    point with this character
+///  * `.` and a precision: Round to this many digits after the decimal point,
+///    rather than showing as many as are needed
+///
+/// The decimal point is always `.`, regardless of the locale.
+///
+/// # Usage
+///
+/// ```lisp
+/// num-format n spec
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// num-format 1.23456 ".2"    ; => "1.23"
+/// num-format 1234567.8 ",.2" ; => "1,234,567.80"
+/// num-format 42 "5"          ; => "   42"
+/// num-format -7 "04"         ; => "-007"
+/// ```
+pub fn crisp_num_format(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let n = extract_value::<f64>(&args[0])?;
+    let spec = extract_value::<String>(&args[1])?;
+    let NumberFormat { zero_pad, width, separator, precision } = parse_number_format(&spec)?;
+
+    let digits = match precision {
+        Some(precision) => format!("{:.*}", precision, n.abs()),
+        None => format_number(n.abs())
+    };
+
+    // Exponent forms, `inf` and `NaN` aren't grouped
+    let digits = match (separator, digits.split_once('.').unwrap_or((&digits, ""))) {
+        (Some(separator), (int, frac)) if int.bytes().all(|b| b.is_ascii_digit()) => {
+            let mut grouped = String::new();
+            for (i, digit) in int.chars().enumerate() {
+                if i > 0 && (int.len() - i) % 3 == 0 {
+                    grouped.push(separator);
+                }
+                grouped.push(digit);
+            }
+
+            if frac.is_empty() { grouped } else { format!("{}.{}", grouped, frac) }
+        },
+        _ => digits
+    };
+
+    let sign = if n < 0.0 { "-" } else { "" };
+    let padding = width.saturating_sub(sign.len() + digits.chars().count());
+
+    Ok(str!(if zero_pad {
+        format!("{}{}{}", sign, "0".repeat(padding), digits)
+    } else {
+        format!("{}{}{}", " ".repeat(padding), sign, digits)
+    }))
+}
+
+/// The options for [`num-format`](crisp_num_format), read from its spec.
+struct NumberFormat {
+    zero_pad: bool,
+    width: usize,
+    separator: Option<char>,
+    precision: Option<usize>
+}
+
+/// Reads the spec given to [`num-format`](crisp_num_format).
+fn parse_number_format(spec: &str) -> Result<NumberFormat, CrispError> {
+    let invalid = || standard_error_unwrapped!(format!("Invalid number format: {:?}", spec));
+
+    let (rest, precision) = match spec.split_once('.') {
+        Some((rest, precision)) => (rest, Some(precision.parse().map_err(|_| invalid())?)),
+        None => (spec, None)
+    };
+
+    let (rest, separator) = match rest.strip_suffix([',', '_']) {
+        Some(stripped) => (stripped, rest.chars().last()),
+        None => (rest, None)
+    };
+
+    let zero_pad = rest.starts_with('0');
+    let width = match rest {
+        "" => 0,
+        _ => rest.parse().map_err(|_| invalid())?
+    };
+
+    Ok(NumberFormat { zero_pad, width, separator, precision })
+}
+
+/// Extracts a vector: a [`List`](CrispExpr) of [`Number`](CrispExpr)s.
+fn extract_vector(expr: &CrispExpr) -> Result<Vec<f64>, CrispError> {
+    extract_list::<f64>(&extract_value::<Vec<CrispExpr>>(expr)?)
+}
+
+/// Extracts a matrix: a [`List`](CrispExpr) of rows, each a
+/// [vector](extract_vector), which must all be the same length.
+fn extract_matrix(expr: &CrispExpr) -> Result<Vec<Vec<f64>>, CrispError> {
+    let rows = extract_value::<Vec<CrispExpr>>(expr)?.iter()
+                                                     .map(extract_vector)
+                                                     .collect::<Result<Vec<Vec<f64>>, _>>()?;
+
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return index_error!("Rows of a matrix must all be the same length.");
+    }
+
+    Ok(rows)
+}
+
+/// Makes a [`List`](CrispExpr) of rows out of a matrix.
+fn matrix_expr(rows: Vec<Vec<f64>>) -> CrispExpr {
+    CrispExpr::List(rows.into_iter().map(|row| {
+        CrispExpr::List(row.into_iter().map(CrispExpr::Number).collect())
+    }).collect())
+}
+
+/// The dimensions of a matrix, e.g. `2x3` for 2 rows of 3, for use in errors.
+fn dimensions(rows: &[Vec<f64>]) -> String {
+    format!("{}x{}", rows.len(), rows.first().map_or(0, Vec::len))
+}
+
+/// `dot` returns the dot product of two vectors ([`List`](CrispExpr)s of
+/// [`Number`](CrispExpr)s) of the same length: the sum of the products of
+/// their items.
+///
+/// # Usage
+///
+/// ```lisp
+/// dot a b
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// dot (1 2 3) (4 5 6) ; => 32
+/// dot () ()           ; => 0
+/// ```
+pub fn crisp_dot(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let a = extract_vector(&args[0])?;
+    let b = extract_vector(&args[1])?;
+
+    if a.len() != b.len() {
+        return index_error!(format!("Can't take the dot product of vectors of lengths {} and {}.",
+                                    a.len(), b.len()));
+    }
+
+    Ok(CrispExpr::Number(a.iter().zip(&b).map(|(x, y)| x * y).sum()))
+}
+
+/// `transpose` flips a matrix (a [`List`](CrispExpr) of rows, each a `List`
+/// of [`Number`](CrispExpr)s) over its diagonal, so that its rows become its
+/// columns.
+///
+/// # Examples
+///
+/// ```lisp
+/// transpose ((1 2 3) (4 5 6)) ; => ((1 4) (2 5) (3 6))
+/// transpose ()                ; => ()
+/// ```
+pub fn crisp_transpose(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let rows = extract_matrix(&args[0])?;
+    let columns = rows.first().map_or(0, Vec::len);
+
+    Ok(matrix_expr((0..columns).map(|j| rows.iter().map(|row| row[j]).collect()).collect()))
+}
+
+/// `matrix-mul` multiplies two matrices (each a [`List`](CrispExpr) of rows,
+/// each a `List` of [`Number`](CrispExpr)s). The first must have as many
+/// columns as the second has rows.
+///
+/// # Usage
+///
+/// ```lisp
+/// matrix-mul a b
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// matrix-mul ((1 2) (3 4)) ((5 6) (7 8)) ; => ((19 22) (43 50))
+/// matrix-mul ((1 2 3)) ((1) (2) (3))     ; => ((14))
+/// ```
+pub fn crisp_matrix_mul(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let a = extract_matrix(&args[0])?;
+    let b = extract_matrix(&args[1])?;
+
+    if a.first().map_or(0, Vec::len) != b.len() {
+        return index_error!(format!("Can't multiply a {} matrix by a {} matrix.",
+                                    dimensions(&a), dimensions(&b)));
+    }
+
+    let columns = b.first().map_or(0, Vec::len);
+    Ok(matrix_expr(a.iter().map(|row| {
+        (0..columns).map(|j| row.iter().zip(&b).map(|(x, b_row)| x * b_row[j]).sum()).collect()
+    }).collect()))
+}
+
+/// `reshape` arranges the [`Number`](CrispExpr)s of a [`List`](CrispExpr)
+/// into a matrix with the given number of rows and columns, filling each row
+/// in turn. The list may also be a matrix, which is read row by row, so that
+/// it can be reshaped into another. Either way, it must have exactly
+/// `rows * columns` numbers.
+///
+/// # Usage
+///
+/// ```lisp
+/// reshape list rows columns
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// reshape (1 2 3 4 5 6) 2 3     ; => ((1 2 3) (4 5 6))
+/// reshape ((1 2 3) (4 5 6)) 3 2 ; => ((1 2) (3 4) (5 6))
+/// ```
+pub fn crisp_reshape(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let list = extract_value::<Vec<CrispExpr>>(&args[0])?;
+    let numbers = match list.first() {
+        Some(CrispExpr::List(_)) => extract_matrix(&args[0])?.concat(),
+        _ => extract_list::<f64>(&list)?
+    };
+    let rows = extract_index(&args[1])?;
+    let columns = extract_index(&args[2])?;
+
+    if numbers.len() != rows * columns {
+        return index_error!(format!("Can't reshape {} numbers into a {}x{} matrix.",
+                                    numbers.len(), rows, columns));
+    }
+
+    Ok(matrix_expr(match columns {
+        // chunks() can't make chunks of 0, but there's nothing to put in them
+        0 => vec![Vec::new(); rows],
+        _ => numbers.chunks(columns).map(<[f64]>::to_vec).collect()
+    }))
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::env::initialize_environment;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
 
     #[test]
     fn test_add() {
@@ -79,4 +455,146 @@ mod tests {
         crisp_assert_eq!(crisp_mod(&num_vec![9.0, 2.0], &mut env), 1.0);
         crisp_assert_eq!(crisp_mod(&num_vec![35.0, 25.0, 6.0], &mut env), 4.0);
     }
+
+    #[test]
+    fn test_division_by_zero() {
+        let mut env = initialize_environment();
+
+        crisp_assert_err!(crisp_div(&[Number(1.0), Number(0.0)], &mut env), MathError);
+        let args = [Number(1.0), Number(2.0), Number(-0.0)];
+        crisp_assert_err!(crisp_div(&args, &mut env), MathError);
+        crisp_assert_err!(crisp_mod(&[Number(5.0), Number(0.0)], &mut env), MathError);
+        // Only the divisors matter
+        crisp_assert_eq!(crisp_div(&[Number(0.0), Number(2.0)], &mut env), 0.0);
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let mut env = initialize_environment();
+
+        crisp_assert_eq!(crisp_sqrt(&[Number(16.0)], &mut env), 4.0);
+        crisp_assert_eq!(crisp_sqrt(&[Number(0.0)], &mut env), 0.0);
+
+        crisp_assert_err!(crisp_sqrt(&[Number(-1.0)], &mut env), MathError);
+        crisp_assert_err!(crisp_sqrt(&[str!("4")], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_log() {
+        let mut env = initialize_environment();
+
+        crisp_assert_eq!(crisp_log(&[Number(1.0)], &mut env), 0.0);
+        crisp_assert_eq!(crisp_log(&[Number(8.0), Number(2.0)], &mut env), 3.0);
+
+        crisp_assert_err!(crisp_log(&[Number(0.0)], &mut env), MathError);
+        crisp_assert_err!(crisp_log(&[Number(-2.0)], &mut env), MathError);
+        crisp_assert_err!(crisp_log(&[Number(8.0), Number(1.0)], &mut env), MathError);
+        crisp_assert_err!(crisp_log(&[Number(8.0), Number(-2.0)], &mut env), MathError);
+    }
+
+    #[test]
+    fn test_use_lenient_math() {
+        assert!(!use_lenient_math(false, None));
+        assert!(use_lenient_math(true, None));
+
+        // Environment variable; an empty value doesn't count
+        assert!(use_lenient_math(false, Some(OsString::from("1"))));
+        assert!(!use_lenient_math(false, Some(OsString::new())));
+    }
+
+    #[test]
+    fn test_num_format() {
+        let mut env = initialize_environment();
+        let mut format = |n: f64, spec: &str| crisp_num_format(&[Number(n), str!(spec)], &mut env);
+
+        assert_eq!(format(1.23456, ".2"), Ok(str!("1.23")));
+        assert_eq!(format(2.0, ".3"), Ok(str!("2.000")));
+        assert_eq!(format(0.1 + 0.2, ""), Ok(str!("0.30000000000000004")));
+        assert_eq!(format(1234567.8, ",.2"), Ok(str!("1,234,567.80")));
+        assert_eq!(format(-1234567.0, "_"), Ok(str!("-1_234_567")));
+        assert_eq!(format(123.0, ","), Ok(str!("123")));
+        assert_eq!(format(42.0, "5"), Ok(str!("   42")));
+        assert_eq!(format(-7.0, "04"), Ok(str!("-007")));
+        assert_eq!(format(1234.5, "010,.1"), Ok(str!("0001,234.5")));
+        assert_eq!(format(123456.0, "2"), Ok(str!("123456")));
+        assert_eq!(format(1e21, ","), Ok(str!("1e21")));
+
+        crisp_assert_err!(format(1.0, "x"), StandardError);
+        crisp_assert_err!(format(1.0, ".x"), StandardError);
+        crisp_assert_err!(crisp_num_format(&[str!("1"), str!("")], &mut env), TypeError);
+        crisp_assert_err!(crisp_num_format(&[Number(1.0)], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(2.0), "2");
+        assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format_number(123456789.0), "123456789");
+        assert_eq!(format_number(1e21), "1e21");
+        assert_eq!(format_number(-1.5e-8), "-1.5e-8");
+        assert_eq!(format_number(0.0), "0");
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut env = initialize_environment();
+
+        crisp_assert_eq!(crisp_dot(&[num_list![1.0, 2.0, 3.0], num_list![4.0, 5.0, 6.0]], &mut env),
+                         32.0);
+        crisp_assert_eq!(crisp_dot(&[list![], list![]], &mut env), 0.0);
+
+        crisp_assert_err!(crisp_dot(&[num_list![1.0, 2.0], num_list![1.0]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_dot(&[num_list![1.0], string_list!["a"]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_transpose(&[list![num_list![1.0, 2.0, 3.0], num_list![4.0, 5.0, 6.0]]],
+                                   &mut env).unwrap(),
+                   list![num_list![1.0, 4.0], num_list![2.0, 5.0], num_list![3.0, 6.0]]);
+        assert_eq!(crisp_transpose(&[list![]], &mut env).unwrap(), list![]);
+
+        crisp_assert_err!(crisp_transpose(&[list![num_list![1.0, 2.0], num_list![3.0]]], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_transpose(&[num_list![1.0, 2.0]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_matrix_mul() {
+        let mut env = initialize_environment();
+        let a = list![num_list![1.0, 2.0], num_list![3.0, 4.0]];
+        let b = list![num_list![5.0, 6.0], num_list![7.0, 8.0]];
+
+        assert_eq!(crisp_matrix_mul(&[a.clone(), b], &mut env).unwrap(),
+                   list![num_list![19.0, 22.0], num_list![43.0, 50.0]]);
+        assert_eq!(crisp_matrix_mul(&[list![num_list![1.0, 2.0, 3.0]],
+                                      list![num_list![1.0], num_list![2.0], num_list![3.0]]],
+                                    &mut env).unwrap(),
+                   list![num_list![14.0]]);
+
+        crisp_assert_err!(crisp_matrix_mul(&[a, list![num_list![1.0, 2.0, 3.0]]], &mut env),
+                          IndexError);
+    }
+
+    #[test]
+    fn test_reshape() {
+        let mut env = initialize_environment();
+        let numbers = num_list![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+        assert_eq!(crisp_reshape(&[numbers.clone(), Number(2.0), Number(3.0)], &mut env).unwrap(),
+                   list![num_list![1.0, 2.0, 3.0], num_list![4.0, 5.0, 6.0]]);
+        assert_eq!(crisp_reshape(&[list![num_list![1.0, 2.0, 3.0], num_list![4.0, 5.0, 6.0]],
+                                   Number(3.0), Number(2.0)], &mut env).unwrap(),
+                   list![num_list![1.0, 2.0], num_list![3.0, 4.0], num_list![5.0, 6.0]]);
+        assert_eq!(crisp_reshape(&[list![], Number(2.0), Number(0.0)], &mut env).unwrap(),
+                   list![list![], list![]]);
+
+        crisp_assert_err!(crisp_reshape(&[numbers.clone(), Number(4.0), Number(2.0)], &mut env),
+                          IndexError);
+        crisp_assert_err!(crisp_reshape(&[numbers, Number(1.5), Number(4.0)], &mut env),
+                          IndexError);
+    }
 }
@@ -0,0 +1,730 @@
+use std::{collections::BTreeSet, sync::{Arc, Mutex, MutexGuard, TryLockError}};
+
+use crate::{error::CrispError, expr::{CrispExpr, CrispLambda},
+            env::{CrispEnv, builtin_names, env_builtin, env_builtins, env_fresh, env_get,
+                  env_host_fn, env_protected},
+            eval::eval, debugger, functions::{extract_value, write_stdout}};
+
+/// `read` parses a [`String`](CrispExpr) of crisp code into an expression
+/// without evaluating it, so that it can be inspected or manipulated as data.
+/// The result can be evaluated with [`eval`](crisp_eval).
+///
+/// # Examples
+///
+/// ```lisp
+/// read "(+ 1 2)"   ; => (+ 1 2)
+/// read "+ 1 2"     ; => (+ 1 2)
+/// read "'foo'"     ; => "foo"
+/// ```
+pub fn crisp_read(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    extract_value::<String>(args.first().unwrap())?.parse()
+}
+
+/// `serialize` converts a value into a [`String`](CrispExpr) which
+/// [`deserialize`](crisp_deserialize) will turn back into an identical value.
+/// This works for any value that can be written as code: numbers, strings,
+/// chars, bools, `nil`, symbols, and lists of them. Anything else, such as a
+/// function, raises an error.
+///
+/// # Examples
+///
+/// ```lisp
+/// serialize (read "(1 'two' ,3)")  ; => "(1 'two' ,3)"
+/// serialize ,space                 ; => ",space"
+/// serialize map                    ; error, functions can't be serialized
+/// ```
+pub fn crisp_serialize(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    args.first().unwrap().to_string_exact().map(|s| CrispExpr::CrispString(s.into()))
+}
+
+/// `deserialize` turns a [`String`](CrispExpr) produced by
+/// [`serialize`](crisp_serialize) back into the value it was made from.
+///
+/// # Examples
+///
+/// ```lisp
+/// deserialize "(1 'two' ,3)"           ; => (1 "two" ,3)
+/// deserialize (serialize "it's")       ; => "it's"
+/// ```
+pub fn crisp_deserialize(
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    crisp_read(args, env)
+}
+
+/// `eval` evaluates an expression, such as one produced by [`read`](crisp_read).
+/// By default the expression is evaluated in the current scope, but an
+/// [`Env`](CrispExpr) (see [`make-env`](crisp_make_env)) may be given to
+/// evaluate it there instead.
+///
+/// # Examples
+///
+/// ```lisp
+/// eval (read "(+ 1 2)")         ; => 3
+/// let expr (read "* n 2")
+/// let n 21
+/// eval expr                     ; => 42
+/// eval expr (make-env)          ; error, `n` isn't set in the new Env
+/// ```
+pub fn crisp_eval(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+
+    match args.get(1) {
+        Some(target) => eval_in(target, args.first().unwrap()),
+        None => eval(args.first().unwrap(), env)
+    }
+}
+
+/// Evaluates `expr` in the [`Env`](CrispExpr) given by `target`.
+fn eval_in(target: &CrispExpr, expr: &CrispExpr) -> Result<CrispExpr, CrispError> {
+    let target = extract_value::<Arc<Mutex<CrispEnv<'static>>>>(target)?;
+
+    let mut target_env = lock_env(&target)?;
+
+    eval(expr, &mut target_env)
+}
+
+/// Locks an [`Env`](CrispExpr) for use. This fails rather than waiting if the
+/// `Env` is already in use, e.g. if code being evaluated in an `Env` tries to
+/// evaluate something else in that same `Env`.
+fn lock_env<'a>(
+    target: &'a Mutex<CrispEnv<'static>>
+) -> Result<MutexGuard<'a, CrispEnv<'static>>, CrispError> {
+    match target.try_lock() {
+        Ok(guard) => Ok(guard),
+        // An error in another thread doesn't leave the `Env` in a bad state
+        Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => standard_error!("Env is already in use.")
+    }
+}
+
+/// `env-keys` returns a sorted [`List`](CrispExpr) of the names of everything
+/// that is bound in the current scope and all of its outer scopes, as
+/// [`String`](CrispExpr)s.
+///
+/// # Examples
+///
+/// ```lisp
+/// env-keys    ; => ('!' '!=' '&&' ... 'words' '||')
+/// ```
+pub fn crisp_env_keys(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    let mut keys = BTreeSet::new();
+    let mut scope = Some(&*env);
+
+    while let Some(current) = scope {
+        keys.extend(current.data.keys().cloned());
+        if current.builtins {
+            keys.extend(builtin_names().map(str::to_string));
+        }
+        scope = current.parent;
+    }
+
+    Ok(CrispExpr::List(keys.into_iter().map(|s| CrispExpr::CrispString(s.into())).collect()))
+}
+
+/// `bound?` checks whether a name is bound in the current scope or any of its
+/// outer scopes.
+///
+/// # Examples
+///
+/// ```lisp
+/// bound? "map"   ; => true
+/// let n 5
+/// bound? "n"     ; => true
+/// bound? "nope"  ; => false
+/// ```
+pub fn crisp_bound(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let name = extract_value::<String>(args.first().unwrap())?;
+
+    Ok(CrispExpr::Bool(env_get(&name, env).is_some()))
+}
+
+/// `unbind!` removes a name from the current scope, returning `true` if it was
+/// bound there. Bindings in outer scopes are not affected, so if the name was
+/// shadowing an outer binding, that binding becomes visible again.
+///
+/// # Examples
+///
+/// ```lisp
+/// let n 5
+/// unbind! "n"   ; => true
+/// bound? "n"    ; => false
+/// unbind! "n"   ; => false
+/// ```
+pub fn crisp_unbind(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let name = extract_value::<String>(args.first().unwrap())?;
+    if env.consts.contains(&name) {
+        return standard_error!(format!("Can't unbind constant: {}", name));
+    }
+    if env_protected(&name, env) {
+        return standard_error!(format!("Can't unbind builtin: {}", name));
+    }
+
+    Ok(CrispExpr::Bool(env.data.remove(&name).is_some()))
+}
+
+/// `arity` returns the fewest and the most arguments that a function can be
+/// called with, as a [`List`](CrispExpr). The most is `nil` if there's no
+/// limit.
+///
+/// # Examples
+///
+/// ```lisp
+/// arity (\ (a b) a)   ; => (2 2)
+/// arity range         ; => (2 3)
+/// arity +             ; => (2 nil)
+/// ```
+pub fn crisp_arity(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let (min, max) = function_arity(&args[0])?;
+
+    Ok(list![CrispExpr::Number(min as f64), max.map_or(CrispExpr::Nil, |max| {
+        CrispExpr::Number(max as f64)
+    })])
+}
+
+/// `fn-name` returns the name of a function as a [`String`](CrispExpr): the
+/// name a builtin is bound to, or the one a [`Lambda`](CrispExpr) was defined
+/// with by `fn`. Anonymous `Lambda`s give `nil`.
+///
+/// # Examples
+///
+/// ```lisp
+/// fn-name map          ; => "map"
+/// fn square n (* n n)
+/// fn-name square       ; => "square"
+/// fn-name (\ n n)      ; => nil
+/// ```
+pub fn crisp_fn_name(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let name = match &args[0] {
+        CrispExpr::Lambda(lambda) => lambda.name.as_deref().map(str::to_string),
+        CrispExpr::Func(func) => env_builtin(*func).map(|builtin| builtin.name.to_string()),
+        _ => return type_error!("Func || Lambda")
+    };
+
+    Ok(name.map_or(CrispExpr::Nil, |name| CrispExpr::CrispString(name.into())))
+}
+
+/// `fn-args` returns the names of the arguments of a [`Lambda`](CrispExpr), as
+/// a [`List`](CrispExpr) of [`Symbol`](CrispExpr)s. Builtins don't name their
+/// arguments, so they give `nil`; see [`arity`](crisp_arity) instead.
+///
+/// # Examples
+///
+/// ```lisp
+/// fn-args (\ (a b) a)  ; => (a b)
+/// fn-args (\ n n)      ; => (n)
+/// fn-args map          ; => nil
+/// ```
+pub fn crisp_fn_args(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    match &args[0] {
+        CrispExpr::Lambda(lambda) => Ok(CrispExpr::List(lambda_args(lambda).to_vec())),
+        CrispExpr::Func(_) => Ok(CrispExpr::Nil),
+        _ => type_error!("Func || Lambda")
+    }
+}
+
+/// The smallest and largest number of arguments that a function takes, with no
+/// largest if it takes any number. Builtins that weren't registered with an
+/// arity (e.g. those added by a host program) may take any number. See
+/// [`crisp_arity()`].
+pub(crate) fn function_arity(func: &CrispExpr) -> Result<(usize, Option<usize>), CrispError> {
+    match func {
+        CrispExpr::Lambda(lambda) => {
+            let n = lambda_args(lambda).len();
+            Ok((n, Some(n)))
+        },
+        CrispExpr::Func(func) => Ok(match env_builtin(*func) {
+            Some(builtin) => (builtin.min_args, builtin.max_args),
+            None => (0, None)
+        }),
+        _ => type_error!("Func || Lambda")
+    }
+}
+
+/// The arguments of `lambda`, which may be a single [`Symbol`](CrispExpr)
+/// rather than a [`List`](CrispExpr).
+fn lambda_args(lambda: &CrispLambda) -> &[CrispExpr] {
+    match lambda.args.as_ref() {
+        CrispExpr::List(args) => args,
+        arg => std::slice::from_ref(arg)
+    }
+}
+
+/// `make-env` creates a new, empty [`Env`](CrispExpr) with only the built-in
+/// functions defined. Code that is evaluated in it with
+/// [`eval-in`](crisp_eval_in) can't see or modify the current scope, so it
+/// can be used as a sandbox. It still shares the current program's input and
+/// output, and is subject to the same restrictions, e.g. in the playground.
+///
+/// # Examples
+///
+/// ```lisp
+/// let sandbox (make-env)
+/// env-set! sandbox "n" 5
+/// eval-in sandbox (read "* n 2") ; => 10
+/// ```
+pub fn crisp_make_env(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    Ok(CrispExpr::Env(Arc::new(Mutex::new(env_fresh(env)))))
+}
+
+/// `env-get` gets the value bound to a name in an [`Env`](CrispExpr).
+///
+/// # Examples
+///
+/// ```lisp
+/// let e (make-env)
+/// env-set! e "n" 5
+/// env-get e "n"      ; => 5
+/// ```
+pub fn crisp_env_get(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let target = extract_value::<Arc<Mutex<CrispEnv<'static>>>>(args.first().unwrap())?;
+    let name = extract_value::<String>(args.get(1).unwrap())?;
+
+    let value = env_get(&name, &*lock_env(&target)?);
+
+    value.ok_or_else(|| parse_error_unwrapped!(format!("Could not find symbol: {}", name)))
+}
+
+/// `env-set!` binds a name to a value in an [`Env`](CrispExpr), returning the
+/// value.
+///
+/// # Examples
+///
+/// ```lisp
+/// let e (make-env)
+/// env-set! e "n" 5   ; => 5
+/// ```
+pub fn crisp_env_set(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let target = extract_value::<Arc<Mutex<CrispEnv<'static>>>>(args.first().unwrap())?;
+    let name = extract_value::<String>(args.get(1).unwrap())?;
+    let value = args.get(2).unwrap().clone();
+
+    lock_env(&target)?.data.insert(name, value.clone());
+
+    Ok(value)
+}
+
+/// `eval-in` evaluates an expression in an [`Env`](CrispExpr). This is the
+/// same as calling [`eval`](crisp_eval) with an `Env`, with the arguments
+/// the other way around.
+///
+/// # Examples
+///
+/// ```lisp
+/// let e (make-env)
+/// eval-in e (read "let n 5")
+/// env-get e "n"               ; => 5
+/// ```
+pub fn crisp_eval_in(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    eval_in(args.first().unwrap(), args.get(1).unwrap())
+}
+
+/// `host-call` calls a function provided by the application that crisp is
+/// embedded in, by name, with a [`List`](CrispExpr) of arguments. Which
+/// functions are available is entirely up to the application (see
+/// [`with_host_fn()`](CrispEnv::with_host_fn)); by default there are none.
+///
+/// # Examples
+///
+/// ```lisp
+/// host-call "notify" ("Build finished")
+/// host-call "window-size"                 ; no arguments
+/// ```
+pub fn crisp_host_call(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+
+    let name = extract_value::<String>(args.first().unwrap())?;
+    let call_args = match args.get(1) {
+        Some(CrispExpr::List(list)) => list.clone(),
+        Some(_) => return type_error!("List"),
+        None => Vec::new()
+    };
+
+    match env_host_fn(&name, env) {
+        Some(f) => f(&call_args),
+        None => standard_error!(format!("No host function named {:?}.", name))
+    }
+}
+
+/// `break` pauses evaluation and drops into the debugger, if crisp was run
+/// with `--debugger`; otherwise it does nothing. At the `debug>` prompt,
+/// `continue` resumes, `step` pauses again before each expression, `locals`
+/// lists the variables in scope, and anything else is evaluated in the scope
+/// that `break` was called from.
+///
+/// # Examples
+///
+/// ```lisp
+/// fn f (n) (break)
+/// f 5               ; debug> n
+///                   ; => 5
+/// ```
+pub fn crisp_break(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    if debugger::is_enabled() {
+        debugger::pause(None, env)?;
+    }
+
+    Ok(CrispExpr::Nil)
+}
+
+/// `help` prints a list of the builtins. With no arguments, it lists the
+/// categories that they're grouped into. Given a [`String`](CrispExpr), it
+/// lists every builtin in the category of that name (in any case), or if
+/// there's no such category, every builtin whose name contains it, each with
+/// a one-line description.
+///
+/// # Examples
+///
+/// ```lisp
+/// help            ; lists the categories
+/// help "lists"    ; lists the builtins in the Lists category
+/// help "->"       ; lists `camel->snake` and `kebab->camel`
+/// ```
+pub fn crisp_help(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 1);
+
+    let builtins = env_builtins();
+
+    let text = match args.first() {
+        None => {
+            let categories: BTreeSet<_> = builtins.iter().map(|builtin| builtin.category)
+                                                         .collect();
+            let lines: Vec<_> = categories.iter().map(|category| format!("  {}\n", category))
+                                                 .collect();
+
+            format!("Categories (see `help \"category\"`):\n{}", lines.concat())
+        },
+
+        Some(query) => {
+            let query = extract_value::<String>(query)?;
+
+            let mut matching: Vec<_> = builtins.iter().filter(|builtin| {
+                builtin.category.eq_ignore_ascii_case(&query)
+            }).collect();
+            if matching.is_empty() {
+                matching = builtins.iter().filter(|builtin| builtin.name.contains(&query))
+                                          .collect();
+            }
+
+            let width = matching.iter().map(|builtin| builtin.name.chars().count())
+                                       .max()
+                                       .unwrap_or(0);
+            let lines: Vec<_> = matching.iter().map(|builtin| {
+                format!("  {:width$}  {}\n", builtin.name, builtin.doc, width = width)
+            }).collect();
+
+            match lines.is_empty() {
+                true => format!("No builtins match \"{}\".\n", query),
+                false => lines.concat()
+            }
+        }
+    };
+
+    write_stdout(&text, env)?;
+    Ok(CrispExpr::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::CrispExpr::*, env::initialize_environment, functions::extract_list};
+
+    #[test]
+    fn test_read() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_read(&[str!("(+ 1 2)")], &mut env).unwrap(),
+                   list![sym!("+"), Number(1.0), Number(2.0)]);
+        assert_eq!(crisp_read(&[str!("+ 1 2")], &mut env).unwrap(),
+                   list![sym!("+"), Number(1.0), Number(2.0)]);
+        assert_eq!(crisp_read(&[str!("'foo'")], &mut env).unwrap(),
+                   str!("foo"));
+        assert_eq!(crisp_read(&[str!("foo")], &mut env).unwrap(),
+                   sym!("foo"));
+
+        crisp_assert_err!(crisp_read(&[str!("(+ 1 2")], &mut env), ParseError);
+        crisp_assert_err!(crisp_read(&[str!("(+ 1 2) 3)")], &mut env), ParseError);
+        crisp_assert_err!(crisp_read(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_help() {
+        use crate::playground::eval;
+
+        let categories = eval("help");
+        assert!(categories.starts_with("Categories"), "{}", categories);
+        assert!(categories.contains("  Lists\n") && categories.contains("  Meta\n"));
+
+        // By category, in any case
+        let lists = eval("help \"lists\"");
+        assert!(lists.contains("  map ") && lists.contains("Calls a function"), "{}", lists);
+        assert!(!lists.contains("  puts "));
+
+        // Otherwise, by name
+        assert_eq!(eval("help \"->\""),
+                   "  camel->snake  Converts camelCase to snake_case.\n\
+                    \x20 kebab->camel  Converts kebab-case to camelCase.\n=> nil");
+        assert_eq!(eval("help \"xyzzy\""), "No builtins match \"xyzzy\".\n=> nil");
+
+        crisp_assert_err!(crisp_help(&[Number(1.0)], &mut initialize_environment()), TypeError);
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let mut env = initialize_environment();
+
+        let value = list![
+            sym!("foo"),
+            str!("it's \"quoted\"\n"),
+            Char(' '),
+            Char(','),
+            Char('\u{7}'),
+            Nil,
+            Bool(false),
+            Number(-0.1),
+            Number(1e300),
+            list![],
+            list![list![Number(1.0)]]
+        ];
+
+        let serialized = crisp_serialize(std::slice::from_ref(&value), &mut env).unwrap();
+        assert_eq!(crisp_deserialize(&[serialized], &mut env).unwrap(), value);
+
+        for atom in [Number(5.0), str!("a b"), Char('x'), Nil, sym!("x")] {
+            let serialized = crisp_serialize(std::slice::from_ref(&atom), &mut env).unwrap();
+            assert_eq!(crisp_deserialize(&[serialized], &mut env).unwrap(), atom);
+        }
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        let mut env = initialize_environment();
+        let map = env_get("map", &env).unwrap();
+
+        crisp_assert_err!(crisp_serialize(std::slice::from_ref(&map), &mut env), StandardError);
+        crisp_assert_err!(crisp_serialize(&[list![Number(1.0), map]], &mut env), StandardError);
+
+        // Symbols which would be read back as something else
+        crisp_assert_err!(crisp_serialize(&[sym!("5")], &mut env), StandardError);
+        crisp_assert_err!(crisp_serialize(&[sym!("true")], &mut env), StandardError);
+        crisp_assert_err!(crisp_serialize(&[sym!("a b")], &mut env), StandardError);
+        crisp_assert_err!(crisp_serialize(&[sym!("")], &mut env), StandardError);
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut env = initialize_environment();
+
+        let expr = crisp_read(&[str!("(* n 2)")], &mut env).unwrap();
+        env.data.insert("n".to_string(), Number(21.0));
+
+        assert_eq!(crisp_eval(&[expr], &mut env).unwrap(), Number(42.0));
+        assert_eq!(crisp_eval(&[Number(5.0)], &mut env).unwrap(), Number(5.0));
+        assert_eq!(crisp_eval(&[sym!("n")], &mut env).unwrap(), Number(21.0));
+
+        crisp_assert_err!(crisp_eval(&[sym!("undefined")], &mut env), ParseError);
+    }
+
+    #[test]
+    fn test_env_keys() {
+        let mut env = initialize_environment();
+        env.data.insert("zzz".to_string(), Number(1.0));
+
+        let keys = extract_value::<Vec<CrispExpr>>(&crisp_env_keys(&[], &mut env).unwrap()).unwrap();
+
+        assert!(keys.contains(&str!("map")));
+        assert!(keys.contains(&str!("zzz")));
+
+        let names = extract_list::<String>(&keys).unwrap();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+
+        // Outer scopes are included
+        let mut child = CrispEnv {
+            data: [("inner".to_string(), Number(2.0))].into_iter().collect(),
+            parent: Some(&env),
+            ..Default::default()
+        };
+        let keys = extract_value::<Vec<CrispExpr>>(&crisp_env_keys(&[], &mut child).unwrap()).unwrap();
+
+        assert!(keys.contains(&str!("inner")));
+        assert!(keys.contains(&str!("zzz")));
+    }
+
+    #[test]
+    fn test_bound() {
+        let mut env = initialize_environment();
+
+        crisp_assert!(crisp_bound(&[str!("map")], &mut env));
+        crisp_assert_false!(crisp_bound(&[str!("n")], &mut env));
+
+        env.data.insert("n".to_string(), Number(1.0));
+        crisp_assert!(crisp_bound(&[str!("n")], &mut env));
+
+        crisp_assert_err!(crisp_bound(&[sym!("n")], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_arity() {
+        let mut env = initialize_environment();
+        let lambda = lambda![args: ["a", "b"], func: [sym!("a")]];
+
+        assert_eq!(crisp_arity(std::slice::from_ref(&lambda), &mut env).unwrap(),
+                   num_list![2.0, 2.0]);
+        assert_eq!(crisp_arity(&[env_get("range", &env).unwrap()], &mut env).unwrap(),
+                   num_list![2.0, 3.0]);
+        assert_eq!(crisp_arity(&[env_get("+", &env).unwrap()], &mut env).unwrap(),
+                   list![Number(2.0), Nil]);
+        assert_eq!(crisp_arity(&[env_get("gets", &env).unwrap()], &mut env).unwrap(),
+                   num_list![0.0, 0.0]);
+
+        crisp_assert_err!(crisp_arity(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_fn_name() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_fn_name(&[env_get("map", &env).unwrap()], &mut env).unwrap(), str!("map"));
+        let anonymous = lambda![args: ["n"], func: [sym!("n")]];
+        assert_eq!(crisp_fn_name(&[anonymous], &mut env).unwrap(), Nil);
+
+        let square = eval(&"fn square n (* n n)".parse().unwrap(), &mut env).unwrap();
+        assert_eq!(crisp_fn_name(&[square], &mut env).unwrap(), str!("square"));
+
+        crisp_assert_err!(crisp_fn_name(&[str!("map")], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_fn_args() {
+        let mut env = initialize_environment();
+
+        let lambda = lambda![args: ["a", "b"], func: [sym!("a")]];
+        assert_eq!(crisp_fn_args(&[lambda], &mut env).unwrap(), list![sym!("a"), sym!("b")]);
+        let single = eval(&"\\ n n".parse().unwrap(), &mut env).unwrap();
+        assert_eq!(crisp_fn_args(&[single], &mut env).unwrap(), list![sym!("n")]);
+        assert_eq!(crisp_fn_args(&[env_get("map", &env).unwrap()], &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(crisp_fn_args(&[Nil], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_unbind() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(1.0));
+
+        crisp_assert!(crisp_unbind(&[str!("n")], &mut env));
+        crisp_assert_false!(crisp_bound(&[str!("n")], &mut env));
+        crisp_assert_false!(crisp_unbind(&[str!("n")], &mut env));
+
+        // Builtins are protected
+        crisp_assert_err!(crisp_unbind(&[str!("+")], &mut env), StandardError);
+        crisp_assert!(crisp_bound(&[str!("+")], &mut env));
+    }
+
+    #[test]
+    fn test_make_env() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(1.0));
+
+        let new_env = crisp_make_env(&[], &mut env).unwrap();
+
+        // Built-ins are available, but not the current scope
+        assert!(matches!(crisp_env_get(&[new_env.clone(), str!("map")], &mut env), Ok(Func(_))));
+        crisp_assert_err!(crisp_env_get(&[new_env.clone(), str!("n")], &mut env), ParseError);
+
+        // Each one is distinct
+        assert_ne!(new_env, crisp_make_env(&[], &mut env).unwrap());
+        assert_eq!(new_env, new_env.clone());
+    }
+
+    #[test]
+    fn test_env_get_set() {
+        let mut env = initialize_environment();
+        let new_env = crisp_make_env(&[], &mut env).unwrap();
+
+        assert_eq!(crisp_env_set(&[new_env.clone(), str!("n"), Number(5.0)], &mut env).unwrap(),
+                   Number(5.0));
+        assert_eq!(crisp_env_get(&[new_env.clone(), str!("n")], &mut env).unwrap(),
+                   Number(5.0));
+
+        // The current scope isn't affected
+        assert!(!env.data.contains_key("n"));
+
+        crisp_assert_err!(crisp_env_get(&[Number(1.0), str!("n")], &mut env), TypeError);
+        crisp_assert_err!(crisp_env_set(&[new_env, sym!("n"), Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_eval_in() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(1.0));
+
+        let new_env = crisp_make_env(&[], &mut env).unwrap();
+        crisp_env_set(&[new_env.clone(), str!("n"), Number(20.0)], &mut env).unwrap();
+
+        let expr = list![sym!("+"), sym!("n"), Number(1.0)];
+        assert_eq!(crisp_eval_in(&[new_env.clone(), expr.clone()], &mut env).unwrap(),
+                   Number(21.0));
+        assert_eq!(crisp_eval(&[expr.clone(), new_env.clone()], &mut env).unwrap(),
+                   Number(21.0));
+        assert_eq!(crisp_eval(&[expr], &mut env).unwrap(),
+                   Number(2.0));
+
+        // Definitions stay in the Env
+        let expr = list![sym!("let"), sym!("m"), Number(3.0)];
+        crisp_eval_in(&[new_env.clone(), expr], &mut env).unwrap();
+        assert_eq!(crisp_env_get(&[new_env, str!("m")], &mut env).unwrap(), Number(3.0));
+        assert!(!env.data.contains_key("m"));
+    }
+
+    #[test]
+    fn test_host_call() {
+        let mut env = initialize_environment()
+            .with_host_fn("sum", |args| {
+                Ok(Number(extract_list::<f64>(args)?.iter().sum()))
+            })
+            .with_host_fn("answer", |_| Ok(Number(42.0)));
+
+        assert_eq!(crisp_host_call(&[str!("sum"), list![Number(1.0), Number(2.0)]], &mut env)
+                       .unwrap(),
+                   Number(3.0));
+        assert_eq!(crisp_host_call(&[str!("answer")], &mut env).unwrap(), Number(42.0));
+        crisp_assert_err!(crisp_host_call(&[str!("sum"), list![str!("a")]], &mut env),
+                          TypeError);
+        crisp_assert_err!(crisp_host_call(&[str!("sum"), Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_host_call(&[str!("missing")], &mut env), StandardError);
+
+        // Nothing is available by default
+        let mut env = initialize_environment();
+        crisp_assert_err!(crisp_host_call(&[str!("answer")], &mut env), StandardError);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::VecDeque, sync::{Arc, Mutex, MutexGuard}};
+
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::extract_value};
+
+/// `queue` creates a [`Queue`](CrispExpr) holding the values given, from front
+/// to back. A `Queue` is a double-ended queue: values can be added or removed
+/// at either end in constant time, unlike a [`List`](CrispExpr), which is
+/// copied whenever it's changed.
+///
+/// Values are usually added to the back with [`enqueue!`](crisp_enqueue) and
+/// taken from the front with [`dequeue!`](crisp_dequeue), but
+/// [`push-front!`](crisp_push_front) and [`pop-back!`](crisp_pop_back) work on
+/// the other ends. Like an [`Atom`](CrispExpr), every copy of a `Queue` refers
+/// to the same one.
+///
+/// # Usage
+///
+/// ```lisp
+/// queue [value...]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let q (queue 1 2)
+/// enqueue! q 3
+/// dequeue! q        ; => 1
+/// pop-back! q       ; => 3
+/// ```
+pub fn crisp_queue(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    Ok(CrispExpr::Queue(Arc::new(Mutex::new(args.iter().cloned().collect()))))
+}
+
+/// `enqueue!` adds a value to the back of a [`Queue`](CrispExpr), returning
+/// the value.
+///
+/// # Usage
+///
+/// ```lisp
+/// enqueue! queue value
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let q (queue)
+/// enqueue! q 1   ; => 1
+/// ```
+pub fn crisp_enqueue(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let queue = extract_value::<Arc<Mutex<VecDeque<CrispExpr>>>>(&args[0])?;
+    lock_queue(&queue).push_back(args[1].clone());
+
+    Ok(args[1].clone())
+}
+
+/// `dequeue!` removes and returns the value at the front of a
+/// [`Queue`](CrispExpr), or `nil` if it's empty.
+///
+/// # Examples
+///
+/// ```lisp
+/// let q (queue 1 2)
+/// dequeue! q        ; => 1
+/// dequeue! q        ; => 2
+/// dequeue! q        ; => nil
+/// ```
+pub fn crisp_dequeue(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let queue = extract_value::<Arc<Mutex<VecDeque<CrispExpr>>>>(&args[0])?;
+    let value = lock_queue(&queue).pop_front();
+
+    Ok(value.unwrap_or(CrispExpr::Nil))
+}
+
+/// `push-front!` adds a value to the front of a [`Queue`](CrispExpr),
+/// returning the value.
+///
+/// # Usage
+///
+/// ```lisp
+/// push-front! queue value
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let q (queue 2)
+/// push-front! q 1
+/// dequeue! q        ; => 1
+/// ```
+pub fn crisp_push_front(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let queue = extract_value::<Arc<Mutex<VecDeque<CrispExpr>>>>(&args[0])?;
+    lock_queue(&queue).push_front(args[1].clone());
+
+    Ok(args[1].clone())
+}
+
+/// `pop-back!` removes and returns the value at the back of a
+/// [`Queue`](CrispExpr), or `nil` if it's empty.
+///
+/// # Examples
+///
+/// ```lisp
+/// let q (queue 1 2)
+/// pop-back! q       ; => 2
+/// ```
+pub fn crisp_pop_back(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let queue = extract_value::<Arc<Mutex<VecDeque<CrispExpr>>>>(&args[0])?;
+    let value = lock_queue(&queue).pop_back();
+
+    Ok(value.unwrap_or(CrispExpr::Nil))
+}
+
+/// Locks a [`Queue`](CrispExpr) for reading or writing.
+fn lock_queue(queue: &Mutex<VecDeque<CrispExpr>>) -> MutexGuard<'_, VecDeque<CrispExpr>> {
+    // A panic in another thread can't leave the queue half-written
+    queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::CrispExpr::*, env::initialize_environment};
+
+    #[test]
+    fn test_queue() {
+        let mut env = initialize_environment();
+        let queue = crisp_queue(&[Number(1.0), Number(2.0)], &mut env).unwrap();
+
+        assert_eq!(crisp_enqueue(&[queue.clone(), Number(3.0)], &mut env).unwrap(), Number(3.0));
+        assert_eq!(crisp_dequeue(std::slice::from_ref(&queue), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_dequeue(std::slice::from_ref(&queue), &mut env).unwrap(), Number(2.0));
+        assert_eq!(crisp_dequeue(std::slice::from_ref(&queue), &mut env).unwrap(), Number(3.0));
+        assert_eq!(crisp_dequeue(std::slice::from_ref(&queue), &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(crisp_enqueue(std::slice::from_ref(&queue), &mut env), ArgumentError);
+        crisp_assert_err!(crisp_dequeue(&[num_list![1.0]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_queue_ends() {
+        let mut env = initialize_environment();
+        let queue = crisp_queue(&[Number(2.0)], &mut env).unwrap();
+
+        crisp_push_front(&[queue.clone(), Number(1.0)], &mut env).unwrap();
+        crisp_enqueue(&[queue.clone(), Number(3.0)], &mut env).unwrap();
+
+        assert_eq!(crisp_pop_back(std::slice::from_ref(&queue), &mut env).unwrap(), Number(3.0));
+        assert_eq!(crisp_pop_back(std::slice::from_ref(&queue), &mut env).unwrap(), Number(2.0));
+        assert_eq!(crisp_dequeue(std::slice::from_ref(&queue), &mut env).unwrap(), Number(1.0));
+        assert_eq!(crisp_pop_back(std::slice::from_ref(&queue), &mut env).unwrap(), Nil);
+
+        // Copies share the same queue
+        let copy = queue.clone();
+        crisp_enqueue(&[copy, Number(4.0)], &mut env).unwrap();
+        assert_eq!(crisp_dequeue(&[queue], &mut env).unwrap(), Number(4.0));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::extract_index};
+
+/// Fills `buf` with cryptographically secure random bytes from the operating
+/// system.
+fn fill_random(buf: &mut [u8]) -> Result<(), CrispError> {
+    getrandom::getrandom(buf)
+        .map_err(|e| standard_error_unwrapped!(format!("Couldn't generate random bytes: {}", e)))
+}
+
+/// `uuid` generates a random (version 4) UUID, as a [`String`](CrispExpr) in
+/// the standard hyphenated form.
+///
+/// # Examples
+///
+/// ```lisp
+/// uuid   ; => "3f2b8c1e-9d4a-4e7b-a0c5-6f1d2e3b4a59"
+/// ```
+pub fn crisp_uuid(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    let mut bytes = [0; 16];
+    fill_random(&mut bytes)?;
+
+    // Set the version (4) and variant (RFC 4122) bits
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = hex::encode(bytes);
+    Ok(str!(format!("{}-{}-{}-{}-{}",
+                                      &hex[0..8], &hex[8..12], &hex[12..16],
+                                      &hex[16..20], &hex[20..32])))
+}
+
+/// `random-bytes` generates the given number of cryptographically secure
+/// random bytes, returned hex-encoded as a [`String`](CrispExpr) (so it's
+/// twice as long as the number of bytes). This is suitable for tokens and
+/// other secrets.
+///
+/// # Examples
+///
+/// ```lisp
+/// random-bytes 4    ; => "9f03a7c2"
+/// random-bytes 16   ; => a 32-character token
+/// ```
+pub fn crisp_random_bytes(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let mut bytes = vec![0; extract_index(args.first().unwrap())?];
+    fill_random(&mut bytes)?;
+
+    Ok(str!(hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, functions::extract_value};
+
+    #[test]
+    fn test_uuid() {
+        let mut env = initialize_environment();
+
+        let uuid = extract_value::<String>(&crisp_uuid(&[], &mut env).unwrap()).unwrap();
+        let groups: Vec<&str> = uuid.split('-').collect();
+
+        assert_eq!(groups.iter().map(|group| group.len()).collect::<Vec<usize>>(),
+                   [8, 4, 4, 4, 12]);
+        assert!(groups[2].starts_with('4'));
+        assert!(groups[3].starts_with(['8', '9', 'a', 'b']));
+        assert_ne!(crisp_uuid(&[], &mut env).unwrap(), crisp_uuid(&[], &mut env).unwrap());
+    }
+
+    #[test]
+    fn test_random_bytes() {
+        let mut env = initialize_environment();
+
+        let bytes = extract_value::<String>(
+            &crisp_random_bytes(&[CrispExpr::Number(16.0)], &mut env).unwrap()
+        ).unwrap();
+
+        assert_eq!(bytes.len(), 32);
+        assert!(bytes.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(crisp_random_bytes(&[CrispExpr::Number(0.0)], &mut env).unwrap(), str!(""));
+
+        crisp_assert_err!(crisp_random_bytes(&[CrispExpr::Number(-1.0)], &mut env), IndexError);
+        crisp_assert_err!(crisp_random_bytes(&[CrispExpr::Number(1.5)], &mut env), IndexError);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::call_function};
+
+/// A tagged value, as made by [`ok`](crisp_ok), [`err`](crisp_err),
+/// [`some`](crisp_some) or [`none`](crisp_none).
+enum Tagged<'a> {
+    Ok(&'a CrispExpr),
+    Err(&'a CrispExpr),
+    Some(&'a CrispExpr),
+    None
+}
+
+/// Makes a tagged value: a [`List`](CrispExpr) starting with the
+/// [`Symbol`](CrispExpr) `tag`.
+fn tag(tag: &str, values: &[CrispExpr]) -> CrispExpr {
+    CrispExpr::List(std::iter::once(sym!(tag))
+                        .chain(values.iter().cloned())
+                        .collect())
+}
+
+/// Reads a tagged value, returning `None` if `expr` isn't one.
+fn read_tagged(expr: &CrispExpr) -> Option<Tagged<'_>> {
+    let CrispExpr::List(list) = expr else {
+        return None;
+    };
+
+    match &list[..] {
+        [CrispExpr::Symbol(tag), value] if &**tag == "ok" => Some(Tagged::Ok(value)),
+        [CrispExpr::Symbol(tag), value] if &**tag == "err" => Some(Tagged::Err(value)),
+        [CrispExpr::Symbol(tag), value] if &**tag == "some" => Some(Tagged::Some(value)),
+        [CrispExpr::Symbol(tag)] if &**tag == "none" => Some(Tagged::None),
+        _ => None
+    }
+}
+
+/// Reads a tagged value, raising a `TypeError` if `expr` isn't one.
+fn extract_tagged(expr: &CrispExpr) -> Result<Tagged<'_>, CrispError> {
+    match read_tagged(expr) {
+        Some(tagged) => Ok(tagged),
+        None => type_error!("Result || Option")
+    }
+}
+
+/// `ok` wraps a value as the result of an operation that succeeded: a list
+/// tagged with `ok`. See also [`err`](crisp_err).
+///
+/// Results let fallible operations be modelled without raising errors; they
+/// can be checked with [`ok?`](crisp_is_ok), and combined with
+/// [`and-then`](crisp_and_then), [`or-else`](crisp_or_else) and
+/// [`unwrap-or`](crisp_unwrap_or).
+///
+/// # Examples
+///
+/// ```lisp
+/// ok 5         ; => (ok 5)
+/// ok? (ok 5)   ; => true
+/// ```
+pub fn crisp_ok(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(tag("ok", args))
+}
+
+/// `err` wraps a value (e.g. a message) as the result of an operation that
+/// failed: a list tagged with `err`. See also [`ok`](crisp_ok).
+///
+/// # Examples
+///
+/// ```lisp
+/// err "Not found"          ; => (err "Not found")
+/// err? (err "Not found")   ; => true
+/// ```
+pub fn crisp_err(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(tag("err", args))
+}
+
+/// `some` wraps a value that may have been missing: a list tagged with
+/// `some`. See also [`none`](crisp_none).
+///
+/// Options work with the same combinators as results, with `some` taking the
+/// place of `ok` and `none` that of `err`.
+///
+/// # Examples
+///
+/// ```lisp
+/// some 5                ; => (some 5)
+/// unwrap-or (some 5) 0  ; => 5
+/// ```
+pub fn crisp_some(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(tag("some", args))
+}
+
+/// `none` stands for a missing value: a list holding only the tag `none`.
+/// See also [`some`](crisp_some).
+///
+/// # Examples
+///
+/// ```lisp
+/// none? (none)          ; => true
+/// unwrap-or (none) 0    ; => 0
+/// ```
+pub fn crisp_none(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    Ok(tag("none", &[]))
+}
+
+/// `ok?` checks whether a value was made with [`ok`](crisp_ok).
+///
+/// # Examples
+///
+/// ```lisp
+/// ok? (ok 5)    ; => true
+/// ok? (err 5)   ; => false
+/// ok? 5         ; => false
+/// ```
+pub fn crisp_is_ok(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(CrispExpr::Bool(matches!(read_tagged(&args[0]), Some(Tagged::Ok(_)))))
+}
+
+/// `err?` checks whether a value was made with [`err`](crisp_err).
+///
+/// # Examples
+///
+/// ```lisp
+/// err? (err 5)   ; => true
+/// err? (ok 5)    ; => false
+/// ```
+pub fn crisp_is_err(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(CrispExpr::Bool(matches!(read_tagged(&args[0]), Some(Tagged::Err(_)))))
+}
+
+/// `some?` checks whether a value was made with [`some`](crisp_some).
+///
+/// # Examples
+///
+/// ```lisp
+/// some? (some 5)   ; => true
+/// some? (none)     ; => false
+/// ```
+pub fn crisp_is_some(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(CrispExpr::Bool(matches!(read_tagged(&args[0]), Some(Tagged::Some(_)))))
+}
+
+/// `none?` checks whether a value was made with [`none`](crisp_none).
+///
+/// # Examples
+///
+/// ```lisp
+/// none? (none)     ; => true
+/// none? (some 5)   ; => false
+/// ```
+pub fn crisp_is_none(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(CrispExpr::Bool(matches!(read_tagged(&args[0]), Some(Tagged::None))))
+}
+
+/// `unwrap-or` returns the value inside of an [`ok`](crisp_ok) or
+/// [`some`](crisp_some), or `default` for an [`err`](crisp_err) or
+/// [`none`](crisp_none).
+///
+/// # Usage
+///
+/// ```lisp
+/// unwrap-or result default
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// unwrap-or (ok 5) 0       ; => 5
+/// unwrap-or (err "x") 0    ; => 0
+/// ```
+pub fn crisp_unwrap_or(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    match extract_tagged(&args[0])? {
+        Tagged::Ok(value) | Tagged::Some(value) => Ok(value.clone()),
+        Tagged::Err(_) | Tagged::None => Ok(args[1].clone())
+    }
+}
+
+/// `and-then` chains another fallible operation onto a result: for an
+/// [`ok`](crisp_ok) or [`some`](crisp_some), it calls the function with the
+/// value inside and returns whatever it returns, which should be another
+/// result (or option). An [`err`](crisp_err) or [`none`](crisp_none) is
+/// returned as it is, without calling the function.
+///
+/// # Usage
+///
+/// ```lisp
+/// and-then result function
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// fn halve (n) (if (= (mod n 2) 0) (ok (/ n 2)) (err "Odd"))
+///
+/// and-then (ok 8) halve                   ; => (ok 4)
+/// and-then (and-then (ok 6) halve) halve  ; => (err "Odd")
+/// and-then (err "x") halve                ; => (err "x")
+/// ```
+pub fn crisp_and_then(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    match extract_tagged(&args[0])? {
+        Tagged::Ok(value) | Tagged::Some(value) => {
+            call_function(&args[1], std::slice::from_ref(value), env)
+        },
+        Tagged::Err(_) | Tagged::None => Ok(args[0].clone())
+    }
+}
+
+/// `or-else` recovers from a failed result: for an [`err`](crisp_err), it
+/// calls the function with the error value, and for a [`none`](crisp_none),
+/// with no arguments, returning whatever it returns. An [`ok`](crisp_ok) or
+/// [`some`](crisp_some) is returned as it is, without calling the function.
+///
+/// # Usage
+///
+/// ```lisp
+/// or-else result function
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// or-else (err "x") (\ (e) (ok 0))   ; => (ok 0)
+/// or-else (none) (\ () (some 0))     ; => (some 0)
+/// or-else (ok 5) (\ (e) (ok 0))      ; => (ok 5)
+/// ```
+pub fn crisp_or_else(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    match extract_tagged(&args[0])? {
+        Tagged::Err(error) => call_function(&args[1], std::slice::from_ref(error), env),
+        Tagged::None => call_function(&args[1], &[], env),
+        Tagged::Ok(_) | Tagged::Some(_) => Ok(args[0].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{expr::{CrispExpr::*, CrispLambda}, env::initialize_environment};
+
+    #[test]
+    fn test_tags() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_ok(&[Number(5.0)], &mut env).unwrap(), list![sym!("ok"), Number(5.0)]);
+        assert_eq!(crisp_err(&[str!("x")], &mut env).unwrap(), list![sym!("err"), str!("x")]);
+        assert_eq!(crisp_some(&[Nil], &mut env).unwrap(), list![sym!("some"), Nil]);
+        assert_eq!(crisp_none(&[], &mut env).unwrap(), list![sym!("none")]);
+        crisp_assert_err!(crisp_ok(&[], &mut env), ArgumentError);
+        crisp_assert_err!(crisp_none(&[Nil], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut env = initialize_environment();
+        let ok = list![sym!("ok"), Number(5.0)];
+        let none = list![sym!("none")];
+
+        assert_eq!(crisp_is_ok(std::slice::from_ref(&ok), &mut env).unwrap(), Bool(true));
+        assert_eq!(crisp_is_err(std::slice::from_ref(&ok), &mut env).unwrap(), Bool(false));
+        assert_eq!(crisp_is_none(std::slice::from_ref(&none), &mut env).unwrap(), Bool(true));
+        assert_eq!(crisp_is_some(std::slice::from_ref(&none), &mut env).unwrap(), Bool(false));
+
+        // Only the exact shape counts
+        assert_eq!(crisp_is_ok(&[Number(5.0)], &mut env).unwrap(), Bool(false));
+        assert_eq!(crisp_is_ok(&[list![sym!("ok")]], &mut env).unwrap(), Bool(false));
+        assert_eq!(crisp_is_none(&[list![sym!("none"), Nil]], &mut env).unwrap(), Bool(false));
+    }
+
+    #[test]
+    fn test_unwrap_or() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_unwrap_or(&[list![sym!("ok"), Number(5.0)], Number(0.0)], &mut env)
+                       .unwrap(), Number(5.0));
+        assert_eq!(crisp_unwrap_or(&[list![sym!("some"), Number(5.0)], Number(0.0)], &mut env)
+                       .unwrap(), Number(5.0));
+        assert_eq!(crisp_unwrap_or(&[list![sym!("err"), str!("x")], Number(0.0)], &mut env)
+                       .unwrap(), Number(0.0));
+        assert_eq!(crisp_unwrap_or(&[list![sym!("none")], Number(0.0)], &mut env).unwrap(),
+                   Number(0.0));
+        crisp_assert_err!(crisp_unwrap_or(&[Number(5.0), Number(0.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_and_then() {
+        let mut env = initialize_environment();
+        let wrap = lambda![args: ["n"],
+                           func: [sym!("ok"), list![sym!("*"), sym!("n"), Number(2.0)]]];
+
+        assert_eq!(crisp_and_then(&[list![sym!("ok"), Number(5.0)], wrap.clone()], &mut env)
+                       .unwrap(), list![sym!("ok"), Number(10.0)]);
+        assert_eq!(crisp_and_then(&[list![sym!("err"), str!("x")], wrap.clone()], &mut env)
+                       .unwrap(), list![sym!("err"), str!("x")]);
+        assert_eq!(crisp_and_then(&[list![sym!("none")], wrap.clone()], &mut env).unwrap(),
+                   list![sym!("none")]);
+        crisp_assert_err!(crisp_and_then(&[Nil, wrap], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_or_else() {
+        let mut env = initialize_environment();
+        let recover = lambda![args: ["e"], func: [sym!("ok"), sym!("e")]];
+        let default = lambda![args: [], func: [sym!("some"), Number(0.0)]];
+
+        assert_eq!(crisp_or_else(&[list![sym!("err"), str!("x")], recover.clone()], &mut env)
+                       .unwrap(), list![sym!("ok"), str!("x")]);
+        assert_eq!(crisp_or_else(&[list![sym!("none")], default], &mut env).unwrap(),
+                   list![sym!("some"), Number(0.0)]);
+        assert_eq!(crisp_or_else(&[list![sym!("ok"), Number(5.0)], recover], &mut env).unwrap(),
+                   list![sym!("ok"), Number(5.0)]);
+    }
+}
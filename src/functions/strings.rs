@@ -0,0 +1,390 @@
+use std::iter;
+
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv,
+            functions::{extract_list, extract_value}};
+
+/// `lines` splits a [`String`](CrispExpr) into a [`List`](CrispExpr) of
+/// `String`s at each line ending. A trailing newline does not produce an
+/// empty line at the end.
+///
+/// # Examples
+///
+/// ```lisp
+/// lines "foo\nbar\n" ; => ('foo' 'bar')
+/// lines ""           ; => ()
+/// ```
+pub fn crisp_lines(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    Ok(CrispExpr::List(str.lines().map(|line| str!(line)).collect()))
+}
+
+/// `words` splits a [`String`](CrispExpr) into a [`List`](CrispExpr) of
+/// `String`s, using any amount of whitespace as the delimiter.
+///
+/// # Examples
+///
+/// ```lisp
+/// words "foo bar"           ; => ('foo' 'bar')
+/// words "  foo\n\tbar baz " ; => ('foo' 'bar' 'baz')
+/// ```
+pub fn crisp_words(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+
+    Ok(CrispExpr::List(str.split_whitespace().map(|word| str!(word)).collect()))
+}
+
+/// `unlines` is the inverse of [`lines`](crisp_lines). It takes a
+/// [`List`](CrispExpr) of [`String`](CrispExpr)s and joins them into one
+/// `String`, appending a newline to each.
+///
+/// # Examples
+///
+/// ```lisp
+/// unlines ("foo" "bar") ; => "foo\nbar\n"
+/// ```
+pub fn crisp_unlines(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let list = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+
+    Ok(str!(extract_list::<String>(&list)?.iter()
+                                          .map(|line| format!("{}\n", line))
+                                          .collect::<String>()))
+}
+
+/// `unwords` is the inverse of [`words`](crisp_words). It takes a
+/// [`List`](CrispExpr) of [`String`](CrispExpr)s and joins them into one
+/// `String`, separated by spaces.
+///
+/// # Examples
+///
+/// ```lisp
+/// unwords ("foo" "bar") ; => "foo bar"
+/// ```
+pub fn crisp_unwords(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let list = extract_value::<Vec<CrispExpr>>(args.first().unwrap())?;
+
+    Ok(str!(extract_list::<String>(&list)?.join(" ")))
+}
+
+/// `str-concat` joins any number of [`String`](CrispExpr)s into one. Any
+/// argument may also be a [`List`](CrispExpr) of `String`s, whose items are
+/// joined in its place. The result is built in one pass, so collecting strings
+/// into a list and concatenating them at the end is much faster than
+/// concatenating them one by one in a loop.
+///
+/// # Examples
+///
+/// ```lisp
+/// str-concat "foo" "bar"          ; => "foobar"
+/// str-concat "<" ("a" "b" "c") ">" ; => "<abc>"
+/// str-concat ()                   ; => ""
+/// ```
+pub fn crisp_str_concat(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let mut result = String::new();
+
+    for arg in args {
+        match arg {
+            CrispExpr::List(list) => result.extend(extract_list::<String>(list)?),
+            _ => result.push_str(&extract_value::<String>(arg)?)
+        }
+    }
+
+    Ok(str!(result))
+}
+
+/// `str-join` joins a [`List`](CrispExpr) of [`String`](CrispExpr)s into one
+/// `String`, with the separator `sep` between each of them.
+///
+/// # Usage
+///
+/// ```lisp
+/// str-join sep list
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// str-join ", " ("foo" "bar" "baz") ; => "foo, bar, baz"
+/// str-join ", " ()                  ; => ""
+/// ```
+pub fn crisp_str_join(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let sep = extract_value::<String>(&args[0])?;
+    let list = extract_value::<Vec<CrispExpr>>(&args[1])?;
+
+    Ok(str!(extract_list::<String>(&list)?.join(&sep)))
+}
+
+/// Uppercases the first character of `word` and lowercases the rest.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new()
+    }
+}
+
+/// `kebab->camel` converts a `kebab-case` [`String`](CrispExpr) to
+/// `camelCase`: each word after the first is [capitalized](crisp_capitalize),
+/// and the dashes are removed.
+///
+/// # Examples
+///
+/// ```lisp
+/// kebab->camel "foo-bar-baz" ; => "fooBarBaz"
+/// kebab->camel "Über-größe"  ; => "überGröße"
+/// ```
+pub fn crisp_kebab_to_camel(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let string = extract_value::<String>(&args[0])?;
+    let mut words = string.split('-').filter(|word| !word.is_empty());
+
+    Ok(str!(match words.next() {
+        Some(first) => iter::once(first.to_lowercase()).chain(words.map(capitalize)).collect(),
+        None => String::new()
+    }))
+}
+
+/// `camel->snake` converts a `camelCase` (or `PascalCase`)
+/// [`String`](CrispExpr) to `snake_case`: an underscore is put before each
+/// word that starts with an uppercase letter, and everything is lowercased.
+/// A run of uppercase letters is kept together as one word.
+///
+/// # Examples
+///
+/// ```lisp
+/// camel->snake "fooBarBaz"    ; => "foo_bar_baz"
+/// camel->snake "HTTPServer"   ; => "http_server"
+/// camel->snake "straßeÜber"   ; => "straße_über"
+/// ```
+pub fn crisp_camel_to_snake(
+    args: &[CrispExpr],
+    _env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let chars: Vec<char> = extract_value::<String>(&args[0])?.chars().collect();
+    let mut result = String::new();
+
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            // The start of a word, or the last capital of an acronym before one
+            if previous.is_lowercase() || previous.is_numeric()
+               || (previous.is_uppercase() && next_is_lower) {
+                result.push('_');
+            }
+        }
+
+        result.extend(ch.to_lowercase());
+    }
+
+    Ok(str!(result))
+}
+
+/// `capitalize` uppercases the first character of a [`String`](CrispExpr)
+/// and lowercases the rest. Casing follows Unicode, so a character may
+/// become more than one, e.g. `ß` uppercases to `SS`.
+///
+/// # Examples
+///
+/// ```lisp
+/// capitalize "hello WORLD" ; => "Hello world"
+/// capitalize "élan"        ; => "Élan"
+/// ```
+pub fn crisp_capitalize(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    Ok(str!(capitalize(&extract_value::<String>(&args[0])?)))
+}
+
+/// `titlecase` [capitalizes](crisp_capitalize) each word of a
+/// [`String`](CrispExpr), keeping the whitespace between them as it is.
+///
+/// # Examples
+///
+/// ```lisp
+/// titlecase "the old  MAN and the sea" ; => "The Old  Man And The Sea"
+/// ```
+pub fn crisp_titlecase(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let string = extract_value::<String>(&args[0])?;
+
+    Ok(str!(string.split_inclusive(char::is_whitespace).map(|word| {
+        // Keep the whitespace that split_inclusive leaves on the end
+        let trimmed = word.trim_end_matches(char::is_whitespace);
+        format!("{}{}", capitalize(trimmed), &word[trimmed.len()..])
+    }).collect::<String>()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::CrispExpr::*, env::initialize_environment};
+
+    #[test]
+    fn test_lines() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_lines(&[str!("foo\nbar\n")], &mut env).unwrap(),
+                   string_list!["foo", "bar"]);
+        assert_eq!(crisp_lines(&[str!("foo\r\nbar")], &mut env).unwrap(),
+                   string_list!["foo", "bar"]);
+        assert_eq!(crisp_lines(&[str!("foo\n\nbar")], &mut env).unwrap(),
+                   string_list!["foo", "", "bar"]);
+        assert_eq!(crisp_lines(&[str!("")], &mut env).unwrap(),
+                   list![]);
+
+        crisp_assert_err!(crisp_lines(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_words() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_words(&[str!("foo bar")], &mut env).unwrap(),
+                   string_list!["foo", "bar"]);
+        assert_eq!(crisp_words(&[str!("  foo\n\tbar baz ")], &mut env).unwrap(),
+                   string_list!["foo", "bar", "baz"]);
+        assert_eq!(crisp_words(&[str!("   ")], &mut env).unwrap(),
+                   list![]);
+    }
+
+    #[test]
+    fn test_unlines() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_unlines(&[string_list!["foo", "bar"]], &mut env).unwrap(),
+                   str!("foo\nbar\n"));
+        assert_eq!(crisp_unlines(&[list![]], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_unlines(&[num_list![1.0]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_unwords() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_unwords(&[string_list!["foo", "bar"]], &mut env).unwrap(),
+                   str!("foo bar"));
+        assert_eq!(crisp_unwords(&[list![]], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_unwords(&[str!("foo")], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_str_concat() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_str_concat(&[str!("foo"), str!("bar")], &mut env).unwrap(),
+                   str!("foobar"));
+        assert_eq!(crisp_str_concat(&[str!("<"), string_list!["a", "b", "c"], str!(">")],
+                                    &mut env).unwrap(),
+                   str!("<abc>"));
+        assert_eq!(crisp_str_concat(&[list![]], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_str_concat(&[], &mut env), ArgumentError);
+        crisp_assert_err!(crisp_str_concat(&[str!("foo"), Number(1.0)], &mut env), TypeError);
+        crisp_assert_err!(crisp_str_concat(&[num_list![1.0]], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_str_join() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_str_join(&[str!(", "), string_list!["foo", "bar", "baz"]], &mut env)
+                       .unwrap(),
+                   str!("foo, bar, baz"));
+        assert_eq!(crisp_str_join(&[str!(", "), list![]], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_str_join(&[str!(", ")], &mut env), ArgumentError);
+        crisp_assert_err!(crisp_str_join(&[str!(", "), str!("foo")], &mut env), TypeError);
+        crisp_assert_err!(crisp_str_join(&[Number(1.0), string_list!["foo"]], &mut env),
+                          TypeError);
+    }
+
+    #[test]
+    fn test_kebab_to_camel() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_kebab_to_camel(&[str!("foo-bar-baz")], &mut env).unwrap(),
+                   str!("fooBarBaz"));
+        assert_eq!(crisp_kebab_to_camel(&[str!("Über-größe")], &mut env).unwrap(),
+                   str!("überGröße"));
+        assert_eq!(crisp_kebab_to_camel(&[str!("-foo--bar-")], &mut env).unwrap(),
+                   str!("fooBar"));
+        assert_eq!(crisp_kebab_to_camel(&[str!("")], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_kebab_to_camel(&[Number(1.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_camel_to_snake() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_camel_to_snake(&[str!("fooBarBaz")], &mut env).unwrap(),
+                   str!("foo_bar_baz"));
+        assert_eq!(crisp_camel_to_snake(&[str!("FooBar")], &mut env).unwrap(),
+                   str!("foo_bar"));
+        assert_eq!(crisp_camel_to_snake(&[str!("HTTPServer")], &mut env).unwrap(),
+                   str!("http_server"));
+        assert_eq!(crisp_camel_to_snake(&[str!("base64Encode")], &mut env).unwrap(),
+                   str!("base64_encode"));
+        assert_eq!(crisp_camel_to_snake(&[str!("straßeÜber")], &mut env).unwrap(),
+                   str!("straße_über"));
+        assert_eq!(crisp_camel_to_snake(&[str!("foo")], &mut env).unwrap(),
+                   str!("foo"));
+    }
+
+    #[test]
+    fn test_capitalize() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_capitalize(&[str!("hello WORLD")], &mut env).unwrap(),
+                   str!("Hello world"));
+        assert_eq!(crisp_capitalize(&[str!("élan")], &mut env).unwrap(),
+                   str!("Élan"));
+        assert_eq!(crisp_capitalize(&[str!("ßa")], &mut env).unwrap(),
+                   str!("SSa"));
+        assert_eq!(crisp_capitalize(&[str!("")], &mut env).unwrap(),
+                   str!(""));
+
+        crisp_assert_err!(crisp_capitalize(&[], &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_titlecase() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_titlecase(&[str!("the old  MAN and\tthe sea")], &mut env).unwrap(),
+                   str!("The Old  Man And\tThe Sea"));
+        assert_eq!(crisp_titlecase(&[str!(" émile ")], &mut env).unwrap(),
+                   str!(" Émile "));
+        assert_eq!(crisp_titlecase(&[str!("")], &mut env).unwrap(),
+                   str!(""));
+    }
+}
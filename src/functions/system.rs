@@ -0,0 +1,106 @@
+use std::{env, process};
+
+use crate::{error::CrispError, expr::CrispExpr, env::{CrispEnv, env_args, env_host},
+            functions::extract_value};
+
+/// `pid` returns the ID of the crisp process.
+///
+/// # Examples
+///
+/// ```lisp
+/// pid   ; => 48213
+/// ```
+pub fn crisp_pid(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    Ok(CrispExpr::Number(process::id() as f64))
+}
+
+/// `cwd` returns the current working directory, which relative paths given to
+/// the file builtins (e.g. [`ls`](super::crisp_ls)) are resolved against.
+///
+/// # Examples
+///
+/// ```lisp
+/// cwd   ; => "/home/me/project"
+/// ```
+pub fn crisp_cwd(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+    env_host(env).check_file_access()?;
+
+    env::current_dir()
+        .map(|dir| str!(dir.display()))
+        .map_err(|e| standard_error_unwrapped!(format!("Couldn't get the working directory: {}",
+                                                       e)))
+}
+
+/// `chdir` changes the current working directory (see [`cwd`](crisp_cwd)).
+/// This affects the whole program, including any tasks that are running.
+///
+/// # Examples
+///
+/// ```lisp
+/// chdir "out"
+/// chdir ".."
+/// ```
+pub fn crisp_chdir(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+    env_host(env).check_file_access()?;
+
+    let path = extract_value::<String>(args.first().unwrap())?;
+
+    env::set_current_dir(&path).map(|_| CrispExpr::Nil)
+                               .map_err(|_| CrispError::LoadError(path))
+}
+
+/// `args` returns the command line arguments given to the program after the
+/// name of the file, as a [`List`](CrispExpr) of [`String`](CrispExpr)s.
+///
+/// # Examples
+///
+/// ```lisp
+/// ; crisp script.crisp foo --bar
+/// args   ; => ('foo' '--bar')
+/// ```
+pub fn crisp_args(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    Ok(CrispExpr::List(env_args(env).into_iter().map(|arg| CrispExpr::CrispString(arg.into()))
+                                                 .collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
+
+    #[test]
+    fn test_pid() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_pid(&[], &mut env).unwrap(), Number(process::id() as f64));
+    }
+
+    #[test]
+    fn test_cwd_and_chdir() {
+        let mut env = initialize_environment();
+
+        let cwd = crisp_cwd(&[], &mut env).unwrap();
+        assert_eq!(cwd, str!(env::current_dir().unwrap().display()));
+
+        // Other tests rely on the working directory, so it's left unchanged
+        assert_eq!(crisp_chdir(std::slice::from_ref(&cwd), &mut env).unwrap(), Nil);
+        assert_eq!(crisp_cwd(&[], &mut env).unwrap(), cwd);
+
+        crisp_assert_err!(crisp_chdir(&[str!("tests/does_not_exist")], &mut env), LoadError);
+    }
+
+    #[test]
+    fn test_args() {
+        let mut env = initialize_environment();
+        assert_eq!(crisp_args(&[], &mut env).unwrap(), list![]);
+
+        let mut env = initialize_environment().with_args(&["a".to_string(), "b".to_string()]);
+        assert_eq!(crisp_args(&[], &mut env).unwrap(), list![str!("a"), str!("b")]);
+    }
+}
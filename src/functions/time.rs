@@ -0,0 +1,247 @@
+use chrono::{DateTime, Months, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+
+use crate::{error::CrispError, expr::CrispExpr, env::CrispEnv, functions::extract_value};
+
+// Times are represented as a `Number` of seconds since the Unix epoch, in UTC.
+
+/// Converts a time in seconds since the Unix epoch to a [`DateTime`].
+fn to_datetime(expr: &CrispExpr) -> Result<DateTime<Utc>, CrispError> {
+    let seconds = extract_value::<f64>(expr)?;
+
+    let whole = seconds.floor();
+    let nanos = ((seconds - whole) * 1e9).round() as u32;
+
+    DateTime::from_timestamp(whole as i64, nanos.min(999_999_999))
+        .ok_or_else(|| standard_error_unwrapped!(format!("Time out of range: {}", seconds)))
+}
+
+/// Converts a [`DateTime`] to a [`Number`](CrispExpr) of seconds since the
+/// Unix epoch.
+fn from_datetime(datetime: DateTime<Utc>) -> CrispExpr {
+    let seconds = datetime.timestamp() as f64;
+    CrispExpr::Number(seconds + datetime.timestamp_subsec_nanos() as f64 / 1e9)
+}
+
+/// The length of one of the fixed-length date units in seconds.
+fn unit_seconds(unit: &str) -> Option<f64> {
+    match unit {
+        "seconds" => Some(1.0),
+        "minutes" => Some(60.0),
+        "hours" => Some(60.0 * 60.0),
+        "days" => Some(24.0 * 60.0 * 60.0),
+        "weeks" => Some(7.0 * 24.0 * 60.0 * 60.0),
+        _ => None
+    }
+}
+
+/// `now` returns the current time, as a [`Number`](CrispExpr) of seconds
+/// since the Unix epoch. All of the date and time functions work with times
+/// in this form, in UTC.
+///
+/// # Examples
+///
+/// ```lisp
+/// now   ; => 1719243712.532
+/// ```
+pub fn crisp_now(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 0, 0);
+
+    Ok(from_datetime(Utc::now()))
+}
+
+/// `time-format` formats a time (see [`now`](crisp_now)) using a strftime-style
+/// pattern, such as `"%Y-%m-%d %H:%M:%S"`.
+///
+/// # Examples
+///
+/// ```lisp
+/// time-format 0 "%Y-%m-%d"              ; => "1970-01-01"
+/// time-format (now) "%a %b %e %H:%M"    ; => "Mon Jun 24 15:41"
+/// ```
+pub fn crisp_time_format(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let datetime = to_datetime(args.first().unwrap())?;
+    let pattern = extract_value::<String>(args.get(1).unwrap())?;
+
+    let mut formatted = String::new();
+    std::fmt::write(&mut formatted, format_args!("{}", datetime.format(&pattern)))
+        .map_err(|_| parse_error_unwrapped!(format!("Invalid time format: {}", pattern)))?;
+
+    Ok(str!(formatted))
+}
+
+/// `time-parse` parses a time from a [`String`](CrispExpr) using a
+/// strftime-style pattern, the inverse of [`time-format`](crisp_time_format).
+/// If the pattern only contains a date, the time is midnight.
+///
+/// # Examples
+///
+/// ```lisp
+/// time-parse "1970-01-02" "%Y-%m-%d"                      ; => 86400
+/// time-parse "2024-06-24 09:30:00" "%Y-%m-%d %H:%M:%S"    ; => 1719221400
+/// ```
+pub fn crisp_time_parse(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 2);
+
+    let str = extract_value::<String>(args.first().unwrap())?;
+    let pattern = extract_value::<String>(args.get(1).unwrap())?;
+
+    NaiveDateTime::parse_from_str(&str, &pattern)
+        .or_else(|_| NaiveDate::parse_from_str(&str, &pattern).map(|date| date.into()))
+        .map(|datetime| from_datetime(datetime.and_utc()))
+        .map_err(|e| parse_error_unwrapped!(format!("Couldn't parse {:?} as {:?}: {}",
+                                                    str, pattern, e)))
+}
+
+/// `date-add` adds an amount of time to a time (see [`now`](crisp_now)). The
+/// unit is one of `"seconds"`, `"minutes"`, `"hours"`, `"days"`, `"weeks"`,
+/// `"months"`, or `"years"`. Months and years must be whole numbers; adding
+/// them keeps the same day of the month where possible, otherwise using the
+/// last day of the month.
+///
+/// # Examples
+///
+/// ```lisp
+/// date-add 0 2 "days"         ; => 172800
+/// date-add (now) -1 "hours"
+/// date-add (time-parse "2024-01-31" "%Y-%m-%d") 1 "months"   ; 2024-02-29
+/// ```
+pub fn crisp_date_add(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, 3);
+
+    let datetime = to_datetime(args.first().unwrap())?;
+    let amount = extract_value::<f64>(args.get(1).unwrap())?;
+    let unit = extract_value::<String>(args.get(2).unwrap())?;
+
+    let result = match (unit.as_str(), unit_seconds(&unit)) {
+        (_, Some(seconds)) => {
+            TimeDelta::try_milliseconds((amount * seconds * 1000.0).round() as i64)
+                .and_then(|delta| datetime.checked_add_signed(delta))
+        },
+
+        ("months" | "years", _) => {
+            if amount.fract() != 0.0 {
+                return standard_error!(format!("Can only add a whole number of {}.", unit));
+            }
+
+            let months = amount.abs() as u32 * if unit == "years" { 12 } else { 1 };
+            if amount < 0.0 {
+                datetime.checked_sub_months(Months::new(months))
+            } else {
+                datetime.checked_add_months(Months::new(months))
+            }
+        },
+
+        _ => return standard_error!(format!("Unknown time unit: {}", unit))
+    };
+
+    result.map(from_datetime)
+          .ok_or_else(|| standard_error_unwrapped!("Resulting time is out of range."))
+}
+
+/// `date-diff` returns how much later the first time is than the second, in
+/// the given unit (`"seconds"` by default; see [`date-add`](crisp_date_add)
+/// for the others, apart from months and years).
+///
+/// # Usage
+///
+/// ```lisp
+/// date-diff a b [unit]
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// date-diff 172800 0 "days"   ; => 2
+/// date-diff 0 90              ; => -90
+/// ```
+pub fn crisp_date_diff(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, 3);
+
+    let a = extract_value::<f64>(args.first().unwrap())?;
+    let b = extract_value::<f64>(args.get(1).unwrap())?;
+    let unit = match args.get(2) {
+        Some(expr) => extract_value::<String>(expr)?,
+        None => "seconds".to_string()
+    };
+
+    match unit_seconds(&unit) {
+        Some(seconds) => Ok(CrispExpr::Number((a - b) / seconds)),
+        None => standard_error!(format!("Unknown time unit: {}", unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*};
+
+    #[test]
+    fn test_time_format() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_time_format(&[Number(0.0), str!("%Y-%m-%d %H:%M:%S")], &mut env)
+                       .unwrap(),
+                   str!("1970-01-01 00:00:00"));
+        assert_eq!(crisp_time_format(&[Number(1719221400.5), str!("%d/%m/%y %H:%M:%S%.3f")],
+                                     &mut env).unwrap(),
+                   str!("24/06/24 09:30:00.500"));
+
+        crisp_assert_err!(crisp_time_format(&[Number(0.0), str!("%Q")], &mut env), ParseError);
+        crisp_assert_err!(crisp_time_format(&[Number(1e20), str!("%Y")], &mut env),
+                          StandardError);
+    }
+
+    #[test]
+    fn test_time_parse() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_time_parse(&[str!("2024-06-24 09:30:00"), str!("%Y-%m-%d %H:%M:%S")],
+                                    &mut env).unwrap(),
+                   Number(1719221400.0));
+        assert_eq!(crisp_time_parse(&[str!("1970-01-02"), str!("%Y-%m-%d")], &mut env).unwrap(),
+                   Number(86400.0));
+
+        crisp_assert_err!(crisp_time_parse(&[str!("June"), str!("%Y-%m-%d")], &mut env),
+                          ParseError);
+    }
+
+    #[test]
+    fn test_date_add() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_date_add(&[Number(0.0), Number(2.0), str!("days")], &mut env).unwrap(),
+                   Number(172800.0));
+        assert_eq!(crisp_date_add(&[Number(60.0), Number(-1.5), str!("minutes")], &mut env)
+                       .unwrap(),
+                   Number(-30.0));
+
+        // 2024-01-31 + 1 month = 2024-02-29
+        assert_eq!(crisp_date_add(&[Number(1706659200.0), Number(1.0), str!("months")], &mut env)
+                       .unwrap(),
+                   Number(1709164800.0));
+        assert_eq!(crisp_date_add(&[Number(1709164800.0), Number(-1.0), str!("years")], &mut env)
+                       .unwrap(),
+                   Number(1677542400.0));
+
+        crisp_assert_err!(crisp_date_add(&[Number(0.0), Number(0.5), str!("months")], &mut env),
+                          StandardError);
+        crisp_assert_err!(crisp_date_add(&[Number(0.0), Number(1.0), str!("fortnights")],
+                                         &mut env),
+                          StandardError);
+    }
+
+    #[test]
+    fn test_date_diff() {
+        let mut env = initialize_environment();
+
+        assert_eq!(crisp_date_diff(&[Number(172800.0), Number(0.0), str!("days")], &mut env)
+                       .unwrap(),
+                   Number(2.0));
+        assert_eq!(crisp_date_diff(&[Number(0.0), Number(90.0)], &mut env).unwrap(),
+                   Number(-90.0));
+        crisp_assert_err!(crisp_date_diff(&[Number(0.0), Number(0.0), str!("months")], &mut env),
+                          StandardError);
+    }
+}
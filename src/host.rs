@@ -0,0 +1,108 @@
+use crate::{error::CrispError, expr::CrispExpr};
+
+use std::{fs, io, path::Path, process, sync::Mutex};
+
+/// Everything that crisp code can do outside of the interpreter itself:
+/// reading files and ending the process. Native builds use [`NativeHost`];
+/// a [`SandboxHost`] can be swapped in with
+/// [`with_host()`](crate::env::CrispEnv::with_host) where those aren't
+/// available or shouldn't be allowed, e.g. in the
+/// [playground](crate::playground).
+pub trait CrispHost: Send + Sync {
+    /// Reads the whole of the file at `path`.
+    fn read_file(&self, path: &Path) -> io::Result<String>;
+
+    /// Checks whether `path` is a file that can be read.
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Checks whether the filesystem may be used directly, e.g. by `ls` or
+    /// `rm`, returning an error if not.
+    fn check_file_access(&self) -> Result<(), CrispError>;
+
+    /// Ends the program with the exit code `code`. Hosts which can't do that
+    /// return an error instead.
+    fn exit(&self, code: i32) -> Result<CrispExpr, CrispError>;
+
+    /// The exit code that the program asked to [`exit()`](CrispHost::exit)
+    /// with, for hosts where that doesn't end the process, or `None` if it
+    /// hasn't.
+    fn exit_code(&self) -> Option<i32> {
+        None
+    }
+}
+
+/// The host for running natively, with access to the real filesystem.
+pub struct NativeHost;
+
+impl CrispHost for NativeHost {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn check_file_access(&self) -> Result<(), CrispError> {
+        Ok(())
+    }
+
+    fn exit(&self, code: i32) -> Result<CrispExpr, CrispError> {
+        process::exit(code);
+    }
+}
+
+/// A host for running a program natively without letting it end the process,
+/// e.g. so that `crisp --watch` can run it again. `exit` raises an error to
+/// stop the program, and keeps the [exit code](CrispHost::exit_code) for
+/// whatever is running it.
+#[derive(Default)]
+pub struct ContainedHost {
+    code: Mutex<Option<i32>>
+}
+
+impl CrispHost for ContainedHost {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        NativeHost.read_file(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        NativeHost.is_file(path)
+    }
+
+    fn check_file_access(&self) -> Result<(), CrispError> {
+        NativeHost.check_file_access()
+    }
+
+    fn exit(&self, code: i32) -> Result<CrispExpr, CrispError> {
+        *self.code.lock().unwrap_or_else(|e| e.into_inner()) = Some(code);
+        standard_error!(format!("Exited with code {}.", code))
+    }
+
+    fn exit_code(&self) -> Option<i32> {
+        *self.code.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A host with no files, which doesn't allow the program to exit.
+#[cfg(any(test, feature = "playground"))]
+pub struct SandboxHost;
+
+#[cfg(any(test, feature = "playground"))]
+impl CrispHost for SandboxHost {
+    fn read_file(&self, _path: &Path) -> io::Result<String> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Files can't be read in the sandbox."))
+    }
+
+    fn is_file(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn check_file_access(&self) -> Result<(), CrispError> {
+        standard_error!("The filesystem isn't available in the sandbox.")
+    }
+
+    fn exit(&self, _code: i32) -> Result<CrispExpr, CrispError> {
+        standard_error!("exit isn't available in the sandbox.")
+    }
+}
@@ -1,25 +1,81 @@
-use std::{rc::Rc, process};
-
-use crate::{error::CrispError, expr::{CrispExpr, CrispLambda}, env::CrispEnv, eval::eval};
+use std::{collections::HashMap, iter, path::{Path, PathBuf}, sync::{Arc, Mutex, RwLock, mpsc},
+          thread, time::Duration};
+
+use regex::Regex;
+
+use crate::{cache, error::{CrispError, catch_panic},
+            expr::{CrispDelay, CrispExpr, CrispGenerator, CrispLambda, CrispTask},
+            env::{CrispEnv, CrispTest, CrispWriter, PLACEHOLDER, env_bind, env_host,
+                  env_loading, env_new_scope, env_param, env_protected, env_shadow,
+                  env_snapshot, env_source_dir},
+            eval::{GeneratorBody, eval, map_clones, with_source_map,
+                   without_pausing},
+            eval_top_level,
+            functions::{self, extract_index}, package, split_exprs,
+            warning::{CrispWarning, warn}};
+
+/// A special form is given its arguments unevaluated, along with the scope
+/// that it's used in, so that it can decide which of them to evaluate, and
+/// when. Builtin [`Func`](CrispExpr)s, on the other hand, are always given
+/// their arguments already evaluated.
+pub type CrispSpecialForm = fn(&[CrispExpr], &mut CrispEnv) -> Result<CrispExpr, CrispError>;
+
+/// The keywords and other special forms, by name. See [`eval_keyword()`].
+pub const SPECIAL_FORMS: &[(&str, CrispSpecialForm)] = &[
+    ("if", eval_if),
+    ("case", eval_case),
+    ("do", eval_do),
+    ("let", eval_let),
+    ("const", eval_const),
+    ("shadow", eval_shadow),
+    ("defparam", eval_defparam),
+    ("binding", eval_binding),
+    ("letrec", eval_letrec),
+    ("\\", eval_keyword_lambda),
+    ("fn", eval_fn),
+    ("exit", eval_exit),
+    ("embed", eval_embed),
+    ("load", eval_load),
+    ("require", eval_require),
+    ("spawn", eval_spawn),
+    ("delay", eval_delay),
+    ("generator", eval_generator),
+    ("match-str", eval_match_str),
+    ("assert-raises", eval_assert_raises),
+    ("deftest", eval_deftest),
+    ("describe", eval_describe),
+    ("with-redefs", eval_with_redefs),
+    ("with-output-to-string", eval_with_output_to_string),
+    ("with-error-output-to-string", eval_with_error_output_to_string),
+    ("with-retry", eval_with_retry),
+    ("with-timeout", eval_with_timeout),
+    // These short-circuit, so they can't be `Func`s
+    ("&&", functions::crisp_and),
+    ("||", functions::crisp_or)
+];
+
+/// The special forms which keep nothing but their scope while evaluating
+/// their arguments, so they can be paused by `yield` within a generator's
+/// body. See [`resume_generator()`](crate::eval::resume_generator).
+pub const RESUMABLE_FORMS: &[&str] = &[
+    "if", "case", "do", "let", "const", "shadow", "\\", "fn", "generator", "delay", "&&", "||"
+];
 
 /// When a [`Symbol`](CrispExpr) begins a [`List`](CrispExpr), it is passed
-/// through this function which checks if it is a keyword and if so, evaluates
-/// the list via one of the routines in this file.
+/// through this function which checks if it is a keyword (or another of the
+/// [`SPECIAL_FORMS`]) and if so, evaluates the list via one of the routines in
+/// this file.
 pub fn eval_keyword(expr: &CrispExpr, args: &[CrispExpr],
                     env: &mut CrispEnv) -> Option<Result<CrispExpr, CrispError>> {
-    match expr {
-        CrispExpr::Symbol(s) => {
-            match s.as_ref() {
-                "if" => Some(eval_if(args, env)),
-                "let" => Some(eval_let(args, env)),
-                "\\" => Some(eval_keyword_lambda(args)),
-                "fn" => Some(eval_fn(args, env)),
-                "exit" => Some(eval_exit(args, env)),
-                _ => None
-            }
-        },
+    let CrispExpr::Symbol(s) = expr else {
+        return None;
+    };
+
+    let (name, special_form) = SPECIAL_FORMS.iter().find(|(name, _)| *name == &**s)?;
 
-        _ => None
+    match RESUMABLE_FORMS.contains(name) {
+        true => Some(special_form(args, env)),
+        false => Some(without_pausing(name, || special_form(args, env)))
     }
 }
 
@@ -54,7 +110,101 @@ fn eval_if(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispErr
     }
 }
 
+/// With at least this many arms, `case` finds the matching arm with a hash
+/// table rather than comparing against each arm in turn.
+const CASE_TABLE_ARMS: usize = 8;
+
+/// `case` evaluates a value and compares it against the constant keys of a
+/// number of arms, evaluating the body of the first arm that matches. Keys
+/// aren't evaluated. A key may be a [`List`](CrispExpr), matching any of its
+/// elements, or `else`, matching anything. If no arm matches, `nil` is
+/// returned.
+///
+/// # Usage
+///
+/// ```lisp
+/// case value (key body) ((key...) body) (else body)
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// fn describe-count n
+///   (case n
+///     (0 "none")
+///     (1 "one")
+///     ((2 3) "a few")
+///     (else "many"))
+///
+/// describe-count 3    ; => "a few"
+/// describe-count 10   ; => "many"
+/// ```
+fn eval_case(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let value = eval(args.first().unwrap(), env)?;
+
+    // The key of each arm, or `None` for `else`, and its body
+    let mut arms = Vec::new();
+    for arm in &args[1..] {
+        match arm {
+            CrispExpr::List(arm) if arm.len() == 2 => match &arm[0] {
+                CrispExpr::Symbol(s) if &**s == "else" => arms.push((None, &arm[1])),
+                key => arms.push((Some(key), &arm[1]))
+            },
+            _ => return parse_error!("case arms must have the form (key body) or (else body).")
+        }
+    }
+
+    let matched = if arms.len() >= CASE_TABLE_ARMS {
+        case_table_lookup(&arms, &value)
+    } else {
+        arms.iter().position(|(key, _)| key.is_none_or(|key| case_key_matches(key, &value)))
+    };
+
+    match matched {
+        Some(i) => eval(arms[i].1, env),
+        None => Ok(CrispExpr::Nil)
+    }
+}
+
+/// Whether the `case` key `key` matches `value`.
+fn case_key_matches(key: &CrispExpr, value: &CrispExpr) -> bool {
+    match key {
+        CrispExpr::List(alternatives) => alternatives.contains(value),
+        _ => key == value
+    }
+}
+
+/// Finds the first of the `case` arms `arms` that matches `value`, using a
+/// hash table of their keys.
+fn case_table_lookup(
+    arms: &[(Option<&CrispExpr>, &CrispExpr)],
+    value: &CrispExpr
+) -> Option<usize> {
+    let mut table = HashMap::new();
+    for (i, (key, _)) in arms.iter().enumerate() {
+        let alternatives = match key {
+            Some(CrispExpr::List(alternatives)) => alternatives.as_slice(),
+            Some(key) => std::slice::from_ref(*key),
+            None => &[]
+        };
+
+        for alternative in alternatives {
+            if let Ok(alternative) = alternative.to_string_exact() {
+                table.entry(alternative).or_insert(i);
+            }
+        }
+    }
+
+    let keyed = value.to_string_exact().ok().and_then(|value| table.get(&value).copied());
+    let otherwise = arms.iter().position(|(key, _)| key.is_none());
+
+    keyed.into_iter().chain(otherwise).min()
+}
+
 /// `let` is the variable assignment keyword. It returns the assigned value.
+/// Assigning to `_` evaluates the value without binding it.
 ///
 /// # Usage
 ///
@@ -70,38 +220,365 @@ fn eval_if(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispErr
 /// let xs (1 2 3 4 5)
 /// ```
 fn eval_let(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let (name, value) = eval_let_args(args, env)?;
+    env_bind(name, value.clone(), env)?;
+
+    Ok(value)
+}
+
+/// `shadow` binds a variable like [`let`](eval_let), except that it may
+/// rebind a builtin, which `let` refuses to do so that e.g. `+` isn't
+/// overwritten by accident. Returns the assigned value.
+///
+/// # Usage
+///
+/// ```lisp
+/// shadow var_name value
+/// ```
+///
+/// Shadowing a builtin gives a `shadowed-builtin` warning, since it's easy to
+/// lose track of which name means what.
+///
+/// # Examples
+///
+/// ```lisp
+/// let map 5               ; StandardError
+/// shadow map 5
+/// ```
+fn eval_shadow(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let (name, value) = eval_let_args(args, env)?;
+
+    if env_protected(&name, env) {
+        let expr = CrispExpr::List(iter::once(sym!("shadow")).chain(args.iter().cloned()).collect());
+        warn(CrispWarning::ShadowedBuiltin(name.clone()), Some(expr));
+    }
+    env_shadow(name, value.clone(), env)?;
+
+    Ok(value)
+}
+
+/// `defparam` defines a parameter: a variable like one bound with
+/// [`let`](eval_let), whose value can also be overridden temporarily with
+/// [`binding`](eval_binding). By convention, their names are surrounded with
+/// `*`s. Returns the value.
+///
+/// # Usage
+///
+/// ```lisp
+/// defparam var_name value
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// defparam *indent* 2
+/// ```
+fn eval_defparam(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let (name, value) = eval_let_args(args, env)?;
+    env_bind(name.clone(), value.clone(), env)?;
+    env.params.insert(name);
+
+    Ok(value)
+}
+
+/// `binding` overrides the values of one or more parameters defined with
+/// [`defparam`](eval_defparam) while evaluating one or more expressions,
+/// returning the result of the last. The new values are seen by everything
+/// called from within, and the old ones are back in effect afterwards, even if
+/// there is an error.
+///
+/// The bindings may be a single `(name value)` pair, or a list of them. All of
+/// the new values are evaluated before any of them are bound.
+///
+/// # Usage
+///
+/// ```lisp
+/// binding (name value) expr...
+/// binding ((name value)...) expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// defparam *greeting* "Hello"
+/// fn greet name (format "{}, {}!" *greeting* name)
+///
+/// binding (*greeting* "Hi") (greet "world")    ; => "Hi, world!"
+/// greet "world"                                ; => "Hello, world!"
+/// ```
+fn eval_binding(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let mut data = HashMap::new();
+    for (name, value) in binding_pairs(args.first().unwrap(), "binding")? {
+        if !env_param(&name, env) {
+            return standard_error!(format!("Not a parameter (see `defparam`): {}", name));
+        }
+
+        data.insert(name, eval(value, env)?);
+    }
+
+    // The bindings only last as long as this scope
+    let mut scope = env_new_scope(data, env);
+    eval_do(&args[1..], &mut scope)
+}
+
+/// `letrec` binds one or more variables in a new scope while evaluating one or
+/// more expressions, returning the result of the last. The names are bound
+/// (to `nil`) before any of the values are evaluated, and each value is
+/// evaluated in the new scope, so a function can refer to itself or to the
+/// others, e.g. for a recursive helper. None of the names are visible
+/// afterwards.
+///
+/// The bindings may be a single `(name value)` pair, or a list of them.
+///
+/// # Usage
+///
+/// ```lisp
+/// letrec (name value) expr...
+/// letrec ((name value)...) expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// fn sum-to n
+///   (letrec (go (\ (i total) (if (> i n) total (go (+ i 1) (+ total i)))))
+///     (go 1 0))
+///
+/// sum-to 4    ; => 10
+/// go 1 0      ; ParseError, `go` isn't defined here
+/// ```
+fn eval_letrec(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let bindings = binding_pairs(args.first().unwrap(), "letrec")?;
+    let names = bindings.iter().map(|(name, _)| (name.clone(), CrispExpr::Nil));
+
+    let mut scope = env_new_scope(names.collect(), env);
+    for (name, value) in bindings {
+        let value = eval(value, &mut scope)?;
+        scope.data.insert(name, value);
+    }
+
+    eval_do(&args[1..], &mut scope)
+}
+
+/// Takes the names and (unevaluated) values from the bindings given to a
+/// keyword like `binding`, which are either a single `(name value)` pair or a
+/// list of them.
+fn binding_pairs<'a>(
+    bindings: &'a CrispExpr,
+    keyword: &str
+) -> Result<Vec<(String, &'a CrispExpr)>, CrispError> {
+    let bindings = match bindings {
+        CrispExpr::List(pair) if matches!(pair.first(), Some(CrispExpr::Symbol(_))) => {
+            std::slice::from_ref(bindings)
+        },
+        CrispExpr::List(bindings) => bindings.as_slice(),
+        _ => return type_error!("List")
+    };
+
+    bindings.iter().map(|binding| match binding {
+        CrispExpr::List(pair) if pair.len() == 2 => match &pair[0] {
+            CrispExpr::Symbol(name) => Ok((name.to_string(), &pair[1])),
+            _ => type_error!("Symbol")
+        },
+        _ => parse_error!(format!("{} bindings must have the form (name value).", keyword))
+    }).collect()
+}
+
+/// Takes the name and evaluated value from the arguments of a binding like
+/// `let`.
+fn eval_let_args(
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<(String, CrispExpr), CrispError> {
     check_argument_error!(args, 2, 2);
 
-    let name_sym = args.first().unwrap();
-    let name = match name_sym {
-        CrispExpr::Symbol(s) => Ok(s.clone()),
-        _ => type_error!("Symbol")
-    }?;
+    let name = match args.first().unwrap() {
+        CrispExpr::Symbol(s) => s.to_string(),
+        _ => return type_error!("Symbol")
+    };
 
-    let value = eval(args.get(1).unwrap(), env)?;
-    env.data.insert(name, value.clone());
+    Ok((name, eval(args.get(1).unwrap(), env)?))
+}
 
-    Ok(value.clone())
+/// `const` binds a constant, like [`let`](eval_let), except that the name
+/// can't be bound again in the same scope (by `let`, `fn`, `const` or
+/// `unbind!`), e.g. for configuration values which shouldn't be overwritten by
+/// accident. It can still be shadowed within a function. Returns the value.
+///
+/// # Usage
+///
+/// ```lisp
+/// const var_name value
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// const max-retries 3
+/// let max-retries 4    ; StandardError
+/// ```
+fn eval_const(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let value = eval_let(args, env)?;
+
+    // `eval_let` has already checked that this is a symbol
+    if let Some(CrispExpr::Symbol(name)) = args.first() {
+        env.consts.insert(name.to_string());
+    }
+
+    Ok(value)
+}
+
+/// `match-str` matches a [`String`](CrispExpr) against a number of regular
+/// expressions, evaluating the body of the first arm whose pattern matches
+/// (anywhere in the `String`; use `^` and `$` to match all of it). If no arm
+/// matches, `nil` is returned. An arm whose pattern is `_` always matches.
+///
+/// Each arm is a [`List`](CrispExpr) containing the pattern, optionally a
+/// `List` of [`Symbol`](CrispExpr)s to bind the capture groups to, in order,
+/// and the body. Named capture groups are also bound to their names. Capture
+/// groups which didn't take part in the match are bound to `nil`, and those
+/// given the name `_` aren't bound.
+///
+/// # Usage
+///
+/// ```lisp
+/// match-str str (pattern body) (pattern (captures...) body) (_ body)
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// fn classify line
+///   (match-str line
+///     ("^(\\w+)=(\\d+)$" (key value) (format "{} is {}" key value))
+///     ("^#" "comment")
+///     (_ "unknown"))
+///
+/// classify "x=42"       ; => "x is 42"
+/// classify "# foo"      ; => "comment"
+/// classify "bar"        ; => "unknown"
+///
+/// match-str "2023-06-01"
+///   ("(?P<year>\\d{4})-\\d\\d-\\d\\d" year) ; => "2023"
+/// ```
+fn eval_match_str(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let str = match eval(args.first().unwrap(), env)? {
+        CrispExpr::CrispString(s) => s,
+        _ => return type_error!("String")
+    };
+
+    for arm in &args[1..] {
+        let (pattern, names, body) = match arm {
+            CrispExpr::List(arm) if arm.len() == 2 => (&arm[0], None, &arm[1]),
+            CrispExpr::List(arm) if arm.len() == 3 => match &arm[1] {
+                CrispExpr::List(names) => (&arm[0], Some(names), &arm[2]),
+                _ => return type_error!("List")
+            },
+            _ => return parse_error!("match-str arms must have the form (pattern body) \
+                                      or (pattern (captures...) body).")
+        };
+
+        if let CrispExpr::Symbol(s) = pattern {
+            if &**s == "_" {
+                return eval(body, env);
+            }
+        }
+
+        let pattern = match eval(pattern, env)? {
+            CrispExpr::CrispString(s) => s,
+            _ => return type_error!("String")
+        };
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(e) => return parse_error!(format!("Invalid regex {:?}: {}", pattern, e))
+        };
+
+        let captures = match regex.captures(&str) {
+            Some(captures) => captures,
+            None => continue
+        };
+        let capture_expr = |m: Option<regex::Match>| match m {
+            Some(m) => CrispExpr::CrispString(m.as_str().into()),
+            None => CrispExpr::Nil
+        };
+
+        let mut data = HashMap::new();
+        for name in regex.capture_names().flatten() {
+            data.insert(name.to_string(), capture_expr(captures.name(name)));
+        }
+        if let Some(names) = names {
+            for (i, name) in names.iter().enumerate() {
+                match name {
+                    CrispExpr::Symbol(name) if &**name == PLACEHOLDER => (),
+                    CrispExpr::Symbol(name) => {
+                        data.insert(name.to_string(), capture_expr(captures.get(i + 1)));
+                    },
+                    _ => return type_error!("Symbol")
+                }
+            }
+        }
+
+        return eval(body, &mut env_new_scope(data, env));
+    }
+
+    Ok(CrispExpr::Nil)
+}
+
+/// `do` evaluates each of its expressions in turn, returning the value of the
+/// last (or `nil` if there are none). This is how the bodies of functions with
+/// more than one expression are evaluated.
+///
+/// # Examples
+///
+/// ```lisp
+/// do (puts "one") (puts "two") 3 ; => 3, after printing "one" and "two"
+/// ```
+fn eval_do(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    args.iter().try_fold(CrispExpr::Nil, |_, expr| eval(expr, env))
 }
 
 /// A [`Lambda`](CrispExpr) is an anonymous function. It is declared like so:
 ///
 /// ```lisp
-/// (\ args expression)
+/// (\ args expression...)
 /// ```
 ///
 /// `args` is either a [`Symbol`](CrispExpr) or a [`List`](CrispExpr) of
 /// `Symbol`s, and when the `Lambda` is called, the values given as arguments
-/// will be available within the expression with those variable names.
+/// will be available within the expressions with those variable names. If
+/// there is more than one expression, they are evaluated in turn as if by
+/// [`do`](eval_do), and the value of the last is returned. An argument named
+/// `_` is ignored, and isn't bound to anything. Any other argument that isn't
+/// used in the expressions gives an `unused-argument` warning, unless its name
+/// starts with `_`.
 ///
 /// # Examples
 ///
 /// ```lisp
 /// ((\ (a b) (* a b)) 3 5)       ; => 15
 /// map (\ n (* 2 n)) (1 2 3 4 5) ; => (2 4 6 8 10)
+/// (\ n (puts n) (* 2 n)) 5      ; => 10, after printing 5
+/// ((\ (_ x _) x) 1 2 3)         ; => 2
 /// ```
-fn eval_keyword_lambda(args: &[CrispExpr]) -> Result<CrispExpr, CrispError> {
-    check_argument_error!(args, 2, 2);
+fn eval_keyword_lambda(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let lambda = make_lambda(args)?;
+    warn_unused_args(&lambda, || {
+        CrispExpr::List(iter::once(sym!("\\")).chain(args.iter().cloned()).collect())
+    });
+
+    Ok(CrispExpr::Lambda(Arc::new(lambda)))
+}
+
+/// Builds the [`Lambda`](CrispExpr) for [`\\`](eval_keyword_lambda) from its
+/// arguments.
+fn make_lambda(args: &[CrispExpr]) -> Result<CrispLambda, CrispError> {
+    check_argument_error!(args, 2, -1);
 
     let a = args.first().unwrap().clone();
     let arg_list = match a {
@@ -110,19 +587,72 @@ fn eval_keyword_lambda(args: &[CrispExpr]) -> Result<CrispExpr, CrispError> {
         _ => return type_error!("Symbol || List<Symbol>"),
     };
 
-    // Make sure all elements in the arg_list are Symbols
+    // Make sure all elements in the arg_list are Symbols, and that none of
+    // them (other than `_`) are repeated
     if let CrispExpr::List(list) = &arg_list {
-        for arg in list {
-            if !matches!(arg, CrispExpr::Symbol(_)) {
-                return type_error!("Symbol || List<Symbol>");
+        for (i, arg) in list.iter().enumerate() {
+            match arg {
+                CrispExpr::Symbol(name) if &**name == PLACEHOLDER => (),
+                CrispExpr::Symbol(_) if list[..i].contains(arg) => {
+                    return parse_error!(format!("Duplicate argument: {}", arg));
+                },
+                CrispExpr::Symbol(_) => (),
+                _ => return type_error!("Symbol || List<Symbol>")
             }
         }
     }
 
-    Ok(CrispExpr::Lambda(CrispLambda {
-        args: Rc::new(arg_list),
-        func: Rc::new(args.get(1).unwrap().clone()),
-    }))
+    let (func, source) = match &args[1..] {
+        [body] => {
+            let func = body.clone();
+            let source = map_clones([(body, &func)]);
+            (func, source)
+        },
+        bodies => {
+            let func = CrispExpr::List(iter::once(sym!("do")).chain(bodies.iter().cloned())
+                                                             .collect());
+            let CrispExpr::List(clones) = &func else { unreachable!() };
+            let source = map_clones(bodies.iter().zip(&clones[1..]));
+            (func, source)
+        }
+    };
+
+    Ok(CrispLambda {
+        args: Arc::new(arg_list),
+        func: Arc::new(func),
+        name: None,
+        source
+    })
+}
+
+/// Gives an `unused-argument` warning, about the expression made by `expr`,
+/// for each argument of `lambda` that isn't used in its body. Arguments that
+/// start with `_` are left alone, as are those of shorthand lambdas (e.g.
+/// `%1` in `#(* %2 2)`), which are numbered rather than chosen.
+fn warn_unused_args(lambda: &CrispLambda, expr: impl Fn() -> CrispExpr) {
+    let CrispExpr::List(args) = &*lambda.args else {
+        return;
+    };
+
+    for arg in args {
+        match arg {
+            CrispExpr::Symbol(name) if !name.starts_with(['_', '%'])
+                                       && !mentions(&lambda.func, name) => {
+                let function = lambda.name.as_ref().map(|name| name.to_string());
+                warn(CrispWarning::UnusedArgument(name.to_string(), function), Some(expr()));
+            },
+            _ => ()
+        }
+    }
+}
+
+/// Checks whether the symbol `name` appears anywhere within `expr`.
+fn mentions(expr: &CrispExpr, name: &str) -> bool {
+    match expr {
+        CrispExpr::Symbol(symbol) => &**symbol == name,
+        CrispExpr::List(list) => list.iter().any(|expr| mentions(expr, name)),
+        _ => false
+    }
 }
 
 /// `fn` defines a function by creating a [`Lambda`](CrispExpr) and saving it
@@ -135,7 +665,7 @@ fn eval_keyword_lambda(args: &[CrispExpr]) -> Result<CrispExpr, CrispError> {
 /// Rather, with `fn` you can just do this:
 ///
 /// ```lisp
-/// fn name args expression
+/// fn name args expression...
 /// ```
 ///
 /// # Examples
@@ -146,9 +676,15 @@ fn eval_keyword_lambda(args: &[CrispExpr]) -> Result<CrispExpr, CrispError> {
 ///
 /// fn add (a b) (+ a b)
 /// add 10 20            ; => 30
+///
+/// fn area (w h)
+///   (let n (* w h))
+///   (puts "Area: {}" n)
+///   n
+/// area 3 4             ; => 12, after printing "Area: 12"
 /// ```
 fn eval_fn(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
-    check_argument_error!(args, 3, 3);
+    check_argument_error!(args, 3, -1);
 
     let (head, tail) = args.split_first().unwrap();
 
@@ -157,10 +693,15 @@ fn eval_fn(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispErr
         _ => return type_error!("Symbol")
     };
 
-    let lambda = eval_keyword_lambda(tail)?;
-    env.data.insert(name, lambda.clone());
+    let lambda = CrispLambda { name: Some(name.clone()), ..make_lambda(tail)? };
+    warn_unused_args(&lambda, || {
+        CrispExpr::List(iter::once(sym!("fn")).chain(args.iter().cloned()).collect())
+    });
+
+    let lambda = CrispExpr::Lambda(Arc::new(lambda));
+    env_bind(name.to_string(), lambda.clone(), env)?;
 
-    Ok(lambda.clone())
+    Ok(lambda)
 }
 
 /// `exit` exits the program with the return code given to it. If no
@@ -172,7 +713,7 @@ fn eval_fn(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispErr
 /// exit
 /// exit 1
 /// ```
-fn eval_exit(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+fn eval_exit(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
     check_argument_error!(args, 0, 1);
 
     let code = match args.first() {
@@ -180,13 +721,589 @@ fn eval_exit(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, Crisp
         _ => &0.0
     };
 
-    process::exit(code.round() as i32);
+    env_host(env).exit(code.round() as i32)
+}
+
+/// `embed` reads a file and returns its contents as a [`String`](CrispExpr).
+/// Relative paths are resolved against the directory of the file that is
+/// being run, so small data files can be shipped alongside a script.
+///
+/// # Examples
+///
+/// ```lisp
+/// let usage (embed "usage.txt")
+/// lines (embed "data/names.txt")
+/// ```
+fn eval_embed(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let path = match eval(args.first().unwrap(), env)? {
+        CrispExpr::CrispString(s) => env_source_dir(env).join(&*s),
+        _ => return type_error!("String")
+    };
+
+    env_host(env).read_file(&path)
+                  .map(|s| CrispExpr::CrispString(s.into()))
+                  .map_err(|_| CrispError::LoadError(path.display().to_string()))
+}
+
+/// `load` runs another crisp file within the current scope, so that anything
+/// it defines becomes available, and returns the value of its last expression.
+///
+/// Relative paths are resolved against the directory of the file that is
+/// being run; if the file isn't found there, each directory in the load path
+/// (see [`configure_load_path()`]) and then in the `CRISP_PATH` environment
+/// variable is searched in turn. Files loaded from
+/// within a loaded file are resolved relative to that file.
+///
+/// It is an error for a file to load itself, whether directly or through
+/// other files.
+///
+/// # Examples
+///
+/// ```lisp
+/// load "lib/util.crisp"
+/// load "/home/me/crisp/prelude.crisp"
+/// ```
+fn eval_load(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let name = match eval(args.first().unwrap(), env)? {
+        CrispExpr::CrispString(s) => s,
+        _ => return type_error!("String")
+    };
+
+    let path = find_load_path(&name, env)?;
+    load_file(path, env)
+}
+
+/// `require` runs a library installed with `crisp install` within the current
+/// scope, like [`load`](eval_load), given its name. This is the file of the
+/// same name in the library's directory, e.g. `json/json.crisp`, or failing
+/// that its `main.crisp`. Libraries are installed in `~/.crisp/libs`, or in
+/// the directory given by the `CRISP_LIBS` environment variable.
+///
+/// # Examples
+///
+/// ```lisp
+/// require "json"
+/// ```
+fn eval_require(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let name = match eval(args.first().unwrap(), env)? {
+        CrispExpr::CrispString(s) => s,
+        _ => return type_error!("String")
+    };
+
+    let host = env_host(env);
+    let path = package::library_files(&name).into_iter()
+                                             .find(|file| host.is_file(file))
+                                             .ok_or(CrispError::LoadError(name.to_string()))?;
+    load_file(path, env)
+}
+
+/// Runs the file at `path` within the scope `env`, for [`load`](eval_load) and
+/// [`require`](eval_require).
+fn load_file(path: PathBuf, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    let canonical_path = path.canonicalize()
+                             .map_err(|_| CrispError::LoadError(path.display().to_string()))?;
+
+    let loading = env_loading(env);
+    if let Some(start) = loading.iter().position(|file| file == &canonical_path) {
+        let cycle = loading[start..].iter()
+                                    .chain(iter::once(&canonical_path))
+                                    .map(|file| file.display().to_string())
+                                    .collect::<Vec<String>>();
+
+        return standard_error!(format!("Circular load detected: {}", cycle.join(" -> ")));
+    }
+
+    let source = env_host(env).read_file(&path)
+                              .map_err(|_| CrispError::LoadError(path.display().to_string()))?;
+
+    let parsed = cache::parse_exprs(&canonical_path, &split_exprs(&source));
+
+    let outer_file = env.file.replace(canonical_path.clone());
+    env.loading.push(canonical_path);
+
+    let result = parsed.into_iter().try_fold(CrispExpr::Nil, |_, parsed| {
+        let (ast, mut map) = parsed?;
+        map.file.clone_from(&env.file);
+        with_source_map(&Arc::new(map), || catch_panic(|| eval_top_level(ast, env)))
+    });
+
+    env.loading.pop();
+    env.file = outer_file;
+
+    result
+}
+
+/// The directories that [`load`](eval_load) searches, other than those in
+/// `CRISP_PATH`. See [`configure_load_path()`].
+static LOAD_PATH: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+/// Configures the directories that [`load`](eval_load) searches for files that
+/// aren't found relative to the file loading them, before those in
+/// `CRISP_PATH`, for the rest of the program. `crisp run` sets these to the
+/// project's source directories.
+pub fn configure_load_path(dirs: Vec<PathBuf>) {
+    *LOAD_PATH.write().unwrap_or_else(|e| e.into_inner()) = dirs;
+}
+
+/// Finds the file referred to by `name` for [`load`](eval_load).
+pub fn find_load_path(name: &str, env: &CrispEnv) -> Result<PathBuf, CrispError> {
+    let path = Path::new(name);
+    let host = env_host(env);
+
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+
+    let crisp_path = std::env::var_os("CRISP_PATH")
+        .map(|dirs| std::env::split_paths(&dirs).collect::<Vec<PathBuf>>())
+        .unwrap_or_default();
+
+    let load_path = LOAD_PATH.read().unwrap_or_else(|e| e.into_inner()).clone();
+
+    iter::once(env_source_dir(env))
+        .chain(load_path)
+        .chain(crisp_path)
+        .map(|dir| dir.join(path))
+        .find(|candidate| host.is_file(candidate))
+        .ok_or_else(|| CrispError::LoadError(name.to_string()))
+}
+
+/// `spawn` starts evaluating an expression on another thread and immediately
+/// returns a [`Task`](CrispExpr), which can be passed to
+/// [`await`](crate::functions::crisp_await) to wait for the result.
+///
+/// The expression is evaluated in a copy of the current scope, so any
+/// variables it sets are not visible outside of it.
+///
+/// # Examples
+///
+/// ```lisp
+/// fn slow-square n (* n n)
+/// let task (spawn (slow-square 12))
+/// puts "Working..."
+/// await task                        ; => 144
+/// ```
+fn eval_spawn(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let expr = args.first().unwrap().clone();
+    let mut snapshot = env_snapshot(env);
+
+    let handle = thread::spawn(move || eval(&expr, &mut snapshot));
+
+    Ok(CrispExpr::Task(Arc::new(Mutex::new(CrispTask::Running(handle)))))
+}
+
+/// `delay` wraps an expression in a [`Delay`](CrispExpr) without evaluating
+/// it. The expression is evaluated when the `Delay` is passed to
+/// [`force`](crate::functions::crisp_force), and only the first time; after
+/// that, `force` returns the same result again.
+///
+/// As with [`spawn`](eval_spawn), the expression is evaluated in a copy of the
+/// current scope, as it was when `delay` was called.
+///
+/// # Examples
+///
+/// ```lisp
+/// let answer (delay (do-something-slow))
+/// force answer                           ; Slow
+/// force answer                           ; Fast
+/// ```
+fn eval_delay(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 1);
+
+    let expr = args.first().unwrap().clone();
+
+    let delay = CrispDelay::Pending(expr, Box::new(env_snapshot(env)));
+    Ok(CrispExpr::Delay(Arc::new(Mutex::new(delay))))
+}
+
+/// `generator` makes a [`Generator`](CrispExpr) out of one or more
+/// expressions, which can [`yield`](crate::functions::crisp_yield) values.
+/// Nothing is evaluated until a value is asked for with
+/// [`next`](crate::functions::crisp_next) (or e.g.
+/// [`take`](crate::functions::crisp_take)); the expressions are then
+/// evaluated in order up until the next `yield`, and stay paused there until
+/// another value is asked for.
+///
+/// The expressions are evaluated on whichever thread asks for the value, in a
+/// scope nested inside of the one it's asked for in, like the body of a
+/// [`Lambda`](CrispExpr); so e.g. a variable rebound with `let` between two
+/// values being asked for is seen by the body. See
+/// [`resume_generator()`](crate::eval::resume_generator) for how the body is
+/// paused. `yield` can't be used inside of any of the special forms other
+/// than [`RESUMABLE_FORMS`].
+///
+/// # Usage
+///
+/// ```lisp
+/// generator expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// let g (generator (yield 1) (yield 2))
+/// next g                                ; => 1
+/// next g                                ; => 2
+/// next g                                ; => nil
+///
+/// fn count-from n (cons (yield n) (count-from (+ n 1)))
+/// take 3 (generator (count-from 10))    ; => (10 11 12)
+/// ```
+fn eval_generator(args: &[CrispExpr], _env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let body = CrispGenerator::Body(GeneratorBody::new(args));
+    Ok(CrispExpr::Generator(Arc::new(Mutex::new(body))))
+}
+
+/// `assert-raises` evaluates an expression and returns `true` if it raises
+/// an error, otherwise it raises an `AssertionError`. If the name of an error
+/// type is given, the error must also be of that type.
+///
+/// # Usage
+///
+/// ```lisp
+/// assert-raises expr
+/// assert-raises expr ErrorType
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// assert-raises (+ 1 "foo")
+/// assert-raises (+ 1 "foo") TypeError
+/// assert-raises (take 5 (1 2)) TypeError ; this would raise an AssertionError
+/// assert-raises (+ 1 2)                 ; this would raise an AssertionError
+/// ```
+fn eval_assert_raises(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, 2);
+
+    let expected = match args.get(1) {
+        Some(CrispExpr::Symbol(s)) => Some(s),
+        Some(_) => return type_error!("Symbol"),
+        None => None
+    };
+
+    match (eval(args.first().unwrap(), env), expected) {
+        (Ok(value), _) => assertion_error!(format!("Expected an error, got {}.", value)),
+        (Err(error), Some(name)) if error.name() != &**name => {
+            assertion_error!(format!("Expected {}, got {}.", name, error.name()))
+        },
+        (Err(_), _) => Ok(CrispExpr::Bool(true))
+    }
+}
+
+/// `deftest` defines a named test made up of one or more expressions. The
+/// test isn't run right away; instead, it is run by `crisp test`, which
+/// counts it as failed if any of the expressions raise an error (such as a
+/// failed [`assert`](crate::functions::crisp_assert)). Tests should be
+/// defined at the top level of a file, or inside of [`describe`](eval_describe).
+///
+/// # Usage
+///
+/// ```lisp
+/// deftest name expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// deftest "addition" (assert-eq (+ 1 2) 3)
+/// deftest "division by zero"
+///   (assert-raises (/ 1 0))
+///   (assert-eq (/ 0 1) 0)
+/// ```
+fn eval_deftest(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let name = test_name(args.first().unwrap())?;
+    env.tests.push(CrispTest { name, body: args[1..].to_vec() });
+
+    Ok(CrispExpr::Nil)
+}
+
+/// `describe` groups tests together. Its expressions are evaluated in order,
+/// and the name of the group is added to the front of the name of any test
+/// defined by them with [`deftest`](eval_deftest). Groups can be nested.
+///
+/// # Usage
+///
+/// ```lisp
+/// describe name expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// describe "math"
+///   (deftest "addition" (assert-eq (+ 1 2) 3))       ; "math addition"
+///   (deftest "subtraction" (assert-eq (- 3 2) 1))    ; "math subtraction"
+/// ```
+fn eval_describe(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let name = test_name(args.first().unwrap())?;
+    let start = env.tests.len();
+
+    for expr in &args[1..] {
+        eval(expr, env)?;
+    }
+
+    for test in &mut env.tests[start..] {
+        test.name = format!("{} {}", name, test.name);
+    }
+
+    Ok(CrispExpr::Nil)
+}
+
+/// The name given to [`deftest`](eval_deftest) or [`describe`](eval_describe),
+/// which may be a [`String`](CrispExpr) or a [`Symbol`](CrispExpr).
+fn test_name(expr: &CrispExpr) -> Result<String, CrispError> {
+    match expr {
+        CrispExpr::CrispString(s) => Ok(s.to_string()),
+        CrispExpr::Symbol(s) => Ok(s.to_string()),
+        _ => type_error!("String")
+    }
+}
+
+/// `with-redefs` temporarily rebinds names (usually of functions) while
+/// evaluating one or more expressions, returning the result of the last. The
+/// new bindings are seen by everything called from within, and the old ones
+/// are put back afterwards, even if there is an error. This is useful for
+/// stubbing out functions in tests.
+///
+/// All of the new values are evaluated before any of them are bound.
+///
+/// # Usage
+///
+/// ```lisp
+/// with-redefs ((name value)...) expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// fn greet name (puts (format "Hello, {}!" name))
+///
+/// with-redefs ((puts (\ s s)))
+///   (greet "world")                ; => "Hello, world!", without printing
+/// ```
+fn eval_with_redefs(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let bindings = match args.first().unwrap() {
+        CrispExpr::List(bindings) => bindings,
+        _ => return type_error!("List")
+    };
+
+    let mut redefs = Vec::new();
+    for binding in bindings {
+        match binding {
+            CrispExpr::List(pair) if pair.len() == 2 => match &pair[0] {
+                CrispExpr::Symbol(name) => redefs.push((name.to_string(), eval(&pair[1], env)?)),
+                _ => return type_error!("Symbol")
+            },
+            _ => return parse_error!("with-redefs bindings must have the form (name value).")
+        }
+    }
+
+    let originals: Vec<(String, Option<CrispExpr>)> = redefs.into_iter()
+        .map(|(name, value)| (name.clone(), env.data.insert(name, value)))
+        .collect();
+
+    let result = args[1..].iter().try_fold(CrispExpr::Nil, |_, expr| eval(expr, env));
+
+    // Put the originals back in reverse, in case a name was redefined twice
+    for (name, original) in originals.into_iter().rev() {
+        match original {
+            Some(value) => env.data.insert(name, value),
+            None => env.data.remove(&name)
+        };
+    }
+
+    result
+}
+
+/// `with-output-to-string` evaluates one or more expressions, capturing
+/// anything they write to stdout (e.g. with
+/// [`puts`](crate::functions::crisp_puts)) instead of printing it, and
+/// returns the captured output as a [`String`](CrispExpr).
+///
+/// # Usage
+///
+/// ```lisp
+/// with-output-to-string expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// with-output-to-string (puts "foo") (print "bar") ; => "foo\nbar"
+/// ```
+fn eval_with_output_to_string(
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    capture_output(args, env, |env| &mut env.stdout)
+}
+
+/// `with-error-output-to-string` works like
+/// [`with-output-to-string`](eval_with_output_to_string), but captures
+/// anything written to stderr (e.g. with
+/// [`eputs`](crate::functions::crisp_eputs)).
+///
+/// # Examples
+///
+/// ```lisp
+/// with-error-output-to-string (eputs "oops") ; => "oops\n"
+/// ```
+fn eval_with_error_output_to_string(
+    args: &[CrispExpr],
+    env: &mut CrispEnv
+) -> Result<CrispExpr, CrispError> {
+    capture_output(args, env, |env| &mut env.stderr)
+}
+
+/// Evaluates `args` with the output stream picked out by `stream` redirected
+/// to a buffer, returning the contents of the buffer.
+fn capture_output(
+    args: &[CrispExpr],
+    env: &mut CrispEnv,
+    stream: for<'e, 'p> fn(&'e mut CrispEnv<'p>) -> &'e mut Option<CrispWriter>
+) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 1, -1);
+
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let original = stream(env).replace(buffer.clone());
+
+    let result = args.iter().try_fold(CrispExpr::Nil, |_, expr| eval(expr, env));
+    *stream(env) = original;
+    result?;
+
+    let output = buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(str!(String::from_utf8_lossy(&output).into_owned()))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{env::initialize_environment, eval::eval, expr::CrispExpr::*};
+/// `with-retry` evaluates one or more expressions, starting over from the
+/// first if any of them raises an error, up to `attempts` times in all. It
+/// waits `delay` milliseconds before each retry. If the last attempt fails
+/// too, its error is raised.
+///
+/// # Usage
+///
+/// ```lisp
+/// with-retry attempts delay expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// ; Try to fetch the page up to 3 times, a second apart
+/// with-retry 3 1000 (fetch-page url)
+/// ```
+fn eval_with_retry(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 3, -1);
+
+    let attempts = extract_index(&eval(&args[0], env)?)?;
+    let delay = extract_millis(&eval(&args[1], env)?)?;
+
+    if attempts == 0 {
+        return standard_error!("with-retry needs at least 1 attempt.");
+    }
+
+    let mut result = eval_do(&args[2..], env);
+    for _ in 1..attempts {
+        if result.is_ok() {
+            break;
+        }
+
+        thread::sleep(delay);
+        result = eval_do(&args[2..], env);
+    }
+
+    result
+}
+
+/// `with-timeout` evaluates one or more expressions and returns the result of
+/// the last, raising an error if they haven't finished after `timeout`
+/// milliseconds.
+///
+/// This is a soft timeout: the expressions are evaluated on another thread,
+/// which is left to finish in the background if it's too slow, rather than
+/// stopped. As with [`spawn`](eval_spawn), they are evaluated in a copy of the
+/// current scope, so any variables they set are not visible outside of it.
+///
+/// # Usage
+///
+/// ```lisp
+/// with-timeout timeout expr...
+/// ```
+///
+/// # Examples
+///
+/// ```lisp
+/// with-timeout 5000 (fetch-page url)
+/// ```
+fn eval_with_timeout(args: &[CrispExpr], env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    check_argument_error!(args, 2, -1);
+
+    let timeout = extract_millis(&eval(&args[0], env)?)?;
+
+    let body = args[1..].to_vec();
+    let mut snapshot = env_snapshot(env);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        // If it timed out, nobody is listening anymore
+        let _ = sender.send(catch_panic(|| eval_do(&body, &mut snapshot)));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            standard_error!(format!("Timed out after {} ms.", timeout.as_millis()))
+        },
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err(CrispError::InternalError("The body stopped without a result.".to_string()))
+        }
+    }
+}
+
+/// Reads a non-negative [`Number`](CrispExpr) of milliseconds, as given to
+/// [`with-retry`](eval_with_retry) or [`with-timeout`](eval_with_timeout).
+fn extract_millis(expr: &CrispExpr) -> Result<Duration, CrispError> {
+    match expr {
+        CrispExpr::Number(n) if *n >= 0.0 && n.is_finite() => {
+            Ok(Duration::from_secs_f64(n / 1000.0))
+        },
+        CrispExpr::Number(n) => index_error!(format!("Expected a non-negative time, got {}.", n)),
+        _ => type_error!("Number")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::{env_get, initialize_environment}, eval::eval, expr::CrispExpr::*};
+
+    #[test]
+    fn test_keywords() {
+        let mut env = initialize_environment();
+
+        // `exit` would end the test run
+        for (keyword, _) in SPECIAL_FORMS.iter().filter(|(keyword, _)| *keyword != "exit") {
+            assert!(eval_keyword(&sym!(*keyword), &[], &mut env).is_some(), "{}", keyword);
+        }
+        assert!(eval_keyword(&sym!("puts"), &[], &mut env).is_none());
+    }
 
     // if keyword
 
@@ -287,6 +1404,60 @@ mod tests {
         assert_eq!(eval_if(&list, &mut env).unwrap(), Number(7.0));
     }
 
+    // case keyword
+
+    #[test]
+    fn test_case() {
+        let mut env = initialize_environment();
+        let case = |value: CrispExpr, env: &mut CrispEnv| eval(&list![
+            sym!("case"), value,
+            list![Number(1.0), str!("one")],
+            list![list![Number(2.0), Number(3.0)], str!("a few")],
+            list![str!("x"), str!("a string")],
+            list![sym!("else"), str!("many")]
+        ], env);
+
+        assert_eq!(case(Number(1.0), &mut env).unwrap(), str!("one"));
+        assert_eq!(case(list![sym!("+"), Number(1.0), Number(2.0)], &mut env).unwrap(),
+                   str!("a few"));
+        assert_eq!(case(str!("x"), &mut env).unwrap(), str!("a string"));
+        assert_eq!(case(Number(4.0), &mut env).unwrap(), str!("many"));
+
+        // Without a match
+        assert_eq!(eval_case(&[Number(4.0), list![Number(1.0), str!("one")]], &mut env).unwrap(),
+                   Nil);
+
+        // Only the matching arm is evaluated
+        let list = list![sym!("case"), Number(1.0),
+                         list![Number(1.0), str!("one")],
+                         list![sym!("else"), list![sym!("+"), Number(1.0), Bool(true)]]];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("one"));
+
+        crisp_assert_err!(eval_case(&[], &mut env), ArgumentError);
+        crisp_assert_err!(eval_case(&[Number(1.0), Number(1.0)], &mut env), ParseError);
+        crisp_assert_err!(eval_case(&[Number(1.0), list![Number(1.0)]], &mut env), ParseError);
+    }
+
+    #[test]
+    fn test_case_table() {
+        let mut env = initialize_environment();
+        let mut args = vec![sym!("n")];
+        args.extend((0..20).map(|i| list![Number(i as f64), Number((i * 10) as f64)]));
+        args.push(list![list![Number(5.0), Number(100.0)], str!("5 or 100")]);
+        args.push(list![sym!("else"), str!("other")]);
+
+        for (n, expected) in [(0.0, Number(0.0)), (5.0, Number(50.0)), (19.0, Number(190.0)),
+                              (100.0, str!("5 or 100")), (20.0, str!("other"))] {
+            env.data.insert("n".to_string(), Number(n));
+            assert_eq!(eval_case(&args, &mut env).unwrap(), expected);
+        }
+
+        // Arms before `else` still win, and those after it don't
+        let arms = [(Some(&Number(1.0)), &Nil), (None, &Nil), (Some(&Number(2.0)), &Nil)];
+        assert_eq!(case_table_lookup(&arms, &Number(1.0)), Some(0));
+        assert_eq!(case_table_lookup(&arms, &Number(2.0)), Some(1));
+    }
+
     // let keyword
 
     #[test]
@@ -320,6 +1491,138 @@ mod tests {
         assert_eq!(env.data.get("foo").unwrap(), &Number(10.0));
     }
 
+    #[test]
+    fn test_let_placeholder() {
+        let mut env = initialize_environment();
+        let list = list![sym!("let"), sym!("_"), list![sym!("+"), Number(1.0), Number(2.0)]];
+
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(3.0));
+        assert!(!env.data.contains_key("_"));
+    }
+
+    #[test]
+    fn test_const() {
+        let mut env = initialize_environment();
+        let list = list![sym!("const"), sym!("foo"), Number(5.0)];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(5.0));
+        assert_eq!(env.data.get("foo").unwrap(), &Number(5.0));
+
+        // It can't be rebound in the same scope
+        crisp_assert_err!(eval_let(&[sym!("foo"), Number(10.0)], &mut env), StandardError);
+        crisp_assert_err!(eval_const(&[sym!("foo"), Number(10.0)], &mut env), StandardError);
+        crisp_assert_err!(eval(&list![sym!("fn"), sym!("foo"), list![], Number(1.0)], &mut env),
+                          StandardError);
+        crisp_assert_err!(eval(&list![sym!("unbind!"), str!("foo")], &mut env), StandardError);
+        assert_eq!(env.data.get("foo").unwrap(), &Number(5.0));
+
+        // But it can be shadowed in an inner scope
+        let mut scope = env_new_scope(HashMap::new(), &env);
+        eval_let(&[sym!("foo"), Number(10.0)], &mut scope).unwrap();
+        assert_eq!(scope.data.get("foo").unwrap(), &Number(10.0));
+
+        crisp_assert_err!(eval_const(&[Number(1.0), Number(2.0)], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_shadow() {
+        let mut env = initialize_environment();
+
+        // `let` and `fn` can't rebind builtins, in any scope
+        crisp_assert_err!(eval(&list![sym!("let"), sym!("+"), Number(5.0)], &mut env),
+                          StandardError);
+        crisp_assert_err!(eval(&list![sym!("fn"), sym!("map"), list![], Number(1.0)], &mut env),
+                          StandardError);
+        let mut scope = env_new_scope(HashMap::new(), &env);
+        crisp_assert_err!(eval_let(&[sym!("+"), Number(5.0)], &mut scope), StandardError);
+        assert!(matches!(env_get("+", &env).unwrap(), Func(_)));
+
+        // `shadow` can
+        let list = list![sym!("shadow"), sym!("+"), Number(5.0)];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(5.0));
+        assert_eq!(env.data.get("+").unwrap(), &Number(5.0));
+
+        // But not constants
+        eval_const(&[sym!("foo"), Number(1.0)], &mut env).unwrap();
+        crisp_assert_err!(eval_shadow(&[sym!("foo"), Number(2.0)], &mut env), StandardError);
+
+        // Parameters may still have the same name as a builtin
+        let f = list![sym!("\\"), list![sym!("map")], sym!("map")];
+        assert_eq!(eval(&list![f, Number(3.0)], &mut env).unwrap(), Number(3.0));
+    }
+
+    #[test]
+    fn test_binding() {
+        let mut env = initialize_environment();
+        let get_n = list![sym!("\\"), list![], sym!("*n*")];
+        eval(&list![sym!("defparam"), sym!("*n*"), Number(1.0)], &mut env).unwrap();
+        eval_let(&[sym!("get-n"), get_n], &mut env).unwrap();
+
+        // Seen by functions called from within
+        let list = list![sym!("binding"), list![sym!("*n*"), Number(2.0)], list![sym!("get-n")]];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(2.0));
+        assert_eq!(eval(&list![sym!("get-n")], &mut env).unwrap(), Number(1.0));
+
+        // Several at once, evaluated before any are bound
+        eval_defparam(&[sym!("*m*"), Number(10.0)], &mut env).unwrap();
+        let list = list![
+            sym!("binding"),
+            list![list![sym!("*n*"), sym!("*m*")], list![sym!("*m*"), sym!("*n*")]],
+            list![sym!("-"), sym!("*n*"), sym!("*m*")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(9.0));
+
+        // Restored after an error
+        let list = list![sym!("binding"), list![sym!("*n*"), Number(2.0)],
+                         list![sym!("+"), Number(1.0), Bool(true)]];
+        crisp_assert_err!(eval(&list, &mut env), TypeError);
+        assert_eq!(eval(&sym!("*n*"), &mut env).unwrap(), Number(1.0));
+
+        // Only parameters can be bound
+        eval_let(&[sym!("x"), Number(1.0)], &mut env).unwrap();
+        crisp_assert_err!(eval_binding(&[list![sym!("x"), Number(2.0)], sym!("x")], &mut env),
+                          StandardError);
+        crisp_assert_err!(eval_binding(&[list![list![Number(1.0)]], sym!("x")], &mut env),
+                          ParseError);
+        crisp_assert_err!(eval_binding(&[Number(1.0), sym!("x")], &mut env), TypeError);
+        crisp_assert_err!(eval_binding(&[list![sym!("*n*"), Number(2.0)]], &mut env),
+                          ArgumentError);
+    }
+
+    #[test]
+    fn test_letrec() {
+        let mut env = initialize_environment();
+        let go = list![
+            sym!("\\"), list![sym!("i"), sym!("total")],
+            list![sym!("if"), list![sym!(">"), sym!("i"), Number(4.0)],
+                  sym!("total"),
+                  list![sym!("go"), list![sym!("+"), sym!("i"), Number(1.0)],
+                                    list![sym!("+"), sym!("total"), sym!("i")]]]
+        ];
+
+        let list = list![sym!("letrec"), list![sym!("go"), go.clone()],
+                         list![sym!("go"), Number(1.0), Number(0.0)]];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(10.0));
+        // The names don't leak out
+        assert!(!env.data.contains_key("go"));
+
+        // Several bindings, which can see each other
+        let list = list![
+            sym!("letrec"),
+            list![list![sym!("go"), go], list![sym!("start"), Number(1.0)]],
+            list![sym!("go"), sym!("start"), Number(0.0)]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(10.0));
+
+        // Names are bound to nil until their values have been evaluated
+        let bindings = list![list![sym!("a"), sym!("b")], list![sym!("b"), Number(1.0)]];
+        let list = list![sym!("letrec"), bindings, sym!("a")];
+        assert_eq!(eval(&list, &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(eval_letrec(&[list![sym!("a"), Number(1.0)]], &mut env), ArgumentError);
+        crisp_assert_err!(eval_letrec(&[list![list![sym!("a")]], Nil], &mut env), ParseError);
+        crisp_assert_err!(eval_letrec(&[list![list![Number(1.0), Nil]], Nil], &mut env), TypeError);
+    }
+
     #[test]
     fn test_let_evaluates() {
         let mut env = initialize_environment();
@@ -350,6 +1653,255 @@ mod tests {
         assert_eq!(eval(&sym!("foo"), &mut env).unwrap(), Number(5.0));
     }
 
+    // match-str keyword
+
+    #[test]
+    fn test_match_str() {
+        let mut env = initialize_environment();
+
+        let match_str = |s: &str| list![
+            sym!("match-str"),
+            str!(s),
+            list![
+                str!(r"^(\w+)=(\d+)$"),
+                list![sym!("k"), sym!("v")],
+                list![sym!("format"), str!("{}{}"), sym!("k"), sym!("v")]
+            ],
+            list![str!("^#"), str!("comment")],
+            list![sym!("_"), str!("unknown")]
+        ];
+
+        assert_eq!(eval(&match_str("foo=42"), &mut env).unwrap(), str!("foo42"));
+        assert_eq!(eval(&match_str("# foo"), &mut env).unwrap(), str!("comment"));
+        assert_eq!(eval(&match_str("foo"), &mut env).unwrap(), str!("unknown"));
+
+        // Captures don't leak out of the arm
+        assert!(!env.data.contains_key("k"));
+
+        // Capture groups named `_` are skipped
+        let match_str = |body| list![
+            sym!("match-str"),
+            str!("a=1"),
+            list![str!(r"(\w)=(\d)"), list![sym!("_"), sym!("v")], body]
+        ];
+        assert_eq!(eval(&match_str(sym!("v")), &mut env).unwrap(), str!("1"));
+        crisp_assert_err!(eval(&match_str(sym!("_")), &mut env), ParseError);
+
+        // No match
+        let list = list![sym!("match-str"), str!("foo"), list![str!("bar"), Number(1.0)]];
+        assert_eq!(eval(&list, &mut env).unwrap(), Nil);
+    }
+
+    #[test]
+    fn test_match_str_named_captures() {
+        let mut env = initialize_environment();
+
+        let list = list![
+            sym!("match-str"),
+            str!("2023-06"),
+            list![str!(r"(?P<year>\d{4})-(?P<day>\d\d)?"), sym!("year")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("2023"));
+
+        // Unmatched groups are nil
+        let list = list![
+            sym!("match-str"),
+            str!("a"),
+            list![str!("(a)|(b)"), list![sym!("x"), sym!("y")], sym!("y")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), Nil);
+    }
+
+    #[test]
+    fn test_match_str_errors() {
+        let mut env = initialize_environment();
+
+        let list = list![sym!("match-str"), Number(1.0)];
+        crisp_assert_err!(eval(&list, &mut env), TypeError);
+
+        let list = list![sym!("match-str"), str!("foo"), list![str!("(")]];
+        crisp_assert_err!(eval(&list, &mut env), ParseError);
+
+        let list = list![sym!("match-str"), str!("foo"), list![str!("("), Nil]];
+        crisp_assert_err!(eval(&list, &mut env), ParseError);
+    }
+
+    // assert-raises keyword
+
+    #[test]
+    fn test_assert_raises() {
+        let mut env = initialize_environment();
+
+        let list = list![sym!("assert-raises"), list![sym!("+"), Number(1.0), str!("foo")]];
+        assert_eq!(eval(&list, &mut env).unwrap(), Bool(true));
+
+        let list = list![
+            sym!("assert-raises"),
+            list![sym!("+"), Number(1.0), str!("foo")],
+            sym!("TypeError")
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), Bool(true));
+
+        let list = list![sym!("assert-raises"), Nil];
+        crisp_assert_err!(eval(&list, &mut env), AssertionError);
+
+        let list = list![
+            sym!("assert-raises"),
+            list![sym!("+"), Number(1.0), str!("foo")],
+            sym!("IndexError")
+        ];
+        crisp_assert_err!(eval(&list, &mut env), AssertionError);
+
+        let list = list![sym!("assert-raises"), Nil, str!("TypeError")];
+        crisp_assert_err!(eval(&list, &mut env), TypeError);
+    }
+
+    // deftest and describe keywords
+
+    #[test]
+    fn test_deftest() {
+        let mut env = initialize_environment();
+
+        // The test isn't run yet
+        let list = list![sym!("deftest"), str!("foo"), list![sym!("undefined-function")]];
+        assert_eq!(eval(&list, &mut env).unwrap(), Nil);
+
+        let list = list![sym!("deftest"), sym!("bar"), Number(1.0), Number(2.0)];
+        eval(&list, &mut env).unwrap();
+
+        assert_eq!(env.tests.len(), 2);
+        assert_eq!(env.tests[0].name, "foo");
+        assert_eq!(env.tests[1].name, "bar");
+        assert_eq!(env.tests[1].body, vec![Number(1.0), Number(2.0)]);
+
+        crisp_assert_err!(eval(&list![sym!("deftest"), str!("baz")], &mut env), ArgumentError);
+        crisp_assert_err!(eval(&list![sym!("deftest"), Number(1.0), Nil], &mut env), TypeError);
+    }
+
+    #[test]
+    fn test_describe() {
+        let mut env = initialize_environment();
+
+        eval(&list![sym!("deftest"), str!("outside"), Nil], &mut env).unwrap();
+
+        let list = list![
+            sym!("describe"),
+            str!("math"),
+            list![sym!("deftest"), str!("addition"), Nil],
+            list![
+                sym!("describe"),
+                str!("division"),
+                list![sym!("deftest"), str!("by zero"), Nil]
+            ],
+            list![sym!("let"), sym!("n"), Number(1.0)]
+        ];
+        eval(&list, &mut env).unwrap();
+
+        let names: Vec<&str> = env.tests.iter().map(|test| test.name.as_str()).collect();
+        assert_eq!(names, ["outside", "math addition", "math division by zero"]);
+
+        // Other expressions are evaluated as usual
+        assert_eq!(env.data.get("n").unwrap(), &Number(1.0));
+    }
+
+    // with-redefs keyword
+
+    #[test]
+    fn test_with_redefs() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(1.0));
+
+        let add_n = lambda![
+            args: ["x"],
+            func: [
+                sym!("+"),
+                sym!("x"),
+                sym!("n")
+            ]
+        ];
+        env.data.insert("add-n".to_string(), add_n);
+
+        // Functions called from within see the new bindings
+        let list = list![
+            sym!("with-redefs"),
+            list![list![sym!("n"), Number(10.0)], list![sym!("m"), Number(5.0)]],
+            list![sym!("let"), sym!("x"), Number(2.0)],
+            list![sym!("add-n"), sym!("m")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(15.0));
+
+        // The old bindings are put back, but other changes are kept
+        assert_eq!(env.data.get("n").unwrap(), &Number(1.0));
+        assert!(!env.data.contains_key("m"));
+        assert_eq!(env.data.get("x").unwrap(), &Number(2.0));
+    }
+
+    #[test]
+    fn test_with_redefs_error() {
+        let mut env = initialize_environment();
+
+        // Builtins are restored after an error
+        let list = list![
+            sym!("with-redefs"),
+            list![list![sym!("puts"), Number(1.0)]],
+            list![sym!("undefined-function")]
+        ];
+        crisp_assert_err!(eval(&list, &mut env), ParseError);
+        assert!(matches!(env_get("puts", &env).unwrap(), Func(_)));
+
+        let list = list![sym!("with-redefs"), list![Number(1.0)], Nil];
+        crisp_assert_err!(eval(&list, &mut env), ParseError);
+
+        let list = list![sym!("with-redefs"), Nil, Nil];
+        crisp_assert_err!(eval(&list, &mut env), TypeError);
+    }
+
+    // with-output-to-string keywords
+
+    #[test]
+    fn test_with_output_to_string() {
+        let mut env = initialize_environment();
+
+        let list = list![
+            sym!("with-output-to-string"),
+            list![sym!("puts"), str!("foo")],
+            list![sym!("eputs"), str!("not captured")],
+            list![sym!("print"), str!("bar")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("foo\nbar"));
+        assert!(env.stdout.is_none());
+
+        let list = list![
+            sym!("with-error-output-to-string"),
+            list![sym!("puts"), str!("not captured")],
+            list![sym!("eputs"), str!("foo")]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("foo\n"));
+        assert!(env.stderr.is_none());
+    }
+
+    #[test]
+    fn test_with_output_to_string_nested() {
+        let mut env = initialize_environment();
+
+        let list = list![
+            sym!("with-output-to-string"),
+            list![sym!("puts"), str!("outer")],
+            list![
+                sym!("let"),
+                sym!("inner"),
+                list![sym!("with-output-to-string"), list![sym!("puts"), str!("inner")]]
+            ]
+        ];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("outer\n"));
+        assert_eq!(env.data.get("inner").unwrap(), &str!("inner\n"));
+
+        // The output stream is put back after an error
+        let list = list![sym!("with-output-to-string"), list![sym!("undefined-function")]];
+        crisp_assert_err!(eval(&list, &mut env), ParseError);
+        assert!(env.stdout.is_none());
+    }
+
     // Lambdas
 
     #[test]
@@ -488,6 +2040,23 @@ mod tests {
         assert_eq!(eval(&call, &mut env).unwrap(), Number(6.0));
     }
 
+    #[test]
+    fn test_lambda_placeholder() {
+        let mut env = initialize_environment();
+
+        let call = list![list![sym!("\\"), list![sym!("_"), sym!("x"), sym!("_")], sym!("x")],
+                         Number(1.0), Number(2.0), Number(3.0)];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(2.0));
+
+        // `_` isn't bound
+        let call = list![list![sym!("\\"), list![sym!("_")], sym!("_")], Number(1.0)];
+        crisp_assert_err!(eval(&call, &mut env), ParseError);
+
+        // Other names can't be repeated
+        let lambda = list![sym!("\\"), list![sym!("x"), sym!("x")], sym!("x")];
+        crisp_assert_err!(eval(&lambda, &mut env), ParseError);
+    }
+
     #[test]
     fn test_lambda_list_err() {
         // Number as single arg (occurs on instantiation)
@@ -534,7 +2103,8 @@ mod tests {
 
         crisp_assert_err!(eval(&call, &mut env), ArgumentError);
 
-        // Too many args
+        // Any number of expressions may follow the args, but they are still
+        // checked
         let mut env = initialize_environment();
         let call = list![
             sym!("\\"),
@@ -547,7 +2117,7 @@ mod tests {
             Bool(true)
         ];
 
-        crisp_assert_err!(eval(&call, &mut env), ArgumentError);
+        crisp_assert_err!(eval(&call, &mut env), TypeError);
     }
 
     // fn keyword
@@ -601,4 +2171,215 @@ mod tests {
 
         assert_eq!(eval(&call, &mut env).unwrap(), Number(9.0));
     }
+
+    #[test]
+    fn test_fn_multiple_expressions() {
+        let mut env = initialize_environment();
+        let list = list![
+            sym!("fn"),
+            sym!("area"),
+            list![sym!("w"), sym!("h")],
+            list![sym!("let"), sym!("n"), list![sym!("*"), sym!("w"), sym!("h")]],
+            list![sym!("puts"), str!("Area: {}"), sym!("n")],
+            sym!("n")
+        ];
+        eval(&list, &mut env).unwrap();
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut scope = env_new_scope(HashMap::new(), &env).with_stdout(output.clone());
+        let call = list![sym!("area"), Number(3.0), Number(4.0)];
+
+        assert_eq!(eval(&call, &mut scope).unwrap(), Number(12.0));
+        assert_eq!(String::from_utf8(output.lock().unwrap().clone()).unwrap(), "Area: 12\n");
+
+        // Lambdas too, and an error stops the body
+        let lambda = list![sym!("\\"), list![],
+                           list![sym!("+"), Number(1.0), Bool(true)],
+                           Number(1.0)];
+        crisp_assert_err!(eval(&list![lambda], &mut env), TypeError);
+
+        crisp_assert_err!(eval(&list![sym!("fn"), sym!("f"), list![]], &mut env), ArgumentError);
+        crisp_assert_err!(eval(&list![sym!("\\"), list![]], &mut env), ArgumentError);
+    }
+
+    // do keyword
+
+    #[test]
+    fn test_do() {
+        let mut env = initialize_environment();
+
+        let list = list![sym!("do"), list![sym!("let"), sym!("n"), Number(1.0)], sym!("n")];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(1.0));
+        assert_eq!(eval(&list![sym!("do")], &mut env).unwrap(), Nil);
+    }
+
+    // embed keyword
+
+    #[test]
+    fn test_embed() {
+        let mut env = initialize_environment();
+        env.file = Some(std::path::PathBuf::from("tests/embed.crisp"));
+
+        let list = list![sym!("embed"), str!("data/embed.txt")];
+        assert_eq!(eval(&list, &mut env).unwrap(), str!("Hello from a data file!\nfoo bar\n"));
+
+        let list = list![sym!("embed"), str!("data/does_not_exist.txt")];
+        crisp_assert_err!(eval(&list, &mut env), LoadError);
+
+        let list = list![sym!("embed"), Number(5.0)];
+        crisp_assert_err!(eval(&list, &mut env), TypeError);
+    }
+
+    // load keyword
+
+    #[test]
+    fn test_load() {
+        let mut env = initialize_environment();
+        env.file = Some(std::path::PathBuf::from("tests/load.crisp"));
+
+        // Returns the value of the last expression in the file
+        let list = list![sym!("load"), str!("lib/math_lib.crisp")];
+        assert_eq!(eval(&list, &mut env).unwrap(), Number(3.0));
+
+        // Definitions from the file, and the file it loaded, are available
+        let call = list![sym!("square"), Number(4.0)];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(16.0));
+        let call = list![sym!("inc"), Number(4.0)];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(5.0));
+
+        // The current file is restored afterwards
+        assert_eq!(env.file, Some(std::path::PathBuf::from("tests/load.crisp")));
+
+        let list = list![sym!("load"), str!("lib/does_not_exist.crisp")];
+        crisp_assert_err!(eval(&list, &mut env), LoadError);
+    }
+
+    #[test]
+    fn test_load_circular() {
+        let mut env = initialize_environment();
+        env.file = Some(std::path::PathBuf::from("tests/load.crisp"));
+
+        let list = list![sym!("load"), str!("lib/cycle_a.crisp")];
+        crisp_assert_err!(eval(&list, &mut env), StandardError);
+
+        // Nothing is left marked as loading after the error
+        assert!(env.loading.is_empty());
+
+        // Loading the same file twice in a row is not circular
+        let list = list![sym!("load"), str!("lib/helpers.crisp")];
+        eval(&list, &mut env).unwrap();
+        eval(&list, &mut env).unwrap();
+    }
+
+    // delay keyword
+
+    #[test]
+    fn test_delay() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(20.0));
+
+        // The expression isn't evaluated yet, so this doesn't error
+        let list = list![sym!("delay"), list![sym!("undefined-function")]];
+        assert!(matches!(eval(&list, &mut env).unwrap(), Delay(_)));
+
+        // The scope is captured when the Delay is made
+        let list = list![sym!("delay"), list![sym!("+"), sym!("n"), Number(1.0)]];
+        let delay = eval(&list, &mut env).unwrap();
+        env.data.insert("n".to_string(), Number(0.0));
+
+        let call = list![sym!("force"), delay];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(21.0));
+
+        crisp_assert_err!(eval(&list![sym!("delay")], &mut env), ArgumentError);
+    }
+
+    // generator keyword
+
+    #[test]
+    fn test_generator() {
+        let mut env = initialize_environment();
+
+        // The body isn't evaluated until a value is asked for
+        let list = list![sym!("generator"), list![sym!("undefined-function")]];
+        let generator = eval(&list, &mut env).unwrap();
+        assert!(matches!(generator, Generator(_)));
+        crisp_assert_err!(eval(&list![sym!("next"), generator], &mut env), ParseError);
+
+        let list = list![
+            sym!("generator"),
+            list![sym!("yield"), Number(1.0)],
+            list![sym!("yield"), Number(2.0)]
+        ];
+        let generator = eval(&list, &mut env).unwrap();
+        let call = list![sym!("next"), generator];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(1.0));
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(2.0));
+        assert_eq!(eval(&call, &mut env).unwrap(), Nil);
+        assert_eq!(eval(&call, &mut env).unwrap(), Nil);
+
+        crisp_assert_err!(eval(&list![sym!("generator")], &mut env), ArgumentError);
+    }
+
+    // spawn keyword
+
+    #[test]
+    fn test_spawn() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(20.0));
+
+        let list = list![sym!("spawn"), list![sym!("+"), sym!("n"), Number(1.0)]];
+        let task = eval(&list, &mut env).unwrap();
+        assert!(matches!(task, Task(_)));
+
+        let call = list![sym!("await"), task];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(21.0));
+
+        // Variables set in the task don't leak out
+        let list = list![sym!("spawn"), list![sym!("let"), sym!("n"), Number(0.0)]];
+        let call = list![sym!("await"), eval(&list, &mut env).unwrap()];
+        assert_eq!(eval(&call, &mut env).unwrap(), Number(0.0));
+        assert_eq!(env.data.get("n").unwrap(), &Number(20.0));
+    }
+
+    #[test]
+    fn test_with_retry() {
+        let mut env = initialize_environment();
+        eval(&"let tries (atom 0)".parse().unwrap(), &mut env).unwrap();
+
+        // Fails twice, then succeeds
+        let expr = "(with-retry 3 0 (swap! tries (\\ n (+ n 1))) \
+                     (if (< (deref tries) 3) (nope) (deref tries)))";
+        assert_eq!(eval(&expr.parse().unwrap(), &mut env).unwrap(), Number(3.0));
+
+        // Gives up after the last attempt
+        eval(&"reset! tries 0".parse().unwrap(), &mut env).unwrap();
+        let expr = "(with-retry 2 0 (swap! tries (\\ n (+ n 1))) (nope))";
+        crisp_assert_err!(eval(&expr.parse().unwrap(), &mut env), ParseError);
+        assert_eq!(eval(&"deref tries".parse().unwrap(), &mut env).unwrap(), Number(2.0));
+
+        crisp_assert_err!(eval(&"with-retry 0 0 1".parse().unwrap(), &mut env), StandardError);
+        crisp_assert_err!(eval(&"with-retry 1 -5 1".parse().unwrap(), &mut env), IndexError);
+        crisp_assert_err!(eval(&"with-retry 1 0".parse().unwrap(), &mut env), ArgumentError);
+    }
+
+    #[test]
+    fn test_with_timeout() {
+        let mut env = initialize_environment();
+        env.data.insert("n".to_string(), Number(2.0));
+
+        let expr = "with-timeout 5000 (let m 3) (* n m)";
+        assert_eq!(eval(&expr.parse().unwrap(), &mut env).unwrap(), Number(6.0));
+        // Evaluated in a copy of the scope
+        assert!(!env.data.contains_key("m"));
+
+        // Errors are passed on
+        crisp_assert_err!(eval(&"with-timeout 5000 (nope)".parse().unwrap(), &mut env),
+                          ParseError);
+
+        // Waits on a channel that never receives anything
+        let expr = "with-timeout 10 (recv! (chan))";
+        crisp_assert_err!(eval(&expr.parse().unwrap(), &mut env), StandardError);
+
+        crisp_assert_err!(eval(&"with-timeout 'a' 1".parse().unwrap(), &mut env), TypeError);
+    }
 }
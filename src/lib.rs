@@ -0,0 +1,405 @@
+//! crisp, a Lisp interpreter. The `crisp` binary is a thin command line
+//! interface over this library; see `src/main.rs`.
+
+#[macro_use]
+#[allow(unused_imports, unused_macros)]
+mod macros;
+
+#[macro_use]
+#[allow(unused_imports, unused_macros)]
+pub mod error;
+
+// Counts allocations for the REPL's `:memory` command
+#[cfg(feature = "alloc-stats")]
+mod alloc_stats;
+pub mod ast;
+pub mod bundle;
+pub mod cache;
+pub mod color;
+pub mod debugger;
+pub mod diagnostic;
+pub mod env;
+pub mod eval;
+pub mod expr;
+pub mod functions;
+pub mod host;
+pub mod keywords;
+pub mod lint;
+pub mod lsp;
+pub mod package;
+// Used when embedding crisp, rather than by the CLI
+#[cfg(any(test, feature = "playground"))]
+pub mod playground;
+pub mod project;
+pub mod reader;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod repl;
+pub mod tags;
+pub mod test_runner;
+pub mod warning;
+pub mod watch;
+pub mod xref;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use colored::*;
+use snailquote::escape;
+
+use diagnostic::{Diagnostic, ErrorLocation, WarningDiagnostic};
+use env::{CrispEnv, initialize_environment};
+use error::{CrispError, catch_panic};
+use eval::{ErrorSource, eval, resolve, take_error_source, with_source_map};
+use expr::CrispExpr;
+use functions::FAIL_ERR_CODE;
+use host::CrispHost;
+use reader::{locate, parse, tokenize, Reader, Span};
+use warning::take_warnings;
+
+
+/// Runs the file `filename` expression-by-expression in a fresh environment,
+/// with `args` as its command line arguments.
+///
+/// If the last expression in the file returns a [`Number`](CrispExpr), it is
+/// used as the exit code, unless an error was raised along the way, in which
+/// case the exit code is 1. Errors are reported with [`report_error()`], along
+/// with where in the file they were raised.
+///
+/// The program is given `host` (see [`CrispHost`]). If its
+/// [`exit`](CrispHost::exit) doesn't end the process, the program is stopped
+/// instead, with the code it exited with as the exit code.
+///
+/// # Returns
+///
+/// The exit code for the process, or a [`CrispError`] if the file couldn't be
+/// read.
+pub fn run_file(
+    filename: &str,
+    args: &[String],
+    debug: bool,
+    strict: bool,
+    host: Arc<dyn CrispHost>
+) -> Result<i32, CrispError> {
+    let exprs = read_numbered_exprs(filename)?;
+    Ok(run_exprs(filename, &exprs, args, debug, strict, host))
+}
+
+/// Runs the numbered top-level expressions `exprs` of the file `filename`, as
+/// read by [`read_numbered_exprs()`], for [`run_file()`].
+///
+/// # Returns
+///
+/// The exit code for the process.
+pub fn run_exprs(
+    filename: &str,
+    exprs: &[(usize, String)],
+    args: &[String],
+    debug: bool,
+    strict: bool,
+    host: Arc<dyn CrispHost>
+) -> i32 {
+    let mut env = file_environment(filename).with_args(args).with_host(host.clone());
+
+    let mut ret = CrispExpr::Nil;
+    let mut failed = false;
+    for (i, parsed) in cache::parse_exprs(Path::new(filename), exprs).into_iter().enumerate() {
+        take_error_source();
+
+        let result = parsed.and_then(|(ast, mut map)| {
+            map.file.clone_from(&env.file);
+            with_source_map(&Arc::new(map), || process_ast(ast, &mut env, debug))
+        });
+        report_warnings(Some(filename), exprs, i);
+
+        ret = match result {
+            Ok(ret) => ret,
+            Err(_) if host.exit_code().is_some() => break,
+            Err(e) => {
+                let location = error_location(env.file.as_deref(), exprs, i);
+                if let Some(code) = report_error(&e, Some(filename), location, strict) {
+                    return code;
+                }
+
+                failed = true;
+                CrispExpr::Nil
+            }
+        };
+    }
+
+    match host.exit_code() {
+        Some(code) => code,
+        None if failed => 1,
+        None => exit_code(&ret)
+    }
+}
+
+/// Runs a program read from stdin in a fresh environment. Each expression is
+/// evaluated as soon as it has been read, so the program is never held in
+/// memory all at once. Expressions are read with a [`Reader`], so unlike in a
+/// file, the outer parens can't be left off.
+///
+/// Otherwise, this behaves the same as [`run_file()`].
+pub fn run_stdin(args: &[String], debug: bool, strict: bool) -> Result<i32, CrispError> {
+    let mut env = initialize_environment().with_args(args);
+
+    let mut ret = CrispExpr::Nil;
+    let mut failed = false;
+    for expr in Reader::new(io::stdin().lock()) {
+        let result = expr.and_then(|ast| process_ast(ast, &mut env, debug));
+        report_warnings(None, &[], 0);
+
+        ret = match result {
+            Ok(ret) => ret,
+            Err(e) => {
+                if let Some(code) = report_error(&e, None, None, strict) {
+                    return Ok(code);
+                }
+
+                failed = true;
+                CrispExpr::Nil
+            }
+        };
+    }
+
+    Ok(if failed { 1 } else { exit_code(&ret) })
+}
+
+/// Prints an error raised by a top-level expression in `file` (or stdin) to
+/// stderr as a [`Diagnostic`], along with its `location` in the source code if
+/// it's known.
+///
+/// # Returns
+///
+/// `Some(code)` if the program should stop and exit with `code`, which is the
+/// case for any error in `strict` mode, or for a failed assertion. Otherwise
+/// `None`, and evaluation carries on with the next expression (though the
+/// program will still exit with an error).
+fn report_error(
+    e: &CrispError,
+    file: Option<&str>,
+    location: Option<ErrorLocation>,
+    strict: bool
+) -> Option<i32> {
+    eprintln!("{}", Diagnostic { error: e, file, location });
+
+    if strict || matches!(e, CrispError::AssertionError(_)) {
+        Some(error_code(e))
+    } else {
+        None
+    }
+}
+
+/// Prints the warnings recorded while evaluating a top-level expression in
+/// `file` (or stdin) to stderr, each as a [`WarningDiagnostic`] along with its
+/// location, found like that of an error (see [`expr_location()`]).
+fn report_warnings(file: Option<&str>, exprs: &[(usize, String)], current: usize) {
+    for (warning, expr) in take_warnings() {
+        let location = expr.and_then(|expr| expr_location(exprs, current, Some(expr)));
+        eprintln!("{}", WarningDiagnostic { warning: &warning, file, location });
+    }
+}
+
+/// Finds where in the file `file` the last error was raised, given the
+/// numbered top-level expressions of the file, of which the one at `current`
+/// was being evaluated. If the expression that raised it wasn't read from
+/// `file` (e.g. it was in a function defined by a file that was loaded), or
+/// its span isn't known (e.g. it was made by a macro), the whole of `current`
+/// is given instead.
+fn error_location(
+    file: Option<&Path>,
+    exprs: &[(usize, String)],
+    current: usize
+) -> Option<ErrorLocation> {
+    let source = take_error_source().filter(|source| source.file.as_deref() == file);
+
+    match source {
+        Some(ErrorSource { expr, span: Some((start, end)), .. }) => {
+            Some(ErrorLocation { start, end, expr: Some(expr) })
+        },
+        _ => expr_location(exprs, current, None)
+    }
+}
+
+/// Finds where in a file the expression `source` is, e.g. the subject of a
+/// warning, given the numbered top-level expressions of the file, of which the
+/// one at `current` was being evaluated.
+///
+/// Expressions are only matched by value, so the location is only given if
+/// `source` appears exactly once in the file. Otherwise, or if it isn't known
+/// (e.g. for an error while parsing), the whole of `current` is given instead.
+fn expr_location(
+    exprs: &[(usize, String)],
+    current: usize,
+    source: Option<CrispExpr>
+) -> Option<ErrorLocation> {
+    let found: Vec<_> = source.iter().flat_map(|source| {
+        exprs.iter().flat_map(|(line, expr)| {
+            locate(expr, source).into_iter().map(|spans| (*line, spans))
+        })
+    }).collect();
+
+    let (Some(source), [(line, (start, end))]) = (source, &found[..]) else {
+        let (line, expr) = exprs.get(current)?;
+        let start = Span { line: *line, column: 1 };
+
+        return Some(ErrorLocation { start, end: start.last_of(expr.trim_end()), expr: None });
+    };
+
+    // Spans are relative to the start of the expression
+    let in_file = |span: Span| Span { line: line + span.line - 1, column: span.column };
+
+    Some(ErrorLocation { start: in_file(*start), end: in_file(*end), expr: Some(source) })
+}
+
+/// The exit code for a program stopped by the error `e`.
+pub fn error_code(e: &CrispError) -> i32 {
+    match e {
+        CrispError::AssertionError(_) => FAIL_ERR_CODE,
+        _ => 1
+    }
+}
+
+/// Initializes a fresh environment for evaluating the file `filename`. The
+/// file is kept by its canonical path where there is one, so that the files
+/// it loads are found relative to it even if it changes directory.
+pub fn file_environment(filename: &str) -> CrispEnv<'static> {
+    let mut env = initialize_environment();
+    let canonical = Path::new(filename).canonicalize();
+    env.file = Some(canonical.as_ref().map_or_else(|_| PathBuf::from(filename), Clone::clone));
+    env.loading.extend(canonical);
+
+    env
+}
+
+/// Reads the file `filename` and splits it into its top-level expressions. An
+/// expression continues onto the following lines for as long as they are
+/// indented.
+pub fn read_exprs<P>(filename: P) -> Result<Vec<String>, CrispError>
+where P: AsRef<Path>, {
+    Ok(read_numbered_exprs(filename)?.into_iter().map(|(_, expr)| expr).collect())
+}
+
+/// Reads the top-level expressions of the file `filename` like
+/// [`read_exprs()`], along with the line number that each begins on.
+pub fn read_numbered_exprs<P>(filename: P) -> Result<Vec<(usize, String)>, CrispError>
+where P: AsRef<Path>, {
+    match fs::read_to_string(&filename) {
+        Ok(source) => Ok(split_exprs(&source)),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            standard_error!(format!("Error reading file: {}", filename.as_ref().display()))
+        },
+        Err(_) => load_error!(filename.as_ref().display())
+    }
+}
+
+/// Splits the source code of a file into its top-level expressions, along
+/// with the line number that each begins on. An expression continues onto the
+/// following lines for as long as they are indented.
+pub fn split_exprs(source: &str) -> Vec<(usize, String)> {
+    let mut exprs = Vec::new();
+    let mut current_expr = String::new();
+    let mut current_line = 1;
+
+    // Build onto the current expression as long as the line is indented (or
+    // blank, e.g. within a heredoc)
+    for (i, str) in source.lines().enumerate() {
+        if !current_expr.is_empty() && !str.is_empty()
+           && !str.starts_with(' ') && !str.starts_with('\t') {
+            exprs.push((current_line, current_expr.clone()));
+            current_expr.clear();
+        }
+
+        if current_expr.is_empty() {
+            current_line = i + 1;
+        }
+
+        // Blank lines within an expression are kept so that line numbers
+        // still match up with the file
+        if !str.is_empty() || !current_expr.is_empty() {
+            current_expr.push_str(str);
+            current_expr.push('\n');
+        }
+    }
+
+    // There might be one more expression in the buffer
+    if !current_expr.is_empty() {
+        exprs.push((current_line, current_expr));
+    }
+
+    exprs
+}
+
+/// Converts the return value of a program into an exit code. A
+/// [`Number`](CrispExpr) is rounded to the nearest integer; anything else
+/// exits successfully.
+fn exit_code(ret: &CrispExpr) -> i32 {
+    match ret {
+        CrispExpr::Number(n) => n.round() as i32,
+        _ => 0
+    }
+}
+
+/// Evaluates an already-parsed top-level expression, printing the return if
+/// `print_ret` is set.
+fn process_ast(ast: CrispExpr, env: &mut CrispEnv, print_ret: bool) -> Result<CrispExpr, CrispError> {
+    let ret = eval_top_level(ast, env)?;
+    if print_ret {
+        print_return(&ret);
+    }
+    Ok(ret)
+}
+
+/// Parses and evaluates an expression from a Rust [`String`]. This never
+/// panics; if the interpreter panics, an `InternalError` is returned
+/// instead.
+pub fn send(input: String, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    catch_panic(|| {
+        let (ast, _) = parse(&tokenize(input))?;
+        eval_top_level(ast, env)
+    })
+}
+
+/// Evaluates a top-level expression. A lone [`Symbol`](CrispExpr) may be a
+/// call to a function that takes no arguments.
+pub fn eval_top_level(ast: CrispExpr, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    match ast {
+        CrispExpr::Symbol(_) => {
+            match resolve(&[ast.clone()], env) {
+                Some(response) => response,
+                None => eval(&ast, env)
+            }
+        }
+        _ => eval(&ast, env),
+    }
+}
+
+/// Prints the return value from the [`CrispExpr`] `ret` in its
+/// [`inspect`](CrispExpr::inspect)ed form, with a colored indicator preceding
+/// it.
+pub fn print_return(ret: &CrispExpr) {
+    let ret_indicator = "=> ".bright_green();
+
+    println!("{}{}", ret_indicator, ret.inspect());
+}
+
+/// Escapes a string literal for display e.g. in the REPL return or displaying
+/// a [`List`](CrispExpr) containing [`String`](CrispExpr)s.
+///
+/// # Examples
+///
+/// ```
+/// let str = escape_string("foo");
+/// assert_eq!(str, "'foo'".to_string());
+///
+/// let str = escape_string("a'b");
+/// assert_eq!(str, "\"a'b\"".to_string());
+/// ```
+pub fn escape_string(str: &str) -> String {
+    match escape(&str) {
+        escaped if &escaped == str => format!("'{}'", escaped),
+        escaped => escaped.to_string()
+    }
+
+}
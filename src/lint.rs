@@ -0,0 +1,519 @@
+use crate::{diagnostic::{Diagnostic, ErrorLocation, WarningDiagnostic},
+            env::{builtin_func, builtin_names, env_builtin, PLACEHOLDER}, error::CrispError,
+            eval::spread_name, expr::CrispExpr, expr_location, keywords::SPECIAL_FORMS,
+            reader::{parse, tokenize}, split_exprs, test_runner::find_crisp_files,
+            warning::{allowed, CrispWarning}};
+
+use std::{collections::{HashMap, HashSet}, fs, path::Path};
+
+/// The keywords that bind a name in the scope that they're used in.
+const BINDERS: &[&str] = &["let", "const", "shadow", "defparam", "fn"];
+
+/// Something found by [`lint()`]: a [`CrispWarning`], or a `ParseError` for
+/// code that couldn't be read at all.
+#[derive(Debug, PartialEq)]
+pub enum Finding {
+    Warning(CrispWarning),
+    Error(CrispError)
+}
+
+/// Runs `crisp lint`, which checks the `.crisp` files at `path` for likely
+/// mistakes without running them (see [`lint()`]), and prints what it finds.
+/// Kinds of warning can be left out with `--allow`, like those raised while
+/// running a program.
+///
+/// # Returns
+///
+/// The exit code for the process: `0` if nothing was found, otherwise `1`, so
+/// that it can be used to fail a CI build.
+pub fn run(path: &Path) -> i32 {
+    let files = match find_crisp_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut found = 0;
+    for file in files {
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Couldn't read {}: {}", file.display(), e);
+                return 1;
+            }
+        };
+
+        let file = file.display().to_string();
+        for (finding, location) in lint(&source) {
+            match finding {
+                Finding::Warning(warning) if allowed(&warning) => continue,
+                Finding::Warning(warning) => {
+                    let file = Some(file.as_str());
+                    println!("{}", WarningDiagnostic { warning: &warning, file, location });
+                },
+                Finding::Error(error) => {
+                    println!("{}", Diagnostic { error: &error, file: Some(&file), location });
+                }
+            }
+
+            found += 1;
+        }
+    }
+
+    match found {
+        0 => 0,
+        _ => {
+            eprintln!("Found {} problem{}.", found, if found == 1 { "" } else { "s" });
+            1
+        }
+    }
+}
+
+/// Checks the source code of a file for likely mistakes, without evaluating
+/// it:
+///
+/// * Names that aren't bound anywhere, unless the file `load`s or `require`s
+///   another (which might bind them).
+/// * Calls to builtins, and to functions defined with `fn` at the top level,
+///   with the wrong number of arguments.
+/// * Names bound with `let` or `const` within a function, and arguments of
+///   functions, which are never used. As with
+///   [`warn()`](crate::warning::warn), names starting with `_` are left alone.
+/// * `if`s whose predicate is `true` or `false`, so that one branch is never
+///   taken.
+/// * Builtins rebound with `shadow`.
+///
+/// # Returns
+///
+/// What was found, in the order it appears in the file, along with where.
+pub fn lint(source: &str) -> Vec<(Finding, Option<ErrorLocation>)> {
+    let exprs = split_exprs(source);
+    let parsed: Vec<_> = exprs.iter().map(|(_, expr)| {
+        parse(&tokenize(expr.clone())).map(|(expr, _)| expr)
+    }).collect();
+
+    let mut linter = Linter::new(parsed.iter().flatten());
+    let mut findings = Vec::new();
+
+    for (i, expr) in parsed.into_iter().enumerate() {
+        match expr {
+            Ok(expr) => {
+                linter.walk(&expr);
+                // Everything found is within this expression, so it's only
+                // looked for there
+                let current = &exprs[i..=i];
+                findings.extend(linter.found.drain(..).map(|(warning, target)| {
+                    (Finding::Warning(warning), expr_location(current, 0, Some(target)))
+                }));
+            },
+
+            Err(e) => findings.push((Finding::Error(e), expr_location(&exprs, i, None)))
+        }
+    }
+
+    findings
+}
+
+/// Walks the expressions of a file for [`lint()`], keeping track of what's
+/// in scope.
+struct Linter {
+    /// The names of the builtins.
+    builtins: HashSet<String>,
+    /// The names that can be seen everywhere in the file: the builtins, the
+    /// special forms, and whatever the file binds at the top level.
+    globals: HashSet<String>,
+    /// The number of arguments that each function with a known arity takes.
+    arities: HashMap<String, (usize, Option<usize>)>,
+    /// Whether names that aren't bound are reported. See [`lint()`].
+    check_unbound: bool,
+    /// The names bound in each function that is being walked, innermost last.
+    scopes: Vec<HashSet<String>>,
+    /// The warnings found so far, each with the expression it's about.
+    found: Vec<(CrispWarning, CrispExpr)>
+}
+
+impl Linter {
+    /// Prepares to lint the top-level expressions `exprs` of a file.
+    fn new<'a>(exprs: impl Iterator<Item = &'a CrispExpr> + Clone) -> Self {
+        let mut arities: HashMap<String, (usize, Option<usize>)> = builtin_names()
+            .filter_map(|name| {
+                let builtin = builtin_func(name).and_then(env_builtin)?;
+                Some((name.to_string(), (builtin.min_args, builtin.max_args)))
+            }).collect();
+
+        let builtins: HashSet<String> = builtin_names().map(str::to_string).collect();
+        let mut globals = builtins.clone();
+        globals.extend(SPECIAL_FORMS.iter().map(|(name, _)| name.to_string()));
+
+        let mut top_level = Vec::new();
+        for expr in exprs.clone() {
+            definitions(expr, &mut top_level);
+        }
+
+        // A function's arity is only known if it's defined just the once
+        for (_, name, _) in &top_level {
+            arities.remove(*name);
+        }
+        for (keyword, name, expr) in &top_level {
+            if let (&"fn", CrispExpr::List(list)) = (keyword, expr) {
+                let arity = params(&list[2]).map(|params| params.len());
+                if top_level.iter().filter(|(_, other, _)| other == name).count() == 1 {
+                    arities.extend(arity.map(|n| (name.to_string(), (n, Some(n)))));
+                }
+            }
+        }
+
+        globals.extend(top_level.iter().map(|(_, name, _)| name.to_string()));
+
+        Linter {
+            builtins,
+            globals,
+            arities,
+            check_unbound: !exprs.into_iter().any(|expr| {
+                mentions(expr, "load") || mentions(expr, "require")
+            }),
+            scopes: Vec::new(),
+            found: Vec::new()
+        }
+    }
+
+    /// Records `warning` about `expr`, unless it's already been found.
+    fn report(&mut self, warning: CrispWarning, expr: &CrispExpr) {
+        let found = (warning, expr.clone());
+        if !self.found.contains(&found) {
+            self.found.push(found);
+        }
+    }
+
+    /// Checks whether `name` is bound within one of the functions being
+    /// walked.
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    /// Checks an expression, and everything within it.
+    fn walk(&mut self, expr: &CrispExpr) {
+        match expr {
+            CrispExpr::Symbol(name) => {
+                self.check_bound(spread_name(expr).unwrap_or(name), expr)
+            },
+
+            CrispExpr::List(list) => match &list[..] {
+                [CrispExpr::Symbol(head), args @ ..] if !self.is_local(head) => {
+                    self.walk_form(head, args, expr)
+                },
+                _ => self.walk_all(list)
+            },
+
+            _ => ()
+        }
+    }
+
+    fn walk_all(&mut self, exprs: &[CrispExpr]) {
+        exprs.iter().for_each(|expr| self.walk(expr));
+    }
+
+    /// Checks `expr`, a list starting with the name `head` followed by `args`,
+    /// which is either one of the [`SPECIAL_FORMS`] or a function call.
+    fn walk_form(&mut self, head: &str, args: &[CrispExpr], expr: &CrispExpr) {
+        match (head, args) {
+            ("if", [CrispExpr::Bool(always), ..]) => {
+                self.report(CrispWarning::UnreachableBranch(*always), expr);
+                self.walk_all(args);
+            },
+
+            ("shadow", [CrispExpr::Symbol(name), value @ ..]) => {
+                if self.builtins.contains(&**name) && !self.is_local(name) {
+                    self.report(CrispWarning::ShadowedBuiltin(name.to_string()), expr);
+                }
+                self.walk_all(value);
+            },
+
+            // The name is being bound, not looked up
+            ("let" | "const" | "defparam", [_, value @ ..]) => self.walk_all(value),
+
+            ("fn", [CrispExpr::Symbol(name), params, body @ ..]) => {
+                self.walk_lambda(Some(name), params, body)
+            },
+            ("\\", [params, body @ ..]) => self.walk_lambda(None, params, body),
+
+            ("letrec", [bindings, body @ ..]) => {
+                let pairs = match bindings {
+                    CrispExpr::List(list) if matches!(list.first(), Some(CrispExpr::Symbol(_))) => {
+                        std::slice::from_ref(bindings)
+                    },
+                    CrispExpr::List(list) => list,
+                    _ => &[]
+                };
+
+                let mut scope = HashSet::new();
+                for pair in pairs {
+                    if let CrispExpr::List(pair) = pair {
+                        if let [CrispExpr::Symbol(name), ..] = &pair[..] {
+                            scope.insert(name.to_string());
+                        }
+                    }
+                }
+
+                self.scopes.push(scope);
+                for pair in pairs {
+                    if let CrispExpr::List(pair) = pair {
+                        self.walk_all(pair.get(1..).unwrap_or_default());
+                    }
+                }
+                self.walk_all(body);
+                self.scopes.pop();
+            },
+
+            // The keys of the arms aren't evaluated
+            ("case", [value, arms @ ..]) => {
+                self.walk(value);
+                for arm in arms {
+                    if let CrispExpr::List(arm) = arm {
+                        self.walk_all(arm.get(1..).unwrap_or_default());
+                    }
+                }
+            },
+
+            ("match-str", [value, arms @ ..]) => {
+                self.walk(value);
+                for arm in arms {
+                    if let CrispExpr::List(arm) = arm {
+                        self.walk_match_arm(arm);
+                    }
+                }
+            },
+
+            // The name of the error type isn't looked up
+            ("assert-raises", [body, ..]) => self.walk(body),
+
+            // Nor the name of the test
+            ("deftest" | "describe", [_, body @ ..]) => self.walk_all(body),
+
+            _ => {
+                self.check_bound(head, &sym!(head));
+
+                // A list spread into the arguments could be any length
+                let spread = args.iter().any(|arg| spread_name(arg).is_some());
+
+                if let (Some(&(min, max)), false) = (self.arities.get(head), spread) {
+                    let given = args.len();
+                    if given < min || max.is_some_and(|max| given > max) {
+                        let name = head.to_string();
+                        self.report(CrispWarning::ArityMismatch { name, given, min, max }, expr);
+                    }
+                }
+
+                self.walk_all(args);
+            }
+        }
+    }
+
+    /// Checks a function with the arguments `args` (and the name `name`, if it
+    /// has one), which evaluates `body`.
+    fn walk_lambda(&mut self, name: Option<&str>, args: &CrispExpr, body: &[CrispExpr]) {
+        let Some(params) = params(args) else {
+            return self.walk_all(body);
+        };
+
+        for param in &params {
+            if !param.starts_with(['_', '%']) && !body.iter().any(|expr| mentions(expr, param)) {
+                let warning = CrispWarning::UnusedArgument(param.to_string(),
+                                                           name.map(str::to_string));
+                self.report(warning, &sym!(param));
+            }
+        }
+
+        let mut local = Vec::new();
+        for expr in body {
+            definitions(expr, &mut local);
+        }
+
+        for (keyword, name, expr) in &local {
+            // The name itself is one mention
+            let uses: usize = body.iter().map(|expr| count_mentions(expr, name)).sum();
+            if matches!(*keyword, "let" | "const") && !name.starts_with('_') && uses < 2 {
+                self.report(CrispWarning::UnusedBinding(name.to_string()), expr);
+            }
+        }
+
+        let scope = params.into_iter().chain(local.iter().map(|(_, name, _)| *name))
+                                      .map(str::to_string)
+                                      .collect();
+        self.scopes.push(scope);
+        self.walk_all(body);
+        self.scopes.pop();
+    }
+
+    /// Checks an arm of `match-str`: `(pattern body)`, or
+    /// `(pattern (captures...) body)`. The names of the captures, and of any
+    /// named groups in the pattern, are bound in the body.
+    fn walk_match_arm(&mut self, arm: &[CrispExpr]) {
+        let mut scope = HashSet::new();
+
+        if let Some(CrispExpr::CrispString(pattern)) = arm.first() {
+            for part in pattern.split("(?P<").skip(1).chain(pattern.split("(?<").skip(1)) {
+                scope.extend(part.split_once('>').map(|(name, _)| name.to_string()));
+            }
+        }
+
+        let body = match arm {
+            [_, captures, body @ ..] if !body.is_empty() => {
+                scope.extend(params(captures).into_iter().flatten().map(str::to_string));
+                body
+            },
+            _ => arm.get(1..).unwrap_or_default()
+        };
+
+        self.scopes.push(scope);
+        self.walk_all(body);
+        self.scopes.pop();
+    }
+
+    /// Reports `name` if it isn't bound. `expr` is where it appears.
+    fn check_bound(&mut self, name: &str, expr: &CrispExpr) {
+        if self.check_unbound && name != PLACEHOLDER && !self.globals.contains(name)
+           && !self.is_local(name) {
+            self.report(CrispWarning::UnboundSymbol(name.to_string()), expr);
+        }
+    }
+}
+
+/// The names of the arguments of a function, which may be a single
+/// [`Symbol`](CrispExpr) or a [`List`](CrispExpr) of them, or `None` if they
+/// aren't valid.
+fn params(params: &CrispExpr) -> Option<Vec<&str>> {
+    match params {
+        CrispExpr::Symbol(name) => Some(vec![name]),
+        CrispExpr::List(list) => list.iter().map(|param| match param {
+            CrispExpr::Symbol(name) => Some(&**name),
+            _ => None
+        }).collect(),
+        _ => None
+    }
+}
+
+/// Collects the names bound by `expr` in the scope it's evaluated in, with the
+/// keyword that binds each and the whole expression that binds it: those
+/// bound with one of the [`BINDERS`], other than within a function (which has
+/// its own scope).
+fn definitions<'a>(expr: &'a CrispExpr, found: &mut Vec<(&'a str, &'a str, &'a CrispExpr)>) {
+    let CrispExpr::List(list) = expr else {
+        return;
+    };
+
+    match &list[..] {
+        [CrispExpr::Symbol(head), ..] if &**head == "\\" || &**head == "letrec" => (),
+
+        [CrispExpr::Symbol(head), CrispExpr::Symbol(name), rest @ ..]
+        if BINDERS.contains(&&**head) => {
+            found.push((head, name, expr));
+            if &**head != "fn" {
+                rest.iter().for_each(|expr| definitions(expr, found));
+            }
+        },
+
+        _ => list.iter().for_each(|expr| definitions(expr, found))
+    }
+}
+
+/// Checks whether the symbol `name` appears anywhere within `expr`.
+fn mentions(expr: &CrispExpr, name: &str) -> bool {
+    count_mentions(expr, name) > 0
+}
+
+/// Counts how many times the symbol `name` appears within `expr`, including
+/// spread into a call, e.g. as `@name`.
+fn count_mentions(expr: &CrispExpr, name: &str) -> usize {
+    match expr {
+        CrispExpr::Symbol(symbol) => {
+            usize::from(&**symbol == name || spread_name(expr) == Some(name))
+        },
+        CrispExpr::List(list) => list.iter().map(|expr| count_mentions(expr, name)).sum(),
+        _ => 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The names of the warnings found in `source`, with the line and column
+    /// of each.
+    fn found(source: &str) -> Vec<(&'static str, usize, usize)> {
+        lint(source).into_iter().map(|(finding, location)| {
+            let name = match finding {
+                Finding::Warning(warning) => warning.name(),
+                Finding::Error(error) => error.name()
+            };
+            let start = location.unwrap().start;
+
+            (name, start.line, start.column)
+        }).collect()
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        let source = "fn square n (* n n)\n\
+                      fn area (w h)\n  (let a (* w h))\n  (puts \"Area: $a\")\n  a\n\
+                      let xs (map #(square %) [1 2 3])\n\
+                      puts (later 5)\n\
+                      fn later _x (case 1 (1 'one') (else (letrec (go (\\ i (go i))) go)))\n\
+                      match-str 'a=1' ('(?P<k>\\w)=(\\d)' (_ v) (puts k v)) (_ nil)\n\
+                      assert-raises (sqrt 'a') TypeError\n";
+
+        assert_eq!(found(source), vec![]);
+    }
+
+    #[test]
+    fn test_lint_unbound() {
+        assert_eq!(found("fn f x (+ x y)\nputs (g 1)"), vec![
+            ("unbound-symbol", 1, 13),
+            ("unbound-symbol", 2, 7)
+        ]);
+
+        // Arguments and local bindings are only seen within their function
+        assert_eq!(found("fn f (x) (let y x)\n  y\nputs x y"), vec![
+            ("unbound-symbol", 3, 6),
+            ("unbound-symbol", 3, 8)
+        ]);
+
+        // Another file might bind anything
+        assert_eq!(found("load 'lib.crisp'\nputs (g 1)"), vec![]);
+    }
+
+    #[test]
+    fn test_lint_arity() {
+        assert_eq!(found("sqrt 1 2\nputs (+ 1)\nfn f (a b) (+ a b)\nf 1\nlet xs [1]\nf @xs"), vec![
+            ("arity-mismatch", 1, 1),
+            ("arity-mismatch", 2, 6),
+            ("arity-mismatch", 4, 1)
+        ]);
+
+        // Not if the name might mean something else
+        assert_eq!(found("fn f (sqrt) (sqrt 1 2)\nfn g a a\nfn g (a _b) a\ng 1 2"), vec![]);
+    }
+
+    #[test]
+    fn test_lint_unused() {
+        assert_eq!(found("fn f (a b)\n  (let c a)\n  (let _d a)\n  b"), vec![
+            ("unused-binding", 2, 3)
+        ]);
+        assert_eq!(found("map (\\ (x y) x) [1]"), vec![("unused-argument", 1, 11)]);
+
+        // Top-level bindings might be used by another file
+        assert_eq!(found("let x 5"), vec![]);
+    }
+
+    #[test]
+    fn test_lint_other() {
+        assert_eq!(found("if true 1 2\nputs (if false 1 2)"), vec![
+            ("unreachable-branch", 1, 1),
+            ("unreachable-branch", 2, 6)
+        ]);
+        assert_eq!(found("shadow map 5"), vec![("shadowed-builtin", 1, 1)]);
+        assert_eq!(found("puts 1\nputs (+ 1"), vec![("ParseError", 2, 1)]);
+    }
+}
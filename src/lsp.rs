@@ -0,0 +1,408 @@
+use crate::{env::builtin_names, keywords::SPECIAL_FORMS, reader::{parse, tokenize, Span},
+            split_exprs, xref::{definitions, token_at}};
+
+use std::{collections::HashMap, io::{self, BufRead, Write}};
+
+use serde_json::{json, Value};
+
+/// Runs `crisp lsp`, a language server speaking the [Language Server
+/// Protocol](https://microsoft.github.io/language-server-protocol/) over stdin
+/// and stdout. It reports parse errors as diagnostics, finds the definitions
+/// of names bound with `fn`, `let`, `const` or `defmacro` within a file, and
+/// completes the names of builtins and keywords.
+///
+/// # Returns
+///
+/// The exit code for the process: `0` if the client shut the server down
+/// properly before it exited, otherwise `1`.
+pub fn run() -> i32 {
+    let mut server = Server::default();
+    let (mut input, mut output) = (io::stdin().lock(), io::stdout().lock());
+
+    loop {
+        let body = match read_message(&mut input) {
+            Ok(Some(body)) => body,
+            // The client went away without exiting
+            Ok(None) => return 1,
+            Err(e) => {
+                eprintln!("Couldn't read message: {}", e);
+                return 1;
+            }
+        };
+
+        let replies = match serde_json::from_slice::<Value>(&body) {
+            Ok(message) if message["method"] == "exit" => {
+                return if server.shut_down { 0 } else { 1 };
+            },
+            Ok(message) => server.handle(&message),
+            // There's no telling what it was, so carry on with the next one
+            Err(e) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": Value::Null,
+                "error": { "code": -32700, "message": format!("Parse error: {}", e) }
+            })]
+        };
+
+        for reply in replies {
+            if let Err(e) = write_message(&mut output, &reply) {
+                eprintln!("Couldn't write message: {}", e);
+                return 1;
+            }
+        }
+    }
+}
+
+/// Reads a message from the client: a `Content-Length` header, then a blank
+/// line, then that many bytes of JSON.
+///
+/// # Returns
+///
+/// The body of the message, which is yet to be parsed, or `None` at the end
+/// of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        // Other headers (i.e. `Content-Type`) can be ignored
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                                                     "missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    Ok(Some(body))
+}
+
+/// Writes a message to the client, framed the same way as [`read_message()`].
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// The state of the language server: the documents the client has open.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    /// Whether the client has asked the server to shut down, after which it
+    /// should exit with `0`.
+    shut_down: bool
+}
+
+impl Server {
+    /// Handles a message from the client, returning any messages that should
+    /// be sent back: the response if it's a request, and notifications such
+    /// as diagnostics.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = &message["id"];
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+
+        match message["method"].as_str().unwrap_or_default() {
+            "initialize" => vec![response(id, json!({
+                "capabilities": {
+                    // The full text is sent with every change
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "completionProvider": {}
+                },
+                "serverInfo": { "name": "crisp", "version": env!("CARGO_PKG_VERSION") }
+            }))],
+
+            "shutdown" => {
+                self.shut_down = true;
+                vec![response(id, Value::Null)]
+            },
+
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![publish_diagnostics(&uri, text)]
+            },
+
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes.and_then(|changes| changes.last())
+                                        .and_then(|change| change["text"].as_str()) else {
+                    return Vec::new();
+                };
+
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![publish_diagnostics(&uri, text)]
+            },
+
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, "")]
+            },
+
+            "textDocument/definition" => {
+                let location = self.documents.get(&uri).and_then(|text| {
+                    let position = &params["position"];
+                    let (line, character) = (position["line"].as_u64()?, position["character"].as_u64()?);
+                    let column = char_column(text, line as usize, character as usize);
+                    definition(text, line as usize, column)
+                });
+
+                vec![response(id, match location {
+                    Some(range) => json!({ "uri": uri, "range": range }),
+                    None => Value::Null
+                })]
+            },
+
+            "textDocument/completion" => vec![response(id, completions())],
+
+            // Unknown requests must be answered, but notifications can be ignored
+            method if !id.is_null() => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("Unknown method: {}", method) }
+            })],
+            _ => Vec::new()
+        }
+    }
+}
+
+/// A successful response to the request with the ID `id`.
+fn response(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+/// A notification giving the client the diagnostics for the document `uri`,
+/// whose text is `text`.
+fn publish_diagnostics(uri: &str, text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics(text) }
+    })
+}
+
+/// Parses each top-level expression of `text`, returning a diagnostic for
+/// each one that fails, covering the whole expression.
+fn diagnostics(text: &str) -> Vec<Value> {
+    split_exprs(text).into_iter().filter_map(|(line, expr)| {
+        let e = parse(&tokenize(expr.clone())).err()?;
+        let start = Span { line, column: 1 };
+
+        Some(json!({
+            "range": range(text, start, start.last_of(expr.trim_end())),
+            // Error
+            "severity": 1,
+            "source": "crisp",
+            "code": e.name(),
+            "message": e.message()
+        }))
+    }).collect()
+}
+
+/// Finds where the name at the given position of `text` (a zero-based line
+/// and character) was defined, returning the range of its name there.
+fn definition(text: &str, line: usize, column: usize) -> Option<Value> {
+    let name = token_at(text, Span { line: line + 1, column: column + 1 })?;
+
+    definitions(text).into_iter()
+                     .find(|definition| definition.name == name)
+                     .map(|definition| range(text, definition.span, definition.span.last_of(&name)))
+}
+
+/// Completion items for every builtin function and keyword.
+fn completions() -> Value {
+    let mut builtins: Vec<&str> = builtin_names().collect();
+    builtins.sort();
+
+    let functions = builtins.iter().map(|name| json!({ "label": name, "kind": 3 }));
+    let keywords = SPECIAL_FORMS.iter().map(|(name, _)| json!({ "label": name, "kind": 14 }));
+
+    Value::Array(functions.chain(keywords).collect())
+}
+
+/// An LSP range in `text`, which is zero-based and excludes its end, from the
+/// first character `start` to the last character `end`.
+fn range(text: &str, start: Span, end: Span) -> Value {
+    let (start_line, end_line) = (start.line - 1, end.line - 1);
+
+    json!({
+        "start": { "line": start_line, "character": utf16_offset(text, start_line, start.column - 1) },
+        "end": { "line": end_line, "character": utf16_offset(text, end_line, end.column) }
+    })
+}
+
+/// LSP counts the characters of a line in UTF-16 code units, so e.g. an emoji
+/// counts as two. This converts the first `chars` characters of the
+/// (zero-based) `line` of `text` to that.
+fn utf16_offset(text: &str, line: usize, chars: usize) -> usize {
+    match text.lines().nth(line) {
+        Some(line) => line.chars().take(chars).map(char::len_utf16).sum(),
+        None => chars
+    }
+}
+
+/// The opposite of [`utf16_offset()`]: how many characters of the
+/// (zero-based) `line` of `text` come before its UTF-16 offset `character`.
+fn char_column(text: &str, line: usize, character: usize) -> usize {
+    let Some(line) = text.lines().nth(line) else {
+        return character;
+    };
+
+    let mut units = 0;
+    line.chars().take_while(|ch| {
+        units += ch.len_utf16();
+        units <= character
+    }).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A range on a single (zero-based) line.
+    fn on_line(line: usize, start: usize, end: usize) -> Value {
+        json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end }
+        })
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "id": 1 })).unwrap();
+        write_message(&mut buffer, &json!({ "id": 2 })).unwrap();
+        assert_eq!(String::from_utf8(buffer.clone()).unwrap(),
+                   "Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 8\r\n\r\n{\"id\":2}");
+
+        let mut input = buffer.as_slice();
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"{\"id\":1}".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"{\"id\":2}".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut input = "Content-Type: text/plain\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert_eq!(diagnostics("let x 5\nputs x\n"), Vec::<Value>::new());
+
+        assert_eq!(diagnostics("let x 5\nlet y (+ x\n       1\nputs y\n"), vec![json!({
+            "range": {
+                "start": { "line": 1, "character": 0 },
+                "end": { "line": 2, "character": 8 }
+            },
+            "severity": 1,
+            "source": "crisp",
+            "code": "ParseError",
+            "message": "Couldn't find closing `)`."
+        })]);
+    }
+
+    #[test]
+    fn test_definition() {
+        let text = "fn square (n) (* n n)\nlet x 5\n\nputs (square\n  x)\n";
+
+        assert_eq!(definition(text, 3, 7), Some(on_line(0, 3, 9)));
+        assert_eq!(definition(text, 3, 6), Some(on_line(0, 3, 9)));
+        assert_eq!(definition(text, 3, 11), Some(on_line(0, 3, 9)));
+        assert_eq!(definition(text, 4, 2), Some(on_line(1, 4, 5)));
+
+        // Definitions are found from the name itself, too
+        assert_eq!(definition(text, 1, 4), Some(on_line(1, 4, 5)));
+
+        // Not defined in the file
+        assert_eq!(definition(text, 3, 1), None);
+        // Not on a name
+        assert_eq!(definition(text, 2, 0), None);
+    }
+
+    #[test]
+    fn test_utf16() {
+        // The emoji is two UTF-16 code units, but one character
+        let text = "let a '😀'\nputs '😀' (square a)";
+        assert_eq!(utf16_offset(text, 1, 10), 11);
+        assert_eq!(char_column(text, 1, 11), 10);
+        assert_eq!(char_column(text, 1, 6), 6);
+        assert_eq!(char_column(text, 1, 7), 6);
+        assert_eq!(char_column(text, 1, 8), 7);
+
+        // Positions from the client and ranges sent back are both in UTF-16
+        let text = "puts '😀' (let x 2)\nputs x";
+        assert_eq!(definition(text, 1, 5), Some(on_line(0, 15, 16)));
+        assert_eq!(definition(text, 0, char_column(text, 0, 15)), Some(on_line(0, 15, 16)));
+        assert_eq!(diagnostics("puts '😀' (+ 1")[0]["range"], on_line(0, 0, 14));
+    }
+
+    #[test]
+    fn test_completions() {
+        let completions = completions();
+        let items = completions.as_array().unwrap();
+
+        assert!(items.contains(&json!({ "label": "puts", "kind": 3 })));
+        assert!(items.contains(&json!({ "label": "fn", "kind": 14 })));
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut server = Server::default();
+
+        let replies = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                                             "params": {} }));
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["result"]["capabilities"]["definitionProvider"], true);
+
+        let replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///a.crisp", "text": "let x (+ 1" } }
+        }));
+        assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+        assert_eq!(replies[0]["params"]["diagnostics"].as_array().unwrap().len(), 1);
+
+        let replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": "file:///a.crisp" },
+                "contentChanges": [{ "text": "let x 1\nputs x" }]
+            }
+        }));
+        assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+
+        let replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/definition",
+            "params": {
+                "textDocument": { "uri": "file:///a.crisp" },
+                "position": { "line": 1, "character": 5 }
+            }
+        }));
+        assert_eq!(replies[0]["result"], json!({ "uri": "file:///a.crisp",
+                                                 "range": on_line(0, 4, 5) }));
+
+        // Unknown requests get an error, and unknown notifications are ignored
+        let replies = server.handle(&json!({ "jsonrpc": "2.0", "id": 3, "method": "foo" }));
+        assert_eq!(replies[0]["error"]["code"], -32601);
+        assert!(server.handle(&json!({ "jsonrpc": "2.0", "method": "foo" })).is_empty());
+
+        assert!(!server.shut_down);
+        let replies = server.handle(&json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }));
+        assert_eq!(replies[0]["result"], Value::Null);
+        assert!(server.shut_down);
+    }
+}
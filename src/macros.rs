@@ -1,12 +1,12 @@
 macro_rules! str {
     ($name:expr) => {
-        CrispExpr::CrispString($name.to_string())
+        CrispExpr::CrispString($name.to_string().into())
     }
 }
 
 macro_rules! sym {
     ($name:expr) => {
-        CrispExpr::Symbol($name.to_string())
+        CrispExpr::Symbol(::std::sync::Arc::from(AsRef::<str>::as_ref(&$name)))
     }
 }
 
@@ -18,10 +18,12 @@ macro_rules! list {
 
 macro_rules! lambda {
     (args: [$($arg:expr),*], func: [$($func:expr),*]) => {{
-        CrispExpr::Lambda(CrispLambda {
-            args: Rc::new(list![$(sym!($arg)),*]),
-            func: Rc::new(list![$($func),*])
-        })
+        CrispExpr::Lambda(Arc::new(CrispLambda {
+            args: Arc::new(list![$(sym!($arg)),*]),
+            func: Arc::new(list![$($func),*]),
+            name: None,
+            source: None
+        }))
     }};
 }
 
@@ -51,13 +53,13 @@ macro_rules! num_vec {
 
 macro_rules! string_list {
     ($($elem:expr),*) => {
-        CrispExpr::List(vec![$(CrispExpr::CrispString($elem.to_string())),*])
+        CrispExpr::List(vec![$(CrispExpr::CrispString($elem.to_string().into())),*])
     }
 }
 
 macro_rules! string_vec {
     ($($elem:expr),*) => {
-        vec![$(CrispExpr::CrispString($elem.to_string())),*]
+        vec![$(CrispExpr::CrispString($elem.to_string().into())),*]
     }
 }
 
@@ -1,158 +1,243 @@
-#[macro_use]
-#[allow(unused_imports, unused_macros)]
-mod macros;
-
-#[macro_use]
-#[allow(unused_imports, unused_macros)]
-mod error;
-
-mod env;
-mod eval;
-mod expr;
-mod functions;
-mod keywords;
-mod reader;
-mod repl;
-
-use std::fs::File;
-use std::io::{self, BufRead};
 use std::path::Path;
+use std::process;
+use std::sync::Arc;
 
-use clap::{arg, command, ArgMatches};
-use colored::*;
-use snailquote::escape;
+use clap::{arg, builder::PossibleValuesParser, command, ArgMatches, Command};
 
-use env::{CrispEnv, initialize_environment};
-use error::CrispError;
-use eval::{eval, resolve};
-use expr::CrispExpr;
-use reader::{parse, tokenize};
+use crisp::{bundle, cache, color, debugger, diagnostic::{self, Diagnostic}, error::CrispError,
+            error_code, eval, functions, host::NativeHost, lint, lsp, package, project, repl,
+            run_exprs, run_file, run_stdin, split_exprs, tags, test_runner,
+            warning::{self, CrispWarning}, watch};
 
 /// Parses the CLI arguments. See the [`clap`
 /// examples](https://github.com/clap-rs/clap/tree/master/examples)
 /// for more information.
 fn parse_args() -> ArgMatches {
     command!()
-        .arg(arg!([input] "File to run."))
+        .args_conflicts_with_subcommands(true)
+        .arg(arg!([input] "File to run, or `-` to read the program from stdin."))
         .arg(arg!(-d --debug ... "Display debug information"))
+        .arg(arg!(--debugger "Pause at each `break` to inspect the program"))
+        .arg(arg!(--strict "Stop at the first uncaught error, rather than carrying on"))
+        .arg(arg!(--"strict-calls" "Raise an error for lists that don't start with a function")
+                 .global(true))
+        .arg(arg!(--"lenient-math" "Give NaN rather than a MathError for e.g. `sqrt -1`")
+                 .global(true))
+        .arg(arg!(-w --watch "Re-run the file whenever it changes").requires("input"))
+        .arg(arg!(--"no-color" "Disable colored output").global(true))
+        .arg(arg!(--"plain-errors" "Print errors without any formatting, for comparing output")
+                 .global(true))
+        .arg(arg!(--"error-format" <FORMAT> "How to print errors")
+                 .value_parser(["human", "json"])
+                 .default_value("human")
+                 .global(true))
+        .arg(arg!(--"no-warnings" "Don't print any warnings").global(true))
+        .arg(arg!(--"no-cache" "Don't cache the parsed forms of files").global(true))
+        .arg(arg!(-A --allow <WARNING> ... "Don't print warnings of this kind")
+                 .value_parser(PossibleValuesParser::new(CrispWarning::NAMES.iter().copied()))
+                 .global(true))
+        .arg(arg!([args] ... "Arguments for the program, available to it with `args`")
+                 .trailing_var_arg(true)
+                 .requires("input"))
+        .subcommand(
+            Command::new("run")
+                .about("Run the main file of the project in this directory, as given by crisp.toml")
+                .arg(arg!(-d --debug ... "Display debug information"))
+                .arg(arg!(--strict "Stop at the first uncaught error, rather than carrying on"))
+                .arg(arg!([args] ... "Arguments for the program, available to it with `args`")
+                         .trailing_var_arg(true))
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Run the tests in a file or directory")
+                .arg(arg!([path] "File or directory to test").default_value("tests"))
+        )
+        .subcommand(
+            Command::new("tags")
+                .about("Write a tags file of the definitions in a file or directory")
+                .arg(arg!([path] "File or directory to scan").default_value("."))
+                .arg(arg!(-e --etags "Write an Emacs-style TAGS file instead"))
+                .arg(arg!(-o --output <FILE> "Where to write the tags, or `-` for stdout"))
+        )
+        .subcommand(
+            Command::new("bundle")
+                .about("Bundle a file and everything it loads into a single file or binary")
+                .arg(arg!([input] "File to bundle (default: the project's main file)"))
+                .arg(arg!(-o --output <FILE> "Where to write the bundle, or `-` for stdout"))
+                .arg(arg!(-b --binary "Embed the bundle into a copy of the interpreter"))
+        )
+        .subcommand(
+            Command::new("install")
+                .about("Install a library for `require`, and record it in crisp.deps")
+                .arg(arg!([source] "Git URL or directory of the library, or all in crisp.deps"))
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Check files for likely mistakes without running them")
+                .arg(arg!([path] "File or directory to check").default_value("."))
+        )
+        .subcommand(
+            Command::new("lsp")
+                .about("Start a language server, speaking LSP over stdin and stdout")
+        )
+        .subcommand(
+            Command::new("repl")
+                .about("Start the REPL (the default if no file is given)")
+                .arg(arg!(-p --preload <FILE> ... "Load a file before the first prompt"))
+        )
         .get_matches()
 }
 
-/// Main entry point for the program. Defers to [`repl::run()`] if there is no
-/// file given, otherwise runs the file.
-fn main() -> Result<(), CrispError> {
+/// Main entry point for the program. A binary written by
+/// `crisp bundle --binary` only runs the program embedded in it (see
+/// [`bundle::embedded()`]). Otherwise, this runs the project's main file, as
+/// found by [`project::prepare()`], for `crisp run`, bundles files with
+/// [`bundle::run()`] for `crisp bundle`, installs libraries with
+/// [`package::run()`] for `crisp install`, runs the tests with
+/// [`test_runner::run()`] for `crisp test`, writes tags with [`tags::run()`]
+/// for `crisp tags`, checks files with [`lint::run()`] for `crisp lint`, starts
+/// the language server with [`lsp::run()`] for `crisp lsp`, or defers to
+/// [`repl::run()`] for `crisp repl` or if there is no file given, otherwise
+/// runs the file (continually, with [`watch::run()`], if `--watch` is given).
+fn main() {
+    // A binary written by `crisp bundle --binary` only runs its program
+    let exe = std::env::current_exe();
+    if let Some(source) = exe.as_deref().ok().and_then(bundle::embedded) {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let filename = exe.as_deref().map(Path::display).unwrap().to_string();
+        let host = Arc::new(NativeHost);
+        process::exit(run_exprs(&filename, &split_exprs(&source), &args, false, false, host));
+    }
+
     let matches = parse_args();
 
     let debug = matches.get_one::<u8>("debug").unwrap() > &0;
+    let strict = matches.get_flag("strict");
 
-    if let Some(filename) = matches.get_one::<String>("input") {
-        if let Ok(lines) = read_lines(filename) {
-            let mut env = initialize_environment();
-
-            let mut current_expr = String::new();
-
-            // Build onto the current expression as long as the line is indented
-            for line in lines {
-                if let Ok(str) = line {
-                    if !current_expr.is_empty() && !str.starts_with(' ') && !str.starts_with('\t') {
-                        process_expr(&current_expr, &mut env, debug)?;
-                        current_expr.clear();
-                    }
-
-                    if !str.is_empty() {
-                        current_expr.push_str(&str);
-                        current_expr.push(' ');
-                    }
-                } else {
-                    return standard_error!(format!("Error reading file: {}", filename));
-                }
-            }
+    color::configure(matches.get_flag("no-color"));
+    color::configure_errors(matches.get_flag("plain-errors"));
+    diagnostic::configure(matches.get_one::<String>("error-format").unwrap());
+    let allowed = matches.get_many::<String>("allow")
+                         .map(|names| names.cloned().collect::<Vec<String>>())
+                         .unwrap_or_default();
+    warning::configure(matches.get_flag("no-warnings"), &allowed);
+    cache::configure(!matches.get_flag("no-cache"));
+    eval::configure_strict_calls(matches.get_flag("strict-calls"));
+    functions::configure_lenient_math(matches.get_flag("lenient-math"));
+    if matches.get_flag("debugger") {
+        debugger::enable();
+    }
 
-            // There might be one more expression in the buffer
-            if !current_expr.is_empty() {
-                process_expr(&current_expr, &mut env, debug)?;
+    if let Some(("run", run_matches)) = matches.subcommand() {
+        let args = run_matches.get_many::<String>("args")
+                              .map(|args| args.cloned().collect::<Vec<String>>())
+                              .unwrap_or_default();
+
+        let dir = std::env::current_dir().map_err(|e| CrispError::StandardError(e.to_string()));
+        match dir.and_then(|dir| project::prepare(&dir)) {
+            Ok(main) => run_and_exit(&main.display().to_string(), &args,
+                                     run_matches.get_one::<u8>("debug").unwrap() > &0,
+                                     run_matches.get_flag("strict")),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
             }
-        } else {
-            return load_error!(filename);
         }
-    } else {
-        repl::run();
     }
 
-    Ok(())
-}
+    if let Some(("test", test_matches)) = matches.subcommand() {
+        let path = test_matches.get_one::<String>("path").unwrap();
+        process::exit(test_runner::run(Path::new(path)));
+    }
 
-/// Reads the lines of a file specified by the provided `filename` and returns
-/// an iterator over the lines wrapped in an [`io::Result`] representing the
-/// success or failure of the operation.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where P: AsRef<Path>, {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
-}
+    if let Some(("tags", tags_matches)) = matches.subcommand() {
+        let path = tags_matches.get_one::<String>("path").unwrap();
+        let output = tags_matches.get_one::<String>("output").map(String::as_str);
+        process::exit(tags::run(Path::new(path), tags_matches.get_flag("etags"), output));
+    }
 
-/// Local function for parsing, evaluating, and then printing the return
-/// if `print_ret` is set.
-fn process_expr(expr: &String, env: &mut CrispEnv, print_ret: bool) -> Result<CrispExpr, CrispError> {
-    let ret = send(expr.clone(), env)?;
-    if print_ret {
-        print_return(&ret);
+    if let Some(("bundle", bundle_matches)) = matches.subcommand() {
+        let input = bundle_matches.get_one::<String>("input").map(String::as_str);
+        let output = bundle_matches.get_one::<String>("output").map(String::as_str);
+        process::exit(bundle::run(input, output, bundle_matches.get_flag("binary")));
     }
-    Ok(ret)
-}
 
-/// Parses and evaluates an expression from a Rust [`String`].
-pub fn send(input: String, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
-    let (ast, _) = parse(&tokenize(input))?;
+    if let Some(("install", install_matches)) = matches.subcommand() {
+        let source = install_matches.get_one::<String>("source").map(String::as_str);
+        process::exit(package::run(source));
+    }
 
-    match ast {
-        CrispExpr::Symbol(_) => {
-            match resolve(&[ast.clone()], env) {
-                Some(response) => response,
-                None => eval(&ast, env)
-            }
-        }
-        _ => eval(&ast, env),
+    if let Some(("lint", lint_matches)) = matches.subcommand() {
+        let path = lint_matches.get_one::<String>("path").unwrap();
+        process::exit(lint::run(Path::new(path)));
     }
-}
 
-/// Prints the return value from the [`CrispExpr`] `ret`, with a colored
-/// indicator preceding it.
-pub fn print_return(ret: &CrispExpr) {
-    let ret_indicator = "=> ".bright_green();
+    if let Some(("lsp", _)) = matches.subcommand() {
+        process::exit(lsp::run());
+    }
 
-    match ret {
-        CrispExpr::CrispString(str) => println!("{}{}", ret_indicator, escape_string(str)),
-        _ => println!("{}{}", ret_indicator, ret)
+    if let Some(("repl", repl_matches)) = matches.subcommand() {
+        let preload = repl_matches.get_many::<String>("preload")
+                                  .map(|files| files.cloned().collect::<Vec<String>>())
+                                  .unwrap_or_default();
+        repl::run(&preload);
+        return;
     }
-}
 
-/// Escapes a string literal for display e.g. in the REPL return or displaying
-/// a [`List`](CrispExpr) containing [`String`](CrispExpr)s.
-///
-/// # Examples
-///
-/// ```
-/// let str = escape_string("foo");
-/// assert_eq!(str, "'foo'".to_string());
-///
-/// let str = escape_string("a'b");
-/// assert_eq!(str, "\"a'b\"".to_string());
-/// ```
-pub fn escape_string(str: &str) -> String {
-    match escape(&str) {
-        escaped if &escaped == str => format!("'{}'", escaped),
-        escaped => escaped.to_string()
+    if let Some(filename) = matches.get_one::<String>("input") {
+        let args = matches.get_many::<String>("args")
+                          .map(|args| args.cloned().collect::<Vec<String>>())
+                          .unwrap_or_default();
+
+        if matches.get_flag("watch") {
+            watch::run(filename, &args, debug, strict);
+        }
+
+        run_and_exit(filename, &args, debug, strict);
+    } else {
+        repl::run(&[]);
     }
+}
 
+/// Runs the file `filename` (or stdin, if it's `-`) with [`run_file()`], and
+/// exits with its exit code.
+fn run_and_exit(filename: &str, args: &[String], debug: bool, strict: bool) -> ! {
+    let result = match filename {
+        "-" => run_stdin(args, debug, strict),
+        _ => run_file(filename, args, debug, strict, Arc::new(NativeHost))
+    };
+
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            let file = Some(filename).filter(|filename| *filename != "-");
+            eprintln!("{}", Diagnostic { error: &e, file, location: None });
+            process::exit(error_code(&e));
+        }
+    }
 }
 
+
 #[cfg(test)]
 mod tests {
     /// This module contains runners for the part of the test suite that is
     /// written in crisp. The external files are located in `tests/`.
     mod crisp_native {
         use assert_cmd::Command;
+        use predicates::prelude::*;
+
+        /// A scratch directory for the CLI to cache parsed files in, so that
+        /// the tests don't touch the real cache.
+        fn cache_dir() -> std::path::PathBuf {
+            std::env::temp_dir().join(format!("crisp-test-cache-{}", std::process::id()))
+        }
+
+        /// Prepares to run the crisp binary, with its cache in [`cache_dir()`].
+        fn crisp() -> Command {
+            let mut cmd = Command::cargo_bin("crisp").unwrap();
+            cmd.env("CRISP_CACHE", cache_dir());
+            cmd
+        }
 
         /// Generates a test that runs the external file `$name.crisp` and asserts that
         /// the output to stdout matches `$expected`.
@@ -160,18 +245,36 @@ mod tests {
             ($name:ident, $expected:expr) => {
                 #[test]
                 fn $name() {
-                    let mut cmd = Command::cargo_bin("crisp").unwrap();
+                    let mut cmd = crisp();
                     let assert = cmd.arg(&format!("tests/{}.crisp", stringify!($name))).assert();
                     assert.success().stdout($expected);
                 }
             }
         }
 
+        /// Generates a test that runs the external file `$name.crisp` and asserts that
+        /// the output to stdout and stderr match `$stdout` and `$stderr`.
+        macro_rules! test_stdout_stderr {
+            ($name:ident, $stdout:expr, $stderr:expr) => {
+                #[test]
+                fn $name() {
+                    let mut cmd = crisp();
+                    let assert = cmd.arg(&format!("tests/{}.crisp", stringify!($name))).assert();
+                    assert.success().stdout($stdout).stderr($stderr);
+                }
+            }
+        }
+
         test_stdout!(comments,
             "true\n\
              false\n"
         );
 
+        test_stdout!(crlf,
+            "true\n\
+             42\n"
+        );
+
         test_stdout!(exit_success, "");
 
         test_stdout!(print,
@@ -182,21 +285,393 @@ mod tests {
              foo\n"
         );
 
+        test_stdout_stderr!(eprint,
+            "out\n",
+            "Hello, world!\n\
+             12345\n"
+        );
+
+        /// Generates a test that runs the external file `$name.crisp` and asserts that
+        /// the process exits with the code `$code`.
+        macro_rules! test_exit_code {
+            ($name:ident, $code:expr) => {
+                #[test]
+                fn $name() {
+                    let mut cmd = crisp();
+                    let assert = cmd.arg(&format!("tests/{}.crisp", stringify!($name))).assert();
+                    assert.code($code);
+                }
+            }
+        }
+
+        test_exit_code!(exit_failure, 3);
+        test_exit_code!(exit_code, 42);
+        test_exit_code!(assert_raises_failure, 101);
+        test_exit_code!(assert_raises_wrong_type, 101);
+
+        #[test]
+        fn lenient_errors() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/strict.crisp").assert();
+            assert.code(1)
+                  .stdout("before\nafter\n")
+                  .stderr(predicates::str::contains("TypeError"));
+        }
+
+        #[test]
+        fn strict_errors() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("--strict").arg("tests/strict.crisp").assert();
+            assert.code(1)
+                  .stdout("before\n")
+                  .stderr(predicates::str::contains("TypeError"));
+        }
+
+        #[test]
+        fn chdir_then_load() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/chdir_load.crisp").assert();
+            assert.code(1)
+                  .stdout("9\n")
+                  .stderr("[TypeError] Expected Number.\n    \
+                           at tests/chdir_load.crisp:7:1: (+ 1 'a')\n");
+        }
+
+        #[test]
+        fn strict_calls() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/strict_calls.crisp").assert();
+            assert.success()
+                  .stdout("(1 2 3)\n(x 1 2)\n(5 1 2)\n")
+                  .stderr("");
+
+            let mut cmd = crisp();
+            let assert = cmd.args(["--plain-errors", "--strict-calls", "tests/strict_calls.crisp"])
+                            .assert();
+            assert.code(1)
+                  .stdout("(5 1 2)\n")
+                  .stderr("[NotCallableError] Not a function or keyword: 1\n    \
+                           at tests/strict_calls.crisp:5:6: (1 2 3)\n\
+                           [NotCallableError] Not a function or keyword: x (which is 5)\n    \
+                           at tests/strict_calls.crisp:6:6: (x 1 2)\n");
+
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/strict_calls.crisp")
+                            .env("CRISP_STRICT_CALLS", "1")
+                            .assert();
+            assert.stderr(predicates::str::contains("NotCallableError"));
+        }
+
+        #[test]
+        fn error_locations() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/strict.crisp").assert();
+            assert.stderr(predicates::str::contains("    at tests/strict.crisp:4:1: (+ 1 'two')"));
+
+            // Parse errors point at the whole expression
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/parse_error.crisp").assert();
+            assert.code(1)
+                  .stderr("[ParseError] Couldn't find closing `)`.\n    \
+                           at tests/parse_error.crisp:3:1\n");
+
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/error_location.crisp").assert();
+            assert.code(1)
+                  .stderr(predicates::str::contains("    at tests/error_location.crisp:4:8: \
+                                                     (* 2 'a')"))
+                  .stderr(predicates::str::contains("    at tests/error_location.crisp:6:15: \
+                                                     (+ 1 true)"));
+
+            // Identical expressions are told apart, but one in another file
+            // points at the whole of the expression being evaluated
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/ambiguous_location.crisp").assert();
+            assert.stderr("[TypeError] Expected Number.\n    \
+                           at tests/ambiguous_location.crisp:2:7: (+ 1 'a')\n\
+                           [TypeError] Expected Number.\n    \
+                           at tests/ambiguous_location.crisp:3:7: (+ 1 'a')\n\
+                           [TypeError] Expected Number.\n    \
+                           at tests/ambiguous_location.crisp:6:1\n\
+                           [TypeError] Expected Number.\n    \
+                           at tests/ambiguous_location.crisp:7:7: (+ 1 'b')\n");
+        }
+
+        #[test]
+        fn errors_not_colored_when_piped() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/strict.crisp").assert();
+            assert.stderr(predicates::str::contains("\x1b[").not());
+        }
+
+        #[test]
+        fn plain_errors() {
+            let expected = "[TypeError] Expected Number.\n    \
+                            at tests/error_location.crisp:4:8: (* 2 'a')\n\
+                            [TypeError] Expected Number.\n    \
+                            at tests/error_location.crisp:6:15: (+ 1 true)\n";
+
+            let mut cmd = crisp();
+            let assert = cmd.args(["--plain-errors", "tests/error_location.crisp"]).assert();
+            assert.code(1)
+                  .stderr(expected);
+
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/error_location.crisp")
+                            .env("CRISP_PLAIN_ERRORS", "1")
+                            .assert();
+            assert.code(1)
+                  .stderr(expected);
+        }
+
+        #[test]
+        fn json_errors() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["--error-format", "json", "tests/error_location.crisp"]).assert();
+            assert.code(1)
+                  .stderr("{\"code\":\"TypeError\",\"column\":8,\"expr\":\"(* 2 'a')\",\
+                           \"file\":\"tests/error_location.crisp\",\"line\":4,\
+                           \"message\":\"Expected Number.\",\
+                           \"span\":{\"end\":{\"column\":16,\"line\":4},\
+                           \"start\":{\"column\":8,\"line\":4}}}\n\
+                           {\"code\":\"TypeError\",\"column\":15,\"expr\":\"(+ 1 true)\",\
+                           \"file\":\"tests/error_location.crisp\",\"line\":6,\
+                           \"message\":\"Expected Number.\",\
+                           \"span\":{\"end\":{\"column\":24,\"line\":6},\
+                           \"start\":{\"column\":15,\"line\":6}}}\n");
+
+            // Without a source location
+            let mut cmd = crisp();
+            let assert = cmd.args(["--error-format=json", "-"]).write_stdin("(+ 1 'a')").assert();
+            assert.code(1)
+                  .stderr("{\"code\":\"TypeError\",\"column\":null,\"expr\":null,\"file\":null,\
+                           \"line\":null,\"message\":\"Expected Number.\",\"span\":null}\n");
+        }
+
+        #[test]
+        fn tags() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["tags", "tests/runner/pass", "-o", "-"]).assert();
+            assert.success()
+                  .stdout("!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+                           !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted/\n\
+                           n\ttests/runner/pass/math.crisp\t10;\"\tv\n\
+                           square\ttests/runner/pass/math.crisp\t3;\"\tf\n");
+        }
+
+        #[test]
+        fn lsp() {
+            let message = |body: &str| format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+            let input = [
+                r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+                r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+                r#"{"jsonrpc":"2.0","method":"exit"}"#
+            ].map(message).concat();
+
+            let mut cmd = crisp();
+            let assert = cmd.arg("lsp").write_stdin(input).assert();
+            assert.success()
+                  .stdout(predicates::str::contains(r#""definitionProvider":true"#))
+                  .stdout(predicates::str::ends_with(message(r#"{"id":2,"jsonrpc":"2.0","result":null}"#)));
+
+            // Messages that aren't JSON are answered with an error, and the
+            // server carries on
+            let input = ["{", r#"{"jsonrpc":"2.0","method":"exit"}"#].map(message).concat();
+            let mut cmd = crisp();
+            let assert = cmd.arg("lsp").write_stdin(input).assert();
+            assert.code(1)
+                  .stdout(predicates::str::contains(r#""code":-32700"#));
+
+            // Exiting without shutting down first
+            let mut cmd = crisp();
+            let assert = cmd.arg("lsp").write_stdin(message(r#"{"jsonrpc":"2.0","method":"exit"}"#))
+                            .assert();
+            assert.code(1);
+        }
+
+        #[test]
+        fn load_from_crisp_path() {
+            let mut cmd = crisp();
+            let assert = cmd.env("CRISP_PATH", "tests/lib")
+                            .arg("tests/load_crisp_path.crisp")
+                            .assert();
+            assert.success();
+        }
+
+        #[test]
+        fn require_from_crisp_libs() {
+            let mut cmd = crisp();
+            let assert = cmd.env("CRISP_LIBS", "tests/libs").arg("tests/require.crisp").assert();
+            assert.success();
+
+            let mut cmd = crisp();
+            let assert = cmd.env("CRISP_LIBS", "tests/does_not_exist")
+                            .args(["--strict", "tests/require.crisp"])
+                            .assert();
+            assert.code(1).stderr(predicates::str::contains("LoadError"));
+        }
+
+        #[test]
+        fn run_project() {
+            let mut cmd = crisp();
+            let assert = cmd.current_dir("tests/project/src").arg("run").assert();
+            assert.success().stdout("12\n");
+
+            let mut cmd = crisp();
+            let assert = cmd.current_dir("src").arg("run").assert();
+            assert.code(1).stderr(predicates::str::contains("Couldn't find a crisp.toml"));
+        }
+
+        #[test]
+        fn bundle() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["bundle", "tests/bundle/main.crisp", "-o", "-"]).assert();
+            assert.success().stdout(predicates::str::contains(";;; tests/bundle/lib/shapes.crisp"));
+
+            let output = std::env::temp_dir().join(format!("crisp-bundled-{}", std::process::id()));
+            let mut cmd = crisp();
+            let assert = cmd.env("CRISP_LIBS", "tests/libs")
+                            .args(["bundle", "tests/bundle/require.crisp", "--binary", "-o"])
+                            .arg(&output)
+                            .assert();
+            assert.success();
+
+            // The bundled binary runs its program, whatever it's given
+            let assert = Command::new(&output).env("CRISP_CACHE", cache_dir())
+                                              .arg("--help")
+                                              .assert();
+            assert.success().stdout("Hello, bundle!\n");
+            std::fs::remove_file(output).unwrap();
+        }
+
+        #[test]
+        fn load_circular() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("--strict").arg("tests/lib/cycle_a.crisp").assert();
+            assert.code(1)
+                  .stderr(predicates::str::contains("Circular load detected"));
+        }
+
+        #[test]
+        fn missing_file() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/does_not_exist.crisp").assert();
+            assert.code(1)
+                  .stderr(predicates::str::contains("LoadError"));
+        }
+
+        #[test]
+        fn assertion_failure() {
+            // Failed assertions stop the file even outside of strict mode
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/assert_raises_failure.crisp").assert();
+            assert.code(101)
+                  .stdout("")
+                  .stderr(predicates::str::contains("AssertionError"));
+        }
+
+        #[test]
+        fn test_runner_pass() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["test", "tests/runner/pass"]).assert();
+            assert.success()
+                  .stdout(predicates::str::contains("math.crisp: math addition ... ok"))
+                  .stdout(predicates::str::contains("test result: ok. 4 passed; 0 failed"));
+        }
+
+        #[test]
+        fn test_runner_fail() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["test", "tests/runner"]).assert();
+            assert.code(1)
+                  .stdout(predicates::str::contains("failing.crisp: fails ... FAILED"))
+                  .stdout(predicates::str::contains("[AssertionError]"))
+                  .stdout(predicates::str::contains("    checking 1 = 2\n"))
+                  .stdout(predicates::str::contains("exiting.crisp: exits ... FAILED"))
+                  .stdout(predicates::str::contains("test result: FAILED. 6 passed; 5 failed"));
+        }
+
+        /// Runs the stress tests in `tests/recursion/`. See the comments at the
+        /// top of each file for the limits they test against.
+        #[test]
+        fn recursion_stress() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["test", "tests/recursion"]).assert();
+            assert.success()
+                  .stdout(predicates::str::contains("test result: ok. 11 passed; 0 failed"));
+        }
+
+        #[test]
+        fn run_from_stdin() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["--strict", "-"])
+                            .write_stdin("(let x\n  (+ 1 2))\n(puts x) (puts 'done')\n(* x 2)")
+                            .assert();
+            assert.code(6)
+                  .stdout("3\ndone\n");
+        }
+
+        #[test]
+        fn run_from_stdin_errors() {
+            let mut cmd = crisp();
+            let assert = cmd.arg("-").write_stdin("(puts 'before') (+ 1 'a') (puts 'after') (").assert();
+            assert.code(1)
+                  .stdout("before\nafter\n")
+                  .stderr(predicates::str::contains("TypeError"))
+                  .stderr(predicates::str::contains("Couldn't find closing"));
+        }
+
+        #[test]
+        fn program_args() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["tests/args.crisp", "one", "--two", "3"]).assert();
+            assert.success()
+                  .stdout("('one' '--two' '3')\n");
+        }
+
+        #[test]
+        fn debugger() {
+            let mut cmd = crisp();
+            let assert = cmd.args(["--debugger", "tests/debugger.crisp"])
+                            .write_stdin("n\nlet n 7\nstep\ncontinue\n")
+                            .assert();
+            assert.success()
+                  .stdout("[debug] break\ndebug> => 5\ndebug> => 7\n\
+                           debug> [debug] (puts (* n 2))\ndebug> 14\ndone\n");
+
+            // Without `--debugger`, `break` does nothing
+            let mut cmd = crisp();
+            let assert = cmd.arg("tests/debugger.crisp").assert();
+            assert.success()
+                  .stdout("10\ndone\n");
+        }
+
         macro_rules! test_success {
             ($name:ident) => {
                 #[test]
                 fn $name() {
-                    let mut cmd = Command::cargo_bin("crisp").unwrap();
+                    let mut cmd = crisp();
                     let assert = cmd.arg(&format!("tests/{}.crisp", stringify!($name))).assert();
-                    assert.success();
+                    assert.success().stderr("");
                 }
             }
         }
 
         test_success!(assert);
+        test_success!(atoms);
+        test_success!(concurrency);
+        test_success!(delay);
+        test_success!(env_objects);
+        test_success!(embed);
+        test_success!(load);
+        test_success!(output_capture);
+        test_success!(match_str);
         test_success!(function);
+        test_success!(generators);
         test_success!(if_expr);
         test_success!(lambda);
+        test_success!(read_eval);
         test_success!(variable);
+        test_success!(with_redefs);
     }
 }
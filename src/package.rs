@@ -0,0 +1,425 @@
+use crate::{error::CrispError, functions::copy_dir, project::{find_manifest, read_manifest}};
+
+use std::{env, ffi::OsString, fs, path::{Path, PathBuf}, process::Command};
+
+use directories::BaseDirs;
+
+/// The file in a project's directory that lists the libraries it depends on.
+/// See [`Dependency`].
+pub const DEPS_FILE: &str = "crisp.deps";
+
+/// A library that a project depends on, as recorded in its [`DEPS_FILE`].
+/// Each is a line of the file giving the name of the library, where it was
+/// installed from, and the commit it was at (or `-` if it wasn't a git
+/// repository), separated by spaces:
+///
+/// ```text
+/// json https://github.com/someone/json.git 3f2c9a1b...
+/// ```
+///
+/// Blank lines and lines starting with `;` are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub source: String,
+    pub version: Option<String>
+}
+
+/// Runs `crisp install`, which installs the library at `source` (a git URL, or
+/// a directory to copy) into the [`libs_dir()`], and records it in the
+/// [`DEPS_FILE`] in the current directory. If `source` isn't given, every
+/// library in the [`DEPS_FILE`] is installed instead, at the version recorded
+/// for it, along with any dependencies in the project's
+/// [manifest](crate::project::Manifest) that aren't recorded yet.
+///
+/// # Returns
+///
+/// The exit code for the process.
+pub fn run(source: Option<&str>) -> i32 {
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let Some(libs) = libs_dir(env::var_os("CRISP_LIBS"), home) else {
+        eprintln!("Couldn't find the home directory; set CRISP_LIBS to install libraries.");
+        return 1;
+    };
+
+    let deps_file = Path::new(DEPS_FILE);
+    let result = read_deps(deps_file).and_then(|mut deps| match source {
+        Some(source) => {
+            let Some(name) = library_name(source) else {
+                return standard_error!(format!("Can't tell the name of the library at {}.",
+                                               source));
+            };
+            let dep = install(&name, source, None, &libs)?;
+            println!("Installed {} to {}", dep.name, libs.join(&dep.name).display());
+
+            match deps.iter_mut().find(|other| other.name == dep.name) {
+                Some(other) => *other = dep,
+                None => deps.push(dep)
+            }
+            write_deps(deps_file, &deps)
+        },
+
+        None => {
+            deps.iter().try_for_each(|dep| {
+                install(&dep.name, &dep.source, dep.version.as_deref(), &libs)?;
+                println!("Installed {}", dep.name);
+                Ok(())
+            })?;
+
+            let manifest = match find_manifest(Path::new(".")) {
+                Some(path) => read_manifest(&path)?.dependencies,
+                None => Vec::new()
+            };
+            match install_new(&mut deps, &manifest, &libs)? {
+                true => write_deps(deps_file, &deps),
+                false => Ok(())
+            }
+        }
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Installs the dependencies from a project's [manifest](crate::project::Manifest)
+/// (`manifest`, by name and source) that aren't in `deps` yet into `libs`, under
+/// the names they're given in the manifest, and adds them to `deps`.
+///
+/// # Returns
+///
+/// Whether any were installed.
+pub fn install_new(
+    deps: &mut Vec<Dependency>,
+    manifest: &[(String, String)],
+    libs: &Path
+) -> Result<bool, CrispError> {
+    let new: Vec<_> = manifest.iter().filter(|(name, _)| {
+        !deps.iter().any(|dep| &dep.name == name)
+    }).collect();
+
+    for (name, source) in &new {
+        deps.push(install(name, source, None, libs)?);
+        println!("Installed {}", name);
+    }
+
+    Ok(!new.is_empty())
+}
+
+/// Finds the directory that libraries are installed into: the `CRISP_LIBS`
+/// environment variable, given as `var`, if it's set, otherwise `.crisp/libs`
+/// in the user's `home` directory.
+pub fn libs_dir(var: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+    var.map(PathBuf::from).or_else(|| home.map(|home| home.join(".crisp").join("libs")))
+}
+
+/// The files that `require` tries for the library `name`, in order: the file
+/// of the same name in the library's directory, then its `main.crisp`.
+pub fn library_files(name: &str) -> Vec<PathBuf> {
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let Some(libs) = libs_dir(env::var_os("CRISP_LIBS"), home) else {
+        return Vec::new();
+    };
+
+    if !is_library_name(name) {
+        return Vec::new();
+    }
+
+    let dir = libs.join(name);
+    vec![dir.join(format!("{}.crisp", name)), dir.join("main.crisp")]
+}
+
+/// The name that a library installed from `source` is known by: the last
+/// component of its path, without any `.git` extension (or `.git` directory).
+/// There is none if that isn't a [valid name](is_library_name).
+pub fn library_name(source: &str) -> Option<String> {
+    let separators = ['/', '\\'];
+    let path = source.trim_end_matches(separators);
+    let path = path.strip_suffix(".git").unwrap_or(path).trim_end_matches(separators);
+    let name = path.rsplit(['/', '\\', ':']).next()?;
+
+    is_library_name(name).then(|| name.to_string())
+}
+
+/// Checks whether `name` can be the name of a library, which is the name of
+/// its directory in the [`libs_dir()`]: it can't be empty, `.` or `..`, contain
+/// a path separator, or start with `-` (so that it can't be taken for an
+/// option by git).
+pub fn is_library_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..") && !name.contains(['/', '\\']) && !name.starts_with('-')
+}
+
+/// Installs the library at `source` into `libs` as `name`, replacing any older
+/// copy. A directory is copied; anything else is cloned with git, and then checked out
+/// at `version` if one is given.
+///
+/// The library is fetched into a temporary directory first, which is only
+/// moved into place once it's complete, so the older copy is left as it was if
+/// anything goes wrong.
+pub fn install(
+    name: &str,
+    source: &str,
+    version: Option<&str>,
+    libs: &Path
+) -> Result<Dependency, CrispError> {
+    if !is_library_name(name) {
+        return standard_error!(format!("`{}` isn't a valid name for a library.", name));
+    }
+
+    fs::create_dir_all(libs).map_err(|e| {
+        CrispError::StandardError(format!("Couldn't create {}: {}", libs.display(), e))
+    })?;
+
+    let dest = libs.join(name);
+    if dest.parent() != Some(libs) || dest.file_name() != Some(name.as_ref()) {
+        return standard_error!(format!("Can't install `{}` outside of {}.", name, libs.display()));
+    }
+
+    let temp = libs.join(format!(".{}.installing-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&temp);
+    let version = fetch(source, version, &temp).inspect_err(|_| {
+        let _ = fs::remove_dir_all(&temp);
+    })?;
+
+    if dest.exists() {
+        fs::remove_dir_all(&dest).map_err(|e| {
+            CrispError::StandardError(format!("Couldn't remove {}: {}", dest.display(), e))
+        })?;
+    }
+    fs::rename(&temp, &dest).map_err(|e| {
+        CrispError::StandardError(format!("Couldn't move {} to {}: {}", temp.display(),
+                                          dest.display(), e))
+    })?;
+
+    Ok(Dependency { name: name.to_string(), source: source.to_string(), version })
+}
+
+/// Copies or clones the library at `source` to `dest` for [`install()`].
+///
+/// # Returns
+///
+/// The commit it was checked out at, if it's a git repository.
+fn fetch(source: &str, version: Option<&str>, dest: &Path) -> Result<Option<String>, CrispError> {
+    let local = Path::new(source);
+    if local.is_dir() && !is_repository(local) {
+        copy_dir(local, dest).map_err(|(e, path)| {
+            CrispError::StandardError(format!("Couldn't copy {}: {}", path.display(), e))
+        })?;
+    } else {
+        git(None, &["clone", "--quiet", "--", source, &dest.display().to_string()])?;
+        if let Some(version) = version {
+            git(Some(dest), &["checkout", "--quiet", version])?;
+        }
+    }
+
+    match dest.join(".git").exists() {
+        true => Ok(Some(git(Some(dest), &["rev-parse", "HEAD"])?)),
+        false => Ok(None)
+    }
+}
+
+/// Checks whether the directory `dir` is a git repository of its own, rather
+/// than a plain directory (which may be within one).
+fn is_repository(dir: &Path) -> bool {
+    matches!(git(Some(dir), &["rev-parse", "--git-dir"]).as_deref(), Ok("." | ".git"))
+}
+
+/// Runs git with `args`, in the directory `dir` if given, and returns what it
+/// printed, trimmed.
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, CrispError> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = command.args(args).output().map_err(|e| {
+        CrispError::StandardError(format!("Couldn't run git: {}", e))
+    })?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => standard_error!(format!("git {} failed: {}", args.join(" "),
+                                         String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// Reads the dependencies listed in the [`DEPS_FILE`] at `path`. There are none
+/// if it doesn't exist.
+pub fn read_deps(path: &Path) -> Result<Vec<Dependency>, CrispError> {
+    let Ok(source) = fs::read_to_string(path) else {
+        return Ok(Vec::new());
+    };
+
+    source.lines().enumerate().filter(|(_, line)| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with(';')
+    }).map(|(i, line)| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+        [name, source, version] => Ok(Dependency {
+            name: name.to_string(),
+            source: source.to_string(),
+            version: (version != "-").then(|| version.to_string())
+        }),
+        _ => standard_error!(format!("{}:{}: Expected a name, a source and a version.",
+                                     path.display(), i + 1))
+    }).collect()
+}
+
+/// Writes `deps` to the [`DEPS_FILE`] at `path`, sorted by name.
+pub fn write_deps(path: &Path, deps: &[Dependency]) -> Result<(), CrispError> {
+    let mut deps = deps.to_vec();
+    deps.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let lines: String = deps.iter().map(|dep| {
+        format!("{} {} {}\n", dep.name, dep.source, dep.version.as_deref().unwrap_or("-"))
+    }).collect();
+
+    fs::write(path, lines).map_err(|e| {
+        CrispError::StandardError(format!("Couldn't write {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty scratch directory for a test, unique to `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("crisp-package-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_libs_dir() {
+        assert_eq!(libs_dir(Some(OsString::from("/opt/libs")), Some(PathBuf::from("/home/me"))),
+                   Some(PathBuf::from("/opt/libs")));
+        assert_eq!(libs_dir(None, Some(PathBuf::from("/home/me"))),
+                   Some(PathBuf::from("/home/me/.crisp/libs")));
+        assert_eq!(libs_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_library_name() {
+        assert_eq!(library_name("https://github.com/someone/json.git"), Some("json".to_string()));
+        assert_eq!(library_name("git@github.com:someone/json.git"), Some("json".to_string()));
+        assert_eq!(library_name("git@github.com:json"), Some("json".to_string()));
+        assert_eq!(library_name("../libs/strings/"), Some("strings".to_string()));
+        assert_eq!(library_name("../libs/strings/.git"), Some("strings".to_string()));
+        assert_eq!(library_name("/"), None);
+        assert_eq!(library_name("../libs/.."), None);
+        assert_eq!(library_name("./."), None);
+        assert_eq!(library_name("git@github.com:-upload-pack=evil"), None);
+    }
+
+    #[test]
+    fn test_is_library_name() {
+        assert!(is_library_name("json"));
+        assert!(is_library_name("json-utils"));
+        assert!(is_library_name(".hidden"));
+        assert!(!is_library_name(""));
+        assert!(!is_library_name("."));
+        assert!(!is_library_name(".."));
+        assert!(!is_library_name("../json"));
+        assert!(!is_library_name("libs\\json"));
+        assert!(!is_library_name("-json"));
+        assert!(library_files("../json").is_empty());
+    }
+
+    #[test]
+    fn test_deps() {
+        let dir = scratch_dir("deps");
+        let path = dir.join(DEPS_FILE);
+        assert_eq!(read_deps(&path), Ok(vec![]));
+
+        let deps = vec![
+            Dependency { name: "strings".to_string(), source: "../strings".to_string(),
+                         version: None },
+            Dependency { name: "json".to_string(),
+                         source: "https://example.com/json.git".to_string(),
+                         version: Some("3f2c9a1".to_string()) }
+        ];
+        write_deps(&path, &deps).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(),
+                   "json https://example.com/json.git 3f2c9a1\nstrings ../strings -\n");
+        assert_eq!(read_deps(&path).unwrap(), vec![deps[1].clone(), deps[0].clone()]);
+
+        fs::write(&path, "; Comments are skipped\n\njson\n").unwrap();
+        crisp_assert_err!(read_deps(&path), StandardError);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_install() {
+        let dir = scratch_dir("install");
+        let source = dir.join("greet");
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("greet.crisp"), "fn greet name \"Hello, $name!\"").unwrap();
+        fs::write(source.join("src").join("util.crisp"), "let x 1").unwrap();
+
+        let libs = dir.join("libs");
+        let path = source.display().to_string();
+        let dep = install("greet", &path, None, &libs).unwrap();
+        assert_eq!(dep.name, "greet");
+        assert_eq!(dep.version, None);
+        assert!(libs.join("greet").join("greet.crisp").is_file());
+        assert!(libs.join("greet").join("src").join("util.crisp").is_file());
+
+        // Installing again replaces the old copy
+        fs::remove_file(source.join("src").join("util.crisp")).unwrap();
+        install("greet", &path, None, &libs).unwrap();
+        assert!(!libs.join("greet").join("src").join("util.crisp").exists());
+
+        // A failed install leaves the old copy, and nothing else, behind
+        fs::rename(&source, dir.join("moved")).unwrap();
+        crisp_assert_err!(install("greet", &path, None, &libs), StandardError);
+        assert!(libs.join("greet").join("greet.crisp").is_file());
+        assert_eq!(fs::read_dir(&libs).unwrap().count(), 1);
+
+        // Sources that look like options aren't taken as them by git
+        let marker = dir.join("marker");
+        let evil = format!("--upload-pack=touch {}", marker.display());
+        crisp_assert_err!(install("evil", &evil, None, &libs), StandardError);
+        assert!(!marker.exists());
+
+        for name in ["..", "../escape", "-evil", ""] {
+            crisp_assert_err!(install(name, &path, None, &libs), StandardError);
+        }
+        assert!(dir.is_dir());
+        assert!(!dir.join("escape").exists());
+
+        // A library can't be installed from a directory that holds the libraries
+        crisp_assert_err!(install("all", &dir.display().to_string(), None, &libs), StandardError);
+        assert!(!libs.join("all").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_install_new() {
+        let dir = scratch_dir("install-new");
+        let source = dir.join("greet-crisp");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("main.crisp"), "fn greet name \"Hello, $name!\"").unwrap();
+
+        // Libraries are installed under the name they're given in the manifest
+        let libs = dir.join("libs");
+        let mut deps = Vec::new();
+        let manifest = vec![("greet".to_string(), source.display().to_string())];
+        assert_eq!(install_new(&mut deps, &manifest, &libs), Ok(true));
+        assert_eq!(deps[0].name, "greet");
+        assert!(libs.join("greet").join("main.crisp").is_file());
+        assert!(!libs.join("greet-crisp").exists());
+
+        // and only once
+        assert_eq!(install_new(&mut deps, &manifest, &libs), Ok(false));
+        assert_eq!(deps.len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{env::initialize_environment, host::SandboxHost, send, split_exprs};
+
+use std::{io, sync::{Arc, Mutex}};
+
+/// Evaluates the program `source` in a fresh, sandboxed environment, for
+/// running crisp somewhere without a terminal or filesystem, such as the
+/// browser playground. The program can't read files or exit, and `gets`
+/// reads nothing. When built for WebAssembly, this is exported to JavaScript.
+///
+/// # Returns
+///
+/// Everything that the program printed (to stdout or stderr), followed by the
+/// return value of its last expression as the REPL shows it. Errors are
+/// included in the output in place of the expression's value, and evaluation
+/// carries on with the next expression.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+pub fn eval(source: &str) -> String {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let mut env = initialize_environment().with_stdout(output.clone())
+                                          .with_stderr(output.clone())
+                                          .with_stdin(Arc::new(Mutex::new(io::empty())))
+                                          .with_host(Arc::new(SandboxHost));
+
+    let mut ret = None;
+    for (_, expr) in split_exprs(source) {
+        match send(expr, &mut env) {
+            Ok(value) => ret = Some(value),
+            Err(e) => {
+                output.lock().unwrap().extend(format!("{}\n", e).bytes());
+                ret = None;
+            }
+        }
+    }
+
+    let mut result = String::from_utf8_lossy(&output.lock().unwrap()).into_owned();
+    if let Some(value) = ret {
+        result.push_str(&format!("=> {}", value.inspect()));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::builtin_names, keywords::SPECIAL_FORMS};
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval("puts 'hi'\nfn double n\n  (* n 2)\ndouble 21"), "hi\n=> 42");
+        assert_eq!(eval("read \"('a' ,b)\""), "=> ('a' ,b)");
+        assert_eq!(eval(""), "");
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let output = eval("puts 1\n+ 1 'a'");
+        assert!(output.starts_with("1\n"));
+        assert!(output.contains("TypeError"));
+        assert!(!output.contains("=>"));
+
+        // Nothing outside of the sandbox is available
+        assert!(eval("embed \"Cargo.toml\"").contains("LoadError"));
+        assert!(eval("load \"tests/lib/helpers.crisp\"").contains("LoadError"));
+        assert!(eval("exit 1\nputs 'still running'").contains("still running"));
+
+        // Nor from a new environment
+        let output = eval("eval-in (make-env) (read \"exit 3\")\nputs 'still running'");
+        assert!(output.contains("still running"));
+        assert!(eval("eval-in (make-env) (read \"ls '.'\")").contains("StandardError"));
+        assert_eq!(eval("eval-in (make-env) (read \"puts 'hi'\")"), "hi\n=> 'hi'");
+    }
+
+    /// Code that is unusual or malformed in the ways most likely to find
+    /// a panic, which should instead be reported as an error.
+    #[test]
+    fn test_eval_adversarial() {
+        let sources = [
+            "()", "(())", "((()))", "[]", "#()", "#(%0)", "#(#(%))", "(\\)", "(\\ () )",
+            "(\\ (1) 1)", "(fn)", "(fn 1 2 3)", "(let)", "(let 1 2)", "(if)", "(if 1 2 3)",
+            "(case)", "(match-str)", "(deftest)", "((\\ (a) a))", "((\\ (a) a) 1 2)",
+            "(@x)", "(+ @)", "(+ ...)", "(1..)", "(..1)", "1..=", ",", ",u", ",uffffffff",
+            "'", "\"", "\"$\"", "\"${\"", "\"${}\"", "\"\\\"", "<<~", "<<~END",
+            "(read \"(\")", "(eval (list))", "(eval (list (list)))", "(eval (list + ()))",
+            "(foldl1 + ())", "(map + ())", "(map (\\ () 1) (1 2))", "(sort (list + -))",
+            "(compare + -)", "(take -1 (1 2))", "(slice 3 1 (1 2))", "(chunks 0 (1 2))",
+            "(windows 0 (1 2))", "(reshape 0 0 ())", "(transpose (()))", "(dot () (1))",
+            "(matrix-mul (()) (()))", "(/ 0 0)", "(mod 1 0)", "(sqrt -1)", "(log 0)",
+            "(num-format 1e300 400)", "(hex-decode 'z')", "(base64-decode '!')",
+            "(deserialize '(')", "(heap-pop! (heap-new))", "(dequeue! (queue))",
+            "(next 1)", "(force 1)", "(deref 1)", "(yield 1)", "(arity 1)", "(fn-args +)",
+            "(env-get (make-env) 'x')", "(unbind! 'nothing')", "(host-call 'nothing')",
+            "(csv-parse '\"')", "(str-join 1 2)", "(diff (1) 'a')", "(unwrap-or 1 2)"
+        ];
+
+        for source in sources {
+            let result = std::panic::catch_unwind(|| eval(source));
+            assert!(result.is_ok(), "panicked evaluating {:?}", source);
+        }
+    }
+
+    /// Calls every builtin and special form with up to two arguments out of a
+    /// handful of awkward values, none of which should panic.
+    #[test]
+    fn test_builtins_adversarial() {
+        let values = ["nil", "0", "-1", "2.5", "''", "'a,b'", "()", "(list 1 2)", "(list ())",
+                      "true", ",x", "+", "(\\ (a b) a)", "x", "_"];
+        // These could block, waiting for something which never comes
+        let skip = ["recv!", "await", "join"];
+
+        let names: Vec<String> = builtin_names().map(str::to_string)
+            .chain(SPECIAL_FORMS.iter().map(|(name, _)| name.to_string()))
+            .filter(|name| !skip.contains(&name.as_str()))
+            .collect();
+
+        for name in names {
+            let mut calls = vec![format!("({})", name)];
+            for a in values {
+                calls.push(format!("({} {})", name, a));
+                calls.extend(values.iter().map(|b| format!("({} {} {})", name, a, b)));
+            }
+
+            for call in calls {
+                let result = std::panic::catch_unwind(|| eval(&call));
+                assert!(result.is_ok(), "panicked evaluating {:?}", call);
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{error::CrispError, keywords::configure_load_path, package::library_files};
+
+use std::{fs, path::{Path, PathBuf}};
+
+use toml::{Table, Value};
+
+/// The file that marks the root directory of a project. See [`Manifest`].
+pub const MANIFEST_FILE: &str = "crisp.toml";
+
+/// Describes a project, as read from its [`MANIFEST_FILE`]:
+///
+/// ```toml
+/// [package]
+/// name = "weather"
+/// version = "0.1.0"
+/// main = "src/main.crisp"
+/// source-dirs = ["src", "lib"]
+///
+/// [dependencies]
+/// json = "https://github.com/someone/json.git"
+/// ```
+///
+/// Only the `name` is required. The `main` file defaults to `main.crisp`; the
+/// `source-dirs` are searched by `load`, after the directory of the file that
+/// is loading, and default to none. Each dependency is a library for
+/// `require`, with the source to install it from with `crisp install`. Paths
+/// are relative to the directory of the manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    pub main: PathBuf,
+    pub source_dirs: Vec<PathBuf>,
+    /// The names of the libraries the project depends on, with their sources.
+    pub dependencies: Vec<(String, String)>
+}
+
+/// Prepares to run the project containing the directory `dir` (which should be
+/// absolute, so that the directories above it can be searched), for
+/// `crisp run`: finds its manifest (see [`find_manifest()`]), adds its source
+/// directories to the load path, and checks that its dependencies have been
+/// installed.
+///
+/// # Returns
+///
+/// The path of the project's main file.
+pub fn prepare(dir: &Path) -> Result<PathBuf, CrispError> {
+    let Some(path) = find_manifest(dir) else {
+        return standard_error!(format!("Couldn't find a {} in {} or any directory above it.",
+                                       MANIFEST_FILE, dir.display()));
+    };
+
+    let manifest = read_manifest(&path)?;
+    let root = path.parent().unwrap_or(Path::new("."));
+
+    for (name, _) in &manifest.dependencies {
+        if !library_files(name).iter().any(|file| file.is_file()) {
+            return standard_error!(format!("Dependency `{}` isn't installed. Run `crisp install` \
+                                            to install it.", name));
+        }
+    }
+
+    configure_load_path(manifest.source_dirs.iter().map(|dir| root.join(dir)).collect());
+
+    // Relative to `dir` if it's within it, which reads better in errors
+    let main = root.join(manifest.main);
+    Ok(main.strip_prefix(dir).map(Path::to_path_buf).unwrap_or(main))
+}
+
+/// Finds the [`MANIFEST_FILE`] of the project containing the directory `dir`,
+/// searching it and then each directory above it.
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|dir| dir.join(MANIFEST_FILE)).find(|path| path.is_file())
+}
+
+/// Reads the [`Manifest`] at `path`.
+pub fn read_manifest(path: &Path) -> Result<Manifest, CrispError> {
+    let source = fs::read_to_string(path)
+                    .map_err(|_| CrispError::LoadError(path.display().to_string()))?;
+
+    parse_manifest(&source).map_err(|e| {
+        CrispError::StandardError(format!("Invalid {}: {}", path.display(), e.message()))
+    })
+}
+
+/// Parses the source code of a [`MANIFEST_FILE`].
+pub fn parse_manifest(source: &str) -> Result<Manifest, CrispError> {
+    let table: Table = source.parse().map_err(|e: toml::de::Error| {
+        CrispError::ParseError(e.message().to_string())
+    })?;
+
+    let package = match table.get("package") {
+        Some(Value::Table(package)) => package,
+        _ => return standard_error!("Expected a [package] table.")
+    };
+
+    let string = |key: &str| match package.get(key) {
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        None => Ok(None),
+        Some(_) => standard_error!(format!("Expected `{}` to be a string.", key))
+    };
+
+    let Some(name) = string("name")? else {
+        return standard_error!("Expected the package to have a `name`.");
+    };
+
+    let source_dirs = match package.get("source-dirs") {
+        Some(Value::Array(dirs)) => dirs.iter().map(|dir| match dir {
+            Value::String(dir) => Ok(PathBuf::from(dir)),
+            _ => standard_error!("Expected `source-dirs` to be a list of strings.")
+        }).collect::<Result<Vec<PathBuf>, CrispError>>()?,
+        None => Vec::new(),
+        Some(_) => return standard_error!("Expected `source-dirs` to be a list of strings.")
+    };
+
+    let dependencies = match table.get("dependencies") {
+        Some(Value::Table(deps)) => deps.iter().map(|(name, source)| match source {
+            Value::String(source) => Ok((name.clone(), source.clone())),
+            _ => standard_error!(format!("Expected the source of `{}` to be a string.", name))
+        }).collect::<Result<Vec<(String, String)>, CrispError>>()?,
+        None => Vec::new(),
+        Some(_) => return standard_error!("Expected a [dependencies] table.")
+    };
+
+    Ok(Manifest {
+        name,
+        version: string("version")?,
+        main: PathBuf::from(string("main")?.unwrap_or_else(|| "main.crisp".to_string())),
+        source_dirs,
+        dependencies
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let source = "[package]\n\
+                      name = \"weather\"\n\
+                      version = \"0.1.0\"\n\
+                      main = \"src/main.crisp\"\n\
+                      source-dirs = [\"src\", \"lib\"]\n\
+                      \n\
+                      [dependencies]\n\
+                      json = \"https://github.com/someone/json.git\"\n";
+
+        assert_eq!(parse_manifest(source), Ok(Manifest {
+            name: "weather".to_string(),
+            version: Some("0.1.0".to_string()),
+            main: PathBuf::from("src/main.crisp"),
+            source_dirs: vec![PathBuf::from("src"), PathBuf::from("lib")],
+            dependencies: vec![("json".to_string(),
+                                "https://github.com/someone/json.git".to_string())]
+        }));
+
+        assert_eq!(parse_manifest("[package]\nname = \"tiny\""), Ok(Manifest {
+            name: "tiny".to_string(),
+            version: None,
+            main: PathBuf::from("main.crisp"),
+            source_dirs: vec![],
+            dependencies: vec![]
+        }));
+
+        crisp_assert_err!(parse_manifest("[package"), ParseError);
+        crisp_assert_err!(parse_manifest("name = \"tiny\""), StandardError);
+        crisp_assert_err!(parse_manifest("[package]\nversion = \"1.0\""), StandardError);
+        crisp_assert_err!(parse_manifest("[package]\nname = 5"), StandardError);
+        crisp_assert_err!(parse_manifest("[package]\nname = \"a\"\nsource-dirs = \"src\""),
+                          StandardError);
+    }
+
+    #[test]
+    fn test_find_manifest() {
+        assert_eq!(find_manifest(Path::new("tests/project/src")),
+                   Some(PathBuf::from("tests/project/crisp.toml")));
+        assert_eq!(find_manifest(Path::new("/")), None);
+    }
+}
@@ -1,7 +1,166 @@
-use crate::{error::CrispError, expr::CrispExpr};
+use crate::{ast::{Ast, NodeId}, error::CrispError, escape_string, expr::CrispExpr};
+
+use std::{collections::{BTreeMap, VecDeque}, io::BufRead, str::FromStr, sync::RwLock};
 
 use snailquote::unescape;
 
+/// Characters which can be written by name as char literals, e.g. `,space`.
+pub const CHAR_NAMES: [(&str, char); 5] = [
+    ("space", ' '),
+    ("newline", '\n'),
+    ("tab", '\t'),
+    ("return", '\r'),
+    ("null", '\0')
+];
+
+/// Expands the forms read by a reader macro into the expression they stand
+/// for. See [`register_reader_macro()`].
+pub type ReaderMacro = fn(CrispExpr) -> Result<CrispExpr, CrispError>;
+
+/// The reader macros which have been registered, by their dispatch character.
+static READER_MACROS: RwLock<BTreeMap<char, ReaderMacro>> = RwLock::new(BTreeMap::new());
+
+/// The reader macros that crisp comes with. They can be replaced with
+/// [`register_reader_macro()`].
+const BUILTIN_READER_MACROS: [(char, ReaderMacro); 1] = [
+    ('#', read_shorthand_lambda)
+];
+
+/// Registers a reader macro, so that a list written with the character
+/// `dispatch` directly before its opening `(` or `[`, e.g. `^(...)`, is read
+/// by passing what the list would otherwise be read as to `expand`, without
+/// evaluating it. Whatever `expand` returns is read in its place. This allows
+/// new syntax to be added without changing the tokenizer.
+///
+/// Registering a character again, including one of crisp's own (such as `#`
+/// for [shorthand lambdas](read_shorthand_lambda)), replaces its macro. Letters, digits,
+/// whitespace and characters that the tokenizer already gives a meaning to
+/// (parens, brackets, quotes, `,` and `;`) can't be used.
+///
+/// # Examples
+///
+/// ```
+/// // Reads `@(x y)` as `(deref (list x y))`
+/// register_reader_macro('@', |forms| Ok(list![sym!("deref"), list![sym!("list"), forms]]))?;
+/// ```
+// Used when embedding crisp, rather than by the CLI
+#[allow(dead_code)]
+pub fn register_reader_macro(dispatch: char, expand: ReaderMacro) -> Result<(), CrispError> {
+    if dispatch.is_alphanumeric() || dispatch.is_whitespace() || "()[]\"',;".contains(dispatch) {
+        return standard_error!(format!("`{}` can't be used for a reader macro.", dispatch));
+    }
+
+    READER_MACROS.write()
+                 .unwrap_or_else(|poisoned| poisoned.into_inner())
+                 .insert(dispatch, expand);
+    Ok(())
+}
+
+/// Checks whether any reader macros have been registered with
+/// [`register_reader_macro()`], so that source may not be read as usual.
+pub fn custom_reader_macros() -> bool {
+    !READER_MACROS.read().unwrap_or_else(|poisoned| poisoned.into_inner()).is_empty()
+}
+
+/// Finds the reader macro registered for `dispatch`, if there is one.
+fn reader_macro(dispatch: char) -> Option<ReaderMacro> {
+    READER_MACROS.read()
+                 .unwrap_or_else(|poisoned| poisoned.into_inner())
+                 .get(&dispatch)
+                 .copied()
+                 .or_else(|| {
+                     BUILTIN_READER_MACROS.iter()
+                                          .find(|(builtin, _)| *builtin == dispatch)
+                                          .map(|(_, expand)| *expand)
+                 })
+}
+
+/// Reads `#(...)` as a [`Lambda`](CrispExpr) taking the arguments `%1`, `%2`
+/// and so on, up to the highest one used in its body. `%` is the same as
+/// `%1`. For example, `#(+ % %2)` is read as `(\ (%1 %2) (+ %1 %2))`, and
+/// `#[% %]` as `(\ (%1) [%1 %1])`. They can't be nested.
+///
+/// # Examples
+///
+/// ```lisp
+/// map #(* % 2) [1 2 3]              ; => (2 4 6)
+/// foldl #(+ %1 (* %2 %2)) 0 [1 2]   ; => 5
+/// ```
+fn read_shorthand_lambda(body: CrispExpr) -> Result<CrispExpr, CrispError> {
+    let mut arity = 0;
+    let body = number_placeholders(body, &mut arity)?;
+    let args = (1..=arity).map(|n| sym!(format!("%{}", n))).collect();
+
+    Ok(list![sym!("\\"), CrispExpr::List(args), body])
+}
+
+/// Replaces each `%` in `expr` with `%1`, counting up the highest placeholder
+/// (e.g. `%2`) found in `arity`.
+fn number_placeholders(expr: CrispExpr, arity: &mut usize) -> Result<CrispExpr, CrispError> {
+    match expr {
+        CrispExpr::Symbol(s) if &*s == "%" => {
+            *arity = (*arity).max(1);
+            Ok(sym!("%1"))
+        },
+
+        CrispExpr::Symbol(s) => {
+            if let Some(n) = placeholder(&s) {
+                *arity = (*arity).max(n);
+            }
+            Ok(CrispExpr::Symbol(s))
+        },
+
+        CrispExpr::List(list) => {
+            // A shorthand lambda within this one has already been read
+            if let [CrispExpr::Symbol(head), CrispExpr::List(args), _] = &list[..] {
+                if &**head == "\\" && args.iter().any(|arg| matches!(arg, CrispExpr::Symbol(s)
+                                                              if placeholder(s).is_some())) {
+                    return parse_error!("Shorthand lambdas can't be nested.");
+                }
+            }
+
+            list.into_iter()
+                .map(|expr| number_placeholders(expr, arity))
+                .collect::<Result<Vec<CrispExpr>, CrispError>>()
+                .map(CrispExpr::List)
+        },
+
+        expr => Ok(expr)
+    }
+}
+
+/// The number of a placeholder argument of a shorthand lambda, e.g. 2 for
+/// `%2`. See [`read_shorthand_lambda()`].
+fn placeholder(name: &str) -> Option<usize> {
+    name.strip_prefix('%')?.parse().ok().filter(|&n| n > 0)
+}
+
+/// Checks whether `token` opens a reader macro, e.g. `#(`.
+///
+/// # Returns
+///
+/// The macro and the token that closes it, or `None` if `token` is anything
+/// else.
+fn reader_macro_opening(token: &str) -> Option<(ReaderMacro, &'static str)> {
+    let mut chars = token.chars();
+    let (dispatch, opening) = (chars.next()?, chars.next()?);
+
+    let closing = match opening {
+        _ if chars.next().is_some() => return None,
+        '(' => ")",
+        '[' => "]",
+        _ => return None
+    };
+
+    Some((reader_macro(dispatch)?, closing))
+}
+
+/// Checks whether `token` opens a list, i.e. it's `(`, `[` or the opening of a
+/// reader macro.
+fn is_opening(token: &str) -> bool {
+    token == "(" || token == "[" || reader_macro_opening(token).is_some()
+}
+
 /// The tokenizer alternates between these states as it scans across the input
 /// character-by-character. `Scanning` is the default state, indicating that we
 /// are reading tokens that are delimited by whitespace (or parens). Tokens with
@@ -12,116 +171,569 @@ enum TokenState {
     Scanning,
 
     Char,
+    CharName,
     Comment,
-    String
+    String,
+    Heredoc
 }
 
-/// Tokenizes a piece of code. `(` and `)` are their own tokens; everything
-/// else is delimited by whitespace.
-pub fn tokenize(input: String) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut state = TokenState::Scanning;
+/// A heredoc, opened with e.g. `<<~END`: the following lines, up to one
+/// holding only `END`, are read as a [`String`](CrispExpr). See
+/// [`Tokenizer`].
+struct Heredoc {
+    tag: String,
+    /// Where the `<<~` was.
+    start: Span,
+    lines: Vec<String>,
+    line: String,
+    /// The tokens after the opening tag on the same line, which come after the
+    /// string once it's finished.
+    deferred: Vec<(String, Span)>
+}
+
+impl Heredoc {
+    /// The text of the heredoc, with the indentation that all of its
+    /// (non-blank) lines have in common stripped off.
+    fn text(&self) -> String {
+        let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+        let common = self.lines.iter()
+                               .filter(|line| !line.trim().is_empty())
+                               .map(|line| indentation(line))
+                               .min()
+                               .unwrap_or(0);
+
+        self.lines.iter()
+                  .map(|line| line.trim_end_matches('\r').get(common..).unwrap_or(""))
+                  .map(|line| format!("{}\n", line))
+                  .collect()
+    }
+}
+
+/// A position in the source code. Lines and columns both count from 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    /// The position of the last character of `token` (or any other piece of
+    /// code), if it starts here.
+    pub fn last_of(self, token: &str) -> Span {
+        let mut position = self;
+
+        // Every character but the last moves the position along
+        for (ch, _) in token.chars().zip(token.chars().skip(1)) {
+            if ch == '\n' {
+                position = Span { line: position.line + 1, column: 1 };
+            } else {
+                position.column += 1;
+            }
+        }
+
+        position
+    }
+}
+
+/// The [`Span`] of each node of a parsed expression, mirroring its structure:
+/// a [`List`](CrispExpr) has a child for each of its elements. `end` is the
+/// position of the node's last character, e.g. a list's closing paren.
+#[derive(Debug, PartialEq)]
+pub struct SpanTree {
+    pub span: Span,
+    pub end: Span,
+    pub children: Vec<SpanTree>
+}
 
-    for ch in input.chars() {
-        match state {
+impl SpanTree {
+    /// Searches `expr`, whose spans are given by this tree, for `target`,
+    /// returning the subtrees of every node equal to it, in order.
+    pub fn find_all<'a>(&'a self, expr: &CrispExpr, target: &CrispExpr) -> Vec<&'a SpanTree> {
+        if expr == target {
+            return vec![self];
+        }
+
+        match expr {
+            CrispExpr::List(list) => list.iter().zip(&self.children).flat_map(|(child, tree)| {
+                tree.find_all(child, target)
+            }).collect(),
+
+            _ => Vec::new()
+        }
+    }
+}
+
+/// Splits code into tokens as it is fed in character-by-character, so that the
+/// input doesn't need to be available all at once. Finished tokens are
+/// collected, along with the [`Span`] they started at, until they are taken
+/// with [`take_tokens()`](Tokenizer::take_tokens).
+///
+/// A heredoc is opened by a token such as `<<~END` at the end of a line. The
+/// lines after it, up to one holding only `END`, make up a string token, with
+/// their common indentation stripped. Nothing within it is escaped or
+/// interpolated.
+struct Tokenizer {
+    state: TokenState,
+    current_token: String,
+    tokens: Vec<(String, Span)>,
+    /// A heredoc that has been opened on this line, or is being read.
+    heredoc: Option<Heredoc>,
+
+    // The position of the next character, and that of the current token
+    position: Span,
+    token_start: Span
+}
+
+impl Tokenizer {
+    fn new() -> Self {
+        let start = Span { line: 1, column: 1 };
+
+        Tokenizer {
+            state: TokenState::Scanning,
+            current_token: String::new(),
+            tokens: Vec::new(),
+            heredoc: None,
+            position: start,
+            token_start: start
+        }
+    }
+
+    /// Ends the current token, if there is one.
+    fn end_token(&mut self) {
+        if self.current_token.is_empty() {
+            return;
+        }
+
+        let token = std::mem::take(&mut self.current_token);
+        match heredoc_tag(&token) {
+            Some(tag) if self.heredoc.is_none() => {
+                self.heredoc = Some(Heredoc {
+                    tag: tag.to_string(),
+                    start: self.token_start,
+                    lines: Vec::new(),
+                    line: String::new(),
+                    deferred: Vec::new()
+                });
+            },
+
+            _ => self.emit(token, self.token_start)
+        }
+    }
+
+    /// Adds a finished token, or holds on to it until the heredoc that was
+    /// opened before it on the same line is finished.
+    fn emit(&mut self, token: String, span: Span) {
+        match &mut self.heredoc {
+            Some(heredoc) => heredoc.deferred.push((token, span)),
+            None => self.tokens.push((token, span))
+        }
+    }
+
+    /// Checks whether the current token is the dispatch character of a reader
+    /// macro.
+    fn opens_reader_macro(&self) -> bool {
+        let mut chars = self.current_token.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(dispatch), None) => reader_macro(dispatch).is_some(),
+            _ => false
+        }
+    }
+
+    /// Starts reading the lines of a heredoc, if one was opened on the line
+    /// that just ended.
+    fn start_heredoc(&mut self) {
+        if self.heredoc.is_some() {
+            self.state = TokenState::Heredoc;
+        }
+    }
+
+    /// Ends the heredoc being read. If it was closed, it becomes a string
+    /// token; otherwise the opening tag is left as it is, which fails to
+    /// parse.
+    fn end_heredoc(&mut self, closed: bool) {
+        let Some(heredoc) = self.heredoc.take() else {
+            return;
+        };
+
+        let token = match closed {
+            true => escape_string(&heredoc.text()),
+            false => format!("<<~{}", heredoc.tag)
+        };
+
+        self.tokens.push((token, heredoc.start));
+        self.tokens.extend(heredoc.deferred);
+        self.state = TokenState::Scanning;
+    }
+
+    /// Processes the next character of input.
+    fn push(&mut self, ch: char) {
+        let here = self.position;
+
+        if ch == '\n' {
+            self.position = Span { line: here.line + 1, column: 1 };
+        } else {
+            self.position.column += 1;
+        }
+
+        self.scan(ch, here);
+    }
+
+    /// Processes the character `ch`, found at `here`.
+    fn scan(&mut self, ch: char, here: Span) {
+        match self.state {
             TokenState::Scanning => {
+                if self.current_token.is_empty() {
+                    self.token_start = here;
+                }
+
                 match ch {
                     ',' => {
-                        state = TokenState::Char;
-                        current_token.push(ch);
+                        self.state = TokenState::Char;
+                        self.current_token.push(ch);
                     },
 
                     ';' => {
-                        if !current_token.is_empty() {
-                            tokens.push(current_token.clone());
-                            current_token.clear();
-                        }
-
-                        state = TokenState::Comment;
+                        self.end_token();
+                        self.state = TokenState::Comment;
                     },
 
                     '"' | '\'' => {
-                        state = TokenState::String;
-                        current_token.push(ch);
+                        self.state = TokenState::String;
+                        self.current_token.push(ch);
                     },
 
-                    ' ' | '\n' | '\t' => {
-                        // End of token
-                        if !current_token.is_empty() {
-                            tokens.push(current_token.clone());
-                            current_token.clear();
-                        }
+                    '\n' => {
+                        self.end_token();
+                        self.start_heredoc();
                     },
 
-                    '(' => {
-                        // End of token
-                        if !current_token.is_empty() {
-                            tokens.push(current_token.clone());
-                            current_token.clear();
-                        }
-                        tokens.push("(".to_string());
+                    // End of token
+                    ' ' | '\r' | '\t' => self.end_token(),
+
+                    // The opening of a reader macro, e.g. `#(`
+                    '(' | '[' if self.opens_reader_macro() => {
+                        self.current_token.push(ch);
+                        self.end_token();
                     },
 
-                    ')' => {
+                    '(' | ')' | '[' | ']' => {
                         // End of token
-                        if !current_token.is_empty() {
-                            tokens.push(current_token.clone());
-                            current_token.clear();
-                        }
-                        tokens.push(")".to_string());
+                        self.end_token();
+                        self.emit(ch.to_string(), here);
                     },
 
                     // Otherwise, we're still mid-token
-                    _ => current_token.push(ch)
+                    _ => self.current_token.push(ch)
                 }
             },
 
             TokenState::Char => {
-                current_token.push(ch);
-                tokens.push(current_token.clone());
-                current_token.clear();
-                state = TokenState::Scanning;
+                self.current_token.push(ch);
+
+                // Named and unicode chars, e.g. `,space` or `,u00e9`
+                if ch.is_alphanumeric() {
+                    self.state = TokenState::CharName;
+                } else {
+                    self.end_token();
+                    self.state = TokenState::Scanning;
+                }
+            },
+
+            TokenState::CharName => {
+                if ch.is_alphanumeric() {
+                    self.current_token.push(ch);
+                } else {
+                    // The name has ended, so this character is scanned as usual
+                    self.end_token();
+                    self.state = TokenState::Scanning;
+                    self.scan(ch, here);
+                }
             },
 
             TokenState::Comment => {
+                // `\r\n` line endings are covered, since the `\r` is skipped
                 if ch == '\n' {
-                    state = TokenState::Scanning;
+                    self.state = TokenState::Scanning;
+                    self.start_heredoc();
+                }
+            },
+
+            TokenState::Heredoc => {
+                let Some(heredoc) = &mut self.heredoc else {
+                    return;
+                };
+
+                if ch != '\n' {
+                    heredoc.line.push(ch);
+                } else if heredoc.line.trim() == heredoc.tag {
+                    self.end_heredoc(true);
+                } else {
+                    let line = std::mem::take(&mut heredoc.line);
+                    heredoc.lines.push(line);
                 }
             }
 
             TokenState::String => {
                 match ch {
-                    '"' | '\'' if current_token.chars().last().unwrap() != '\\' => {
-                        current_token.push(ch);
-                        tokens.push(current_token.clone());
-                        current_token.clear();
-                        state = TokenState::Scanning;
+                    // Only the same kind of quote that opened the string closes it
+                    '"' | '\'' if self.current_token.starts_with(ch)
+                                && !ends_with_escape(&self.current_token) => {
+                        self.current_token.push(ch);
+                        self.end_token();
+                        self.state = TokenState::Scanning;
                     },
 
                     // Otherwise, just a normal character
-                    _ => current_token.push(ch)
+                    _ => self.current_token.push(ch)
                 }
             }
         }
     }
 
+    /// Signals the end of the input, ending any dangling token.
+    fn finish(&mut self) {
+        self.end_token();
+
+        // The closing tag might not be followed by a newline
+        let closed = self.heredoc.as_ref().is_some_and(|heredoc| {
+            matches!(self.state, TokenState::Heredoc) && heredoc.line.trim() == heredoc.tag
+        });
+        self.end_heredoc(closed);
+
+        self.state = TokenState::Scanning;
+    }
+
+    /// Takes the tokens that have been completed so far.
+    fn take_tokens(&mut self) -> Vec<(String, Span)> {
+        std::mem::take(&mut self.tokens)
+    }
+}
+
+/// Checks whether the string token so far ends with a `\` that escapes the
+/// next character, as in `"a\`, rather than one that is escaped itself, as
+/// in `"a\\`.
+fn ends_with_escape(token: &str) -> bool {
+    token.chars().rev().take_while(|&ch| ch == '\\').count() % 2 == 1
+}
+
+/// Tokenizes a piece of code. Parens and square brackets are their own tokens;
+/// everything else is delimited by whitespace.
+pub fn tokenize(input: String) -> Vec<String> {
+    tokenize_with_spans(&input).into_iter().map(|(token, _)| token).collect()
+}
+
+/// Tokenizes a piece of code like [`tokenize()`], also returning the [`Span`]
+/// at which each token starts.
+pub fn tokenize_with_spans(input: &str) -> Vec<(String, Span)> {
+    let mut tokenizer = Tokenizer::new();
+
+    input.chars().for_each(|ch| tokenizer.push(ch));
+
     // If the outer parens are left off (see next if statement), there might
     // be a dangling token at the end
-    if !current_token.is_empty() {
-        tokens.push(current_token.clone());
-    }
+    tokenizer.finish();
+    let mut tokens = tokenizer.take_tokens();
 
     // Allow outer parens to be left off
-    if tokens.len() > 1 && *tokens.first().unwrap() != "(".to_string() {
-        tokens.insert(0, "(".to_string());
-        tokens.push(")".to_string());
+    if let [(first, start), .., (last, last_start)] = &tokens[..] {
+        if !is_opening(first) {
+            let (start, end) = (*start, last_start.last_of(last));
+            tokens.insert(0, ("(".to_string(), start));
+            tokens.push((")".to_string(), end));
+        }
     }
 
     tokens
 }
 
-/// Parses an expression from a slice of tokens.
+/// Counts the lists that are still open at the end of `input`, e.g. 2 for
+/// `(let x (+ 1`, so that the REPL can tell when a line needs to be continued
+/// and how far to indent the continuation. Parens within strings and comments
+/// aren't counted, and neither are extra closing parens.
+pub fn open_lists(input: &str) -> usize {
+    let mut tokenizer = Tokenizer::new();
+
+    input.chars().for_each(|ch| tokenizer.push(ch));
+
+    tokenizer.take_tokens().iter().fold(0, |depth, (token, _)| match &token[..] {
+        _ if is_opening(token) => depth + 1,
+        ")" | "]" => depth.saturating_sub(1),
+        _ => depth
+    })
+}
+
+/// Builds the [`SpanTree`] for the first expression in `tokens`, mirroring
+/// [`parse()`].
+///
+/// # Returns
+///
+/// The tree and the remaining tokens, or `None` if the parens are unbalanced.
+pub fn parse_spans(tokens: &[(String, Span)]) -> Option<(SpanTree, &[(String, Span)])> {
+    let ((token, span), mut rest) = tokens.split_first()?;
+    let mut tree = SpanTree { span: *span, end: span.last_of(token), children: Vec::new() };
+
+    if is_opening(token) {
+        // The `list` that a `[` is read as
+        if token == "[" {
+            tree.children.push(SpanTree { span: *span, end: *span, children: Vec::new() });
+        }
+
+        loop {
+            let ((head, end), tail) = rest.split_first()?;
+            if head == ")" || head == "]" {
+                tree.end = *end;
+                return Some((tree, tail));
+            }
+
+            let (child, unparsed) = parse_spans(rest)?;
+            tree.children.push(child);
+            rest = unparsed;
+        }
+    }
+
+    Some((tree, rest))
+}
+
+/// Finds everywhere that the expression `target` appears in the code
+/// `source`. Expressions are only compared by value, so there's no telling
+/// which of several identical ones an expression was read from.
+///
+/// # Returns
+///
+/// The positions of the first and last characters of each appearance of
+/// `target`, in order.
+pub fn locate(source: &str, target: &CrispExpr) -> Vec<(Span, Span)> {
+    let tokens = tokenize_with_spans(source);
+    let strings: Vec<String> = tokens.iter().map(|(token, _)| token.clone()).collect();
+
+    let (Ok((expr, _)), Some((tree, _))) = (parse(&strings), parse_spans(&tokens)) else {
+        return Vec::new();
+    };
+
+    tree.find_all(&expr, target).into_iter().map(|found| (found.span, found.end)).collect()
+}
+
+/// Reads expressions from any [`BufRead`] source, pulling in input a line at a
+/// time only as it's needed. Unlike [`tokenize()`], the whole program doesn't
+/// have to be held in memory, so very large files (or a program piped in on
+/// stdin) can be evaluated as they are read.
+///
+/// Since the input is consumed as a stream of expressions, the outer parens
+/// may not be left off as they can be with [`tokenize()`]; each atom or
+/// parenthesized list is its own expression.
+///
+/// # Examples
+///
+/// ```
+/// let mut reader = Reader::new("(+ 1 2) foo".as_bytes());
+///
+/// assert_eq!(reader.read_expr().unwrap(), Some(list![sym!("+"), Number(1.0), Number(2.0)]));
+/// assert_eq!(reader.read_expr().unwrap(), Some(sym!("foo")));
+/// assert_eq!(reader.read_expr().unwrap(), None);
+/// ```
+pub struct Reader<R: BufRead> {
+    input: R,
+    tokenizer: Tokenizer,
+    tokens: VecDeque<String>,
+    line: String,
+    /// Reused for every expression that's read.
+    ast: Ast,
+    eof: bool
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(input: R) -> Self {
+        Reader {
+            input,
+            tokenizer: Tokenizer::new(),
+            tokens: VecDeque::new(),
+            line: String::new(),
+            ast: Ast::new(),
+            eof: false
+        }
+    }
+
+    /// Pulls the next token from the input, reading more lines if needed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(token))` for each token in the input.
+    /// * `Ok(None)` once the input has run out.
+    /// * `Err(error)` if the input couldn't be read.
+    pub fn next_token(&mut self) -> Result<Option<String>, CrispError> {
+        while self.tokens.is_empty() && !self.eof {
+            self.line.clear();
+
+            match self.input.read_line(&mut self.line) {
+                Ok(0) => {
+                    self.tokenizer.finish();
+                    self.eof = true;
+                },
+
+                Ok(_) => self.line.chars().for_each(|ch| self.tokenizer.push(ch)),
+
+                Err(e) => return standard_error!(format!("Error reading input: {}", e))
+            }
+
+            self.tokens.extend(self.tokenizer.take_tokens().into_iter().map(|(token, _)| token));
+        }
+
+        Ok(self.tokens.pop_front())
+    }
+
+    /// Reads and parses the next complete expression from the input.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(expr))` for each expression in the input.
+    /// * `Ok(None)` once the input has run out.
+    /// * `Err(error)` if the input couldn't be read or parsed.
+    pub fn read_expr(&mut self) -> Result<Option<CrispExpr>, CrispError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let token = match self.next_token()? {
+                Some(token) => token,
+                None if tokens.is_empty() => return Ok(None),
+                None => return parse_error!("Couldn't find closing `)`.")
+            };
+
+            match &token[..] {
+                _ if is_opening(&token) => depth += 1,
+                ")" | "]" if depth == 0 => return parse_error!(format!("Unexpected `{}`.", token)),
+                ")" | "]" => depth -= 1,
+                _ => ()
+            }
+
+            tokens.push(token);
+
+            if depth == 0 {
+                self.ast.clear();
+                let (root, _) = parse_into(&tokens, &mut self.ast)?;
+                return Ok(Some(self.ast.take_expr(root)));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<CrispExpr, CrispError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_expr().transpose()
+    }
+}
+
+/// How deeply lists may be nested in code that is read. Each level takes up
+/// some of the stack, both to read it and to evaluate it, so without a limit
+/// a long enough run of `(` would overflow the stack rather than raising an
+/// error.
+const MAX_NESTING: usize = 256;
+
+/// Parses an expression from a slice of tokens. A list in square brackets is
+/// read as a call to `list`, so `[1 x]` is the same as `(list 1 x)`.
 ///
 /// # Returns
 ///
@@ -129,61 +741,176 @@ pub fn tokenize(input: String) -> Vec<String> {
 ///   expression and `rest` is the remaining unparsed tokens.
 /// * `Err(error)` if an error occurs during parsing.
 pub fn parse<'a>(tokens: &'a[String]) -> Result<(CrispExpr, &'a[String]), CrispError> {
+    let mut ast = Ast::new();
+    let (root, rest) = parse_into(tokens, &mut ast)?;
+    Ok((ast.take_expr(root), rest))
+}
+
+/// A token that [`parse_into()`] can read: either just its text, as given by
+/// [`tokenize()`], or along with the [`Span`] at which it starts, as given by
+/// [`tokenize_with_spans()`], in which case the span of every node is
+/// recorded in the [`Ast`].
+pub trait Token {
+    fn text(&self) -> &str;
+    fn span(&self) -> Option<Span>;
+}
+
+impl Token for String {
+    fn text(&self) -> &str {
+        self
+    }
+
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+impl Token for (String, Span) {
+    fn text(&self) -> &str {
+        &self.0
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.1)
+    }
+}
+
+/// Parses an expression like [`parse()`], into the arena `ast`, which can be
+/// reused (see [`Ast::clear()`]) to read many expressions without allocating
+/// for each.
+///
+/// # Returns
+///
+/// The node of the parsed expression and the remaining unparsed tokens.
+pub fn parse_into<'a, T: Token>(
+    tokens: &'a[T],
+    ast: &mut Ast
+) -> Result<(NodeId, &'a[T]), CrispError> {
+    let mark = ast.open();
+    let result = parse_nested(tokens, ast, 0);
+    if result.is_err() {
+        ast.abandon(mark);
+    }
+
+    result
+}
+
+/// Parses an expression like [`parse_into()`], within `depth` lists.
+fn parse_nested<'a, T: Token>(
+    tokens: &'a[T],
+    ast: &mut Ast,
+    depth: usize
+) -> Result<(NodeId, &'a[T]), CrispError> {
     if let Some((head, tail)) = tokens.split_first() {
-        match &head[..] {
-            "(" => parse_seq(tail),
-            ")" => parse_error!("Unexpected `)`."),
-            _ => Ok((parse_atom(head)?, tail))
+        let text = head.text();
+        if is_opening(text) && depth >= MAX_NESTING {
+            return parse_error!(format!("Lists can't be nested more than {} deep.",
+                                        MAX_NESTING));
+        }
+
+        let (expand, closing) = match (reader_macro_opening(text), text) {
+            (Some((expand, closing)), _) => (Some(expand), closing),
+            (None, "(") => (None, ")"),
+            (None, "[") => (None, "]"),
+            (None, ")" | "]") => return parse_error!(format!("Unexpected `{}`.", text)),
+            (None, _) => {
+                let atom = ast.atom(parse_atom(text)?);
+                if let Some(span) = head.span() {
+                    ast.set_span(atom, span, span.last_of(text));
+                }
+
+                return Ok((atom, tail));
+            }
+        };
+
+        let mark = ast.open();
+        // The `list` that a `[` is read as
+        if closing == "]" {
+            let list = ast.atom(sym!("list"));
+            if let Some(span) = head.span() {
+                ast.set_span(list, span, span);
+            }
+            ast.push(list);
+        }
+        let (end, rest) = parse_seq(tail, closing, ast, depth + 1)?;
+
+        let node = match expand {
+            Some(expand) => {
+                let forms = CrispExpr::List(ast.close_into_exprs(mark));
+                ast.atom(expand(forms)?)
+            },
+            None => ast.close(mark)
+        };
+        if let (Some(start), Some(end)) = (head.span(), end) {
+            ast.set_span(node, start, end);
         }
+
+        Ok((node, rest))
     } else {
-        Ok((CrispExpr::Nil, &[]))
+        Ok((ast.atom(CrispExpr::Nil), &[]))
     }
 }
 
-/// Parses a sequence after an opening `(`, all the way up until the closing `)`.
-/// This calls [`parse()`] to parse the atom, and recurses back and forth with it
-/// if necessary to handle nesting.
-fn parse_seq<'a>(token_slice: &'a[String]) -> Result<(CrispExpr, &'a[String]), CrispError> {
-    let mut res: Vec<CrispExpr> = vec![];
+/// Parses a sequence after an opening `(` or `[`, all the way up until the
+/// `closing` token, pushing each expression onto the list being read in `ast`.
+/// This calls [`parse_nested()`] to parse the atom, and recurses back and
+/// forth with it if necessary to handle nesting.
+///
+/// # Returns
+///
+/// The [`Span`] of the `closing` token, if known, and the tokens after it.
+fn parse_seq<'a, T: Token>(
+    token_slice: &'a[T],
+    closing: &str,
+    ast: &mut Ast,
+    depth: usize
+) -> Result<(Option<Span>, &'a[T]), CrispError> {
     let mut tokens = token_slice;
 
     loop {
         let (head, tail) = tokens.split_first().ok_or_else(||
-            parse_error_unwrapped!("Couldn't find closing `)`.")
+            parse_error_unwrapped!(format!("Couldn't find closing `{}`.", closing))
         )?;
 
-        if head == ")" {
+        if head.text() == closing {
             // Skip closing `)`
-            return Ok((CrispExpr::List(res), tail))
+            return Ok((head.span(), tail))
         }
 
-        let (expr, unparsed) = parse(&tokens)?;
-        res.push(expr);
+        let (expr, unparsed) = parse_nested(tokens, ast, depth)?;
+        ast.push(expr);
         tokens = unparsed;
     }
 }
 
 /// Parses an atom out of an individual token.
 fn parse_atom(token: &str) -> Result<CrispExpr, CrispError> {
+    if let Some(tag) = heredoc_tag(token) {
+        return parse_error!(format!("Couldn't find the end of the heredoc `{}`.", tag));
+    }
+
     let expr = match token.as_ref() {
         "true" => CrispExpr::Bool(true),
         "false" => CrispExpr::Bool(false),
         "nil" => CrispExpr::Nil,
 
+        "" => return parse_error!("Found an empty token."),
+
         _ => {
-            match token.chars().next().unwrap() {
-                ',' => {
-                    CrispExpr::Char(token.chars().nth(1).unwrap())
-                },
+            match token.chars().next() {
+                Some(',') => CrispExpr::Char(parse_char(&token[1..])?),
 
-                '"' | '\'' => {
-                    unescape(token).map(CrispExpr::CrispString)
-                                   .map_err(|_| parse_error_unwrapped!("Invalid string."))?
+                Some('"' | '\'') => match parse_interpolation(token)? {
+                    Some(call) => call,
+                    None => unescape(token).map(|s| CrispExpr::CrispString(s.into()))
+                                           .map_err(|_| parse_error_unwrapped!("Invalid string."))?
                 },
 
-                _ => {
-                    token.parse().map(CrispExpr::Number)
-                                 .unwrap_or_else(|_| sym!(token))
+                _ => match parse_range(token)? {
+                    Some(range) => range,
+                    None => parse_decimal(token)
+                                .or_else(|| token.parse().map(CrispExpr::Number).ok())
+                                .unwrap_or_else(|| sym!(token))
                 }
             }
         }
@@ -192,6 +919,202 @@ fn parse_atom(token: &str) -> Result<CrispExpr, CrispError> {
     Ok(expr)
 }
 
+/// The tag of a token opening a heredoc, e.g. `END` for `<<~END`. A tag is
+/// made of letters, digits and `_`, and must start with a letter or `_`.
+fn heredoc_tag(token: &str) -> Option<&str> {
+    let tag = token.strip_prefix("<<~")?;
+    let mut chars = tag.chars();
+
+    let valid = chars.next().is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+                && chars.all(|ch| ch.is_alphanumeric() || ch == '_');
+    valid.then_some(tag)
+}
+
+/// Parses a double-quoted string containing `$name` or `${name}` into a call
+/// to `format`, with each name replaced by `{}` and passed as an argument, so
+/// that it's replaced with the value of the variable. For example,
+/// `"Hi, $name!"` is read as `(format "Hi, {}!" name)`.
+///
+/// A name is made of letters, digits, `_` and `-`, and must start with a
+/// letter or `_`, so e.g. `"$5"` is left alone. `${name}` can be used for
+/// other names, or to end one before characters that would otherwise be part
+/// of it. `\$` is a literal `$`. Single-quoted strings aren't
+/// interpolated.
+///
+/// # Returns
+///
+/// The call, or `None` if `token` isn't a string with anything to interpolate.
+fn parse_interpolation(token: &str) -> Result<Option<CrispExpr>, CrispError> {
+    let Some(body) = token.strip_prefix('"').and_then(|body| body.strip_suffix('"')) else {
+        return Ok(None);
+    };
+
+    let chars: Vec<char> = body.chars().collect();
+    // The text around the names, still escaped
+    let mut pieces = vec![String::new()];
+    let mut names = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let Some(piece) = pieces.last_mut() else {
+            break;
+        };
+
+        match chars[i] {
+            // Escapes (including `\$`) are kept to be unescaped with the rest
+            '\\' => {
+                piece.extend(&chars[i..(i + 2).min(chars.len())]);
+                i += 2;
+            },
+
+            '$' => match interpolated_name(&chars[i + 1..]) {
+                Some((name, length)) => {
+                    names.push(sym!(name));
+                    pieces.push(String::new());
+                    i += 1 + length;
+                },
+                None => {
+                    piece.push('$');
+                    i += 1;
+                }
+            },
+
+            ch => {
+                piece.push(ch);
+                i += 1;
+            }
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    let mut format_str = String::new();
+    for (n, piece) in pieces.iter().enumerate() {
+        if n > 0 {
+            format_str.push_str("{}");
+        }
+
+        let text = unescape(&format!("\"{}\"", piece))
+                       .map_err(|_| parse_error_unwrapped!("Invalid string."))?;
+        format_str.push_str(&text.replace('{', "{{").replace('}', "}}"));
+    }
+
+    let mut call = vec![sym!("format"), str!(format_str)];
+    call.extend(names);
+
+    Ok(Some(CrispExpr::List(call)))
+}
+
+/// Reads the name to interpolate from the characters after a `$`, for
+/// [`parse_interpolation()`].
+///
+/// # Returns
+///
+/// The name and how many characters it took up (including any braces), or
+/// `None` if there isn't one.
+fn interpolated_name(chars: &[char]) -> Option<(String, usize)> {
+    match chars.first()? {
+        '{' => {
+            let length = chars.iter().position(|&ch| ch == '}')?;
+            let name: String = chars[1..length].iter().collect();
+            (!name.is_empty()).then_some((name, length + 1))
+        },
+
+        ch if ch.is_alphabetic() || *ch == '_' => {
+            let mut length = chars.iter()
+                                  .take_while(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-'))
+                                  .count();
+            // e.g. `"$low-$high"`
+            while chars[length - 1] == '-' {
+                length -= 1;
+            }
+
+            Some((chars[..length].iter().collect(), length))
+        },
+
+        _ => None
+    }
+}
+
+/// Parses a range such as `1..10` or `1..=n` into a call to `range` or
+/// `range-inclusive`. Either end may be a number or a symbol.
+///
+/// # Returns
+///
+/// The call, or `None` if `token` isn't a range.
+fn parse_range(token: &str) -> Result<Option<CrispExpr>, CrispError> {
+    let (function, (start, end)) = match (token.split_once("..="), token.split_once("..")) {
+        (Some(bounds), _) => ("range-inclusive", bounds),
+        (None, Some(bounds)) => ("range", bounds),
+        _ => return Ok(None)
+    };
+
+    // e.g. the spread `...xs`, or a symbol such as `..`
+    if start.is_empty() || end.is_empty() || end.starts_with('.') {
+        return Ok(None);
+    }
+
+    Ok(Some(list![sym!(function), parse_atom(start)?, parse_atom(end)?]))
+}
+
+/// Parses a [`Decimal`](CrispExpr) literal, a number followed by `d`, e.g.
+/// `1.10d`.
+///
+/// # Returns
+///
+/// The `Decimal`, or `None` if `token` isn't one.
+#[cfg(feature = "decimal")]
+fn parse_decimal(token: &str) -> Option<CrispExpr> {
+    token.strip_suffix('d')?.parse().ok().map(CrispExpr::Decimal)
+}
+
+/// Without the `decimal` feature, tokens such as `1.10d` are symbols.
+#[cfg(not(feature = "decimal"))]
+fn parse_decimal(_token: &str) -> Option<CrispExpr> {
+    None
+}
+
+impl FromStr for CrispExpr {
+    type Err = CrispError;
+
+    /// Reads a single expression from `s` without evaluating it. As with
+    /// [`tokenize()`], the outer parens may be left off.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s.to_string());
+        let (expr, rest) = parse(&tokens)?;
+
+        if !rest.is_empty() {
+            return parse_error!("Unexpected tokens after the end of the expression.");
+        }
+
+        Ok(expr)
+    }
+}
+
+/// Parses the part of a char literal after the `,`. This is either a single
+/// character, the name of one of the [`CHAR_NAMES`], or `u` followed by a
+/// hexadecimal unicode code point.
+fn parse_char(literal: &str) -> Result<char, CrispError> {
+    let mut chars = literal.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => return Ok(ch),
+        (None, _) => return parse_error!("Expected a character after `,`."),
+        _ => ()
+    }
+
+    if let Some((_, ch)) = CHAR_NAMES.iter().find(|(name, _)| *name == literal) {
+        return Ok(*ch);
+    }
+
+    literal.strip_prefix('u')
+           .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+           .and_then(char::from_u32)
+           .ok_or_else(|| parse_error_unwrapped!(format!("Unknown character: ,{}", literal)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +1135,21 @@ mod tests {
                    vec!["(", "*", "5", "(", "+", "3", "2", ")", ")"]);
     }
 
+    #[test]
+    fn test_tokenize_crlf() {
+        assert_eq!(tokenize("(* 5\r\n    (+ 3 2))".to_string()),
+                   vec!["(", "*", "5", "(", "+", "3", "2", ")", ")"]);
+
+        assert_eq!(tokenize("(+ 3 var) ;test\r\n".to_string()),
+                   vec!["(", "+", "3", "var", ")"]);
+
+        assert_eq!(tokenize("(* 5 ; wtf\r\n    (+ 3 2))".to_string()),
+                   vec!["(", "*", "5", "(", "+", "3", "2", ")", ")"]);
+
+        assert_eq!(tokenize("+ 3 var\r\n".to_string()),
+                   vec!["(", "+", "3", "var", ")"]);
+    }
+
     #[test]
     fn test_tokenize_chars() {
         assert_eq!(tokenize("(,a)".to_string()),
@@ -222,6 +1160,9 @@ mod tests {
 
         assert_eq!(tokenize("(,a,b,c)".to_string()),
                    vec!["(", ",a", ",b", ",c", ")"]);
+
+        assert_eq!(tokenize("(,space ,u00e9,( ,))".to_string()),
+                   vec!["(", ",space", ",u00e9", ",(", ",)", ")"]);
     }
 
     #[test]
@@ -244,6 +1185,10 @@ mod tests {
         assert_eq!(tokenize("('foo' '(bar) baz')".to_string()),
                    vec!["(", "'foo'", "'(bar) baz'", ")"]);
 
+        // The other kind of quote doesn't end the string
+        assert_eq!(tokenize("(\"it's\" 'say \"hi\"')".to_string()),
+                   vec!["(", "\"it's\"", "'say \"hi\"'", ")"]);
+
         // `tokenize()` does not unescape the strings:
 
         assert_eq!(tokenize("(\"foo \\\"(bar)\\\" baz\")".to_string()),
@@ -252,10 +1197,82 @@ mod tests {
         assert_eq!(tokenize("(\"foo\\n\\tbar\")".to_string()),
                    vec!["(", "\"foo\\n\\tbar\"", ")"]);
 
+        // An escaped backslash doesn't escape the closing quote
+        assert_eq!(tokenize("(\"foo\\\\\" bar)".to_string()),
+                   vec!["(", "\"foo\\\\\"", "bar", ")"]);
+
         assert_eq!(tokenize("(\"Pok\\u{00e9}mon\")".to_string()),
                    vec!["(", "\"Pok\\u{00e9}mon\"", ")"]);
     }
 
+    #[test]
+    fn test_tokenize_heredocs() {
+        assert_eq!(tokenize("(puts <<~END)\n    hello\n      world\n  END\n".to_string()),
+                   vec!["(", "puts", "\"hello\\n  world\\n\"", ")"]);
+
+        // Blank lines are kept, and nothing is escaped
+        assert_eq!(tokenize("puts <<~END\n  a $b\n\n  'c'\\n\nEND".to_string()),
+                   vec!["(", "puts", "\"a \\$b\\n\\n'c'\\\\n\\n\"", ")"]);
+
+        // A tag that isn't closed is left as it is
+        assert_eq!(tokenize("puts <<~END\n  a\n".to_string()),
+                   vec!["(", "puts", "<<~END", ")"]);
+        crisp_assert_err!("puts <<~END\n  a\n".parse::<CrispExpr>(), ParseError);
+
+        assert_eq!("str-concat <<~A 'b'\n  a\n  A".parse::<CrispExpr>().unwrap(),
+                   list![sym!("str-concat"), str!("a\n"), str!("b")]);
+    }
+
+    #[test]
+    fn test_reader_macros() {
+        // Reads `^(a b)` as `(list (quote a) (quote b))`
+        register_reader_macro('^', |forms| match forms {
+            CrispExpr::List(forms) => Ok(CrispExpr::List(
+                std::iter::once(sym!("list")).chain(forms.into_iter().map(|form| {
+                    list![sym!("quote"), form]
+                })).collect()
+            )),
+            _ => unreachable!()
+        }).unwrap();
+
+        assert_eq!(tokenize("(f ^(a b) ^ (c))".to_string()),
+                   vec!["(", "f", "^(", "a", "b", ")", "^", "(", "c", ")", ")"]);
+        assert_eq!("puts ^[a (b)]".parse::<CrispExpr>().unwrap(),
+                   list![sym!("puts"), list![sym!("list"), list![sym!("quote"), sym!("list")],
+                                             list![sym!("quote"), sym!("a")],
+                                             list![sym!("quote"), list![sym!("b")]]]]);
+
+        let mut reader = Reader::new("^(x) ^(y".as_bytes());
+        assert_eq!(reader.read_expr().unwrap(),
+                   Some(list![sym!("list"), list![sym!("quote"), sym!("x")]]));
+        crisp_assert_err!(reader.read_expr(), ParseError);
+
+        for dispatch in ['a', '1', ' ', '(', ',', '\''] {
+            crisp_assert_err!(register_reader_macro(dispatch, Ok), StandardError);
+        }
+    }
+
+    #[test]
+    fn test_shorthand_lambdas() {
+        let lambda = |args: &[&str], body| {
+            list![sym!("\\"), CrispExpr::List(args.iter().map(|arg| sym!(arg)).collect()), body]
+        };
+
+        assert_eq!("#(* % 2)".parse::<CrispExpr>().unwrap(),
+                   lambda(&["%1"], list![sym!("*"), sym!("%1"), Number(2.0)]));
+        assert_eq!("#(f %3 (g %))".parse::<CrispExpr>().unwrap(),
+                   lambda(&["%1", "%2", "%3"],
+                          list![sym!("f"), sym!("%3"), list![sym!("g"), sym!("%1")]]));
+        assert_eq!("#[% %]".parse::<CrispExpr>().unwrap(),
+                   lambda(&["%1"], list![sym!("list"), sym!("%1"), sym!("%1")]));
+        assert_eq!("#(now)".parse::<CrispExpr>().unwrap(), lambda(&[], list![sym!("now")]));
+        // `%0` isn't a placeholder
+        assert_eq!("#(f %0)".parse::<CrispExpr>().unwrap(),
+                   lambda(&[], list![sym!("f"), sym!("%0")]));
+
+        crisp_assert_err!("#(map #(+ % 1) %)".parse::<CrispExpr>(), ParseError);
+    }
+
     #[test]
     fn test_tokenize_no_outer_parens() {
         assert_eq!(tokenize("1".to_string()),
@@ -292,6 +1309,21 @@ mod tests {
         assert!(line_comment.is_empty());
     }
 
+    #[test]
+    fn test_open_lists() {
+        assert_eq!(open_lists(""), 0);
+        assert_eq!(open_lists("let x 5"), 0);
+        assert_eq!(open_lists("(+ 1 2)"), 0);
+        assert_eq!(open_lists("(let x (+ 1"), 2);
+        assert_eq!(open_lists("(let x (+ 1\n  2)"), 1);
+        assert_eq!(open_lists("map #(* % 2) [1 2"), 1);
+
+        // Only real parens are counted
+        assert_eq!(open_lists("puts \"(\" ; ("), 0);
+        assert_eq!(open_lists("list ,( ,["), 0);
+        assert_eq!(open_lists("(+ 1 2))) (foo"), 1);
+    }
+
     #[test]
     fn test_parse_bool() {
         assert_eq!(parse_atom("true").unwrap(), Bool(true));
@@ -305,6 +1337,18 @@ mod tests {
         assert_eq!(parse_atom(",\\").unwrap(), Char('\\'));
         assert_eq!(parse_atom(",\"").unwrap(), Char('"'));
         assert_eq!(parse_atom(",'").unwrap(), Char('\''));
+
+        // Named and unicode chars
+        assert_eq!(parse_atom(",space").unwrap(), Char(' '));
+        assert_eq!(parse_atom(",newline").unwrap(), Char('\n'));
+        assert_eq!(parse_atom(",tab").unwrap(), Char('\t'));
+        assert_eq!(parse_atom(",u00e9").unwrap(), Char('é'));
+        assert_eq!(parse_atom(",u1F600").unwrap(), Char('😀'));
+        assert_eq!(parse_atom(",u").unwrap(), Char('u'));
+
+        crisp_assert_err!(parse_atom(",foo"), ParseError);
+        crisp_assert_err!(parse_atom(",uD800"), ParseError);
+        crisp_assert_err!(parse_atom(","), ParseError);
     }
 
     #[test]
@@ -339,6 +1383,40 @@ mod tests {
         assert_eq!(parse_atom("-420").unwrap(), Number(-420.0));
     }
 
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_atom("1..10").unwrap(),
+                   list![sym!("range"), Number(1.0), Number(10.0)]);
+        assert_eq!(parse_atom("0..=n").unwrap(),
+                   list![sym!("range-inclusive"), Number(0.0), sym!("n")]);
+        assert_eq!(parse_atom("-1.5..2").unwrap(),
+                   list![sym!("range"), Number(-1.5), Number(2.0)]);
+
+        // Not ranges
+        assert_eq!(parse_atom("...xs").unwrap(), sym!("...xs"));
+        assert_eq!(parse_atom("..").unwrap(), sym!(".."));
+        assert_eq!(parse_atom("1..").unwrap(), sym!("1.."));
+    }
+
+    #[test]
+    fn test_parse_interpolation() {
+        let format = |format_str: &str, names: &[&str]| {
+            let mut call = vec![sym!("format"), str!(format_str)];
+            call.extend(names.iter().map(|name| sym!(name)));
+            CrispExpr::List(call)
+        };
+
+        assert_eq!(parse_atom("\"Hi, $name!\"").unwrap(), format("Hi, {}!", &["name"]));
+        assert_eq!(parse_atom("\"$low-$high\"").unwrap(), format("{}-{}", &["low", "high"]));
+        assert_eq!(parse_atom("\"${n}th {x}\\t\"").unwrap(), format("{}th {{x}}\t", &["n"]));
+        assert_eq!(parse_atom("\"$first-name\"").unwrap(), format("{}", &["first-name"]));
+
+        // Nothing to interpolate
+        assert_eq!(parse_atom("\"\\$name costs $5 ${}\"").unwrap(), str!("$name costs $5 ${}"));
+        assert_eq!(parse_atom("'$name'").unwrap(), str!("$name"));
+        assert_eq!(parse_atom("\"{x}\"").unwrap(), str!("{x}"));
+    }
+
     #[test]
     fn test_parse_symbol() {
         assert_eq!(parse_atom("foo").unwrap(), sym!("foo"));
@@ -363,6 +1441,37 @@ mod tests {
         assert!(remaining_tokens.is_empty());
     }
 
+    #[test]
+    fn test_parse_into() {
+        let mut ast = Ast::new();
+
+        let tokens = tokenize("(f [1 (g)] #(+ % 1)) x".to_string());
+        let (root, rest) = parse_into(&tokens, &mut ast).unwrap();
+        assert_eq!(rest, ["x"]);
+        assert_eq!(ast.children(root).len(), 3);
+        assert_eq!(ast.take_expr(root), list![
+            sym!("f"),
+            list![sym!("list"), Number(1.0), list![sym!("g")]],
+            list![sym!("\\"), list![sym!("%1")], list![sym!("+"), sym!("%1"), Number(1.0)]]
+        ]);
+
+        // The arena can be reused, even after an error
+        crisp_assert_err!(parse_into(&tokenize("(a (b".to_string()), &mut ast), ParseError);
+        let (root, _) = parse_into(&tokenize("(c)".to_string()), &mut ast).unwrap();
+        assert_eq!(ast.take_expr(root), list![sym!("c")]);
+
+        // Spans are recorded if the tokens have them
+        let at = |line, column| Span { line, column };
+        let (root, _) = parse_into(&tokenize_with_spans("f [1\n   'ab'] #(g)"), &mut ast).unwrap();
+        let &[f, list, lambda] = ast.children(root) else { panic!() };
+        assert_eq!(ast.span(root), Some((at(1, 1), at(2, 13))));
+        assert_eq!(ast.span(f), Some((at(1, 1), at(1, 1))));
+        assert_eq!(ast.span(list), Some((at(1, 3), at(2, 8))));
+        assert_eq!(ast.span(ast.children(list)[0]), Some((at(1, 3), at(1, 3))));
+        assert_eq!(ast.span(ast.children(list)[2]), Some((at(2, 4), at(2, 7))));
+        assert_eq!(ast.span(lambda), Some((at(2, 10), at(2, 13))));
+    }
+
     #[test]
     fn test_parse_empty() {
         let tokens = vec![];
@@ -395,4 +1504,295 @@ mod tests {
 
         assert!(remaining_tokens.is_empty());
     }
+
+    #[test]
+    fn test_parse_brackets() {
+        let tokens = tokenize("[1 [x] (+ 2 3) []]".to_string());
+        assert_eq!(tokens, vec!["[", "1", "[", "x", "]", "(", "+", "2", "3", ")", "[", "]", "]"]);
+
+        assert_eq!(parse(&tokens).unwrap().0, list![
+            sym!("list"),
+            Number(1.0),
+            list![sym!("list"), sym!("x")],
+            list![sym!("+"), Number(2.0), Number(3.0)],
+            list![sym!("list")]
+        ]);
+
+        // Implicit outer parens are still added around a bracketed list
+        assert_eq!(tokenize("puts [1]".to_string()), vec!["(", "puts", "[", "1", "]", ")"]);
+
+        crisp_assert_err!(parse(&tokenize("[1 2".to_string())), ParseError);
+        crisp_assert_err!(parse(&tokenize("[1 2)".to_string())), ParseError);
+        crisp_assert_err!(parse(&tokenize("(1 2]".to_string())), ParseError);
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = "(+ 1\n   (* 2 3)) ; comment\nfoo 'a b'\n\n,a,space(x)";
+        let exprs: Vec<CrispExpr> = Reader::new(input.as_bytes()).map(Result::unwrap).collect();
+
+        assert_eq!(exprs, vec![
+            list![sym!("+"), Number(1.0), list![sym!("*"), Number(2.0), Number(3.0)]],
+            sym!("foo"),
+            str!("a b"),
+            Char('a'),
+            Char(' '),
+            list![sym!("x")]
+        ]);
+    }
+
+    #[test]
+    fn test_reader_tokens() {
+        let mut reader = Reader::new("(foo\n'bar\nbaz')".as_bytes());
+
+        assert_eq!(reader.next_token().unwrap(), Some("(".to_string()));
+        assert_eq!(reader.next_token().unwrap(), Some("foo".to_string()));
+        assert_eq!(reader.next_token().unwrap(), Some("'bar\nbaz'".to_string()));
+        assert_eq!(reader.next_token().unwrap(), Some(")".to_string()));
+        assert_eq!(reader.next_token().unwrap(), None);
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let mut reader = Reader::new("(+ 1 2".as_bytes());
+        crisp_assert_err!(reader.read_expr(), ParseError);
+
+        let mut reader = Reader::new(") 5".as_bytes());
+        crisp_assert_err!(reader.read_expr(), ParseError);
+        assert_eq!(reader.read_expr().unwrap(), Some(Number(5.0)));
+    }
+
+    #[test]
+    fn test_tokenize_with_spans() {
+        let at = |line, column| Span { line, column };
+
+        assert_eq!(tokenize_with_spans("(foo\n  'a b' ,c)"), vec![
+            ("(".to_string(), at(1, 1)),
+            ("foo".to_string(), at(1, 2)),
+            ("'a b'".to_string(), at(2, 3)),
+            (",c".to_string(), at(2, 9)),
+            (")".to_string(), at(2, 11))
+        ]);
+
+        // Outer parens which were left off
+        assert_eq!(tokenize_with_spans("puts x"), vec![
+            ("(".to_string(), at(1, 1)),
+            ("puts".to_string(), at(1, 1)),
+            ("x".to_string(), at(1, 6)),
+            (")".to_string(), at(1, 6))
+        ]);
+    }
+
+    #[test]
+    fn test_locate() {
+        let source = "let x (+ 1\n       (* 2 'a'))";
+        let target = list![sym!("*"), Number(2.0), str!("a")];
+
+        let at = |line, column| Span { line, column };
+
+        assert_eq!(locate(source, &target), vec![(at(2, 8), at(2, 16))]);
+        assert_eq!(locate(source, &sym!("x")), vec![(at(1, 5), at(1, 5))]);
+        assert_eq!(locate(source, &str!("a")), vec![(at(2, 13), at(2, 15))]);
+        assert_eq!(locate("+ 1 'two'", &list![sym!("+"), Number(1.0), str!("two")]),
+                   vec![(at(1, 1), at(1, 9))]);
+        assert_eq!(locate("puts 'a\nb'", &str!("a\nb")), vec![(at(1, 6), at(2, 2))]);
+        assert_eq!(locate(source, &sym!("y")), vec![]);
+        assert_eq!(locate("(+ 1", &sym!("+")), vec![]);
+
+        // Every appearance is found
+        assert_eq!(locate("(+ x (* x 2))", &sym!("x")), vec![(at(1, 4), at(1, 4)),
+                                                             (at(1, 9), at(1, 9))]);
+
+        // Bracketed lists line up with the `list` they're read as
+        let target = list![sym!("*"), Number(2.0), str!("a")];
+        assert_eq!(locate("[x [1] (* 2 'a')]", &target), vec![(at(1, 8), at(1, 16))]);
+    }
+
+    /// Generates random expressions for [`test_round_trip()`]. This is a
+    /// xorshift generator with a fixed seed, so that any failure can be
+    /// reproduced. (It stands in for a proptest strategy, which would be a
+    /// dependency just for this; [`shrink()`] makes up for proptest's
+    /// shrinking.)
+    struct ExprGen(u64);
+
+    impl ExprGen {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+            items[self.below(items.len())]
+        }
+
+        /// A character, weighted towards those that need escaping or have a
+        /// special meaning to the reader.
+        fn char(&mut self) -> char {
+            match self.below(4) {
+                0 => self.pick(&['a', 'Z', '0', '_', '-']),
+                1 => self.pick(&[' ', '\n', '\t', '\r', '\0', '\u{7}', '\u{7f}']),
+                2 => self.pick(&['(', ')', '[', ']', '\'', '"', '\\', '$', ',', ';', '#',
+                                 '%', '@', '.', '`', '{', '}']),
+                _ => self.pick(&['é', 'λ', '✓', '日', '🦀'])
+            }
+        }
+
+        fn string(&mut self) -> String {
+            (0..self.below(8)).map(|_| self.char()).collect()
+        }
+
+        fn number(&mut self) -> f64 {
+            match self.below(4) {
+                0 => self.below(1000) as f64,
+                1 => -(self.below(1000) as f64) / 8.0,
+                2 => self.pick(&[0.1, 1e21, 1.5e-8, -2.5e300, f64::MAX, f64::MIN_POSITIVE]),
+                // NaN reads back fine, but isn't equal to itself
+                _ => match f64::from_bits(self.next()) {
+                    n if n.is_nan() => 0.0,
+                    n => n
+                }
+            }
+        }
+
+        /// A symbol which is read back as itself, e.g. not `nil` or `12`.
+        fn symbol(&mut self) -> String {
+            loop {
+                let symbol: String = (0..self.below(6) + 1).map(|_| self.pick(&[
+                    'a', 'z', 'K', '-', '+', '*', '/', '<', '=', '>', '!', '?', '1', '.', ':'
+                ])).collect();
+
+                if symbol.parse::<CrispExpr>().ok() == Some(sym!(symbol)) {
+                    return symbol;
+                }
+            }
+        }
+
+        /// Arbitrary code, made of the characters that mean the most to the
+        /// reader.
+        fn code(&mut self) -> String {
+            (0..self.below(40)).map(|_| match self.below(3) {
+                0 => self.pick(&['(', ')', '[', ']', '#', '%', '"', '\'', '\\', ',', ';', '$',
+                                 '{', '}', '.', '=', '<', '~', '@']),
+                1 => self.pick(&['a', 'u', 'd', 'e', 'A', '_', '-', '+', '0', '1', '2']),
+                _ => self.char()
+            }).collect()
+        }
+
+        fn expr(&mut self, depth: usize) -> CrispExpr {
+            match self.below(if depth == 0 { 6 } else { 8 }) {
+                0 => sym!(self.symbol()),
+                1 => Char(self.char()),
+                2 => str!(self.string()),
+                3 => Number(self.number()),
+                4 => Bool(self.below(2) == 0),
+                5 => Nil,
+                _ => List((0..self.below(5)).map(|_| self.expr(depth - 1)).collect())
+            }
+        }
+    }
+
+    /// Shrinks `expr`, for which `fails` is true, to the smallest expression
+    /// that it's still true for, by repeatedly trying each of its items on
+    /// its own, and itself with each item left out.
+    fn shrink(expr: CrispExpr, fails: impl Fn(&CrispExpr) -> bool) -> CrispExpr {
+        let mut expr = expr;
+
+        'shrinking: loop {
+            let List(list) = &expr else {
+                return expr;
+            };
+
+            let smaller = list.iter().cloned().chain((0..list.len()).map(|i| {
+                List(list.iter().enumerate().filter(|(j, _)| *j != i)
+                                            .map(|(_, item)| item.clone())
+                                            .collect())
+            }));
+
+            for candidate in smaller {
+                if fails(&candidate) {
+                    expr = candidate;
+                    continue 'shrinking;
+                }
+            }
+
+            return expr;
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        fn has_string(expr: &CrispExpr) -> bool {
+            match expr {
+                CrispString(_) => true,
+                List(list) => list.iter().any(has_string),
+                _ => false
+            }
+        }
+
+        let expr = list![Number(1.0), list![sym!("a"), list![str!("b"), Nil]], Bool(true)];
+        assert_eq!(shrink(expr, has_string), str!("b"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut generator = ExprGen(0x5eed_c0ffee);
+
+        // Always wrapped in a list, since the outer parens of a single
+        // expression are optional and so it would be read as one
+        let round_trips = |expr: &CrispExpr| {
+            let expr = List(vec![expr.clone()]);
+            let written = expr.to_string_exact().unwrap();
+            written.parse::<CrispExpr>() == Ok(expr)
+        };
+
+        for _ in 0..2000 {
+            let expr = generator.expr(4);
+
+            if !round_trips(&expr) {
+                let smallest = shrink(expr, |expr| !round_trips(expr));
+                panic!("didn't read back the same: {:?}", smallest);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_nesting_limit() {
+        let nested = |depth| format!("{}{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(nested(MAX_NESTING).parse::<CrispExpr>().is_ok());
+        crisp_assert_err!(nested(MAX_NESTING + 1).parse::<CrispExpr>(), ParseError);
+        crisp_assert_err!(parse(&tokenize("(".repeat(100_000))), ParseError);
+        crisp_assert_err!(parse(&tokenize("#(".repeat(MAX_NESTING + 1))), ParseError);
+    }
+
+    #[test]
+    fn test_parse_empty_token() {
+        crisp_assert_err!(parse(&["".to_string()]), ParseError);
+    }
+
+    /// Feeds arbitrary code to the reader, which should raise errors for it
+    /// rather than panicking. This is a quick check; the `reader` fuzz target
+    /// in `fuzz/` searches much further.
+    #[test]
+    fn test_fuzz() {
+        let mut generator = ExprGen(0xf022_5eed);
+
+        for _ in 0..2000 {
+            let code = generator.code();
+
+            let result = std::panic::catch_unwind(|| {
+                let _ = parse(&tokenize(code.clone()));
+                let _ = locate(&code, &Nil);
+                Reader::new(code.as_bytes()).take_while(Result::is_ok).count();
+            });
+
+            assert!(result.is_ok(), "panicked reading {:?}", code);
+        }
+    }
 }
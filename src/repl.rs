@@ -1,30 +1,69 @@
-use crate::{CrispExpr, env::initialize_environment, print_return, send};
+use crate::{CrispExpr, env::{CrispEnv, PLACEHOLDER, env_get, initialize_environment},
+            error::CrispError, eval::eval, functions::extract_index, print_return,
+            reader::open_lists, send, warning::take_warnings};
 
-use std::{collections::hash_map::Entry, process};
+use std::{collections::hash_map::Entry, env, ffi::OsString, fs, path::PathBuf, process,
+          time::Instant};
 
 use colored::*;
-use rustyline::{error::ReadlineError, DefaultEditor};
+use directories::{BaseDirs, ProjectDirs};
+use rustyline::{completion::Completer, error::ReadlineError, highlight::Highlighter,
+                hint::Hinter, history::DefaultHistory,
+                validate::{ValidationContext, ValidationResult, Validator},
+                Cmd, ConditionalEventHandler, Config, Editor, Event, EventContext, EventHandler,
+                Helper, KeyCode, KeyEvent, Modifiers, RepeatCount};
 
 /// The Read-Eval-Print Loop (REPL). Continually prompts the user for
 /// expressions, which it evaluates immediately and prints the return value,
 /// maintaining an environment so the user may execute a program line-by-line.
-pub fn run() {
-    // Find the directory that the executable is running in; this is
-    // where we will save the history file.
-    let binding = std::env::current_exe()
-        .unwrap()
-        .canonicalize()
-        .expect("The current executable should exist.");
-    let dir = binding.parent()
-        .expect("The current executable should be a file.")
-        .to_string_lossy()
-        .to_owned();
-    let history_file: &str = &format!("{}/repl_history", dir);
-
-    let mut rl = DefaultEditor::new().unwrap();
-    let _ = rl.load_history(history_file);
-
+///
+/// The [`rc_file()`], if there is one, and then each of the files in `preload`
+/// are [`load`](crate::keywords)ed into the environment before the first
+/// prompt, so that anything they define can be explored interactively. The
+/// rc file may also change the REPL's [`settings()`].
+///
+/// A line with lists left open is continued on the next line, indented to
+/// match; see [`CrispHelper`].
+///
+/// The value of each expression is kept so that it can be built on later,
+/// without typing the expression again; see [`record_result()`]. A line may
+/// also be one of the commands handled by [`run_command()`].
+pub fn run(preload: &[String]) {
     let env = &mut initialize_environment();
+    let mut results = 0;
+
+    for (name, value) in settings() {
+        env.data.insert(name.to_string(), value);
+    }
+
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let files = rc_file(env::var_os("CRISPRC"), home).filter(|file| file.is_file())
+                                                     .map(|file| file.display().to_string())
+                                                     .into_iter()
+                                                     .chain(preload.iter().cloned());
+
+    for file in files {
+        if let Err(e) = preload_file(&file, env) {
+            eprintln!("{}", e);
+        }
+    }
+
+    if !matches!(env_get("*color*", env), Some(CrispExpr::Bool(true))) {
+        colored::control::set_override(false);
+    }
+
+    let history_file = history_file(
+        ProjectDirs::from("", "", "crisp").map(|dirs| dirs.data_dir().to_path_buf())
+    );
+
+    let config = Config::builder().max_history_size(history_size(env))
+                                  .unwrap_or_else(|_| Config::builder())
+                                  .build();
+    let mut rl = Editor::<CrispHelper, DefaultHistory>::with_config(config).unwrap();
+    rl.set_helper(Some(CrispHelper));
+    rl.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::NONE),
+                     EventHandler::Conditional(Box::new(CrispHelper)));
+    let _ = rl.load_history(&history_file);
 
     loop {
         // Increment/get the current line count. If the value is
@@ -51,16 +90,29 @@ pub fn run() {
             }
         };
 
-        let readline = rl.readline(&format!("crisp:{:03}> ", repl_line_count));
+        let readline = rl.readline(&prompt(repl_line_count, env));
         match readline {
             Ok(line) => {
                 let str = line.as_str();
 
-                match send(str.to_string(), env) {
-                    Ok(ret) => print_return(&ret),
-                    Err(e) => eprintln!("{}", e)
+                match run_command(str, env) {
+                    Some(Ok(output)) => println!("{}", output),
+                    Some(Err(e)) => eprintln!("{}", e),
+
+                    None => match send(str.to_string(), env) {
+                        Ok(ret) => {
+                            print_return(&ret);
+                            results += 1;
+                            record_result(ret, results, env);
+                        },
+                        Err(e) => eprintln!("{}", e)
+                    }
                 };
 
+                for (warning, _) in take_warnings() {
+                    eprintln!("{}", warning);
+                }
+
                 rl.add_history_entry(str).unwrap_or_else(|err| {
                     // Couldn't add to history, warn and continue
                     let message = match err {
@@ -85,7 +137,7 @@ pub fn run() {
             }
         }
 
-        rl.save_history(history_file).unwrap_or_else(|err| {
+        rl.save_history(&history_file).unwrap_or_else(|err| {
             // Couldn't save history, warn and continue
             let message = match err {
                 ReadlineError::Io(io_err) => io_err.kind().to_string(),
@@ -101,3 +153,369 @@ pub fn run() {
         });
     }
 }
+
+/// Keeps an expression going across lines at the prompt, for as long as it
+/// has lists left open (see [`open_lists()`]). Enter then starts a new line
+/// rather than evaluating the input, and the new line is indented by
+/// [`continuation()`] to match how deeply it's nested.
+///
+/// # Examples
+///
+/// ```lisp
+/// crisp:000> fn fact n (if (<= n 1)
+///   1
+///   (* n (fact (- n 1))))
+/// ```
+struct CrispHelper;
+
+impl Helper for CrispHelper {}
+
+impl Completer for CrispHelper {
+    type Candidate = String;
+}
+
+impl Hinter for CrispHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CrispHelper {}
+
+impl Validator for CrispHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(match open_lists(ctx.input()) {
+            0 => ValidationResult::Valid(None),
+            _ => ValidationResult::Incomplete
+        })
+    }
+}
+
+impl ConditionalEventHandler for CrispHelper {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        match open_lists(ctx.line()) {
+            0 => None,
+            depth => Some(Cmd::Insert(1, continuation(depth)))
+        }
+    }
+}
+
+/// The line break and indentation that continue a line with `depth` lists
+/// left open.
+fn continuation(depth: usize) -> String {
+    format!("\n{}", "  ".repeat(depth))
+}
+
+/// The names of the REPL's settings, each with its default value. They're
+/// bound in the REPL's environment, so they can be changed by binding them
+/// again, either at the prompt or in the [`rc_file()`]:
+///
+/// * `*prompt*` is the prompt shown for each line, where `{}` is replaced by
+///   the line number.
+/// * `*history-size*` is how many lines of history are kept, which only takes
+///   effect when set in the rc file.
+/// * `*color*` is whether output is colored at all, which also only takes
+///   effect when set in the rc file.
+///
+/// # Examples
+///
+/// ```lisp
+/// ; ~/.crisprc
+/// let *prompt* "λ {}> "
+/// let *history-size* 5000
+/// fn sq n (* n n)
+/// ```
+fn settings() -> [(&'static str, CrispExpr); 3] {
+    [
+        ("*prompt*", str!("crisp:{}> ")),
+        ("*history-size*", CrispExpr::Number(100.0)),
+        ("*color*", CrispExpr::Bool(true))
+    ]
+}
+
+/// Finds the rc file, crisp code which is run when the REPL starts. This is
+/// the file named by the `CRISPRC` environment variable `crisprc` if it's set
+/// (an empty value means that there is none), or otherwise `.crisprc` in the
+/// user's `home` directory.
+fn rc_file(crisprc: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+    match crisprc {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => home.map(|home| home.join(".crisprc"))
+    }
+}
+
+/// The prompt for line number `line`, from the `*prompt*` [setting](settings).
+fn prompt(line: f64, env: &CrispEnv) -> String {
+    let format = match env_get("*prompt*", env) {
+        Some(CrispExpr::CrispString(format)) => format.to_string(),
+        _ => "crisp:{}> ".to_string()
+    };
+
+    format.replace("{}", &format!("{:03}", line))
+}
+
+/// How many lines of history to keep, from the `*history-size*`
+/// [setting](settings).
+fn history_size(env: &CrispEnv) -> usize {
+    env_get("*history-size*", env).and_then(|size| extract_index(&size).ok())
+                                  .unwrap_or(100)
+}
+
+/// Runs a REPL command, a line starting with `:`, in place of evaluating it:
+///
+/// * `:type expr` evaluates `expr` and shows the type of its value. Unlike at
+///   the prompt, a lone name isn't called, so e.g. `:type puts` is `Func`.
+/// * `:expand expr` shows `expr` as it's read, without evaluating it, so that
+///   the shorthands which the reader expands (such as `#(...)`, `[...]` and
+///   `1..10`) can be seen for what they are.
+/// * `:time expr` evaluates `expr` and shows its value, along with how long it
+///   took.
+/// * `:memory expr` evaluates `expr` and shows its value, along with how many
+///   allocations it made. This needs the `alloc-stats` feature.
+///
+/// # Returns
+///
+/// What to show, or `None` if `line` isn't a command, in which case it's
+/// evaluated as usual. A [`Symbol`](CrispExpr) such as `:foo` isn't mistaken
+/// for a command.
+///
+/// # Examples
+///
+/// ```lisp
+/// crisp:000> :type [1 2]
+/// List
+/// crisp:001> :expand #(* % 2)
+/// (\ (%1) (* %1 2))
+/// crisp:002> :time foldl + 0 1..100000
+/// => 4999950000
+/// Time: 412.85ms
+/// ```
+fn run_command(line: &str, env: &mut CrispEnv) -> Option<Result<String, CrispError>> {
+    let command = line.trim().strip_prefix(':')?;
+    let (name, expr) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+
+    let output = match name {
+        "type" => expr.parse::<CrispExpr>().and_then(|expr| eval(&expr, env))
+                                           .map(|value| value.type_name().to_string()),
+        "expand" => expr.parse::<CrispExpr>().map(|expr| expr.inspect()),
+        "time" => time(expr, env),
+        "memory" => memory(expr, env),
+        _ => return None
+    };
+
+    Some(output)
+}
+
+/// Evaluates `expr` for `:time`, showing its value and how long it took. See
+/// [`run_command()`].
+fn time(expr: &str, env: &mut CrispEnv) -> Result<String, CrispError> {
+    let expr = expr.parse::<CrispExpr>()?;
+
+    let start = Instant::now();
+    let value = eval(&expr, env)?;
+    let elapsed = start.elapsed();
+
+    Ok(format!("{}{}\nTime: {:.2?}", "=> ".bright_green(), value.inspect(), elapsed))
+}
+
+/// Evaluates `expr` for `:memory`, showing its value and the allocations that
+/// it made. See [`run_command()`].
+#[cfg(feature = "alloc-stats")]
+fn memory(expr: &str, env: &mut CrispEnv) -> Result<String, CrispError> {
+    use crate::alloc_stats::AllocStats;
+
+    let expr = expr.parse::<CrispExpr>()?;
+
+    let before = AllocStats::now();
+    let value = eval(&expr, env)?;
+    let allocated = AllocStats::now().since(before);
+
+    Ok(format!("{}{}\nAllocations: {} ({} bytes)", "=> ".bright_green(), value.inspect(),
+               allocated.allocations, allocated.bytes))
+}
+
+/// Without the `alloc-stats` feature, allocations aren't counted.
+#[cfg(not(feature = "alloc-stats"))]
+fn memory(_expr: &str, _env: &mut CrispEnv) -> Result<String, CrispError> {
+    standard_error!("`:memory` needs crisp to be built with the `alloc-stats` feature.")
+}
+
+/// Binds `ret`, the value of the `n`th expression entered into the REPL which
+/// didn't raise an error, to `$n` (so the first is `$1`) in `env`. It's also
+/// bound to `_`, which always holds the most recent value.
+///
+/// # Examples
+///
+/// ```lisp
+/// crisp:000> + 1 2
+/// => 3
+/// crisp:001> * _ 10
+/// => 30
+/// crisp:002> list $1 $2
+/// => (3 30)
+/// ```
+fn record_result(ret: CrispExpr, n: usize, env: &mut CrispEnv) {
+    env.data.insert(format!("${}", n), ret.clone());
+    env.data.insert(PLACEHOLDER.to_string(), ret);
+}
+
+/// Loads the file `file` into the REPL's environment `env`, relative to the
+/// current directory.
+fn preload_file(file: &str, env: &mut CrispEnv) -> Result<CrispExpr, CrispError> {
+    eval(&list![sym!("load"), CrispExpr::CrispString(file.into())], env)
+}
+
+/// Finds the path of the REPL history file. It is kept in the data directory
+/// `data_dir`, which is normally the platform's (e.g. `~/.local/share/crisp` on
+/// Linux or `%APPDATA%\crisp\data` on Windows), falling back to the directory
+/// that the executable is running in if that can't be found or created.
+fn history_file(data_dir: Option<PathBuf>) -> PathBuf {
+    let dir = data_dir
+        .filter(|dir| fs::create_dir_all(dir).is_ok())
+        .unwrap_or_else(|| {
+            std::env::current_exe()
+                .unwrap()
+                .canonicalize()
+                .expect("The current executable should exist.")
+                .parent()
+                .expect("The current executable should be a file.")
+                .to_path_buf()
+        });
+
+    dir.join("repl_history")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_file() {
+        let dir = std::env::temp_dir().join(format!("crisp-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // The data directory is created if it doesn't exist yet
+        let path = history_file(Some(dir.join("data")));
+        assert_eq!(path, dir.join("data").join("repl_history"));
+        assert!(path.parent().unwrap().is_dir());
+
+        // Falls back to the executable's directory
+        let path = history_file(None);
+        assert_eq!(path.file_name().unwrap(), "repl_history");
+        assert!(path.parent().unwrap().is_dir());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rc_file() {
+        let home = Some(PathBuf::from("/home/me"));
+
+        assert_eq!(rc_file(None, home.clone()), Some(PathBuf::from("/home/me/.crisprc")));
+        assert_eq!(rc_file(None, None), None);
+
+        // Environment variable; an empty value means there isn't one
+        assert_eq!(rc_file(Some(OsString::from("/etc/crisprc")), home.clone()),
+                   Some(PathBuf::from("/etc/crisprc")));
+        assert_eq!(rc_file(Some(OsString::new()), home), None);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut env = initialize_environment();
+
+        // Defaults, before there are any settings
+        assert_eq!(prompt(7.0, &env), "crisp:007> ");
+        assert_eq!(history_size(&env), 100);
+
+        for (name, value) in settings() {
+            env.data.insert(name.to_string(), value);
+        }
+        assert_eq!(prompt(12.0, &env), "crisp:012> ");
+
+        send("let *prompt* \"[{}] λ \"".to_string(), &mut env).unwrap();
+        send("let *history-size* 5000".to_string(), &mut env).unwrap();
+        assert_eq!(prompt(3.0, &env), "[003] λ ");
+        assert_eq!(history_size(&env), 5000);
+
+        // Invalid values are ignored
+        send("let *prompt* 5".to_string(), &mut env).unwrap();
+        send("let *history-size* -1".to_string(), &mut env).unwrap();
+        assert_eq!(prompt(3.0, &env), "crisp:003> ");
+        assert_eq!(history_size(&env), 100);
+    }
+
+    #[test]
+    fn test_continuation() {
+        assert_eq!(continuation(1), "\n  ");
+        assert_eq!(continuation(3), "\n      ");
+
+        // Continued lines are read as one expression
+        let mut env = initialize_environment();
+        let input = format!("fn fact n (if (<= n 1){}1{}(* n (fact (- n 1))))",
+                            continuation(1), continuation(1));
+        send(input, &mut env).unwrap();
+        assert_eq!(send("fact 5".to_string(), &mut env), Ok(CrispExpr::Number(120.0)));
+    }
+
+    #[test]
+    fn test_run_command() {
+        let mut env = initialize_environment();
+        let mut run = |line: &str| run_command(line, &mut env);
+
+        assert_eq!(run(":type [1 2]"), Some(Ok("List".to_string())));
+        assert_eq!(run("  :type (+ 1 2)"), Some(Ok("Number".to_string())));
+        assert_eq!(run(":type +"), Some(Ok("Func".to_string())));
+        crisp_assert_err!(run(":type (+ 1 'a')").unwrap(), TypeError);
+
+        // Expanding doesn't evaluate anything
+        assert_eq!(run(":expand #(* % 2)"), Some(Ok("(\\ (%1) (* %1 2))".to_string())));
+        assert_eq!(run(":expand [1 x]"), Some(Ok("(list 1 x)".to_string())));
+        assert_eq!(run(":expand 1..n"), Some(Ok("(range 1 n)".to_string())));
+        assert_eq!(run(":expand puts \"$x!\""), Some(Ok("(puts (format '{}!' x))".to_string())));
+        crisp_assert_err!(run(":expand (+ 1").unwrap(), ParseError);
+
+        let timed = run(":time + 1 2").unwrap().unwrap();
+        assert!(timed.contains("3\nTime: "), "{}", timed);
+        crisp_assert_err!(run(":time (+ 1 'a')").unwrap(), TypeError);
+
+        #[cfg(feature = "alloc-stats")]
+        {
+            let measured = run(":memory list 1 2").unwrap().unwrap();
+            assert!(measured.contains("(1 2)\nAllocations: "), "{}", measured);
+        }
+        #[cfg(not(feature = "alloc-stats"))]
+        crisp_assert_err!(run(":memory list 1 2").unwrap(), StandardError);
+
+        // Anything else is evaluated as usual
+        assert_eq!(run(":foo"), None);
+        assert_eq!(run("+ 1 2"), None);
+    }
+
+    #[test]
+    fn test_record_result() {
+        let mut env = initialize_environment();
+        let run = |code: &str, env: &mut CrispEnv| send(code.to_string(), env).unwrap();
+
+        record_result(run("+ 1 2", &mut env), 1, &mut env);
+        assert_eq!(run("_", &mut env), CrispExpr::Number(3.0));
+
+        record_result(run("* _ 10", &mut env), 2, &mut env);
+        assert_eq!(run("_", &mut env), CrispExpr::Number(30.0));
+        assert_eq!(run("list $1 $2", &mut env),
+                   list![CrispExpr::Number(3.0), CrispExpr::Number(30.0)]);
+
+        // Still ignored where it's used as a placeholder
+        assert_eq!(run("((\\ (_ b) b) 1 2)", &mut env), CrispExpr::Number(2.0));
+    }
+
+    #[test]
+    fn test_preload_file() {
+        let mut env = initialize_environment();
+
+        assert_eq!(preload_file("tests/lib/math_lib.crisp", &mut env).unwrap(),
+                   CrispExpr::Number(3.0));
+        assert_eq!(send("square 4".to_string(), &mut env).unwrap(), CrispExpr::Number(16.0));
+
+        crisp_assert_err!(preload_file("tests/lib/missing.crisp", &mut env), LoadError);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{test_runner::find_crisp_files, xref::{definitions, DefinitionKind}};
+
+use std::{fs, path::Path};
+
+/// Runs `crisp tags`, which scans the `.crisp` files at `path` (without
+/// evaluating them) and writes a tags file listing everything they define, so
+/// that editors can jump to definitions. The file is in the ctags format used
+/// by e.g. Vim, or the etags format used by Emacs if `etags` is set.
+///
+/// The tags are written to `output` if given (`-` for stdout), otherwise to
+/// `tags`, or `TAGS` for etags.
+///
+/// # Returns
+///
+/// The exit code for the process.
+pub fn run(path: &Path, etags: bool, output: Option<&str>) -> i32 {
+    let files = match find_crisp_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let mut sources = Vec::new();
+    for file in files {
+        match fs::read_to_string(&file) {
+            Ok(source) => sources.push((file.display().to_string(), source)),
+            Err(e) => {
+                eprintln!("Couldn't read {}: {}", file.display(), e);
+                return 1;
+            }
+        }
+    }
+
+    let tags = if etags { etags_file(&sources) } else { ctags_file(&sources) };
+
+    match output.unwrap_or(if etags { "TAGS" } else { "tags" }) {
+        "-" => {
+            print!("{}", tags);
+            0
+        },
+
+        output => match fs::write(output, tags) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("Couldn't write {}: {}", output, e);
+                1
+            }
+        }
+    }
+}
+
+/// Builds a ctags file for the given files and their source code. Each tag
+/// gives the line of its definition, and its kind: `f` for functions, `v` for
+/// variables, and `m` for macros. Tags are sorted by name, as editors expect.
+fn ctags_file(sources: &[(String, String)]) -> String {
+    let mut tags: Vec<String> = sources.iter().flat_map(|(file, source)| {
+        definitions(source).into_iter().map(move |definition| {
+            format!("{}\t{}\t{};\"\t{}\n", definition.name, file, definition.span.line,
+                    kind_letter(definition.kind))
+        })
+    }).collect();
+    tags.sort();
+
+    let header = "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+                  !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted/\n";
+
+    format!("{}{}", header, tags.concat())
+}
+
+/// Builds an etags file for the given files and their source code: a section
+/// for each file, giving for each tag the text of its line up to the name, the
+/// name itself, and the line number and byte offset of the line.
+fn etags_file(sources: &[(String, String)]) -> String {
+    sources.iter().map(|(file, source)| {
+        let lines: Vec<&str> = source.split('\n').collect();
+
+        let section: String = definitions(source).into_iter().map(|definition| {
+            let line = definition.span.line;
+            let offset: usize = lines[..line - 1].iter().map(|line| line.len() + 1).sum();
+            let length = definition.span.column - 1 + definition.name.chars().count();
+            let text: String = lines[line - 1].chars().take(length).collect();
+
+            format!("{}\x7f{}\x01{},{}\n", text, definition.name, line, offset)
+        }).collect();
+
+        format!("\x0c\n{},{}\n{}", file, section.len(), section)
+    }).collect()
+}
+
+/// The letter used for the kind of a definition in a ctags file.
+fn kind_letter(kind: DefinitionKind) -> char {
+    match kind {
+        DefinitionKind::Function => 'f',
+        DefinitionKind::Variable => 'v',
+        DefinitionKind::Macro => 'm'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<(String, String)> {
+        vec![
+            ("a.crisp".to_string(), "let x 5\n\nfn square (n) (* n n)\n".to_string()),
+            ("b.crisp".to_string(), "; é\nfn double (n)\n  (let y (* 2 n))\n".to_string())
+        ]
+    }
+
+    #[test]
+    fn test_ctags_file() {
+        assert_eq!(ctags_file(&sources()),
+                   "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+                    !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted/\n\
+                    double\tb.crisp\t2;\"\tf\n\
+                    square\ta.crisp\t3;\"\tf\n\
+                    x\ta.crisp\t1;\"\tv\n\
+                    y\tb.crisp\t3;\"\tv\n");
+    }
+
+    #[test]
+    fn test_etags_file() {
+        assert_eq!(etags_file(&sources()),
+                   "\x0c\na.crisp,33\n\
+                    let x\x7fx\x011,0\n\
+                    fn square\x7fsquare\x013,9\n\
+                    \x0c\nb.crisp,37\n\
+                    fn double\x7fdouble\x012,5\n  \
+                    (let y\x7fy\x013,19\n");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{env::{CrispEnv, env_new_scope}, error::CrispError, eval::eval,
+            expr::CrispExpr, file_environment, host::{ContainedHost, CrispHost}, read_exprs,
+            send};
+
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}, sync::{Arc, Mutex}};
+
+use colored::*;
+
+/// Runs `crisp test`. `path` may be a `.crisp` file, or a directory which is
+/// searched recursively for them. Each file is evaluated in a fresh
+/// environment, after which each of the tests it defined with `deftest` is
+/// run. A file which doesn't define any tests counts as a single test, which
+/// passes if the file runs without any errors.
+///
+/// Anything a test prints is captured, and only shown if the test fails.
+/// Tests can't read from stdin, and a test which calls `exit` fails rather
+/// than ending the test run.
+///
+/// # Returns
+///
+/// The exit code for the process: `0` if every test passed, otherwise `1`.
+pub fn run(path: &Path) -> i32 {
+    let files = match find_crisp_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+
+    for file in files {
+        for test in run_file_tests(&file) {
+            match test.result {
+                Ok(()) => {
+                    println!("test {} ... {}", test.name, "ok".green());
+                    passed += 1;
+                },
+
+                Err(e) => {
+                    println!("test {} ... {}", test.name, "FAILED".red());
+                    println!("    {}", e);
+                    for line in test.output.lines() {
+                        println!("    {}", line);
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    let status = if failed == 0 { "ok".green() } else { "FAILED".red() };
+    println!("\ntest result: {}. {} passed; {} failed", status, passed, failed);
+
+    if failed == 0 { 0 } else { 1 }
+}
+
+/// The outcome of a single test, along with anything it printed.
+struct TestResult {
+    name: String,
+    result: Result<(), CrispError>,
+    output: String
+}
+
+/// Collects the `.crisp` files at `path`, which may be a single file or a
+/// directory to search recursively, sorted by path.
+pub fn find_crisp_files(path: &Path) -> Result<Vec<PathBuf>, CrispError> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return load_error!(path.display())
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+
+        if entry_path.is_dir() {
+            files.extend(find_crisp_files(&entry_path)?);
+        } else if entry_path.extension().is_some_and(|ext| ext == "crisp") {
+            files.push(entry_path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Evaluates the file `file` and runs the tests it defines, returning the
+/// result of each. If the file itself raises an error, that is the only
+/// result.
+fn run_file_tests(file: &Path) -> Vec<TestResult> {
+    let filename = file.display().to_string();
+
+    let exprs = match read_exprs(file) {
+        Ok(exprs) => exprs,
+        Err(e) => return vec![TestResult { name: filename, result: Err(e), output: String::new() }]
+    };
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let host = Arc::new(ContainedHost::default());
+    let mut env = file_environment(&filename).with_host(host.clone())
+                                             .with_stdout(output.clone())
+                                             .with_stderr(output.clone())
+                                             .with_stdin(Arc::new(Mutex::new(io::empty())));
+
+    let result = exprs.into_iter().try_for_each(|expr| send(expr, &mut env).map(|_| ()));
+    let result = check_exit(&host, result);
+    if result.is_err() || env.tests.is_empty() {
+        return vec![TestResult { name: filename, result, output: contents(&output) }];
+    }
+
+    env.tests.iter()
+             .map(|test| run_test(format!("{}: {}", filename, test.name), &test.body, &env))
+             .collect()
+}
+
+/// Runs the body of a test in its own scope, so that tests can't affect each
+/// other.
+fn run_test(name: String, body: &[CrispExpr], env: &CrispEnv) -> TestResult {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let host = Arc::new(ContainedHost::default());
+
+    let mut scope = env_new_scope(HashMap::new(), env);
+    scope.stdout = Some(output.clone());
+    scope.stderr = Some(output.clone());
+    scope.host = Some(host.clone());
+
+    let result = body.iter().try_for_each(|expr| eval(expr, &mut scope).map(|_| ()));
+
+    TestResult { name, result: check_exit(&host, result), output: contents(&output) }
+}
+
+/// Fails a test that called `exit`, even if it went on to catch the error
+/// that raised, e.g. with `assert-raises`.
+fn check_exit(host: &ContainedHost, result: Result<(), CrispError>) -> Result<(), CrispError> {
+    match host.exit_code() {
+        Some(code) => standard_error!(format!("Tried to exit with code {}.", code)),
+        None => result
+    }
+}
+
+/// Reads back the output captured while running a test.
+fn contents(output: &Mutex<Vec<u8>>) -> String {
+    let output = output.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_crisp_files() {
+        let files = find_crisp_files(Path::new("tests/runner")).unwrap();
+        assert_eq!(files, vec![
+            PathBuf::from("tests/runner/fail/broken.crisp"),
+            PathBuf::from("tests/runner/fail/exiting.crisp"),
+            PathBuf::from("tests/runner/fail/failing.crisp"),
+            PathBuf::from("tests/runner/pass/math.crisp"),
+            PathBuf::from("tests/runner/pass/plain.crisp")
+        ]);
+
+        let files = find_crisp_files(Path::new("tests/runner/pass/plain.crisp")).unwrap();
+        assert_eq!(files, vec![PathBuf::from("tests/runner/pass/plain.crisp")]);
+
+        crisp_assert_err!(find_crisp_files(Path::new("tests/runner/missing")), LoadError);
+    }
+
+    #[test]
+    fn test_run_file_tests() {
+        let results = run_file_tests(Path::new("tests/runner/pass/math.crisp"));
+        let names: Vec<&str> = results.iter().map(|test| test.name.as_str()).collect();
+        assert_eq!(names, [
+            "tests/runner/pass/math.crisp: square",
+            "tests/runner/pass/math.crisp: math addition",
+            "tests/runner/pass/math.crisp: math floats"
+        ]);
+        assert!(results.iter().all(|test| test.result.is_ok()));
+
+        let results = run_file_tests(Path::new("tests/runner/fail/failing.crisp"));
+        assert!(results[0].result.is_ok());
+        crisp_assert_err!(results[1].result, AssertionError);
+        crisp_assert_err!(results[2].result, ParseError);
+
+        // Output is captured separately for each test
+        assert_eq!(results[0].output, "");
+        assert_eq!(results[1].output, "checking 1 = 2\n");
+
+        // Exiting fails the test, rather than ending the run
+        let results = run_file_tests(Path::new("tests/runner/fail/exiting.crisp"));
+        assert_eq!(results.len(), 3);
+        crisp_assert_err!(results[0].result, StandardError);
+        crisp_assert_err!(results[1].result, StandardError);
+        assert!(results[2].result.is_ok());
+    }
+
+    #[test]
+    fn test_run_file_tests_without_deftest() {
+        let results = run_file_tests(Path::new("tests/runner/pass/plain.crisp"));
+        assert_eq!(results.len(), 1);
+        assert!(results[0].result.is_ok());
+
+        // Errors in the file itself
+        let results = run_file_tests(Path::new("tests/runner/fail/broken.crisp"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "tests/runner/fail/broken.crisp");
+        assert_eq!(results[0].output, "");
+        crisp_assert_err!(results[0].result, ParseError);
+    }
+}
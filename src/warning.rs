@@ -0,0 +1,210 @@
+use std::{fmt, sync::{Mutex, RwLock}};
+
+use colored::*;
+
+use crate::{color, expr::CrispExpr};
+
+/// Something about a program that is probably a mistake, but not enough of
+/// one to stop it. Unlike a [`CrispError`](crate::error::CrispError), a
+/// warning is only recorded with [`warn()`], and evaluation carries on; the
+/// warnings are then reported alongside the errors, with where they came from
+/// if it's known. The rest are only found by `crisp lint`; see
+/// [`lint()`](crate::lint::lint).
+#[derive(Clone, Debug, PartialEq)]
+pub enum CrispWarning {
+    /// A builtin was rebound with `shadow`.
+    ShadowedBuiltin(String),
+    /// An argument of a function, named by the second field if it has a name,
+    /// is never used in its body.
+    UnusedArgument(String, Option<String>),
+    /// A name that isn't bound anywhere that can be seen.
+    UnboundSymbol(String),
+    /// A function called with `given` arguments, which takes from `min` to
+    /// `max` (or any number more, if `None`).
+    ArityMismatch { name: String, given: usize, min: usize, max: Option<usize> },
+    /// A name bound with `let` within a function, which is never used.
+    UnusedBinding(String),
+    /// An `if` whose predicate is always the given value, so that one of its
+    /// branches is never taken.
+    UnreachableBranch(bool)
+}
+
+impl CrispWarning {
+    /// The names of every kind of warning, as they're given to `--allow`.
+    pub const NAMES: &'static [&'static str] = &[
+        "shadowed-builtin",
+        "unused-argument",
+        "unbound-symbol",
+        "arity-mismatch",
+        "unused-binding",
+        "unreachable-branch"
+    ];
+
+    /// The name of this kind of warning, e.g. `"unused-argument"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrispWarning::ShadowedBuiltin(_) => "shadowed-builtin",
+            CrispWarning::UnusedArgument(_, _) => "unused-argument",
+            CrispWarning::UnboundSymbol(_) => "unbound-symbol",
+            CrispWarning::ArityMismatch { .. } => "arity-mismatch",
+            CrispWarning::UnusedBinding(_) => "unused-binding",
+            CrispWarning::UnreachableBranch(_) => "unreachable-branch"
+        }
+    }
+
+    /// Describes the problem, without the name of the warning.
+    pub fn message(&self) -> String {
+        match self {
+            CrispWarning::ShadowedBuiltin(name) => format!("Shadowing builtin `{}`.", name),
+            CrispWarning::UnusedArgument(arg, Some(name)) => {
+                format!("Argument `{}` of `{}` is never used.", arg, name)
+            },
+            CrispWarning::UnusedArgument(arg, None) => {
+                format!("Argument `{}` is never used.", arg)
+            },
+            CrispWarning::UnboundSymbol(name) => format!("`{}` isn't defined.", name),
+            CrispWarning::ArityMismatch { name, given, min, max } => {
+                let takes = match max {
+                    Some(max) if max == min => min.to_string(),
+                    Some(max) => format!("{} to {}", min, max),
+                    None => format!("at least {}", min)
+                };
+                format!("`{}` takes {} arguments, but is given {}.", name, takes, given)
+            },
+            CrispWarning::UnusedBinding(name) => format!("`{}` is bound but never used.", name),
+            CrispWarning::UnreachableBranch(always) => {
+                let (value, branch) = if *always { ("true", "false") } else { ("false", "true") };
+                format!("This `if` is always {}, so its {} branch is never taken.", value, branch)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CrispWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if color::plain_errors() {
+            write!(f, "[Warning] {} ({})", self.message(), self.name())
+        } else {
+            write!(f, "{} ({})", format!("[{}] {}", "Warning".yellow(), self.message()).bold(),
+                   self.name())
+        }
+    }
+}
+
+/// The names of the warnings that aren't reported, or `None` if none are. See
+/// [`configure()`].
+static ALLOWED: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// The warnings that have been recorded, along with the expression that each
+/// is about, if it's known. Those that haven't been taken yet are `pending`.
+struct Warnings {
+    pending: Vec<(CrispWarning, Option<CrispExpr>)>,
+    seen: Vec<(CrispWarning, Option<CrispExpr>)>
+}
+
+static WARNINGS: Mutex<Warnings> = Mutex::new(Warnings { pending: Vec::new(), seen: Vec::new() });
+
+/// Configures which warnings are reported for the rest of the program. None
+/// are if `none` is set (e.g. by the `--no-warnings` flag); otherwise, those
+/// named in `allowed` (e.g. by `--allow`) aren't.
+pub fn configure(none: bool, allowed: &[String]) {
+    let allowed = match none {
+        true => CrispWarning::NAMES.iter().map(|name| name.to_string()).collect(),
+        false => allowed.to_vec()
+    };
+
+    *ALLOWED.write().unwrap_or_else(|e| e.into_inner()) = Some(allowed);
+}
+
+/// Records `warning`, about the expression `expr` if it's known, to be
+/// reported once the top-level expression that's being evaluated has finished
+/// (see [`take_warnings()`]). The same warning about the same expression is
+/// only recorded once, however many times it comes up, e.g. for a function
+/// that's defined within a loop.
+pub fn warn(warning: CrispWarning, expr: Option<CrispExpr>) {
+    if allowed(&warning) {
+        return;
+    }
+
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let warning = (warning, expr);
+
+    if !warnings.seen.contains(&warning) {
+        warnings.seen.push(warning.clone());
+        warnings.pending.push(warning);
+    }
+}
+
+/// Checks whether `warning` is of a kind that isn't reported. See
+/// [`configure()`].
+pub fn allowed(warning: &CrispWarning) -> bool {
+    let allowed = ALLOWED.read().unwrap_or_else(|e| e.into_inner());
+    allowed.as_ref().is_some_and(|allowed| allowed.iter().any(|name| name == warning.name()))
+}
+
+/// Takes the warnings that have been recorded since this was last called, in
+/// the order that they were recorded.
+pub fn take_warnings() -> Vec<(CrispWarning, Option<CrispExpr>)> {
+    std::mem::take(&mut WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{env::initialize_environment, expr::CrispExpr::*, send};
+
+    #[test]
+    fn test_message() {
+        assert_eq!(CrispWarning::ShadowedBuiltin("+".to_string()).message(),
+                   "Shadowing builtin `+`.");
+        assert_eq!(CrispWarning::UnusedArgument("x".to_string(), Some("f".to_string())).message(),
+                   "Argument `x` of `f` is never used.");
+
+        assert_eq!(CrispWarning::ArityMismatch { name: "sqrt".to_string(), given: 2, min: 1,
+                                                 max: Some(1) }.message(),
+                   "`sqrt` takes 1 arguments, but is given 2.");
+        assert_eq!(CrispWarning::ArityMismatch { name: "+".to_string(), given: 1, min: 2,
+                                                 max: None }.message(),
+                   "`+` takes at least 2 arguments, but is given 1.");
+        assert_eq!(CrispWarning::UnreachableBranch(true).message(),
+                   "This `if` is always true, so its false branch is never taken.");
+
+        // Every kind can be allowed by name
+        let warnings = [
+            CrispWarning::ShadowedBuiltin(String::new()),
+            CrispWarning::UnusedArgument(String::new(), None),
+            CrispWarning::UnboundSymbol(String::new()),
+            CrispWarning::ArityMismatch { name: String::new(), given: 0, min: 0, max: None },
+            CrispWarning::UnusedBinding(String::new()),
+            CrispWarning::UnreachableBranch(false)
+        ];
+        for warning in warnings {
+            assert!(CrispWarning::NAMES.contains(&warning.name()));
+        }
+    }
+
+    #[test]
+    fn test_warn() {
+        let mut env = initialize_environment();
+        let warning = CrispWarning::ShadowedBuiltin("reverse".to_string());
+        let expr = list![sym!("shadow"), sym!("reverse"), Number(1.0)];
+
+        // Warnings are shared between threads, so others' may be mixed in
+        send("shadow reverse 1".to_string(), &mut env).unwrap();
+        assert!(take_warnings().contains(&(warning.clone(), Some(expr.clone()))));
+
+        // Only once
+        send("shadow reverse 1".to_string(), &mut env).unwrap();
+        assert!(!take_warnings().contains(&(warning, Some(expr))));
+
+        send("fn warn-test (a b c) (* a c)".to_string(), &mut env).unwrap();
+        let unused = CrispWarning::UnusedArgument("b".to_string(), Some("warn-test".to_string()));
+        assert!(take_warnings().iter().any(|(warning, _)| warning == &unused));
+
+        // An argument starting with `_` is expected to be unused
+        send("\\ (_a b) b".to_string(), &mut env).unwrap();
+        assert!(!take_warnings().iter().any(|(warning, _)| {
+            matches!(warning, CrispWarning::UnusedArgument(arg, _) if arg == "_a")
+        }));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{host::ContainedHost, run_file};
+
+use std::{fs, path::Path, sync::Arc, thread, time::{Duration, SystemTime}};
+
+use colored::*;
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs `crisp --watch`. The file `filename` is run with [`run_file()`] (with
+/// the arguments `args`), then run again in a fresh environment every time it
+/// changes on disk, with a divider printed between runs. Each run has a
+/// [`ContainedHost`], so that `exit` only ends that run. This never returns;
+/// the user stops watching with Ctrl-C.
+pub fn run(filename: &str, args: &[String], debug: bool, strict: bool) -> ! {
+    let path = Path::new(filename);
+    let mut last_modified = modified(path);
+
+    loop {
+        match run_file(filename, args, debug, strict, Arc::new(ContainedHost::default())) {
+            Ok(0) => (),
+            Ok(code) => eprintln!("{}", format!("Exited with code {}", code).yellow()),
+            Err(e) => eprintln!("{}", e)
+        }
+
+        // Wait until the file changes
+        while modified(path) == last_modified {
+            thread::sleep(POLL_INTERVAL);
+        }
+        last_modified = modified(path);
+
+        println!("{}", format!("---- {} changed, re-running ----", filename).bright_blue());
+    }
+}
+
+/// The time the file at `path` was last modified, or `None` if it can't be
+/// read (e.g. an editor has briefly removed it while saving).
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::CrispHost;
+
+    #[test]
+    fn test_contained_exit() {
+        let host = Arc::new(ContainedHost::default());
+        assert_eq!(run_file("tests/exit_failure.crisp", &[], false, false, host.clone()), Ok(3));
+        assert_eq!(host.exit_code(), Some(3));
+    }
+
+    #[test]
+    fn test_modified() {
+        assert!(modified(Path::new("tests/print.crisp")).is_some());
+        assert!(modified(Path::new("tests/does_not_exist.crisp")).is_none());
+    }
+}
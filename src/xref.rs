@@ -0,0 +1,110 @@
+use crate::{reader::{tokenize_with_spans, Span}, split_exprs};
+
+/// The keywords that define names, along with the kind of definition each
+/// makes.
+const DEFINERS: &[(&str, DefinitionKind)] = &[
+    ("fn", DefinitionKind::Function),
+    ("let", DefinitionKind::Variable),
+    ("const", DefinitionKind::Variable),
+    ("shadow", DefinitionKind::Variable),
+    ("defparam", DefinitionKind::Variable),
+    ("defmacro", DefinitionKind::Macro)
+];
+
+/// What kind of thing a [`Definition`] defines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DefinitionKind {
+    Function,
+    Variable,
+    Macro
+}
+
+/// A name defined in the source code, found without evaluating it.
+#[derive(Debug, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// Where the name itself appears in the definition.
+    pub span: Span
+}
+
+/// Every token of `text` other than parens and brackets, along with its position in the
+/// whole of `text`.
+pub fn tokens(text: &str) -> Vec<(String, Span)> {
+    split_exprs(text).into_iter().flat_map(|(line, expr)| {
+        tokenize_with_spans(&expr).into_iter()
+            .filter(|(token, _)| !matches!(&token[..], "(" | ")" | "[" | "]"))
+            .map(move |(token, span)| (token, Span { line: line + span.line - 1, ..span }))
+    }).collect()
+}
+
+/// Finds every name defined with one of the [`DEFINERS`] (e.g. `fn` or `let`)
+/// in `text`, in the order they appear. This includes definitions nested inside
+/// of other expressions, e.g. in the body of a function.
+pub fn definitions(text: &str) -> Vec<Definition> {
+    tokens(text).windows(2).filter_map(|pair| {
+        let (definer, (name, span)) = (&pair[0].0, &pair[1]);
+        let (_, kind) = DEFINERS.iter().find(|(keyword, _)| keyword == definer)?;
+
+        Some(Definition { name: name.clone(), kind: *kind, span: *span })
+    }).collect()
+}
+
+/// Finds the token of `text` at `position`, if there is one.
+pub fn token_at(text: &str, position: Span) -> Option<String> {
+    let position = (position.line, position.column);
+
+    tokens(text).into_iter().find(|(token, start)| {
+        let end = start.last_of(token);
+        (start.line, start.column) <= position && position <= (end.line, end.column)
+    }).map(|(token, _)| token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let at = |line, column| Span { line, column };
+
+        assert_eq!(tokens("; comment\nlet x (+ 1\n         2)\nputs x"), vec![
+            ("let".to_string(), at(2, 1)),
+            ("x".to_string(), at(2, 5)),
+            ("+".to_string(), at(2, 8)),
+            ("1".to_string(), at(2, 10)),
+            ("2".to_string(), at(3, 10)),
+            ("puts".to_string(), at(4, 1)),
+            ("x".to_string(), at(4, 6))
+        ]);
+    }
+
+    #[test]
+    fn test_definitions() {
+        let text = "fn square (n) (* n n)\nlet x 5\n\nfn f ()\n  (let y 2)\ndefmacro m (x) x\n";
+
+        assert_eq!(definitions(text), vec![
+            Definition { name: "square".to_string(), kind: DefinitionKind::Function,
+                         span: Span { line: 1, column: 4 } },
+            Definition { name: "x".to_string(), kind: DefinitionKind::Variable,
+                         span: Span { line: 2, column: 5 } },
+            Definition { name: "f".to_string(), kind: DefinitionKind::Function,
+                         span: Span { line: 4, column: 4 } },
+            Definition { name: "y".to_string(), kind: DefinitionKind::Variable,
+                         span: Span { line: 5, column: 8 } },
+            Definition { name: "m".to_string(), kind: DefinitionKind::Macro,
+                         span: Span { line: 6, column: 10 } }
+        ]);
+    }
+
+    #[test]
+    fn test_token_at() {
+        let text = "puts (square\n  'a b')";
+        let at = |line, column| Span { line, column };
+
+        assert_eq!(token_at(text, at(1, 1)), Some("puts".to_string()));
+        assert_eq!(token_at(text, at(1, 12)), Some("square".to_string()));
+        assert_eq!(token_at(text, at(2, 5)), Some("'a b'".to_string()));
+        assert_eq!(token_at(text, at(1, 5)), None);
+    }
+}